@@ -11,9 +11,10 @@ use std::fmt;
 /// > If not specified at all `sendrecv` is assumed by default
 ///
 /// [RFC8866](https://www.rfc-editor.org/rfc/rfc8866.html#section-6.7)
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone)]
 pub enum Direction {
     /// Send and receive media data
+    #[default]
     SendRecv,
 
     /// Only receive media data
@@ -42,9 +43,3 @@ impl fmt::Display for Direction {
         write!(f, "a={}", self.as_str())
     }
 }
-
-impl Default for Direction {
-    fn default() -> Self {
-        Direction::SendRecv
-    }
-}
@@ -370,9 +370,7 @@ impl<E> From<nom::Err<nom::error::Error<&str>>> for ParseError<E> {
 }
 
 pub fn parse<B: ParseBuilder>(src: &BytesStr) -> Result<B::Message, ParseError<B::Error>> {
-    let lines = src
-        .split(|c| matches!(c, '\n' | '\r'))
-        .filter(|line| !line.is_empty());
+    let lines = src.split(['\n', '\r']).filter(|line| !line.is_empty());
 
     let mut builder = B::default();
 
@@ -2,27 +2,42 @@ use crate::transaction::{ClientInvTsx, ClientTsx, ServerInvTsx, ServerTsx, TsxKe
 use crate::transaction::{Transactions, TsxMessage};
 use crate::transport::{
     Direction, Factory, OutgoingParts, OutgoingRequest, OutgoingResponse, ReceivedMessage,
-    TpHandle, Transport, Transports, TransportsBuilder,
+    TpHandle, TpKey, Transport, Transports, TransportsBuilder,
 };
-use crate::{BaseHeaders, IncomingRequest, Layer, MayTake, Request, Response, Result};
+use crate::{
+    BaseHeaders, Error, IncomingRequest, Layer, MayTake, Request, Response, Result, WithStatus,
+};
+use anyhow::anyhow;
 use bytes::{Bytes, BytesMut};
 use bytesstr::BytesStr;
-use sip_types::header::typed::{Accept, Allow, Supported, Via};
+use sip_types::header::typed::{Accept, Allow, Route, Routing, Supported, Via};
 use sip_types::host::Host;
 use sip_types::msg::{MessageLine, StatusLine};
 use sip_types::parse::Parser;
-use sip_types::print::{AppendCtx, BytesPrint, PrintCtx};
+use sip_types::print::{AppendCtx, BytesPrint, Print, PrintCtx, PrintMode};
+use sip_types::uri::params::Params;
+use sip_types::uri::sip::SipUri;
+use sip_types::uri::{NameAddr, Uri};
 use sip_types::{Code, Headers, Method, Name};
 use std::fmt::Write;
 use std::marker::PhantomData;
-use std::mem::take;
+use std::mem::{replace, take};
 use std::net::{IpAddr, SocketAddr};
 use std::ops::Index;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{fmt, io};
 use tokio::sync::broadcast;
 use tracing::Instrument;
 
+/// Path MTU assumed when none is configured using [`EndpointBuilder::set_path_mtu`]
+const DEFAULT_PATH_MTU: usize = 1500;
+
+/// Messages sent over unreliable transports must stay this many bytes below the path MTU
+///
+/// [RFC3261, Section 18.1.1](https://datatracker.ietf.org/doc/html/rfc3261#section-18.1.1)
+const PATH_MTU_MARGIN: usize = 200;
+
 /// The endpoint is the centerpiece of the sip stack. It contains all information about the
 /// application and a stack of layered modules which build the logic of SIP applications and
 /// its extensions.
@@ -52,6 +67,12 @@ struct Inner {
     // Parser used for all parsing operations.
     parser: Parser,
 
+    // Mode used to print all outgoing messages.
+    print_mode: PrintMode,
+
+    // Path MTU used to limit the size of messages sent over unreliable transports.
+    path_mtu: usize,
+
     transports: Transports,
     transactions: Transactions,
 
@@ -69,6 +90,11 @@ impl Endpoint {
         self.inner.parser
     }
 
+    /// Returns the [`PrintMode`] used to print outgoing messages
+    pub fn print_mode(&self) -> PrintMode {
+        self.inner.print_mode
+    }
+
     /// Sends an INVITE request and return a [`ClientInvTsx`] which MUST be used to drive the transaction
     pub async fn send_invite(&self, request: Request) -> Result<ClientInvTsx> {
         ClientInvTsx::send(self.clone(), request).await
@@ -117,8 +143,20 @@ impl Endpoint {
 
     /// Takes a request and converts it into an `Outgoing`.
     /// To do so it calculates the destination and retrieves a suitable transport
-    pub async fn create_outgoing(&self, request: Request) -> Result<OutgoingRequest> {
-        let (transport, destination) = self.transports().select(self, &*request.line.uri).await?;
+    ///
+    /// Requests with a `flow` are sent over that flow. Otherwise the target is the URI of
+    /// the topmost `Route` header if present, the request URI if not. Requests to a strict
+    /// router are rewritten as described in RFC 3261 section 12.2.1.1.
+    pub async fn create_outgoing(&self, mut request: Request) -> Result<OutgoingRequest> {
+        let (transport, destination) = if let Some(flow) = &request.flow {
+            self.flow_transport(flow).await?
+        } else {
+            let target = next_hop(&mut request);
+
+            self.transports()
+                .select(self, &*target, request.secure)
+                .await?
+        };
 
         Ok(OutgoingRequest {
             msg: request,
@@ -130,35 +168,77 @@ impl Endpoint {
         })
     }
 
+    /// Returns the transport of a connection oriented flow, if the connection still exists
+    pub async fn claim_transport(&self, flow: &TpKey) -> Option<TpHandle> {
+        self.transports().claim(flow).await
+    }
+
+    async fn flow_transport(&self, flow: &TpKey) -> Result<(TpHandle, Vec<SocketAddr>)> {
+        let remote = match flow.direction {
+            Direction::Outgoing(remote) | Direction::Incoming(remote) => remote,
+            Direction::None => {
+                return Err(Error {
+                    status: Code::FLOW_FAILED,
+                    error: Some(anyhow!("Flows must be connection oriented")),
+                })
+            }
+        };
+
+        match self.claim_transport(flow).await {
+            Some(transport) => Ok((transport, vec![remote])),
+            None => Err(Error {
+                status: Code::FLOW_FAILED,
+                error: Some(anyhow!("Flow no longer exists")),
+            }),
+        }
+    }
+
+    /// Send a CRLF keep-alive ping over the flow and wait for the pong
+    ///
+    /// Fails with `430 Flow Failed` if the flow no longer exists or no pong was received within
+    /// `timeout` ([RFC5626, Section 4.4.1](https://datatracker.ietf.org/doc/html/rfc5626#section-4.4.1)).
+    pub async fn keep_alive(&self, flow: &TpKey, timeout: Duration) -> Result<()> {
+        let (transport, destination) = self.flow_transport(flow).await?;
+
+        let pong = self.transports().await_pong(*flow);
+
+        transport
+            .send(b"\r\n\r\n", destination[0])
+            .await
+            .status(Code::FLOW_FAILED)?;
+
+        match tokio::time::timeout(timeout, pong).await {
+            Ok(Ok(())) => Ok(()),
+            _ => Err(Error {
+                status: Code::FLOW_FAILED,
+                error: Some(anyhow!("No keep-alive pong received")),
+            }),
+        }
+    }
+
     /// Print the request to its buffer (if needed) and send it via the transport
     pub async fn send_outgoing_request(&self, message: &mut OutgoingRequest) -> io::Result<()> {
-        if message.parts.buffer.is_empty() {
-            let mut buffer = BytesMut::new();
-
-            let ctx = PrintCtx {
-                method: Some(&message.msg.line.method),
-                uri: None,
-            };
+        let mut unreliable = None;
 
+        if message.parts.buffer.is_empty() {
             message
                 .msg
                 .headers
                 .insert(Name::CONTENT_LENGTH, message.msg.body.len().to_string());
 
-            write!(
-                buffer,
-                "{}\r\n{}\r\n",
-                message.msg.line.print_ctx(ctx),
-                message.msg.headers
-            )
-            .map_err(|e| {
-                // wrap
-                io::Error::new(io::ErrorKind::Other, e)
-            })?;
-
-            buffer.extend_from_slice(&message.msg.body);
-
-            message.parts.buffer = buffer.freeze();
+            message.parts.buffer = self.print_message(
+                &message.msg.line,
+                Some(&message.msg.line.method),
+                &message.msg.headers,
+                &message.msg.body,
+                &message.parts.transport,
+            )?;
+
+            if !message.parts.transport.reliable()
+                && message.parts.buffer.len() > self.max_unreliable_size()
+            {
+                unreliable = self.switch_to_reliable(message).await?;
+            }
         }
 
         let target = message.parts.destination[0];
@@ -169,42 +249,117 @@ impl Endpoint {
             BytesPrint(&message.parts.buffer)
         );
 
-        message
+        let result = message
             .parts
             .transport
             .send(&message.parts.buffer, message.parts.destination[0])
+            .await;
+
+        match (result, unreliable) {
+            (Err(e), Some((headers, parts))) => {
+                log::warn!(
+                    "failed to send request over {}, falling back to {}: {}",
+                    message.parts.transport,
+                    parts.transport,
+                    e
+                );
+
+                message.msg.headers = headers;
+                message.parts = parts;
+
+                message
+                    .parts
+                    .transport
+                    .send(&message.parts.buffer, message.parts.destination[0])
+                    .await
+            }
+            (result, _) => result,
+        }
+    }
+
+    /// Move a request which is too large for its unreliable transport to a connection oriented one
+    ///
+    /// Required by [RFC3261, Section 18.1.1](https://datatracker.ietf.org/doc/html/rfc3261#section-18.1.1).
+    /// The top Via is changed to the new transport. If no such transport can be found or created,
+    /// the request is sent over the original transport.
+    ///
+    /// Returns the replaced headers and parts, to retry over the original transport if sending fails.
+    async fn switch_to_reliable(
+        &self,
+        message: &mut OutgoingRequest,
+    ) -> io::Result<Option<(Headers, OutgoingParts)>> {
+        let (transport, remote) = match self
+            .transports()
+            .select_reliable(self, &message.parts.destination)
             .await
+        {
+            Ok(selected) => selected,
+            Err(e) => {
+                log::warn!(
+                    "request of {} bytes exceeds the path MTU but no reliable transport is available, falling back to {}: {}",
+                    message.parts.buffer.len(),
+                    message.parts.transport,
+                    e
+                );
+
+                return Ok(None);
+            }
+        };
+
+        log::debug!(
+            "request of {} bytes exceeds the path MTU, switching to {}",
+            message.parts.buffer.len(),
+            transport
+        );
+
+        let headers = message.msg.headers.clone();
+
+        message
+            .msg
+            .headers
+            .edit2(self.parser(), |vias: &mut Vec<Via>| {
+                if let Some(via) = vias.first_mut() {
+                    via.transport = transport.name().into();
+                    via.sent_by = transport.sent_by().into();
+                }
+            })
+            .map_err(io::Error::other)?;
+
+        let buffer = self.print_message(
+            &message.msg.line,
+            Some(&message.msg.line.method),
+            &message.msg.headers,
+            &message.msg.body,
+            &transport,
+        )?;
+
+        let parts = replace(
+            &mut message.parts,
+            OutgoingParts {
+                transport,
+                destination: vec![remote],
+                buffer,
+            },
+        );
+
+        Ok(Some((headers, parts)))
     }
 
     /// Print the request to its buffer (if needed) and send it via the transport
     pub async fn send_outgoing_response(&self, message: &mut OutgoingResponse) -> io::Result<()> {
         if message.parts.buffer.is_empty() {
-            let mut buffer = BytesMut::new();
-
-            let ctx = PrintCtx {
-                method: None,
-                uri: None,
-            };
-
             message
                 .msg
                 .headers
                 .insert(Name::CONTENT_LENGTH, message.msg.body.len().to_string());
 
-            write!(
-                buffer,
-                "{}\r\n{}\r\n",
-                message.msg.line.print_ctx(ctx),
-                message.msg.headers
-            )
-            .map_err(|e| {
-                // wrap
-                io::Error::new(io::ErrorKind::Other, e)
-            })?;
-
-            buffer.extend_from_slice(&message.msg.body);
-
-            message.parts.buffer = buffer.freeze();
+            message.parts.buffer = self.print_message(
+                &message.msg.line,
+                None,
+                &message.msg.headers,
+                &message.msg.body,
+                &message.parts.transport,
+            )?;
         }
 
         let target = message.parts.destination[0];
@@ -222,6 +377,62 @@ impl Endpoint {
             .await
     }
 
+    /// Maximum size of a message sent over an unreliable transport
+    fn max_unreliable_size(&self) -> usize {
+        self.inner.path_mtu.saturating_sub(PATH_MTU_MARGIN)
+    }
+
+    /// Print a message using the endpoint's [`PrintMode`]
+    ///
+    /// Messages sent over an unreliable transport which exceed [`Endpoint::max_unreliable_size`]
+    /// are printed again using [`PrintMode::Compact`] to avoid IP fragmentation.
+    fn print_message<L: Print>(
+        &self,
+        line: &L,
+        method: Option<&Method>,
+        headers: &Headers,
+        body: &Bytes,
+        transport: &TpHandle,
+    ) -> io::Result<Bytes> {
+        let print = |mode| {
+            let ctx = PrintCtx { method, uri: None };
+
+            let mut buffer = BytesMut::new();
+
+            write!(
+                buffer,
+                "{}\r\n{}\r\n",
+                line.print_ctx(ctx),
+                headers.display(mode)
+            )
+            .map_err(|e| {
+                // wrap
+                io::Error::other(e)
+            })?;
+
+            buffer.extend_from_slice(body);
+
+            io::Result::Ok(buffer.freeze())
+        };
+
+        let buffer = print(self.inner.print_mode)?;
+
+        if self.inner.print_mode == PrintMode::Normal
+            && !transport.reliable()
+            && buffer.len() > self.max_unreliable_size()
+        {
+            log::debug!(
+                "message size {} exceeds {} bytes, using compact form",
+                buffer.len(),
+                self.max_unreliable_size()
+            );
+
+            return print(PrintMode::Compact);
+        }
+
+        Ok(buffer)
+    }
+
     /// Create a response to an incoming request with a given status code and optional reason
     ///
     /// This is async as it may need to make a DNS lookup to calculate the response address
@@ -452,6 +663,9 @@ pub struct EndpointBuilder {
     allow: Vec<Allow>,
     supported: Vec<Supported>,
 
+    print_mode: PrintMode,
+    path_mtu: usize,
+
     transports: TransportsBuilder,
     layer: Vec<Box<dyn Layer>>,
 }
@@ -471,6 +685,8 @@ impl EndpointBuilder {
             accept: vec![],
             allow: vec![],
             supported: vec![],
+            print_mode: PrintMode::Normal,
+            path_mtu: DEFAULT_PATH_MTU,
             transports: Default::default(),
             layer: Default::default(),
         }
@@ -500,6 +716,24 @@ impl EndpointBuilder {
         self.supported.push(supported.into())
     }
 
+    /// Set the [`PrintMode`] used to print all outgoing messages
+    ///
+    /// Regardless of this setting, large messages sent over unreliable transports are always printed
+    /// using [`PrintMode::Compact`].
+    pub fn set_print_mode(&mut self, mode: PrintMode) -> &mut Self {
+        self.print_mode = mode;
+        self
+    }
+
+    /// Set the path MTU, defaults to 1500 bytes
+    ///
+    /// Requests sent over unreliable transports that come within 200 bytes of it are printed in
+    /// compact form and, if still too large, sent using a connection oriented transport.
+    pub fn set_path_mtu(&mut self, mtu: usize) -> &mut Self {
+        self.path_mtu = mtu;
+        self
+    }
+
     /// Add an unmanaged transport to the endpoint which will never vanish or break (e.g. UDP)
     pub fn add_unmanaged_transport<T>(&mut self, transport: T) -> &mut Self
     where
@@ -555,6 +789,8 @@ impl EndpointBuilder {
             allow: take(&mut self.allow),
             supported: take(&mut self.supported),
             parser: Default::default(),
+            print_mode: self.print_mode,
+            path_mtu: self.path_mtu,
             transports: self.transports.build(),
             transactions: Default::default(),
             layer,
@@ -570,6 +806,45 @@ impl EndpointBuilder {
     }
 }
 
+/// Returns the URI of the next hop of the request
+///
+/// That is the URI of the topmost `Route` header if present, the request URI if not.
+/// If the topmost route is a strict router (its URI has no `lr` parameter), the request
+/// is rewritten to be sent to it (RFC 3261 section 12.2.1.1): the route's URI becomes
+/// the request URI and the original request URI is appended as last route.
+fn next_hop(request: &mut Request) -> Box<dyn Uri> {
+    let mut routes: Vec<Route> = request.headers.get().unwrap_or_default();
+
+    if routes.is_empty() {
+        return request.line.uri.clone();
+    }
+
+    if is_loose_router(&*routes[0].0.uri.uri) {
+        return routes[0].0.uri.uri.clone();
+    }
+
+    let strict_router = routes.remove(0);
+    let request_uri = replace(&mut request.line.uri, strict_router.0.uri.uri);
+
+    routes.push(Route(Routing {
+        uri: NameAddr::uri(request_uri),
+        params: Params::new(),
+    }));
+
+    request.headers.remove(&Name::ROUTE);
+    request.headers.insert_type(&routes);
+
+    request.line.uri.clone()
+}
+
+/// Only SIP URIs without the `lr` parameter are strict routers
+fn is_loose_router(uri: &dyn Uri) -> bool {
+    match uri.downcast_ref::<SipUri>() {
+        Some(uri) => uri.uri_params.get("lr").is_some(),
+        None => true,
+    }
+}
+
 impl<L: Layer> Index<LayerKey<L>> for Endpoint {
     type Output = L;
 
@@ -594,11 +869,384 @@ impl<L> fmt::Debug for LayerKey<L> {
 
 impl<L> Clone for LayerKey<L> {
     fn clone(&self) -> Self {
-        Self {
-            index: self.index,
-            m: self.m,
-        }
+        *self
     }
 }
 
 impl<L> Copy for LayerKey<L> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transport::streaming::generalized::StreamingTransport;
+    use crate::transport::streaming::tcp::Tcp;
+    use crate::transport::udp::Udp;
+    use sip_types::host::HostPort;
+    use sip_types::uri::sip::UserPart;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    fn request(routes: &[&'static str]) -> Request {
+        let mut uri = SipUri::new(HostPort::host_name("example.com"));
+        uri.user_part = UserPart::User("UserB".into());
+
+        let mut request = Request::new(Method::INVITE, uri);
+
+        for route in routes {
+            request.headers.insert(Name::ROUTE, *route);
+        }
+
+        request
+    }
+
+    fn print<P: Print>(p: &P) -> String {
+        p.default_print_ctx().to_string()
+    }
+
+    fn routes(request: &Request) -> Vec<String> {
+        request
+            .headers
+            .get::<Vec<Route>>()
+            .unwrap_or_default()
+            .iter()
+            .map(print)
+            .collect()
+    }
+
+    #[test]
+    fn next_hop_without_route() {
+        let mut request = request(&[]);
+
+        assert_eq!(print(&next_hop(&mut request)), "sip:UserB@example.com");
+        assert_eq!(print(&request.line.uri), "sip:UserB@example.com");
+        assert!(routes(&request).is_empty());
+    }
+
+    #[test]
+    fn next_hop_loose_router() {
+        let mut request = request(&["<sip:proxy1;lr>", "<sip:proxy2>"]);
+
+        assert_eq!(print(&next_hop(&mut request)), "sip:proxy1;lr");
+        assert_eq!(print(&request.line.uri), "sip:UserB@example.com");
+        assert_eq!(routes(&request), ["<sip:proxy1;lr>", "<sip:proxy2>"]);
+    }
+
+    #[test]
+    fn next_hop_strict_router() {
+        // RFC 3261 Section 12.2.1.1
+        let mut request = request(&[
+            "<sip:proxy1>",
+            "<sip:proxy2>",
+            "<sip:proxy3;lr>",
+            "<sip:proxy4>",
+        ]);
+
+        assert_eq!(print(&next_hop(&mut request)), "sip:proxy1");
+        assert_eq!(print(&request.line.uri), "sip:proxy1");
+        assert_eq!(
+            routes(&request),
+            [
+                "<sip:proxy2>",
+                "<sip:proxy3;lr>",
+                "<sip:proxy4>",
+                "<sip:UserB@example.com>"
+            ]
+        );
+    }
+
+    #[test]
+    fn next_hop_single_strict_router() {
+        let mut request = request(&["<sip:proxy1>"]);
+
+        assert_eq!(print(&next_hop(&mut request)), "sip:proxy1");
+        assert_eq!(routes(&request), ["<sip:UserB@example.com>"]);
+    }
+
+    async fn tcp_endpoint() -> (Endpoint, SocketAddr) {
+        let mut builder = Endpoint::builder();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let bound = listener.local_addr().unwrap();
+        drop(listener);
+
+        Tcp.spawn(&mut builder, bound).await.unwrap();
+
+        (builder.build(), bound)
+    }
+
+    #[tokio::test]
+    async fn keep_alive_waits_for_pong() {
+        let (endpoint, _) = tcp_endpoint().await;
+
+        let peer = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri: SipUri = format!("sip:{};transport=tcp", peer.local_addr().unwrap())
+            .parse()
+            .unwrap();
+
+        let (transport, _) = endpoint
+            .transports()
+            .select(&endpoint, &uri, false)
+            .await
+            .unwrap();
+        let flow = transport.key();
+        let (mut stream, _) = peer.accept().await.unwrap();
+
+        // The transport is stored by its receive task
+        while endpoint.claim_transport(&flow).await.is_none() {
+            tokio::task::yield_now().await;
+        }
+
+        let pong = async {
+            let mut ping = [0; 4];
+            stream.read_exact(&mut ping).await.unwrap();
+            assert_eq!(&ping, b"\r\n\r\n");
+
+            stream.write_all(b"\r\n").await.unwrap();
+        };
+
+        let (result, _) = tokio::join!(endpoint.keep_alive(&flow, Duration::from_secs(5)), pong);
+        result.unwrap();
+
+        let error = endpoint
+            .keep_alive(&flow, Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, Code::FLOW_FAILED);
+    }
+
+    #[tokio::test]
+    async fn keep_alive_ping_is_answered() {
+        let (_endpoint, bound) = tcp_endpoint().await;
+
+        let mut stream = TcpStream::connect(bound).await.unwrap();
+        stream.write_all(b"\r\n\r\n").await.unwrap();
+
+        let mut pong = [0; 2];
+        stream.read_exact(&mut pong).await.unwrap();
+        assert_eq!(&pong, b"\r\n");
+    }
+
+    /// Reliable transport whose sends always fail
+    #[derive(Debug)]
+    struct FailingTransport {
+        bound: SocketAddr,
+        remote: SocketAddr,
+    }
+
+    impl fmt::Display for FailingTransport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "FAILING:remote={}", self.remote)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for FailingTransport {
+        fn name(&self) -> &'static str {
+            "TCP"
+        }
+
+        fn secure(&self) -> bool {
+            false
+        }
+
+        fn reliable(&self) -> bool {
+            true
+        }
+
+        fn bound(&self) -> SocketAddr {
+            self.bound
+        }
+
+        fn sent_by(&self) -> SocketAddr {
+            self.bound
+        }
+
+        fn direction(&self) -> Direction {
+            Direction::Outgoing(self.remote)
+        }
+
+        async fn send(&self, _: &[u8], _: SocketAddr) -> io::Result<()> {
+            Err(io::ErrorKind::ConnectionReset.into())
+        }
+    }
+
+    struct FailingFactory;
+
+    #[async_trait::async_trait]
+    impl Factory for FailingFactory {
+        fn name(&self) -> &'static str {
+            "TCP"
+        }
+
+        fn secure(&self) -> bool {
+            false
+        }
+
+        async fn create(
+            &self,
+            _: Endpoint,
+            addrs: &[SocketAddr],
+        ) -> io::Result<(TpHandle, SocketAddr)> {
+            let transport = FailingTransport {
+                bound: "127.0.0.1:5060".parse().unwrap(),
+                remote: addrs[0],
+            };
+
+            Ok((TpHandle::new(transport), addrs[0]))
+        }
+    }
+
+    /// Endpoint with a UDP transport and a path MTU of 600 bytes
+    async fn udp_endpoint(factory: Option<Arc<dyn Factory>>) -> Endpoint {
+        let mut builder = Endpoint::builder();
+        builder.set_path_mtu(600);
+
+        Udp::spawn(&mut builder, "127.0.0.1:0").await.unwrap();
+
+        if let Some(factory) = factory {
+            builder.add_transport_factory(factory);
+        }
+
+        builder.build()
+    }
+
+    /// Request over UDP to `target` with a body of `body_len` bytes
+    async fn outgoing(endpoint: &Endpoint, target: SocketAddr, body_len: usize) -> OutgoingRequest {
+        let uri: SipUri = format!("sip:{}", target).parse().unwrap();
+
+        let (transport, destination) = endpoint
+            .transports()
+            .select(endpoint, &uri, false)
+            .await
+            .unwrap();
+        assert_eq!(transport.name(), "UDP");
+
+        let mut request = Request::new(Method::MESSAGE, uri);
+        request
+            .headers
+            .insert_type(&Via::new("UDP", transport.sent_by(), "z9hG4bK-test"));
+        request.body = Bytes::from(vec![b'a'; body_len]);
+
+        OutgoingRequest {
+            msg: request,
+            parts: OutgoingParts {
+                transport,
+                destination,
+                buffer: Default::default(),
+            },
+        }
+    }
+
+    async fn receive_udp(socket: &tokio::net::UdpSocket) -> Bytes {
+        let mut buffer = vec![0; 2048];
+        let len = socket.recv(&mut buffer).await.unwrap();
+        buffer.truncate(len);
+        buffer.into()
+    }
+
+    #[tokio::test]
+    async fn small_request_stays_unreliable() {
+        let endpoint = udp_endpoint(None).await;
+        let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        // max unreliable size is 600 - 200 bytes
+        let mut message = outgoing(&endpoint, peer.local_addr().unwrap(), 200).await;
+        endpoint.send_outgoing_request(&mut message).await.unwrap();
+
+        assert!(message.parts.buffer.len() <= 400);
+        assert_eq!(message.parts.transport.name(), "UDP");
+        assert_eq!(receive_udp(&peer).await, message.parts.buffer);
+    }
+
+    #[tokio::test]
+    async fn large_request_switches_to_reliable() {
+        let mut builder = Endpoint::builder();
+        builder.set_path_mtu(600);
+        Udp::spawn(&mut builder, "127.0.0.1:0").await.unwrap();
+        Tcp.spawn(&mut builder, "127.0.0.1:0").await.unwrap();
+        let endpoint = builder.build();
+
+        let peer = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+        let mut message = outgoing(&endpoint, peer.local_addr().unwrap(), 500).await;
+        endpoint.send_outgoing_request(&mut message).await.unwrap();
+
+        let transport = &message.parts.transport;
+        assert_eq!(transport.name(), "TCP");
+        assert_eq!(message.parts.destination, [peer.local_addr().unwrap()]);
+
+        let via = message.msg.headers.get::<Via>().unwrap();
+        assert_eq!(via.transport, "TCP");
+        assert_eq!(via.sent_by, HostPort::from(transport.sent_by()));
+
+        let (mut stream, _) = peer.accept().await.unwrap();
+        let mut received = vec![0; message.parts.buffer.len()];
+        stream.read_exact(&mut received).await.unwrap();
+        assert_eq!(received, message.parts.buffer);
+
+        // The transport is stored by its receive task
+        while endpoint.claim_transport(&transport.key()).await.is_none() {
+            tokio::task::yield_now().await;
+        }
+
+        // The existing connection is reused
+        let mut second = outgoing(&endpoint, peer.local_addr().unwrap(), 500).await;
+        endpoint.send_outgoing_request(&mut second).await.unwrap();
+
+        assert_eq!(second.parts.transport.key(), transport.key());
+    }
+
+    #[tokio::test]
+    async fn large_request_without_reliable_transport() {
+        let endpoint = udp_endpoint(None).await;
+        let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let mut message = outgoing(&endpoint, peer.local_addr().unwrap(), 500).await;
+        endpoint.send_outgoing_request(&mut message).await.unwrap();
+
+        assert_eq!(message.parts.transport.name(), "UDP");
+        assert_eq!(message.msg.headers.get::<Via>().unwrap().transport, "UDP");
+        assert_eq!(receive_udp(&peer).await, message.parts.buffer);
+    }
+
+    #[tokio::test]
+    async fn failed_reliable_send_falls_back_to_unreliable() {
+        let endpoint = udp_endpoint(Some(Arc::new(FailingFactory))).await;
+        let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let mut message = outgoing(&endpoint, peer.local_addr().unwrap(), 500).await;
+        endpoint.send_outgoing_request(&mut message).await.unwrap();
+
+        assert_eq!(message.parts.transport.name(), "UDP");
+        assert_eq!(message.parts.destination, [peer.local_addr().unwrap()]);
+        assert_eq!(message.msg.headers.get::<Via>().unwrap().transport, "UDP");
+
+        // The compact form printed for UDP is sent
+        let received = receive_udp(&peer).await;
+        assert_eq!(received, message.parts.buffer);
+        assert!(received.windows(13).any(|w| w == b"v:SIP/2.0/UDP"));
+    }
+
+    #[tokio::test]
+    async fn select_secure_refuses_insecure_transports() {
+        let endpoint = udp_endpoint(Some(Arc::new(FailingFactory))).await;
+
+        for uri in ["sip:127.0.0.1:5060", "sip:127.0.0.1:5060;transport=tcp"] {
+            let uri: SipUri = uri.parse().unwrap();
+
+            endpoint
+                .transports()
+                .select(&endpoint, &uri, false)
+                .await
+                .unwrap();
+
+            let error = endpoint
+                .transports()
+                .select(&endpoint, &uri, true)
+                .await
+                .unwrap_err();
+            assert!(error
+                .to_string()
+                .contains("no suitable secure transport or factory found"));
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use sip_types::header::HeaderError;
+use sip_types::msg::ParseMessageError;
 use sip_types::Code;
 use std::error::Error as StdError;
 use std::str::Utf8Error;
@@ -72,6 +73,15 @@ impl From<HeaderError> for Error {
     }
 }
 
+impl From<ParseMessageError> for Error {
+    fn from(error: ParseMessageError) -> Self {
+        Self {
+            status: Code::BAD_REQUEST,
+            error: Some(anyhow::Error::new(error)),
+        }
+    }
+}
+
 impl From<Utf8Error> for Error {
     fn from(error: Utf8Error) -> Self {
         Self {
@@ -15,7 +15,7 @@ use sip_types::uri::Uri;
 use sip_types::{Headers, Method};
 use std::fmt;
 use transaction::TsxKey;
-use transport::MessageTpInfo;
+use transport::{MessageTpInfo, TpKey};
 
 #[macro_use]
 mod error;
@@ -31,10 +31,18 @@ pub use error::{Error, Result, WithStatus};
 pub use may_take::MayTake;
 
 /// Basic Response
+///
+/// Serialized like [`SipMessage`](sip_types::msg::SipMessage)
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Response {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_status_line"))]
     pub line: StatusLine,
     pub headers: Headers,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "sip_types::msg::serialize_body")
+    )]
     pub body: Bytes,
 }
 
@@ -44,12 +52,30 @@ impl fmt::Display for Response {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// Basic request
+///
+/// Serialized like [`SipMessage`](sip_types::msg::SipMessage), `secure` and `flow` are omitted.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Request {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_request_line"))]
     pub line: RequestLine,
     pub headers: Headers,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "sip_types::msg::serialize_body")
+    )]
     pub body: Bytes,
+
+    /// Require a secure transport (e.g. TLS) to send the request, even if the
+    /// request URI does not. Set for requests inside secure dialogs.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub secure: bool,
+
+    /// Send the request over this existing connection (flow) instead of selecting
+    /// a transport for the request's target (RFC 5626)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub flow: Option<TpKey>,
 }
 
 impl fmt::Display for Request {
@@ -58,6 +84,24 @@ impl fmt::Display for Request {
     }
 }
 
+/// Serializes the line like [`MessageLine::Request`](sip_types::msg::MessageLine::Request)
+#[cfg(feature = "serde")]
+fn serialize_request_line<S: serde::Serializer>(
+    line: &RequestLine,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_variant("MessageLine", 0, "request", line)
+}
+
+/// Serializes the line like [`MessageLine::Response`](sip_types::msg::MessageLine::Response)
+#[cfg(feature = "serde")]
+fn serialize_status_line<S: serde::Serializer>(
+    line: &StatusLine,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_variant("MessageLine", 1, "response", line)
+}
+
 impl Request {
     /// Create an empty request
     pub fn new<U>(method: Method, uri: U) -> Self
@@ -71,6 +115,8 @@ impl Request {
             },
             headers: Default::default(),
             body: Bytes::new(),
+            secure: false,
+            flow: None,
         }
     }
 }
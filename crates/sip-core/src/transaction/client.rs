@@ -73,6 +73,33 @@ impl ClientTsx {
         })
     }
 
+    /// Internal: Used by [ClientInvTsx::cancel](super::ClientInvTsx::cancel)
+    ///
+    /// The request must already contain the `Via` header of the INVITE it cancels.
+    pub(crate) async fn send_cancel(
+        endpoint: Endpoint,
+        mut request: OutgoingRequest,
+        tsx_key: TsxKey,
+    ) -> Result<Self> {
+        let registration = TsxRegistration::create(endpoint, tsx_key);
+
+        registration
+            .endpoint
+            .send_outgoing_request(&mut request)
+            .await?;
+
+        let timeout = Instant::now() + T1 * 64;
+
+        Ok(Self {
+            inner: Some(ClientTsxInner {
+                registration,
+                request,
+            }),
+            timeout,
+            state: State::Init,
+        })
+    }
+
     /// Receive one or more responses
     ///
     /// Must be called until a final response or error is returned.
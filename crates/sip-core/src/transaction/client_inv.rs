@@ -1,6 +1,6 @@
 use super::consts::T1;
 use super::key::TsxKey;
-use super::{TsxRegistration, TsxResponse};
+use super::{ClientTsx, TsxRegistration, TsxResponse};
 use crate::transport::{OutgoingParts, OutgoingRequest};
 use crate::Result;
 use crate::{Endpoint, Request};
@@ -140,6 +140,30 @@ impl ClientInvTsx {
         }
     }
 
+    /// Cancel the INVITE by sending a CANCEL request and return the transaction of the CANCEL.
+    ///
+    /// Should only be called after a provisional response has been received.
+    /// Returns `None` if the INVITE transaction already received a final response.
+    /// Responses to the INVITE (usually `487 Request Terminated`) must still be received
+    /// using [`ClientInvTsx::receive`].
+    pub async fn cancel(&self) -> Result<Option<ClientTsx>> {
+        let inner = match (&self.inner, &self.state) {
+            (Some(inner), State::Init | State::Proceeding) => inner,
+            _ => return Ok(None),
+        };
+
+        let cancel = create_cancel(&inner.request)?;
+
+        let tsx_key = TsxKey::client_with_branch(
+            &Method::CANCEL,
+            inner.registration.tsx_key.branch().clone(),
+        );
+
+        ClientTsx::send_cancel(inner.registration.endpoint.clone(), cancel, tsx_key)
+            .await
+            .map(Some)
+    }
+
     async fn handle_msg(&mut self, msg: TsxResponse) -> Result<Option<TsxResponse>> {
         match msg.line.code.kind() {
             CodeKind::Provisional => {
@@ -217,6 +241,48 @@ fn create_ack(
             },
             headers,
             body: Bytes::new(),
+            secure: request.msg.secure,
+            flow: request.msg.flow,
+        },
+        parts: OutgoingParts {
+            transport: request.parts.transport.clone(),
+            destination: request.parts.destination.clone(),
+            buffer: Default::default(),
+        },
+    })
+}
+
+fn create_cancel(request: &OutgoingRequest) -> Result<OutgoingRequest, HeaderError> {
+    let mut headers = Headers::with_capacity(6);
+
+    request.msg.headers.clone_into(&mut headers, Name::VIA)?;
+    request.msg.headers.clone_into(&mut headers, Name::FROM)?;
+    request.msg.headers.clone_into(&mut headers, Name::TO)?;
+    request
+        .msg
+        .headers
+        .clone_into(&mut headers, Name::CALL_ID)?;
+
+    let cseq = request.msg.headers.get::<CSeq>()?;
+
+    headers.insert_type(&CSeq {
+        cseq: cseq.cseq,
+        method: Method::CANCEL,
+    });
+
+    // The CANCEL must take the same route as the INVITE
+    let _ = request.msg.headers.clone_into(&mut headers, Name::ROUTE);
+
+    Ok(OutgoingRequest {
+        msg: Request {
+            line: RequestLine {
+                method: Method::CANCEL,
+                uri: request.msg.line.uri.clone(),
+            },
+            headers,
+            body: Bytes::new(),
+            secure: request.msg.secure,
+            flow: request.msg.flow,
         },
         parts: OutgoingParts {
             transport: request.parts.transport.clone(),
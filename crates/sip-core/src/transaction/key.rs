@@ -98,6 +98,16 @@ impl TsxKey {
         }))
     }
 
+    /// Create a client key using an existing branch, used by the CANCEL transaction of an INVITE
+    #[inline]
+    pub(crate) fn client_with_branch(method: &Method, branch: BytesStr) -> Self {
+        TsxKey(Repr::RFC3261(Rfc3261 {
+            role: Role::Client,
+            branch,
+            method: filter_method(method),
+        }))
+    }
+
     #[inline]
     pub fn branch(&self) -> &BytesStr {
         match &self.0 {
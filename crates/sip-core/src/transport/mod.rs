@@ -1,6 +1,5 @@
 use self::resolver::{Resolver, SystemResolver};
-use crate::{Endpoint, Error, Request, Response, Result, WithStatus};
-use anyhow::anyhow;
+use crate::{Endpoint, Request, Response, Result, WithStatus};
 use bytes::Bytes;
 use parking_lot::Mutex;
 use sip_types::host::Host;
@@ -16,6 +15,7 @@ use std::ops::Deref;
 use std::sync::Arc;
 use std::time::SystemTime;
 use std::{fmt, io};
+use tokio::sync::oneshot;
 
 pub mod resolver;
 pub mod streaming;
@@ -232,6 +232,9 @@ pub(crate) struct Transports {
     factories: Box<[Arc<dyn Factory>]>,
     transports: Mutex<HashMap<TpKey, TpHandle>>,
 
+    /// Waiters for CRLF keep-alive pongs of connection oriented transports
+    pongs: Mutex<HashMap<TpKey, oneshot::Sender<()>>>,
+
     resolver: Box<dyn Resolver>,
 }
 
@@ -259,15 +262,19 @@ impl Transports {
     }
 
     /// Will try to find or create a suitable transport the given Uri
+    ///
+    /// With `secure` only secure transports are selected, regardless of the uri.
     #[tracing::instrument(name = "select_transport", level = "trace", skip(self, endpoint))]
     pub(crate) async fn select(
         &self,
         endpoint: &Endpoint,
         uri: &dyn Uri,
+        secure: bool,
     ) -> Result<(TpHandle, Vec<SocketAddr>)> {
         log::trace!("select transport for {:?}", uri);
 
-        let info = uri.info();
+        let mut info = uri.info();
+        info.secure |= secure;
 
         // Resolve host_port to possible remote addresses
         let addresses = self.resolve_uri(&info).await.status(Code::BAD_GATEWAY)?;
@@ -320,10 +327,11 @@ impl Transports {
             }
         }
 
-        let mut last_err = io::Error::new(
-            io::ErrorKind::Other,
-            "no suitable transport or factory found",
-        );
+        let mut last_err = if info.secure {
+            io::Error::other("no suitable secure transport or factory found")
+        } else {
+            io::Error::other("no suitable transport or factory found")
+        };
 
         // Try to build new transport with a factory
         for factory in self.factories.iter() {
@@ -352,6 +360,54 @@ impl Transports {
         Err(last_err.into())
     }
 
+    /// Find or create a connection oriented transport to one of the given addresses
+    ///
+    /// Used for requests which are too large to be sent over a connectionless transport.
+    /// Secure factories are skipped, as the addresses were resolved for an insecure transport.
+    pub(crate) async fn select_reliable(
+        &self,
+        endpoint: &Endpoint,
+        addresses: &[SocketAddr],
+    ) -> io::Result<(TpHandle, SocketAddr)> {
+        // Try to find any idling transport to use
+        {
+            let transports = self.transports.lock();
+
+            for (_, transport) in transports.iter() {
+                let Direction::Outgoing(remote) = transport.direction() else {
+                    continue;
+                };
+
+                if transport.reliable() && !transport.secure() && addresses.contains(&remote) {
+                    log::trace!("selected transport: {}", transport);
+
+                    return Ok((transport.clone(), remote));
+                }
+            }
+        }
+
+        let mut last_err = io::Error::other("no suitable factory found");
+
+        for factory in self.factories.iter() {
+            if factory.secure() {
+                continue;
+            }
+
+            match factory.create(endpoint.clone(), addresses).await {
+                Ok((transport, remote)) => {
+                    log::trace!("created new transport {}", transport);
+
+                    return Ok((transport, remote));
+                }
+                Err(e) => {
+                    last_err = e;
+                }
+            }
+        }
+
+        Err(last_err)
+    }
+
     /// Try to claim a transport with that key from the endpoint.
     /// Sometimes a transport might still be in use from a previous transaction,
     /// this will wait until the transport is released again.
@@ -375,10 +431,35 @@ impl Transports {
         }
     }
 
+    /// Store a connection oriented transport, so it can be reused or claimed while it exists
+    pub fn insert_transport(&self, transport: TpHandle) {
+        log::trace!("insert transport {}", transport);
+
+        self.transports.lock().insert(transport.key(), transport);
+    }
+
     pub fn drop_transport(&self, tp_key: &TpKey) {
         log::trace!("drop transport {:?}", tp_key);
 
         self.transports.lock().remove(tp_key);
+        self.pongs.lock().remove(tp_key);
+    }
+
+    /// Register a waiter for the next CRLF keep-alive pong received over the given transport
+    pub fn await_pong(&self, tp_key: TpKey) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+
+        self.pongs.lock().insert(tp_key, tx);
+
+        rx
+    }
+
+    pub fn receive_pong(&self, tp_key: &TpKey) {
+        log::trace!("received keep-alive pong over {:?}", tp_key);
+
+        if let Some(tx) = self.pongs.lock().remove(tp_key) {
+            let _ = tx.send(());
+        }
     }
 }
 
@@ -408,6 +489,7 @@ impl TransportsBuilder {
             unmanaged: take(&mut self.unmanaged).into_boxed_slice(),
             factories: take(&mut self.factories).into_boxed_slice(),
             transports: Default::default(),
+            pongs: Default::default(),
             resolver: self
                 .resolver
                 .take()
@@ -415,19 +497,3 @@ impl TransportsBuilder {
         }
     }
 }
-
-fn parse_line(src: &Bytes, line: &str, headers: &mut Headers) -> Result<()> {
-    use sip_types::msg::Line;
-
-    match Line::parse(src)(line) {
-        Ok((_, line)) => {
-            headers.insert(line.name, line.value);
-
-            Ok(())
-        }
-        Err(_) => Err(Error {
-            status: Code::BAD_REQUEST,
-            error: Some(anyhow!("Invalid Header Line")),
-        }),
-    }
-}
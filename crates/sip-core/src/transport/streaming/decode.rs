@@ -1,9 +1,7 @@
-use crate::transport::parse_line;
 use crate::{Error, Result, WithStatus};
-use anyhow::anyhow;
 use bytes::{Bytes, BytesMut};
-use sip_types::msg::{MessageLine, PullParser};
-use sip_types::parse::{ParseCtx, Parser};
+use sip_types::msg::{MessageLine, PullParser, SipMessage};
+use sip_types::parse::Parser;
 use sip_types::{Code, Headers};
 use std::mem::replace;
 use std::str::from_utf8;
@@ -17,6 +15,16 @@ pub struct DecodedMessage {
     pub buffer: Bytes,
 }
 
+/// Item produced by the [`StreamingDecoder`]
+pub enum StreamingItem {
+    Message(DecodedMessage),
+    /// CRLF keep-alive ping (`CRLFCRLF`) which must be answered with a pong
+    /// ([RFC5626, Section 3.5.1](https://datatracker.ietf.org/doc/html/rfc5626#section-3.5.1))
+    Ping,
+    /// CRLF keep-alive pong (`CRLF`)
+    Pong,
+}
+
 pub struct StreamingDecoder {
     head_progress: usize,
     parser: Parser,
@@ -32,11 +40,15 @@ impl StreamingDecoder {
 }
 
 impl Decoder for StreamingDecoder {
-    type Item = DecodedMessage;
+    type Item = StreamingItem;
     type Error = Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // TODO check for CRLF and discard
+        if self.head_progress == 0 {
+            if let Some(keep_alive) = decode_keep_alive(src) {
+                return Ok(keep_alive);
+            }
+        }
 
         if src.len() > 4096 {
             // do not allow a message head larger than that
@@ -56,7 +68,11 @@ impl Decoder for StreamingDecoder {
                 let mut split = line.splitn(2, |&c| c == b':');
 
                 if let Some(name) = split.next() {
-                    if name.eq_ignore_ascii_case(b"content-length") || name.starts_with(b"l") {
+                    let name = name.trim_ascii_end();
+
+                    if name.eq_ignore_ascii_case(b"content-length")
+                        || name.eq_ignore_ascii_case(b"l")
+                    {
                         let value = split.next().status(Code::BAD_REQUEST)?;
                         let value = from_utf8(value)?;
 
@@ -100,46 +116,102 @@ impl Decoder for StreamingDecoder {
         // reset state
         self.head_progress = 0;
 
-        // reset parser
-        parser = PullParser::new(&src_bytes, 0);
+        let message = SipMessage::parse_with(&src_bytes, self.parser)?;
 
-        // Now properly parse the message
-        let mut message_line = None;
-        let mut headers = Headers::new();
-
-        for item in &mut parser {
-            let item = item.expect("got error when input was already checked");
-
-            let line = from_utf8(item)?;
+        // e.g. multiple Content-Length headers with different values
+        if message.body.len() != content_len {
+            return Err(Error::new(Code::BAD_REQUEST));
+        }
 
-            if message_line.is_none() {
-                let ctx = ParseCtx::new(&src_bytes, self.parser);
+        Ok(Some(StreamingItem::Message(DecodedMessage {
+            line: message.line,
+            headers: message.headers,
+            body: message.body,
+            buffer: src_bytes,
+        })))
+    }
+}
 
-                match MessageLine::parse(ctx)(line) {
-                    Ok((_, line)) => message_line = Some(line),
-                    Err(_) => {
-                        return Err(Error {
-                            status: Code::BAD_REQUEST,
-                            error: Some(anyhow!("Invalid Message Line")),
-                        })
-                    }
-                }
-            } else {
-                parse_line(&src_bytes, line, &mut headers)?;
+/// Decode CRLF keep-alives in front of messages
+///
+/// Returns `None` if `src` starts with a message, `Some(None)` if more data is required.
+fn decode_keep_alive(src: &mut BytesMut) -> Option<Option<StreamingItem>> {
+    loop {
+        match &src[..] {
+            [] | [b'\r'] | [b'\r', b'\n', b'\r'] => return Some(None),
+            [b'\r', b'\n', b'\r', b'\n', ..] => {
+                let _ = src.split_to(4);
+                return Some(Some(StreamingItem::Ping));
+            }
+            [b'\r', b'\n', ..] => {
+                let _ = src.split_to(2);
+                return Some(Some(StreamingItem::Pong));
             }
+            // Discard stray line breaks
+            [b'\r' | b'\n', ..] => {
+                let _ = src.split_to(1);
+            }
+            _ => return None,
         }
+    }
+}
 
-        let head_end = parser.head_end();
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decode(src: &[u8]) -> Vec<&'static str> {
+        let mut decoder = StreamingDecoder::new(Parser::default());
+        let mut src = BytesMut::from(src);
+        let mut items = vec![];
+
+        while let Some(item) = decoder.decode(&mut src).unwrap() {
+            items.push(match item {
+                StreamingItem::Message(_) => "message",
+                StreamingItem::Ping => "ping",
+                StreamingItem::Pong => "pong",
+            });
+        }
 
-        // slice remaining bytes
-        let body = src_bytes.slice(head_end..head_end + content_len);
-        assert_eq!(content_len, body.len());
+        items
+    }
 
-        Ok(Some(DecodedMessage {
-            line: message_line.status(Code::BAD_REQUEST)?,
-            headers,
-            body,
-            buffer: src_bytes,
-        }))
+    #[test]
+    fn ping() {
+        assert_eq!(decode(b"\r\n\r\n"), ["ping"]);
+        assert_eq!(decode(b"\r\n\r\n\r\n\r\n"), ["ping", "ping"]);
+    }
+
+    #[test]
+    fn pong() {
+        assert_eq!(decode(b"\r\n"), ["pong"]);
+    }
+
+    #[test]
+    fn incomplete_ping() {
+        let mut decoder = StreamingDecoder::new(Parser::default());
+        let mut src = BytesMut::from(&b"\r\n\r"[..]);
+
+        assert!(decoder.decode(&mut src).unwrap().is_none());
+
+        src.extend_from_slice(b"\n");
+
+        assert!(matches!(
+            decoder.decode(&mut src).unwrap(),
+            Some(StreamingItem::Ping)
+        ));
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn keep_alive_before_message() {
+        let message = b"\r\n\r\nOPTIONS sip:example.com SIP/2.0\r\nContent-Length: 0\r\n\r\n\r\n";
+
+        assert_eq!(decode(message), ["ping", "message", "pong"]);
+    }
+
+    #[test]
+    fn stray_line_feed() {
+        assert_eq!(decode(b"\n\r\n"), ["pong"]);
     }
 }
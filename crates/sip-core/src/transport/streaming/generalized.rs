@@ -1,4 +1,4 @@
-use super::decode::StreamingDecoder;
+use super::decode::{StreamingDecoder, StreamingItem};
 use crate::transport::{Direction, Factory, ReceivedMessage, TpHandle, TpKey, Transport};
 use crate::{Endpoint, EndpointBuilder};
 use std::net::SocketAddr;
@@ -155,7 +155,7 @@ where
         endpoint: Endpoint,
         addrs: &[SocketAddr],
     ) -> io::Result<(TpHandle, SocketAddr)> {
-        let mut last_err = io::Error::new(io::ErrorKind::Other, "empty addrs");
+        let mut last_err = io::Error::other("empty addrs");
 
         for &addr in addrs {
             log::trace!("trying to connect to {}", addr);
@@ -270,6 +270,8 @@ async fn receive_task<T>(
         },
     };
 
+    endpoint.transports().insert_transport(transport.clone());
+
     let _drop_guard = UnclaimedGuard {
         endpoint: &endpoint,
         // assume that the transport is incoming when we pass in the `Transport` itself
@@ -278,7 +280,20 @@ async fn receive_task<T>(
 
     loop {
         let message = match framed.next().await {
-            Some(Ok(item)) => item,
+            Some(Ok(StreamingItem::Message(message))) => message,
+            Some(Ok(StreamingItem::Ping)) => {
+                // Answer keep-alive pings with a pong (RFC 5626 Section 3.5.1)
+                if let Err(e) = transport.send(b"\r\n", remote).await {
+                    log::warn!("Failed to send keep-alive pong over {} {}", T::NAME, e);
+                    return;
+                }
+
+                continue;
+            }
+            Some(Ok(StreamingItem::Pong)) => {
+                endpoint.transports().receive_pong(&tp_key);
+                continue;
+            }
             Some(Err(e)) => {
                 log::warn!("An error occurred when reading {} stream {}", T::NAME, e);
                 return;
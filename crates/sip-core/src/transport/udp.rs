@@ -1,13 +1,8 @@
-use crate::transport::{parse_line, Direction, ReceivedMessage, TpHandle, Transport};
-use crate::{Endpoint, EndpointBuilder, Error, Result, WithStatus};
-use anyhow::anyhow;
+use crate::transport::{Direction, ReceivedMessage, TpHandle, Transport};
+use crate::{Endpoint, EndpointBuilder, Result};
 use bytes::Bytes;
-use sip_types::header::typed::ContentLength;
-use sip_types::msg::{MessageLine, PullParser};
-use sip_types::parse::ParseCtx;
-use sip_types::{Code, Headers};
+use sip_types::msg::SipMessage;
 use std::net::SocketAddr;
-use std::str::from_utf8;
 use std::sync::Arc;
 use std::{fmt, io};
 use tokio::net::{ToSocketAddrs, UdpSocket};
@@ -113,71 +108,7 @@ async fn handle_msg(
 
     let buf = Bytes::copy_from_slice(&bytes[..len]);
 
-    let mut parser = PullParser::new(&buf, 0);
-
-    let mut message_line = None;
-    let mut headers = Headers::new();
-
-    for item in &mut parser {
-        match item {
-            Ok(line) => {
-                let line = from_utf8(line)?;
-
-                if message_line.is_none() {
-                    let ctx = ParseCtx::new(&buf, endpoint.parser());
-
-                    match MessageLine::parse(ctx)(line) {
-                        Ok((_, line)) => {
-                            message_line = Some(line);
-                        }
-                        Err(_) => {
-                            return Err(Error {
-                                status: Code::BAD_REQUEST,
-                                error: Some(anyhow!("Invalid Request/Status Line")),
-                            });
-                        }
-                    }
-                } else {
-                    parse_line(&buf, line, &mut headers)?;
-                }
-            }
-            Err(_) => {
-                return Err(Error {
-                    status: Code::BAD_REQUEST,
-                    error: Some(anyhow!("Message Incomplete")),
-                });
-            }
-        }
-    }
-
-    let head_end = parser.head_end();
-
-    // look for optional content-length header
-    let body = match headers.get::<ContentLength>() {
-        Ok(len) => {
-            if len.0 == 0 {
-                Bytes::new()
-            } else if buf.len() >= head_end + len.0 {
-                buf.slice(head_end..head_end + len.0)
-            } else {
-                return Err(Error {
-                    status: Code::BAD_REQUEST,
-                    error: Some(anyhow!("Message Body Incomplete")),
-                });
-            }
-        }
-        Err(_) => {
-            log::trace!("no valid content-length given, guessing body length from udp frame");
-
-            if head_end == buf.len() {
-                Bytes::new()
-            } else {
-                buf.slice(head_end..)
-            }
-        }
-    };
-
-    let line = message_line.status(Code::BAD_REQUEST)?;
+    let message = SipMessage::parse_with(&buf, endpoint.parser())?;
 
     let msg = ReceivedMessage::new(
         remote,
@@ -186,9 +117,9 @@ async fn handle_msg(
         TpHandle::new(Udp {
             inner: inner.clone(),
         }),
-        line,
-        headers,
-        body,
+        message.line,
+        message.headers,
+        message.body,
     );
 
     endpoint.receive(msg);
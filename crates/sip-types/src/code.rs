@@ -7,6 +7,13 @@ type Repr = u16;
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Code(Repr);
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Code {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.0)
+    }
+}
+
 impl fmt::Debug for Code {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut tuple = f.debug_tuple("Code");
@@ -205,6 +212,10 @@ codes! {
     /// 410 Gone
     [410 => GONE, "Gone"];
 
+    /// [[RFC3903, Section 11.2.1](https://datatracker.ietf.org/doc/html/rfc3903#section-11.2.1)]
+    /// 412 Conditional Request Failed
+    [412 => CONDITIONAL_REQUEST_FAILED, "Conditional Request Failed"];
+
     /// [[RFC3621, Section 21.4.11](https://tools.ietf.org/html/rfc3261#section-21.4.11)]
     /// 413 Request Entity Too Large
     [413 => REQUEST_ENTITY_TOO_LARGE, "Request Entity Too Large"];
@@ -237,6 +248,18 @@ codes! {
     /// 423 Interval Too Brief
     [423 => INTERVAL_TOO_BRIEF, "Interval Too Brief"];
 
+    /// [[RFC5626, Section 11.5](https://datatracker.ietf.org/doc/html/rfc5626#section-11.5)]
+    /// 430 Flow Failed
+    [430 => FLOW_FAILED, "Flow Failed"];
+
+    /// [[RFC5626, Section 11.5](https://datatracker.ietf.org/doc/html/rfc5626#section-11.5)]
+    /// 439 First Hop Lacks Outbound Support
+    [439 => FIRST_HOP_LACKS_OUTBOUND_SUPPORT, "First Hop Lacks Outbound Support"];
+
+    /// [[RFC6086, Section 11.6](https://datatracker.ietf.org/doc/html/rfc6086#section-11.6)]
+    /// 469 Bad Info Package
+    [469 => BAD_INFO_PACKAGE, "Bad Info Package"];
+
     /// [[RFC3621, Section 21.4.18](https://tools.ietf.org/html/rfc3261#section-21.4.18)]
     /// 480 Temporarily Unavailable
     [480 => TEMPORARILY_UNAVAILABLE, "Temporarily Unavailable"];
@@ -273,6 +296,10 @@ codes! {
     /// 488 Not Acceptable Here
     [488 => NOT_ACCEPTABLE_HERE, "Not Acceptable Here"];
 
+    /// [[RFC6665, Section 8.3.1](https://datatracker.ietf.org/doc/html/rfc6665#section-8.3.1)]
+    /// 489 Bad Event
+    [489 => BAD_EVENT, "Bad Event"];
+
     /// [[RFC3621, Section 21.4.27](https://tools.ietf.org/html/rfc3261#section-21.4.27)]
     /// 491 Request Pending
     [491 => REQUEST_PENDING, "Request Pending"];
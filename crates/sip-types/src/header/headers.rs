@@ -2,6 +2,7 @@ use super::HeaderError;
 use crate::header::name::Name;
 use crate::header::Header;
 use crate::parse::Parser;
+use crate::print::PrintMode;
 use bytesstr::BytesStr;
 use std::iter::once;
 use std::iter::FromIterator;
@@ -12,11 +13,38 @@ use std::{fmt, slice};
 /// The headers are stored as [BytesStr] under its respective [Name].
 ///
 /// Internally it is a `Vec`-backed multimap to keep insertion order
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Headers {
     entries: Vec<Entry>,
 }
 
+/// Serialized as ordered list of `{"name", "value", "typed"}` objects, see [`SipMessage`](crate::msg::SipMessage)
+#[cfg(feature = "serde")]
+impl serde::Serialize for Headers {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Typed<'h>(&'h Name, &'h BytesStr);
+
+        impl serde::Serialize for Typed<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                super::typed::serialize_typed(self.0, self.1, serializer)
+            }
+        }
+
+        #[derive(serde::Serialize)]
+        struct Header<'h> {
+            name: &'h Name,
+            value: &'h BytesStr,
+            typed: Typed<'h>,
+        }
+
+        serializer.collect_seq(self.iter().map(|(name, value)| Header {
+            name,
+            value,
+            typed: Typed(name, value),
+        }))
+    }
+}
+
 impl Headers {
     /// Returns a new empty [Headers]
     #[inline]
@@ -263,7 +291,7 @@ impl Headers {
     /// Same as [Headers::take] but with custom parser.
     #[inline]
     pub fn take2<H: Header>(&mut self, parser: Parser) -> Option<H> {
-        self.try_take2(parser).map(Result::ok).flatten()
+        self.try_take2(parser).and_then(Result::ok)
     }
 
     /// Returns a parsed header `H` and removes it from the map.
@@ -461,6 +489,28 @@ impl Headers {
         len
     }
 
+    /// Returns a type implementing [`fmt::Display`] which prints the headers using the given [`PrintMode`]
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ezk_sip_types::{Headers, Name};
+    /// use ezk_sip_types::print::PrintMode;
+    ///
+    /// let mut headers = Headers::new();
+    ///
+    /// headers.insert(Name::CONTENT_LENGTH, "0");
+    ///
+    /// assert_eq!(headers.to_string(), "Content-Length: 0\r\n");
+    /// assert_eq!(headers.display(PrintMode::Compact).to_string(), "l:0\r\n");
+    /// ```
+    pub fn display(&self, mode: PrintMode) -> impl fmt::Display + '_ {
+        DisplayHeaders {
+            headers: self,
+            mode,
+        }
+    }
+
     /// Returns an iterator over [Name] and [BytesStr] pairs in the map.
     pub fn iter(&self) -> impl Iterator<Item = (&Name, &BytesStr)> + '_ {
         struct Iter<'s> {
@@ -513,8 +563,22 @@ impl Headers {
 
 impl fmt::Display for Headers {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (name, value) in self.iter() {
-            write!(f, "{}: {}\r\n", name.as_print_str(), value)?;
+        self.display(PrintMode::Normal).fmt(f)
+    }
+}
+
+struct DisplayHeaders<'h> {
+    headers: &'h Headers,
+    mode: PrintMode,
+}
+
+impl fmt::Display for DisplayHeaders<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in self.headers.iter() {
+            match self.mode {
+                PrintMode::Normal => write!(f, "{}: {}\r\n", name.as_print_str(), value)?,
+                PrintMode::Compact => write!(f, "{}:{}\r\n", name.as_compact_str(), value)?,
+            }
         }
 
         Ok(())
@@ -529,13 +593,13 @@ impl Extend<(Name, BytesStr)> for Headers {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 struct Entry {
     name: Name,
     values: Values,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 enum Values {
     One(BytesStr),
     Many(Vec<BytesStr>),
@@ -610,7 +674,7 @@ fn remove_where<T, F>(vec: &mut Vec<T>, f: F) -> Option<T>
 where
     F: Fn(&T) -> bool,
 {
-    vec.iter().position(|item| f(item)).map(|i| vec.remove(i))
+    vec.iter().position(f).map(|i| vec.remove(i))
 }
 
 #[cfg(test)]
@@ -115,6 +115,7 @@ macro_rules! impl_wrap_header {
     };
     ($(#[$meta:meta])* $to_parse:ty, $to_wrap:ty, $wrapper:ident, $kind:ident, $name:expr) => {
         #[derive(Debug, Clone)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
         $(#[$meta])*
         pub struct $wrapper(pub $to_wrap);
 
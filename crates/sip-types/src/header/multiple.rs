@@ -206,4 +206,15 @@ mod test {
         assert!(rem.is_none());
         assert!(multiple.is_empty());
     }
+
+    #[test]
+    fn single_multiple_parse() {
+        let (rem, multiple) =
+            Vec::<SingleHeader>::decode(Default::default(), &mut STRINGS[2..].iter()).unwrap();
+
+        assert!(rem.is_none());
+
+        assert_eq!(multiple.len(), 1);
+        assert_eq!(multiple[0].0, 6);
+    }
 }
@@ -6,6 +6,13 @@ use bytesstr::BytesStr;
 #[derive(Debug, Clone)]
 pub struct Name(Repr);
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Name {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_print_str())
+    }
+}
+
 impl Name {
     /// Creates a new custom Name that is not implemented as constant.
     ///
@@ -104,6 +111,14 @@ macro_rules! header_names {
                 }
             }
 
+            /// Returns the compact form of the name (e.g. `v` for `Via`), or the print string if there is none
+            pub fn as_compact_str(&self) -> &str {
+                self.as_parse_strs()
+                    .and_then(|strs| strs.iter().find(|s| s.len() == 1))
+                    .copied()
+                    .unwrap_or_else(|| self.as_print_str())
+            }
+
             pub const fn as_parse_strs(&self) -> Option<&[&str]> {
                 match &self.0 {
                     $(
@@ -133,6 +148,9 @@ header_names! {
     /// [[RFC3621, Section 20.5](https://tools.ietf.org/html/rfc3261#section-20.5)]
     "Allow",                Allow,              ["allow"],                  ALLOW;
 
+    /// [[RFC6665, Section 8.2.2](https://datatracker.ietf.org/doc/html/rfc6665#section-8.2.2)]
+    "Allow-Events",         AllowEvents,        ["allow-events", "u"],      ALLOW_EVENTS;
+
     /// [[RFC3621, Section 20.6](https://tools.ietf.org/html/rfc3261#section-20.6)]
     "Authentication-Info",  AuthenticationInfo, ["authentication-info"],    AUTHENTICATION_INFO;
 
@@ -169,18 +187,33 @@ header_names! {
     /// [[RFC3621, Section 20.17](https://tools.ietf.org/html/rfc3261#section-20.17)]
     "Date",                 Date,               ["date"],                   DATE;
 
+    /// [[RFC5806, Section 4](https://datatracker.ietf.org/doc/html/rfc5806#section-4)]
+    "Diversion",            Diversion,          ["diversion"],              DIVERSION;
+
     /// [[RFC3621, Section 20.18](https://tools.ietf.org/html/rfc3261#section-20.18)]
     "Error-Info",           ErrorInfo,          ["error-info"],             ERROR_INFO;
 
+    /// [[RFC6665, Section 8.2.1](https://datatracker.ietf.org/doc/html/rfc6665#section-8.2.1)]
+    "Event",                Event,              ["event", "o"],             EVENT;
+
     /// [[RFC3621, Section 20.19](https://tools.ietf.org/html/rfc3261#section-20.19)]
     "Expires",              Expires,            ["expires"],                EXPIRES;
 
+    /// [[RFC5626, Section 11](https://datatracker.ietf.org/doc/html/rfc5626#section-11)]
+    "Flow-Timer",           FlowTimer,          ["flow-timer"],             FLOW_TIMER;
+
     /// [[RFC3621, Section 20.20](https://tools.ietf.org/html/rfc3261#section-20.20)]
     "From",                 From,               ["from", "f"],              FROM;
 
+    /// [[RFC7044, Section 4](https://datatracker.ietf.org/doc/html/rfc7044#section-4)]
+    "History-Info",         HistoryInfo,        ["history-info"],           HISTORY_INFO;
+
     /// [[RFC3621, Section 20.21](https://tools.ietf.org/html/rfc3261#section-20.21)]
     "In-Reply-To",          InReplyTo,          ["in-reply-to"],            IN_REPLY_TO;
 
+    /// [[RFC6086, Section 8.2.1](https://datatracker.ietf.org/doc/html/rfc6086#section-8.2.1)]
+    "Info-Package",         InfoPackage,        ["info-package"],           INFO_PACKAGE;
+
     /// [[RFC3621, Section 20.22](https://tools.ietf.org/html/rfc3261#section-20.22)]
     "Max-Forwards",         MaxForwards,        ["max-forwards"],           MAX_FORWARDS;
 
@@ -196,9 +229,21 @@ header_names! {
     /// [[RFC3621, Section 20.25](https://tools.ietf.org/html/rfc3261#section-20.25)]
     "Organization",         Organization,       ["organization"],           ORGANIZATION;
 
+    /// [[RFC3325, Section 9.1](https://datatracker.ietf.org/doc/html/rfc3325#section-9.1)]
+    "P-Asserted-Identity",  PAssertedIdentity,  ["p-asserted-identity"],    P_ASSERTED_IDENTITY;
+
+    /// [[RFC3325, Section 9.2](https://datatracker.ietf.org/doc/html/rfc3325#section-9.2)]
+    "P-Preferred-Identity", PPreferredIdentity, ["p-preferred-identity"],   P_PREFERRED_IDENTITY;
+
+    /// [[RFC3327, Section 4](https://datatracker.ietf.org/doc/html/rfc3327#section-4)]
+    "Path",                 Path,               ["path"],                   PATH;
+
     /// [[RFC3621, Section 20.26](https://tools.ietf.org/html/rfc3261#section-20.26)]
     "Priority",             Priority,           ["priority"],               PRIORITY;
 
+    /// [[RFC3323, Section 4.2](https://datatracker.ietf.org/doc/html/rfc3323#section-4.2)]
+    "Privacy",              Privacy,            ["privacy"],                PRIVACY;
+
     /// [[RFC3621, Section 20.27](https://tools.ietf.org/html/rfc3261#section-20.27)]
     "Proxy-Authenticate",   ProxyAuthenticate,  ["proxy-authenticate"],     PROXY_AUTHENTICATE;
 
@@ -211,6 +256,12 @@ header_names! {
      /// [[RFC3262, Section 20.34](https://datatracker.ietf.org/doc/html/rfc3262#section-7.2)]
     "RAck",                 RAck,               ["rack"],                   RACK;
 
+    /// [[RFC3326, Section 2](https://datatracker.ietf.org/doc/html/rfc3326#section-2)]
+    "Reason",               Reason,             ["reason"],                 REASON;
+
+    /// [[RFC6086, Section 8.2.2](https://datatracker.ietf.org/doc/html/rfc6086#section-8.2.2)]
+    "Recv-Info",            RecvInfo,           ["recv-info"],              RECV_INFO;
+
     /// [[RFC3621, Section 20.30](https://tools.ietf.org/html/rfc3261#section-20.30)]
     "Record-Route",         RecordRoute,        ["record-route"],           RECORD_ROUTE;
 
@@ -235,6 +286,15 @@ header_names! {
     /// [[RFC3621, Section 20.35](https://tools.ietf.org/html/rfc3261#section-20.35)]
     "Server",               Server,             ["server"],                 SERVER;
 
+    /// [[RFC3608, Section 5](https://datatracker.ietf.org/doc/html/rfc3608#section-5)]
+    "Service-Route",        ServiceRoute,       ["service-route"],          SERVICE_ROUTE;
+
+    /// [[RFC3903, Section 11.3.1](https://datatracker.ietf.org/doc/html/rfc3903#section-11.3.1)]
+    "SIP-ETag",             SipETag,            ["sip-etag"],               SIP_ETAG;
+
+    /// [[RFC3903, Section 11.3.2](https://datatracker.ietf.org/doc/html/rfc3903#section-11.3.2)]
+    "SIP-If-Match",         SipIfMatch,         ["sip-if-match"],           SIP_IF_MATCH;
+
     /// [[RFC4028, Section 20.35](https://datatracker.ietf.org/doc/html/rfc4028#section-4)]
     "Session-Expires",      SessionExpires,     ["session-expires", "x"],        SESSION_EXPIRES;
 
@@ -11,3 +11,63 @@ impl_wrap_header!(
     CSV,
     Name::ACCEPT
 );
+
+impl_wrap_header!(
+    /// `Accept-Encoding` header, contains only one accepted encoding including its parameters.
+    /// To get all accepted encodings use [`Vec`].
+    Text<CsvTextSpec>,
+    BytesStr,
+    AcceptEncoding,
+    CSV,
+    Name::ACCEPT_ENCODING
+);
+
+impl_wrap_header!(
+    /// `Accept-Language` header, contains only one accepted language including its parameters.
+    /// To get all accepted languages use [`Vec`].
+    Text<CsvTextSpec>,
+    BytesStr,
+    AcceptLanguage,
+    CSV,
+    Name::ACCEPT_LANGUAGE
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::Header;
+    use crate::print::AppendCtx;
+    use std::iter::once;
+
+    #[test]
+    fn accept_encoding_multiple() {
+        let input = BytesStr::from_static("gzip, identity;q=0.5");
+
+        let (rem, encodings) =
+            Vec::<AcceptEncoding>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(encodings.len(), 2);
+        assert_eq!(encodings[0].0, "gzip");
+        assert_eq!(encodings[1].0, "identity;q=0.5");
+    }
+
+    #[test]
+    fn accept_language_multiple() {
+        let input = BytesStr::from_static("da, en-gb;q=0.8, en;q=0.7");
+
+        let (rem, languages) =
+            Vec::<AcceptLanguage>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(languages.len(), 3);
+        assert_eq!(languages[1].0, "en-gb;q=0.8");
+    }
+
+    #[test]
+    fn accept_language_print() {
+        let language = AcceptLanguage::from("en");
+
+        assert_eq!(language.default_print_ctx().to_string(), "en");
+    }
+}
@@ -17,9 +17,10 @@ use std::fmt;
 ///
 /// Has some special printing rules. Might not be hardcoded in the future.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AuthParam {
-    name: BytesStr,
-    value: BytesStr,
+    pub name: BytesStr,
+    pub value: BytesStr,
 }
 
 impl fmt::Display for AuthParam {
@@ -27,7 +28,8 @@ impl fmt::Display for AuthParam {
         write!(f, "{}=", self.name)?;
 
         match self.name.as_ref() {
-            "realm" | "domain" | "nonce" | "opaque" | "qop" => {
+            "realm" | "domain" | "nonce" | "opaque" | "qop" | "username" | "uri" | "response"
+            | "cnonce" => {
                 write!(f, "\"{}\"", self.value)?;
             }
 
@@ -42,6 +44,17 @@ impl fmt::Display for AuthParam {
 }
 
 impl AuthParam {
+    pub fn new<N, V>(name: N, value: V) -> Self
+    where
+        N: Into<BytesStr>,
+        V: Into<BytesStr>,
+    {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+
     pub fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
         move |i| {
             map(
@@ -61,6 +74,7 @@ impl AuthParam {
 
 /// Implementation for all Auth kind headers.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Auth {
     pub token: BytesStr,
     pub params: Vec<AuthParam>,
@@ -183,4 +197,21 @@ mod test {
             "Digest some=param, realm=\"example.com\""
         );
     }
+
+    #[test]
+    fn auth_print_credentials() {
+        let auth = Authorization(Auth {
+            token: "Digest".into(),
+            params: vec![
+                AuthParam::new("username", "alice"),
+                AuthParam::new("uri", "sip:example.com"),
+                AuthParam::new("nc", "00000001"),
+            ],
+        });
+
+        assert_eq!(
+            auth.default_print_ctx().to_string(),
+            "Digest username=\"alice\", uri=\"sip:example.com\", nc=00000001"
+        );
+    }
 }
@@ -1,4 +1,5 @@
 use crate::header::name::Name;
+use crate::parse::text::{CsvTextSpec, Text};
 use crate::parse::ParseCtx;
 use crate::print::{Print, PrintCtx};
 use bytesstr::BytesStr;
@@ -7,6 +8,7 @@ use std::fmt;
 
 /// `Call-ID`header
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
 pub struct CallID(pub BytesStr);
 
 impl CallID {
@@ -31,10 +33,22 @@ impl Print for CallID {
 
 __impl_header!(CallID, Single, Name::CALL_ID);
 
+impl_wrap_header!(
+    /// `In-Reply-To` header, contains only one Call-ID of a call this call references.
+    /// To get all Call-IDs use [`Vec`].
+    Text<CsvTextSpec>,
+    BytesStr,
+    InReplyTo,
+    CSV,
+    Name::IN_REPLY_TO
+);
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::header::Header;
     use crate::print::AppendCtx;
+    use std::iter::once;
 
     #[test]
     fn call_id() {
@@ -58,6 +72,19 @@ mod test {
         assert_eq!(cid.0, "«SomeTestBytes»")
     }
 
+    #[test]
+    fn in_reply_to_multiple() {
+        let input = BytesStr::from_static("70710@saturn.bell-tel.com, 17320@saturn.bell-tel.com");
+
+        let (rem, call_ids) =
+            Vec::<InReplyTo>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(call_ids.len(), 2);
+        assert_eq!(call_ids[0].0, "70710@saturn.bell-tel.com");
+        assert_eq!(call_ids[1].0, "17320@saturn.bell-tel.com");
+    }
+
     #[test]
     fn call_id_print() {
         let cid = CallID::new("abc123");
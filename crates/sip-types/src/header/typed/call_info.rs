@@ -0,0 +1,146 @@
+use crate::header::name::Name;
+use crate::parse::ParseCtx;
+use crate::print::{AppendCtx, Print, PrintCtx};
+use crate::uri::params::{Params, CPS};
+use crate::uri::Uri;
+use bytesstr::BytesStr;
+use internal::ws;
+use nom::bytes::complete::tag;
+use nom::combinator::map;
+use nom::sequence::delimited;
+use nom::IResult;
+use std::fmt;
+
+/// `Call-Info` header. Contains only one info. To get all infos use [`Vec`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CallInfo {
+    /// URI of the additional information, usually an absolute URI (e.g. `http:`)
+    pub uri: Box<dyn Uri>,
+    pub params: Params<CPS>,
+}
+
+impl CallInfo {
+    pub fn new<U>(uri: U) -> Self
+    where
+        U: Into<Box<dyn Uri>>,
+    {
+        Self {
+            uri: uri.into(),
+            params: Params::new(),
+        }
+    }
+
+    impl_with_params!(params, with_key_param, with_value_param);
+
+    /// Returns the value of the `purpose` parameter (e.g. `icon`, `info` or `card`)
+    pub fn purpose(&self) -> Option<&BytesStr> {
+        self.params.get_val("purpose")
+    }
+
+    pub fn parse<'p>(ctx: ParseCtx<'p>) -> impl Fn(&'p str) -> IResult<&'p str, Self> + 'p {
+        move |i| {
+            map(
+                ws((
+                    delimited(tag("<"), ctx.parse_uri(), tag(">")),
+                    Params::<CPS>::parse(ctx),
+                )),
+                |(uri, params)| CallInfo { uri, params },
+            )(i)
+        }
+    }
+}
+
+impl Print for CallInfo {
+    fn print(&self, f: &mut fmt::Formatter<'_>, ctx: PrintCtx<'_>) -> fmt::Result {
+        write!(f, "<{}>{}", self.uri.print_ctx(ctx), self.params)
+    }
+}
+
+__impl_header!(CallInfo, CSV, Name::CALL_INFO);
+
+impl_wrap_header!(
+    /// `Alert-Info` header, wraps [`CallInfo`] whose grammar it shares.
+    /// Contains only one alert. To get all alerts use [`Vec`].
+    CallInfo,
+    AlertInfo,
+    CSV,
+    Name::ALERT_INFO
+);
+
+impl_wrap_header!(
+    /// `Error-Info` header, wraps [`CallInfo`] whose grammar it shares.
+    /// Contains only one info. To get all infos use [`Vec`].
+    CallInfo,
+    ErrorInfo,
+    CSV,
+    Name::ERROR_INFO
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::Header;
+    use crate::uri::absolute::AbsoluteUri;
+    use crate::uri::sip::SipUri;
+    use std::iter::once;
+
+    #[test]
+    fn call_info_http() {
+        let input = BytesStr::from_static(
+            "<http://wwww.example.com/alice/photo.jpg> ;purpose=icon, <http://www.example.com/alice/> ;purpose=info",
+        );
+
+        let (rem, infos) = Vec::<CallInfo>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].purpose().unwrap(), "icon");
+        assert_eq!(infos[1].purpose().unwrap(), "info");
+
+        let uri: &AbsoluteUri = infos[1].uri.downcast_ref().unwrap();
+        assert_eq!(uri.scheme_specific, "//www.example.com/alice/");
+    }
+
+    #[test]
+    fn alert_info() {
+        let input = BytesStr::from_static("<http://www.example.com/sounds/moo.wav>");
+
+        let (rem, alerts) =
+            Vec::<AlertInfo>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(alerts.len(), 1);
+
+        let uri: &AbsoluteUri = alerts[0].uri.downcast_ref().unwrap();
+        assert_eq!(uri.scheme, "http");
+    }
+
+    #[test]
+    fn error_info_sip() {
+        let input = BytesStr::from_static("<sip:not-in-service-recording@atlanta.com>");
+
+        let (rem, infos) = Vec::<ErrorInfo>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert!(infos[0].uri.downcast_ref::<SipUri>().is_some());
+        assert_eq!(
+            infos[0].default_print_ctx().to_string(),
+            "<sip:not-in-service-recording@atlanta.com>"
+        );
+    }
+
+    #[test]
+    fn call_info_print() {
+        let info = CallInfo::new(AbsoluteUri::new(
+            "http",
+            "//www.example.com/alice/photo.jpg",
+        ))
+        .with_value_param("purpose", "icon");
+
+        assert_eq!(
+            info.default_print_ctx().to_string(),
+            "<http://www.example.com/alice/photo.jpg>;purpose=icon"
+        );
+    }
+}
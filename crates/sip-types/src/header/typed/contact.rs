@@ -10,6 +10,7 @@ use std::fmt;
 
 /// `Contact` header
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Contact {
     pub uri: NameAddr,
     pub params: Params<CPS>,
@@ -40,12 +41,23 @@ impl Contact {
 impl Print for Contact {
     fn print(&self, f: &mut fmt::Formatter<'_>, mut ctx: PrintCtx<'_>) -> fmt::Result {
         ctx.uri = Some(UriContext::Contact);
-        write!(f, "{}{}", self.uri.print_ctx(ctx), self.params)?;
+        write!(
+            f,
+            "{}{}",
+            self.uri.print_ctx(ctx),
+            self.params.quoted_print_if(is_quoted_param)
+        )?;
         Ok(())
     }
 }
 
-__impl_header!(Contact, Single, Name::CONTACT);
+/// Contact params whose values are quoted strings: feature tags like `+sip.instance`
+/// (RFC 3840) and the GRUUs assigned by a registrar (RFC 5627)
+fn is_quoted_param(name: &str) -> bool {
+    name.starts_with('+') || name == "pub-gruu" || name == "temp-gruu"
+}
+
+__impl_header!(Contact, CSV, Name::CONTACT);
 
 #[cfg(test)]
 mod test {
@@ -70,6 +82,22 @@ mod test {
         assert_eq!(expires, "30");
     }
 
+    #[test]
+    fn contact_multiple() {
+        let mut headers = crate::Headers::new();
+        headers.insert(
+            Name::CONTACT,
+            "<sip:alice@192.0.2.4>;expires=60, \"Alice\" <sip:alice@example.com>",
+        );
+        headers.insert(Name::CONTACT, "<sip:alice@192.0.2.5>");
+
+        let contacts = headers.get::<Vec<Contact>>().unwrap();
+
+        assert_eq!(contacts.len(), 3);
+        assert_eq!(contacts[0].params.get_val("expires").unwrap(), "60");
+        assert_eq!(contacts[1].uri.name.as_deref(), Some("Alice"));
+    }
+
     #[test]
     fn contact_print() {
         let contact = Contact::new(NameAddr::new(
@@ -83,4 +111,49 @@ mod test {
             "\"Bob\"<sip:example.com>;expires=90"
         )
     }
+
+    #[test]
+    fn contact_print_quoted_param() {
+        let contact = Contact::new(NameAddr::uri(SipUri::new(HostPort::host_name(
+            "example.com",
+        ))))
+        .with_value_param(
+            "+sip.instance",
+            "<urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6>",
+        );
+
+        let printed = contact.default_print_ctx().to_string();
+
+        assert_eq!(
+            printed,
+            "<sip:example.com>;+sip.instance=\"<urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6>\""
+        );
+
+        let input = BytesStr::from(printed);
+        let (rem, contact) = Contact::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(
+            contact.params.get_val("+sip.instance").unwrap(),
+            "<urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6>"
+        );
+    }
+
+    #[test]
+    fn contact_print_unquoted_param() {
+        let contact = Contact::new(NameAddr::uri(SipUri::new(HostPort::host_name(
+            "example.com",
+        ))))
+        .with_value_param("x-note", "<a b>")
+        .with_value_param(
+            "pub-gruu",
+            "sip:alice@example.com;gr=urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6",
+        );
+
+        // Only feature tags and GRUUs are quoted, other values keep being percent-encoded
+        assert_eq!(
+            contact.default_print_ctx().to_string(),
+            "<sip:example.com>;x-note=%3Ca%20b%3E;pub-gruu=\"sip:alice@example.com;gr=urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6\""
+        );
+    }
 }
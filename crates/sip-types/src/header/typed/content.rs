@@ -1,16 +1,21 @@
 use crate::header::name::Name;
-use crate::parse::text::{CsvTextSpec, Text};
-use crate::parse::ParseCtx;
+use crate::parse::{token, ParseCtx};
 use crate::print::{Print, PrintCtx};
+use crate::uri::params::{Params, CPS};
 use bytesstr::BytesStr;
+use internal::ws;
+use nom::bytes::complete::{tag, take_while1};
 use nom::combinator::map;
 use nom::IResult;
 use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
 
 // Content Length
 
 decl_from_str_header!(
     /// `Content-Length` header
+    #[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
     ContentLength,
     usize,
     Single,
@@ -19,24 +24,210 @@ decl_from_str_header!(
 
 // Content-Type
 
-/// `Content-Type` header
-#[derive(Debug, Clone, PartialEq)]
-pub struct ContentType(pub BytesStr);
+/// `Content-Type` header, contains a media type with parameters (e.g. `multipart/mixed;boundary=xyz`)
+///
+/// Type, subtype and parameter names are compared case-insensitive.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ContentType {
+    /// Top level media type (e.g. `application`)
+    pub main_type: BytesStr,
+    /// Media subtype (e.g. `sdp`)
+    pub sub_type: BytesStr,
+    pub params: Params<CPS>,
+}
 
 impl ContentType {
+    pub fn new<T, S>(main_type: T, sub_type: S) -> Self
+    where
+        T: Into<BytesStr>,
+        S: Into<BytesStr>,
+    {
+        Self {
+            main_type: main_type.into(),
+            sub_type: sub_type.into(),
+            params: Params::new(),
+        }
+    }
+
+    /// Create a content type from a static `type/subtype` string
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string does not contain a `/`
+    pub fn from_static(media_type: &'static str) -> Self {
+        let (main_type, sub_type) = media_type
+            .split_once('/')
+            .expect("media type must contain a slash");
+
+        Self::new(
+            BytesStr::from_static(main_type),
+            BytesStr::from_static(sub_type),
+        )
+    }
+
+    impl_with_params!(params, with_key_param, with_value_param);
+
+    /// Returns if the media type matches the given `type/subtype` string, ignoring all parameters
+    pub fn matches(&self, media_type: &str) -> bool {
+        match media_type.split_once('/') {
+            Some((main_type, sub_type)) => {
+                self.main_type.eq_ignore_ascii_case(main_type)
+                    && self.sub_type.eq_ignore_ascii_case(sub_type)
+            }
+            None => false,
+        }
+    }
+
+    /// Returns if the top level media type is `multipart`
+    pub fn is_multipart(&self) -> bool {
+        self.main_type.eq_ignore_ascii_case("multipart")
+    }
+
+    /// Returns the value of the parameter, the name is compared case-insensitive
+    pub fn param(&self, name: &str) -> Option<&BytesStr> {
+        self.params
+            .iter()
+            .find(|param| param.name.eq_ignore_ascii_case(name))
+            .and_then(|param| param.value.as_ref())
+    }
+
+    /// Returns the value of the `boundary` parameter of multipart media types
+    pub fn boundary(&self) -> Option<&BytesStr> {
+        self.param("boundary")
+    }
+
+    /// Returns the value of the `charset` parameter
+    pub fn charset(&self) -> Option<&BytesStr> {
+        self.param("charset")
+    }
+
     pub fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
-        move |i| map(Text::<CsvTextSpec>::parse(ctx), ContentType)(i)
+        move |i| {
+            map(
+                ws((
+                    take_while1(token),
+                    tag("/"),
+                    take_while1(token),
+                    Params::<CPS>::parse(ctx),
+                )),
+                |(main_type, _, sub_type, params)| ContentType {
+                    main_type: BytesStr::from_parse(ctx.src, main_type),
+                    sub_type: BytesStr::from_parse(ctx.src, sub_type),
+                    params,
+                },
+            )(i)
+        }
+    }
+}
+
+impl PartialEq for ContentType {
+    fn eq(&self, other: &Self) -> bool {
+        self.main_type.eq_ignore_ascii_case(&other.main_type)
+            && self.sub_type.eq_ignore_ascii_case(&other.sub_type)
+            && self.params.iter().count() == other.params.iter().count()
+            && self.params.iter().all(|param| {
+                other.params.iter().any(|other_param| {
+                    other_param.name.eq_ignore_ascii_case(&param.name)
+                        && other_param.value == param.value
+                })
+            })
     }
 }
 
 impl Print for ContentType {
     fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
-        f.write_str(&self.0)
+        write!(
+            f,
+            "{}/{}{}",
+            self.main_type,
+            self.sub_type,
+            self.params.quoted_print()
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid content type")]
+pub struct InvalidContentType(());
+
+impl FromStr for ContentType {
+    type Err = InvalidContentType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = BytesStr::from(s);
+
+        let ctx = ParseCtx::default(&s);
+
+        let res = Self::parse(ctx)(s.as_ref())
+            .map_err(|_| InvalidContentType(()))
+            .and_then(|(rem, content_type)| {
+                if rem.is_empty() {
+                    Ok(content_type)
+                } else {
+                    Err(InvalidContentType(()))
+                }
+            });
+
+        res
     }
 }
 
 __impl_header!(ContentType, Single, Name::CONTENT_TYPE);
 
+// Content-Disposition
+
+/// `Content-Disposition` header (e.g. `session;handling=required`)
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ContentDisposition {
+    /// Disposition type, e.g. `session`, `render`, `icon` or `alert`
+    pub disposition: BytesStr,
+    pub params: Params<CPS>,
+}
+
+impl ContentDisposition {
+    pub fn new<D>(disposition: D) -> Self
+    where
+        D: Into<BytesStr>,
+    {
+        Self {
+            disposition: disposition.into(),
+            params: Params::new(),
+        }
+    }
+
+    impl_with_params!(params, with_key_param, with_value_param);
+
+    /// Returns if the handling of the content is optional (`handling=optional`), default is required
+    pub fn is_optional(&self) -> bool {
+        self.params
+            .get_val("handling")
+            .map(|handling| handling.eq_ignore_ascii_case("optional"))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            map(
+                ws((take_while1(token), Params::<CPS>::parse(ctx))),
+                |(disposition, params)| ContentDisposition {
+                    disposition: BytesStr::from_parse(ctx.src, disposition),
+                    params,
+                },
+            )(i)
+        }
+    }
+}
+
+impl Print for ContentDisposition {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.disposition, self.params.quoted_print())
+    }
+}
+
+__impl_header!(ContentDisposition, Single, Name::CONTENT_DISPOSITION);
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -77,11 +268,13 @@ mod test {
     fn content_type() {
         let input = BytesStr::from_static("application/sdp");
 
-        let (rem, content_length) = ContentType::parse(ParseCtx::default(&input))(&input).unwrap();
+        let (rem, content_type) = ContentType::parse(ParseCtx::default(&input))(&input).unwrap();
 
         assert!(rem.is_empty());
 
-        assert_eq!(content_length.0, "application/sdp");
+        assert_eq!(content_type.main_type, "application");
+        assert_eq!(content_type.sub_type, "sdp");
+        assert!(content_type.matches("Application/SDP"));
     }
 
     #[test]
@@ -92,16 +285,55 @@ mod test {
 
         assert!(rem.is_empty());
 
-        assert_eq!(content_type.0, "application/sdp");
+        assert!(content_type.matches("application/sdp"));
     }
 
     #[test]
     fn content_type_print() {
-        let content_type = ContentType(BytesStr::from_static("application/sdp"));
+        let content_type = ContentType::from_static("application/sdp");
 
         assert_eq!(
             content_type.default_print_ctx().to_string(),
             "application/sdp"
         );
     }
+
+    #[test]
+    fn content_type_params() {
+        let input =
+            BytesStr::from_static("multipart/mixed; Boundary=\"unique boundary\";charset=UTF-8");
+
+        let (rem, content_type) = ContentType::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert!(content_type.is_multipart());
+        assert_eq!(content_type.boundary().unwrap(), "unique boundary");
+        assert_eq!(content_type.charset().unwrap(), "UTF-8");
+    }
+
+    #[test]
+    fn content_type_params_print() {
+        let content_type = ContentType::from_static("multipart/related")
+            .with_value_param("boundary", "a=b")
+            .with_value_param("type", "application/sdp");
+
+        assert_eq!(
+            content_type.default_print_ctx().to_string(),
+            "multipart/related;boundary=\"a=b\";type=application/sdp"
+        );
+    }
+
+    #[test]
+    fn content_disposition() {
+        let input = BytesStr::from_static("signal;handling=optional");
+
+        let (rem, disposition) =
+            ContentDisposition::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(disposition.disposition, "signal");
+        assert!(disposition.is_optional());
+    }
 }
@@ -13,6 +13,7 @@ use std::str::FromStr;
 
 /// `CSeq` header
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CSeq {
     pub cseq: u32,
     pub method: Method,
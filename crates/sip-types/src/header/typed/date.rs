@@ -0,0 +1,202 @@
+use crate::header::name::Name;
+use crate::parse::{whitespace, ParseCtx};
+use crate::print::{Print, PrintCtx};
+use nom::bytes::complete::{tag, take_while1, take_while_m_n};
+use nom::character::complete::alpha1;
+use nom::combinator::{map_res, opt};
+use nom::sequence::tuple;
+use nom::IResult;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// `Date` header, e.g. `Sat, 13 Nov 2010 23:29:00 GMT`
+///
+/// Only the RFC 1123 format using the `GMT` time zone is allowed. Fractions of seconds are not printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date(pub SystemTime);
+
+/// Serialized as RFC 1123 date string, e.g. `Sat, 13 Nov 2010 23:29:00 GMT`
+#[cfg(feature = "serde")]
+impl serde::Serialize for Date {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use crate::print::AppendCtx;
+
+        serializer.collect_str(&self.default_print_ctx())
+    }
+}
+
+impl Date {
+    /// Returns a new Date header containing the current time
+    pub fn now() -> Self {
+        Self(SystemTime::now())
+    }
+
+    pub(crate) fn parse(_: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            let digits = |n| {
+                map_res(
+                    take_while_m_n(n, n, |c: char| c.is_ascii_digit()),
+                    i64::from_str,
+                )
+            };
+
+            map_res(
+                tuple((
+                    opt(tuple((alpha1, tag(","), take_while1(whitespace)))),
+                    take_while_m_n(1, 2, |c: char| c.is_ascii_digit()),
+                    take_while1(whitespace),
+                    alpha1,
+                    take_while1(whitespace),
+                    digits(4),
+                    take_while1(whitespace),
+                    tuple((digits(2), tag(":"), digits(2), tag(":"), digits(2))),
+                    take_while1(whitespace),
+                    tag("GMT"),
+                )),
+                |(_, day, _, month, _, year, _, (hour, _, minute, _, second), _, _)| {
+                    let day = i64::from_str(day).map_err(|_| ())?;
+                    let month = MONTHS
+                        .iter()
+                        .position(|m| m.eq_ignore_ascii_case(month))
+                        .ok_or(())?;
+
+                    if !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+                        return Err(());
+                    }
+
+                    let days = days_from_civil(year, month as i64 + 1, day);
+                    let secs = days * 86400 + hour * 3600 + minute * 60 + second;
+
+                    let time = if secs >= 0 {
+                        UNIX_EPOCH + Duration::from_secs(secs as u64)
+                    } else {
+                        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+                    };
+
+                    Ok(Date(time))
+                },
+            )(i)
+        }
+    }
+}
+
+impl Print for Date {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
+        let secs = match self.0.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+
+        let days = secs.div_euclid(86400);
+        let secs_of_day = secs.rem_euclid(86400);
+
+        let (year, month, day) = civil_from_days(days);
+
+        // 1970-01-01 was a Thursday
+        let weekday = WEEKDAYS[(days + 4).rem_euclid(7) as usize];
+
+        write!(
+            f,
+            "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+            weekday,
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            secs_of_day / 3600,
+            secs_of_day % 3600 / 60,
+            secs_of_day % 60
+        )
+    }
+}
+
+__impl_header!(Date, Single, Name::DATE);
+
+/// Days since 1970-01-01 of the given proleptic gregorian date
+///
+/// <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+/// Inverse of [`days_from_civil`], returns year, month and day
+///
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::print::AppendCtx;
+    use bytesstr::BytesStr;
+
+    #[test]
+    fn date() {
+        let input = BytesStr::from_static("Sat, 13 Nov 2010 23:29:00 GMT");
+
+        let (rem, date) = Date::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(date.0, UNIX_EPOCH + Duration::from_secs(1289690940));
+    }
+
+    #[test]
+    fn date_before_epoch() {
+        let input = BytesStr::from_static("Thu, 01 Jan 1920 00:00:00 GMT");
+
+        let (_, date) = Date::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert_eq!(date.0, UNIX_EPOCH - Duration::from_secs(1577923200));
+        assert_eq!(
+            date.default_print_ctx().to_string(),
+            "Thu, 01 Jan 1920 00:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn date_invalid_zone() {
+        let input = BytesStr::from_static("Sat, 13 Nov 2010 23:29:00 EST");
+
+        assert!(Date::parse(ParseCtx::default(&input))(&input).is_err());
+    }
+
+    #[test]
+    fn date_invalid_month() {
+        let input = BytesStr::from_static("Sat, 13 Foo 2010 23:29:00 GMT");
+
+        assert!(Date::parse(ParseCtx::default(&input))(&input).is_err());
+    }
+
+    #[test]
+    fn date_print() {
+        let date = Date(UNIX_EPOCH + Duration::from_secs(951_782_400));
+
+        assert_eq!(
+            date.default_print_ctx().to_string(),
+            "Tue, 29 Feb 2000 00:00:00 GMT"
+        );
+    }
+}
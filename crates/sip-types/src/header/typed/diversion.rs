@@ -0,0 +1,277 @@
+use crate::header::name::Name;
+use crate::parse::{token, ParseCtx};
+use crate::print::{Print, PrintCtx, UriContext};
+use crate::uri::params::{Params, CPS};
+use crate::uri::NameAddr;
+use bytesstr::BytesStr;
+use nom::combinator::map;
+use nom::sequence::tuple;
+use nom::IResult;
+use std::fmt;
+use std::str::FromStr;
+
+/// Reason of a [`Diversion`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiversionReason {
+    Unknown,
+    UserBusy,
+    NoAnswer,
+    Unavailable,
+    Unconditional,
+    TimeOfDay,
+    DoNotDisturb,
+    Deflection,
+    FollowMe,
+    OutOfService,
+    Away,
+    Other(BytesStr),
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DiversionReason {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl DiversionReason {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::UserBusy => "user-busy",
+            Self::NoAnswer => "no-answer",
+            Self::Unavailable => "unavailable",
+            Self::Unconditional => "unconditional",
+            Self::TimeOfDay => "time-of-day",
+            Self::DoNotDisturb => "do-not-disturb",
+            Self::Deflection => "deflection",
+            Self::FollowMe => "follow-me",
+            Self::OutOfService => "out-of-service",
+            Self::Away => "away",
+            Self::Other(other) => other,
+        }
+    }
+
+    /// Map a `cause` URI parameter (RFC 4458) to a diversion reason
+    pub fn from_cause(cause: u16) -> Self {
+        match cause {
+            302 => Self::Unconditional,
+            408 => Self::NoAnswer,
+            480 | 487 => Self::Deflection,
+            486 => Self::UserBusy,
+            503 => Self::Unavailable,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn from_param(value: BytesStr) -> Self {
+        [
+            Self::Unknown,
+            Self::UserBusy,
+            Self::NoAnswer,
+            Self::Unavailable,
+            Self::Unconditional,
+            Self::TimeOfDay,
+            Self::DoNotDisturb,
+            Self::Deflection,
+            Self::FollowMe,
+            Self::OutOfService,
+            Self::Away,
+        ]
+        .iter()
+        .find(|reason| reason.as_str().eq_ignore_ascii_case(&value))
+        .cloned()
+        .unwrap_or(Self::Other(value))
+    }
+}
+
+/// `Diversion` header (RFC 5806)
+///
+/// Entries are ordered from the most recent diversion to the first, so the last entry
+/// contains the originally called party.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diversion {
+    /// The party which diverted the request
+    pub uri: NameAddr,
+    pub reason: Option<DiversionReason>,
+    /// Number of diversions this entry represents
+    pub counter: Option<u32>,
+    /// Maximum number of diversions allowed
+    pub limit: Option<u32>,
+    pub privacy: Option<BytesStr>,
+    pub screen: Option<bool>,
+    pub params: Params<CPS>,
+}
+
+impl Diversion {
+    pub fn new(uri: NameAddr, reason: DiversionReason) -> Self {
+        Self {
+            uri,
+            reason: Some(reason),
+            counter: Some(1),
+            limit: None,
+            privacy: None,
+            screen: None,
+            params: Params::new(),
+        }
+    }
+
+    /// Insert the entry of the party `from` diverting the request in front of `diversions`
+    pub fn divert(diversions: &mut Vec<Diversion>, from: NameAddr, reason: DiversionReason) {
+        diversions.insert(0, Diversion::new(from, reason));
+    }
+
+    /// Returns the entry of the originally called party, the last one
+    pub fn original_target(diversions: &[Diversion]) -> Option<&Diversion> {
+        diversions.last()
+    }
+
+    pub(crate) fn parse<'p>(ctx: ParseCtx<'p>) -> impl Fn(&'p str) -> IResult<&'p str, Self> + 'p {
+        move |i| {
+            map(
+                tuple((NameAddr::parse_no_params(ctx), Params::<CPS>::parse(ctx))),
+                |(uri, mut params)| Diversion {
+                    uri,
+                    reason: params.take("reason").map(DiversionReason::from_param),
+                    counter: take_parsed(&mut params, "counter"),
+                    limit: take_parsed(&mut params, "limit"),
+                    privacy: params.take("privacy"),
+                    screen: take_parsed::<YesNo>(&mut params, "screen").map(|screen| screen.0),
+                    params,
+                },
+            )(i)
+        }
+    }
+}
+
+/// Take a parameter if its value can be parsed, invalid values are kept in the params
+fn take_parsed<T: FromStr>(params: &mut Params<CPS>, name: &str) -> Option<T> {
+    let value = params.get_val(name)?.parse().ok()?;
+    params.take(name);
+    Some(value)
+}
+
+struct YesNo(bool);
+
+impl FromStr for YesNo {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("yes") {
+            Ok(Self(true))
+        } else if s.eq_ignore_ascii_case("no") {
+            Ok(Self(false))
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl Print for Diversion {
+    fn print(&self, f: &mut fmt::Formatter<'_>, mut ctx: PrintCtx<'_>) -> fmt::Result {
+        ctx.uri = Some(UriContext::FromTo);
+        self.uri.print(f, ctx)?;
+
+        if let Some(reason) = &self.reason {
+            let reason = reason.as_str();
+
+            if !reason.is_empty() && reason.chars().all(token) {
+                write!(f, ";reason={}", reason)?;
+            } else {
+                write!(f, ";reason=\"{}\"", reason)?;
+            }
+        }
+
+        if let Some(counter) = self.counter {
+            write!(f, ";counter={}", counter)?;
+        }
+
+        if let Some(limit) = self.limit {
+            write!(f, ";limit={}", limit)?;
+        }
+
+        if let Some(privacy) = &self.privacy {
+            write!(f, ";privacy={}", privacy)?;
+        }
+
+        if let Some(screen) = self.screen {
+            write!(f, ";screen={}", if screen { "yes" } else { "no" })?;
+        }
+
+        self.params.print(f, ctx)
+    }
+}
+
+__impl_header!(Diversion, CSV, Name::DIVERSION);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::Header;
+    use crate::host::HostPort;
+    use crate::print::AppendCtx;
+    use crate::uri::sip::{SipUri, UserPart};
+    use std::iter::once;
+
+    #[test]
+    fn diversion() {
+        let input = BytesStr::from_static(
+            "<sip:carol@example.com>;reason=no-answer;counter=1;screen=no;privacy=off, \
+            \"Bob\" <sip:bob@example.com>;reason=\"Vacation Mode\";limit=x",
+        );
+
+        let (rem, diversions) =
+            Vec::<Diversion>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(diversions.len(), 2);
+
+        assert_eq!(diversions[0].reason, Some(DiversionReason::NoAnswer));
+        assert_eq!(diversions[0].counter, Some(1));
+        assert_eq!(diversions[0].screen, Some(false));
+        assert_eq!(diversions[0].privacy.as_ref().unwrap(), "off");
+        assert!(diversions[0].params.is_empty());
+
+        assert_eq!(
+            diversions[1].reason,
+            Some(DiversionReason::Other(BytesStr::from_static(
+                "Vacation Mode"
+            )))
+        );
+        assert_eq!(diversions[1].limit, None);
+        assert_eq!(diversions[1].params.get_val("limit").unwrap(), "x");
+
+        let original = Diversion::original_target(&diversions).unwrap();
+        assert_eq!(original.uri.name.as_ref().unwrap(), "Bob");
+    }
+
+    #[test]
+    fn diversion_print() {
+        let mut uri = SipUri::new(HostPort::host_name("example.com"));
+        uri.user_part = UserPart::User("bob".into());
+
+        let mut diversions = vec![];
+        Diversion::divert(
+            &mut diversions,
+            NameAddr::uri(uri),
+            DiversionReason::UserBusy,
+        );
+        diversions[0].screen = Some(true);
+
+        assert_eq!(
+            diversions[0].default_print_ctx().to_string(),
+            "<sip:bob@example.com>;reason=user-busy;counter=1;screen=yes"
+        );
+    }
+
+    #[test]
+    fn diversion_reason_from_cause() {
+        assert_eq!(DiversionReason::from_cause(486), DiversionReason::UserBusy);
+        assert_eq!(
+            DiversionReason::from_cause(302),
+            DiversionReason::Unconditional
+        );
+        assert_eq!(DiversionReason::from_cause(600), DiversionReason::Unknown);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::header::name::Name;
+use crate::parse::text::{SingleTextSpec, Text};
+use bytesstr::BytesStr;
+
+impl_wrap_header!(
+    /// `SIP-ETag` header, contains the entity tag assigned to a published event state
+    Text<SingleTextSpec>,
+    BytesStr,
+    SipETag,
+    Single,
+    Name::SIP_ETAG
+);
+
+impl_wrap_header!(
+    /// `SIP-If-Match` header, contains the entity tag of the event state a PUBLISH refers to
+    Text<SingleTextSpec>,
+    BytesStr,
+    SipIfMatch,
+    Single,
+    Name::SIP_IF_MATCH
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse::ParseCtx;
+    use crate::print::AppendCtx;
+
+    #[test]
+    fn sip_etag() {
+        let input = BytesStr::from_static("dx200xyz");
+
+        let (rem, etag) = SipETag::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(etag.0, "dx200xyz");
+    }
+
+    #[test]
+    fn sip_if_match_print() {
+        let if_match = SipIfMatch::from("kwj449x");
+
+        assert_eq!(if_match.default_print_ctx().to_string(), "kwj449x");
+    }
+}
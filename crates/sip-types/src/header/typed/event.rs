@@ -0,0 +1,95 @@
+use crate::header::name::Name;
+use crate::parse::text::{CsvTextSpec, Text};
+use crate::parse::{token, ParseCtx};
+use crate::print::{Print, PrintCtx};
+use crate::uri::params::{Params, CPS};
+use bytesstr::BytesStr;
+use internal::ws;
+use nom::bytes::complete::take_while1;
+use nom::combinator::map;
+use nom::IResult;
+use std::fmt;
+
+/// `Event` header
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Event {
+    /// Name of the event package, e.g. `presence`
+    pub package: BytesStr,
+    pub params: Params<CPS>,
+}
+
+impl Event {
+    #[inline]
+    pub fn new<P>(package: P) -> Self
+    where
+        P: Into<BytesStr>,
+    {
+        Self {
+            package: package.into(),
+            params: Params::new(),
+        }
+    }
+
+    impl_with_params!(params, with_key_param, with_value_param);
+
+    /// Returns the value of the `id` parameter
+    pub fn id(&self) -> Option<&BytesStr> {
+        self.params.get_val("id")
+    }
+
+    pub fn parse<'p>(ctx: ParseCtx<'p>) -> impl Fn(&'p str) -> IResult<&'p str, Self> + 'p {
+        move |i| {
+            map(
+                ws((take_while1(token), Params::<CPS>::parse(ctx))),
+                |(package, params)| Event {
+                    package: BytesStr::from_parse(ctx.src, package),
+                    params,
+                },
+            )(i)
+        }
+    }
+}
+
+impl Print for Event {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.package, self.params)
+    }
+}
+
+__impl_header!(Event, Single, Name::EVENT);
+
+impl_wrap_header!(
+    /// `Allow-Events` header, contains only one event package.
+    /// To get all allowed event packages use [`Vec`].
+    Text<CsvTextSpec>,
+    BytesStr,
+    AllowEvents,
+    CSV,
+    Name::ALLOW_EVENTS
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::print::AppendCtx;
+
+    #[test]
+    fn event() {
+        let input = BytesStr::from_static("presence ;id=123");
+
+        let (rem, event) = Event::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(event.package, "presence");
+        assert_eq!(event.id().unwrap(), "123");
+    }
+
+    #[test]
+    fn event_print() {
+        let event = Event::new("dialog").with_value_param("id", "abc");
+
+        assert_eq!(event.default_print_ctx().to_string(), "dialog;id=abc");
+    }
+}
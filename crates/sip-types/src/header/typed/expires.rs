@@ -3,12 +3,33 @@ use crate::header::name::Name;
 decl_from_str_header!(
     /// `Expires` header
     #[derive(Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
     Expires,
     u32,
     Single,
     Name::EXPIRES
 );
 
+decl_from_str_header!(
+    /// `Min-Expires` header
+    #[derive(Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+    MinExpires,
+    u32,
+    Single,
+    Name::MIN_EXPIRES
+);
+
+decl_from_str_header!(
+    /// `Flow-Timer` header, the recommended keep-alive interval of an outbound flow in seconds
+    #[derive(Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+    FlowTimer,
+    u32,
+    Single,
+    Name::FLOW_TIMER
+);
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -42,4 +63,26 @@ mod test {
     fn expires_print() {
         assert_eq!(Expires(30).default_print_ctx().to_string(), "30");
     }
+
+    #[test]
+    fn min_expires() {
+        let input = BytesStr::from_static("3600");
+
+        let (rem, min_expires) = MinExpires::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(min_expires.0, 3600);
+    }
+
+    #[test]
+    fn flow_timer() {
+        let input = BytesStr::from_static("120");
+
+        let (rem, flow_timer) = FlowTimer::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(flow_timer.0, 120);
+    }
 }
@@ -11,6 +11,7 @@ use std::fmt;
 
 /// Type which is being wrapped by [From] and [To]
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FromTo {
     pub uri: NameAddr,
     pub tag: Option<BytesStr>,
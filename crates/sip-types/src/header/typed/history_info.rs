@@ -0,0 +1,419 @@
+use super::Reason;
+use crate::header::name::Name;
+use crate::parse::ParseCtx;
+use crate::print::{AppendCtx, Print, PrintCtx};
+use crate::uri::params::{Params, CPS};
+use crate::uri::sip::SipUri;
+use crate::uri::NameAddr;
+use nom::combinator::map_res;
+use nom::sequence::tuple;
+use nom::IResult;
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// Index of a [`HistoryInfo`] entry, e.g. `1.2.1`
+///
+/// Each component represents a branch of the request, the index of the first entry is `1`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HistoryIndex(pub Vec<u32>);
+
+/// Serialized as string, e.g. `1.2.1`
+#[cfg(feature = "serde")]
+impl serde::Serialize for HistoryIndex {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl HistoryIndex {
+    /// Index of the first entry, `1`
+    pub fn root() -> Self {
+        Self(vec![1])
+    }
+
+    /// Returns the index of the `n`th branch created from this index
+    pub fn child(&self, n: u32) -> Self {
+        let mut index = self.clone();
+        index.0.push(n);
+        index
+    }
+
+    /// Returns the index this index was created from, `None` for the root index
+    pub fn parent(&self) -> Option<Self> {
+        if self.0.len() > 1 {
+            Some(Self(self.0[..self.0.len() - 1].to_vec()))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the index of the next branch from this index, which isn't used by any entry in `history`
+    pub fn next_child(&self, history: &[HistoryInfo]) -> Self {
+        let n = history
+            .iter()
+            .filter(|entry| entry.index.parent().as_ref() == Some(self))
+            .filter_map(|entry| entry.index.0.last().copied())
+            .max()
+            .unwrap_or(0);
+
+        self.child(n + 1)
+    }
+}
+
+impl FromStr for HistoryIndex {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split('.')
+            .map(u32::from_str)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl fmt::Display for HistoryIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut components = self.0.iter();
+
+        if let Some(component) = components.next() {
+            write!(f, "{}", component)?;
+        }
+
+        for component in components {
+            write!(f, ".{}", component)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Tag of a [`HistoryInfo`] entry, describes how its target was determined
+///
+/// Contains the index of the entry the target was derived from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryTag {
+    /// `rc`, the target is a contact bound to the previous target
+    Rc(HistoryIndex),
+    /// `mp`, the previous target was mapped to a new target
+    Mp(HistoryIndex),
+    /// `np`, the target didn't change
+    Np(HistoryIndex),
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for HistoryTag {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let variant_index = match self {
+            HistoryTag::Rc(_) => 0,
+            HistoryTag::Mp(_) => 1,
+            HistoryTag::Np(_) => 2,
+        };
+
+        serializer.serialize_newtype_variant(
+            "HistoryTag",
+            variant_index,
+            self.as_str(),
+            self.index(),
+        )
+    }
+}
+
+impl HistoryTag {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryTag::Rc(_) => "rc",
+            HistoryTag::Mp(_) => "mp",
+            HistoryTag::Np(_) => "np",
+        }
+    }
+
+    pub fn index(&self) -> &HistoryIndex {
+        match self {
+            HistoryTag::Rc(index) | HistoryTag::Mp(index) | HistoryTag::Np(index) => index,
+        }
+    }
+}
+
+/// `History-Info` header (RFC 7044)
+///
+/// The reason a target was retargeted is escaped as `Reason` header inside its URI,
+/// the reason for a new target (RFC 4458) is its `cause` URI parameter.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HistoryInfo {
+    pub uri: NameAddr,
+    pub index: HistoryIndex,
+    pub tag: Option<HistoryTag>,
+    pub params: Params<CPS>,
+}
+
+impl HistoryInfo {
+    pub fn new(uri: NameAddr, index: HistoryIndex) -> Self {
+        Self {
+            uri,
+            index,
+            tag: None,
+            params: Params::new(),
+        }
+    }
+
+    pub fn with_tag(mut self, tag: HistoryTag) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Returns the `cause` URI parameter of a SIP URI, the status code which caused the request to be retargeted to it
+    pub fn cause(&self) -> Option<u16> {
+        self.uri
+            .uri
+            .downcast_ref::<SipUri>()?
+            .uri_params
+            .get_val("cause")?
+            .parse()
+            .ok()
+    }
+
+    /// Set the `cause` URI parameter, does nothing if the URI is not a SIP URI
+    pub fn set_cause(&mut self, cause: u16) {
+        if let Some(uri) = self.uri.uri.downcast_mut::<SipUri>() {
+            uri.uri_params.push_or_edit("cause", cause.to_string());
+        }
+    }
+
+    /// Returns the `Reason` escaped in the URI, why the request to this entry was retargeted
+    pub fn reason(&self) -> Option<Reason> {
+        let value = self
+            .uri
+            .uri
+            .downcast_ref::<SipUri>()?
+            .header_params
+            .iter()
+            .find(|param| param.name.eq_ignore_ascii_case("reason"))?
+            .value
+            .clone()?;
+
+        let (_, reason) = Reason::parse(ParseCtx::default(&value))(&value).ok()?;
+
+        Some(reason)
+    }
+
+    /// Escape the `Reason` in the URI, does nothing if the URI is not a SIP URI
+    pub fn set_reason(&mut self, reason: &Reason) {
+        if let Some(uri) = self.uri.uri.downcast_mut::<SipUri>() {
+            uri.header_params
+                .push_or_edit("Reason", reason.default_print_ctx().to_string());
+        }
+    }
+
+    /// Append the entry of a retargeted request to `history` and return it
+    ///
+    /// The last entry in `history` is the current target and the new entry becomes its next
+    /// branch, tagged with `tag`. The `reason` why the current target was abandoned is escaped in
+    /// its URI. If `history` is empty the new entry is the first one, with index `1` and no tag.
+    pub fn retarget<'h>(
+        history: &'h mut Vec<HistoryInfo>,
+        target: NameAddr,
+        tag: fn(HistoryIndex) -> HistoryTag,
+        reason: Option<&Reason>,
+    ) -> &'h mut HistoryInfo {
+        let entry = match history.last_mut() {
+            Some(current) => {
+                if let Some(reason) = reason {
+                    current.set_reason(reason);
+                }
+
+                let parent = current.index.clone();
+                let index = parent.next_child(history);
+
+                HistoryInfo::new(target, index).with_tag(tag(parent))
+            }
+            None => HistoryInfo::new(target, HistoryIndex::root()),
+        };
+
+        history.push(entry);
+        history.last_mut().expect("just pushed an entry")
+    }
+
+    /// Returns the entry of the originally called party, the one with the lowest index
+    pub fn original_target(history: &[HistoryInfo]) -> Option<&HistoryInfo> {
+        history.iter().min_by(|a, b| {
+            a.index
+                .0
+                .len()
+                .cmp(&b.index.0.len())
+                .then(a.index.cmp(&b.index))
+        })
+    }
+
+    pub(crate) fn parse<'p>(ctx: ParseCtx<'p>) -> impl Fn(&'p str) -> IResult<&'p str, Self> + 'p {
+        move |i| {
+            map_res(
+                tuple((NameAddr::parse_no_params(ctx), Params::<CPS>::parse(ctx))),
+                |(uri, mut params)| -> Result<_, ParseIntError> {
+                    let index = params.take("index").as_deref().unwrap_or("").parse()?;
+                    let tag = take_tag(&mut params);
+
+                    Ok(HistoryInfo {
+                        uri,
+                        index,
+                        tag,
+                        params,
+                    })
+                },
+            )(i)
+        }
+    }
+}
+
+/// Take the first valid `rc`, `mp` or `np` parameter, invalid tags are kept in the params
+fn take_tag(params: &mut Params<CPS>) -> Option<HistoryTag> {
+    for (name, tag) in [
+        ("rc", HistoryTag::Rc as fn(_) -> _),
+        ("mp", HistoryTag::Mp),
+        ("np", HistoryTag::Np),
+    ] {
+        let index = params
+            .get_val(name)
+            .and_then(|index| HistoryIndex::from_str(index).ok());
+
+        if let Some(index) = index {
+            params.take(name);
+            return Some(tag(index));
+        }
+    }
+
+    None
+}
+
+impl Print for HistoryInfo {
+    fn print(&self, f: &mut fmt::Formatter<'_>, mut ctx: PrintCtx<'_>) -> fmt::Result {
+        // The URI's headers (e.g. the escaped Reason) must be printed
+        ctx.uri = None;
+
+        self.uri.print(f, ctx)?;
+        write!(f, ";index={}", self.index)?;
+
+        if let Some(tag) = &self.tag {
+            write!(f, ";{}={}", tag.as_str(), tag.index())?;
+        }
+
+        self.params.print(f, ctx)
+    }
+}
+
+__impl_header!(HistoryInfo, CSV, Name::HISTORY_INFO);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::Header;
+    use crate::host::HostPort;
+    use crate::uri::sip::UserPart;
+    use crate::Code;
+    use bytesstr::BytesStr;
+    use std::iter::once;
+
+    fn sip_uri(user: &str) -> NameAddr {
+        let mut uri = SipUri::new(HostPort::host_name("example.com"));
+        uri.user_part = UserPart::User(user.to_owned().into());
+        NameAddr::uri(uri)
+    }
+
+    #[test]
+    fn history_info() {
+        let input = BytesStr::from_static(
+            "<sip:bob@example.com?Reason=SIP%3Bcause%3D302%3Btext%3D%22Moved%22>;index=1, \
+            <sip:office@example.com;cause=302>;index=1.1;mp=1;foo",
+        );
+
+        let (rem, history) =
+            Vec::<HistoryInfo>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(history.len(), 2);
+
+        assert_eq!(history[0].index, HistoryIndex::root());
+        assert_eq!(history[0].tag, None);
+        assert_eq!(history[0].cause(), None);
+
+        let reason = history[0].reason().unwrap();
+        assert_eq!(reason.protocol, "SIP");
+        assert_eq!(reason.cause, Some(302));
+        assert_eq!(reason.text.unwrap(), "Moved");
+
+        assert_eq!(history[1].index, HistoryIndex(vec![1, 1]));
+        assert_eq!(history[1].tag, Some(HistoryTag::Mp(HistoryIndex::root())));
+        assert_eq!(history[1].cause(), Some(302));
+        assert!(history[1].reason().is_none());
+        assert!(history[1].params.get("foo").is_some());
+    }
+
+    #[test]
+    fn history_info_missing_index() {
+        let input = BytesStr::from_static("<sip:bob@example.com>;rc=1");
+
+        assert!(HistoryInfo::parse(ParseCtx::default(&input))(&input).is_err());
+    }
+
+    #[test]
+    fn history_info_invalid_tag() {
+        let input = BytesStr::from_static("<sip:bob@example.com>;index=1.2;np=x");
+
+        let (_, entry) = HistoryInfo::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert_eq!(entry.tag, None);
+        assert_eq!(entry.params.get_val("np").unwrap(), "x");
+    }
+
+    #[test]
+    fn history_info_retarget() {
+        let mut history = vec![];
+
+        HistoryInfo::retarget(&mut history, sip_uri("bob"), HistoryTag::Mp, None);
+        HistoryInfo::retarget(
+            &mut history,
+            sip_uri("voicemail"),
+            HistoryTag::Mp,
+            Some(&Reason::sip(Code::BUSY_HERE)),
+        )
+        .set_cause(486);
+
+        assert_eq!(
+            history[0].default_print_ctx().to_string(),
+            "<sip:bob@example.com?Reason=SIP%3Bcause%3D486%3Btext%3D%22Busy%20Here%22>;index=1"
+        );
+        assert_eq!(
+            history[1].default_print_ctx().to_string(),
+            "<sip:voicemail@example.com;cause=486>;index=1.1;mp=1"
+        );
+
+        let original = HistoryInfo::original_target(&history).unwrap();
+        assert_eq!(original.index, HistoryIndex::root());
+        assert_eq!(original.reason().unwrap().cause, Some(486));
+    }
+
+    #[test]
+    fn history_index_next_child() {
+        let history = vec![
+            HistoryInfo::new(sip_uri("a"), HistoryIndex::root()),
+            HistoryInfo::new(sip_uri("b"), HistoryIndex(vec![1, 1])),
+            HistoryInfo::new(sip_uri("c"), HistoryIndex(vec![1, 1, 1])),
+            HistoryInfo::new(sip_uri("d"), HistoryIndex(vec![1, 2])),
+        ];
+
+        assert_eq!(
+            HistoryIndex::root().next_child(&history),
+            HistoryIndex(vec![1, 3])
+        );
+        assert_eq!(
+            HistoryIndex(vec![1, 2]).next_child(&history),
+            HistoryIndex(vec![1, 2, 1])
+        );
+        assert_eq!(
+            HistoryIndex(vec![1, 2]).parent(),
+            Some(HistoryIndex::root())
+        );
+        assert_eq!(HistoryIndex::root().parent(), None);
+    }
+}
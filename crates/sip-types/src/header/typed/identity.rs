@@ -0,0 +1,175 @@
+use crate::header::name::Name;
+use crate::parse::{token, whitespace, ParseCtx};
+use crate::print::{Print, PrintCtx};
+use crate::uri::NameAddr;
+use bytesstr::BytesStr;
+use nom::bytes::complete::{take_while, take_while1};
+use nom::character::complete::char;
+use nom::combinator::map;
+use nom::multi::separated_list1;
+use nom::sequence::tuple;
+use nom::IResult;
+use std::fmt;
+
+impl_wrap_header!(
+    /// `P-Asserted-Identity` header. Contains only one identity. To get all identities use [`Vec`].
+    NameAddr,
+    PAssertedIdentity,
+    CSV,
+    Name::P_ASSERTED_IDENTITY
+);
+
+impl_wrap_header!(
+    /// `P-Preferred-Identity` header. Contains only one identity. To get all identities use [`Vec`].
+    NameAddr,
+    PPreferredIdentity,
+    CSV,
+    Name::P_PREFERRED_IDENTITY
+);
+
+/// Privacy type requested in the `Privacy` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrivacyValue {
+    /// Obscure headers which might reveal information about the user
+    Header,
+    /// Provide anonymization for the session(s) initiated by this message
+    Session,
+    /// Privacy services which may only be requested by intermediaries
+    User,
+    /// Privacy must not be applied to this message
+    None,
+    /// Privacy must be applied, or the message must be rejected
+    Critical,
+    /// Keep the network asserted identity private ([RFC3325](https://datatracker.ietf.org/doc/html/rfc3325#section-7))
+    Id,
+    Other(BytesStr),
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PrivacyValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl PrivacyValue {
+    fn from_parse(src: &bytes::Bytes, value: &str) -> Self {
+        match value {
+            "header" => Self::Header,
+            "session" => Self::Session,
+            "user" => Self::User,
+            "none" => Self::None,
+            "critical" => Self::Critical,
+            "id" => Self::Id,
+            _ => Self::Other(BytesStr::from_parse(src, value)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Header => "header",
+            Self::Session => "session",
+            Self::User => "user",
+            Self::None => "none",
+            Self::Critical => "critical",
+            Self::Id => "id",
+            Self::Other(other) => other,
+        }
+    }
+}
+
+/// `Privacy` header
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct Privacy(pub Vec<PrivacyValue>);
+
+impl Privacy {
+    /// Returns if the privacy type was requested
+    pub fn contains(&self, value: &PrivacyValue) -> bool {
+        self.0.contains(value)
+    }
+
+    pub fn parse<'p>(ctx: ParseCtx<'p>) -> impl Fn(&'p str) -> IResult<&'p str, Self> + 'p {
+        move |i| {
+            map(
+                separated_list1(
+                    tuple((take_while(whitespace), char(';'), take_while(whitespace))),
+                    take_while1(token),
+                ),
+                |values: Vec<&str>| {
+                    Privacy(
+                        values
+                            .into_iter()
+                            .map(|value| PrivacyValue::from_parse(ctx.src, value))
+                            .collect(),
+                    )
+                },
+            )(i)
+        }
+    }
+}
+
+impl Print for Privacy {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
+        for (i, value) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(";")?;
+            }
+
+            f.write_str(value.as_str())?;
+        }
+
+        Ok(())
+    }
+}
+
+__impl_header!(Privacy, Single, Name::PRIVACY);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::Header;
+    use crate::print::AppendCtx;
+    use crate::uri::sip::SipUri;
+    use std::iter::once;
+
+    #[test]
+    fn p_asserted_identity_multiple() {
+        let input = BytesStr::from_static(
+            "\"Cullen Jennings\" <sip:fluffy@cisco.com>, <sip:+14085264000@cisco.com;user=phone>",
+        );
+
+        let (rem, identities) =
+            Vec::<PAssertedIdentity>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(identities.len(), 2);
+        assert_eq!(identities[0].name.as_deref(), Some("Cullen Jennings"));
+
+        let sip_uri: &SipUri = identities[1].uri.downcast_ref().unwrap();
+        assert!(sip_uri.uri_params.get("user").is_some());
+    }
+
+    #[test]
+    fn privacy() {
+        let input = BytesStr::from_static("id; header;user");
+
+        let (rem, privacy) = Privacy::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(
+            privacy.0,
+            vec![PrivacyValue::Id, PrivacyValue::Header, PrivacyValue::User]
+        );
+    }
+
+    #[test]
+    fn privacy_print() {
+        let privacy = Privacy(vec![
+            PrivacyValue::Id,
+            PrivacyValue::Other(BytesStr::from_static("x-custom")),
+        ]);
+
+        assert_eq!(privacy.default_print_ctx().to_string(), "id;x-custom");
+    }
+}
@@ -0,0 +1,61 @@
+use crate::header::name::Name;
+use crate::parse::text::{CsvTextSpec, SingleTextSpec, Text};
+use bytesstr::BytesStr;
+
+impl_wrap_header!(
+    /// `Info-Package` header, contains the info package an INFO request is associated with
+    Text<SingleTextSpec>,
+    BytesStr,
+    InfoPackage,
+    Single,
+    Name::INFO_PACKAGE
+);
+
+impl_wrap_header!(
+    /// `Recv-Info` header, contains only one info package the sender is willing to receive.
+    /// To get all info packages use [`Vec`].
+    Text<CsvTextSpec>,
+    BytesStr,
+    RecvInfo,
+    CSV,
+    Name::RECV_INFO
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse::ParseCtx;
+    use crate::print::AppendCtx;
+    use crate::Headers;
+
+    #[test]
+    fn info_package() {
+        let input = BytesStr::from_static("foo");
+
+        let (rem, package) = InfoPackage::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+
+        assert_eq!(package.0, "foo");
+    }
+
+    #[test]
+    fn info_package_print() {
+        let package = InfoPackage::from("foo");
+
+        assert_eq!(package.default_print_ctx().to_string(), "foo");
+    }
+
+    #[test]
+    fn recv_info_multiple() {
+        let mut headers = Headers::new();
+
+        headers.insert(Name::RECV_INFO, "foo, bar");
+
+        let recv_info: Vec<RecvInfo> = headers.get().unwrap();
+
+        assert_eq!(recv_info.len(), 2);
+        assert_eq!(recv_info[0].0, "foo");
+        assert_eq!(recv_info[1].0, "bar");
+    }
+}
@@ -2,6 +2,7 @@ use crate::header::name::Name;
 
 decl_from_str_header!(
     /// `Max-Forwards` header
+    #[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
     MaxForwards,
     u32,
     Single,
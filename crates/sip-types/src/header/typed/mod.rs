@@ -4,34 +4,165 @@ mod accept;
 mod allow;
 mod auth;
 mod call_id;
+mod call_info;
 mod contact;
 mod content;
 mod cseq;
+mod date;
+mod diversion;
+mod etag;
+mod event;
 mod expires;
 mod extensions;
 mod from_to;
+mod history_info;
+mod identity;
+mod info;
 mod max_fwd;
 mod prack;
+mod priority;
+mod reason;
 mod replaces;
+mod reply_to;
 mod retry_after;
 mod routing;
+mod server;
+mod subject;
 mod timer;
+mod timestamp;
 mod via;
+mod warning;
 
-pub use accept::Accept;
+pub use accept::{Accept, AcceptEncoding, AcceptLanguage};
 pub use allow::Allow;
-pub use auth::{Auth, AuthParam, Authorization, ProxyAuthenticate, WWWAuthenticate};
-pub use call_id::CallID;
+pub use auth::{
+    Auth, AuthParam, Authorization, ProxyAuthenticate, ProxyAuthorization, WWWAuthenticate,
+};
+pub use call_id::{CallID, InReplyTo};
+pub use call_info::{AlertInfo, CallInfo, ErrorInfo};
 pub use contact::Contact;
-pub use content::{ContentLength, ContentType};
+pub use content::{ContentDisposition, ContentLength, ContentType};
 pub use cseq::CSeq;
-pub use expires::Expires;
-pub use extensions::{Require, Supported};
+pub use date::Date;
+pub use diversion::{Diversion, DiversionReason};
+pub use etag::{SipETag, SipIfMatch};
+pub use event::{AllowEvents, Event};
+pub use expires::{Expires, FlowTimer, MinExpires};
+pub use extensions::{Require, Supported, Unsupported};
 pub use from_to::{From, FromTo, To};
+pub use history_info::{HistoryIndex, HistoryInfo, HistoryTag};
+pub use identity::{PAssertedIdentity, PPreferredIdentity, Privacy, PrivacyValue};
+pub use info::{InfoPackage, RecvInfo};
 pub use max_fwd::MaxForwards;
 pub use prack::{RAck, RSeq};
+pub use priority::Priority;
+pub use reason::Reason;
 pub use replaces::Replaces;
+pub use reply_to::ReplyTo;
 pub use retry_after::RetryAfter;
-pub use routing::{RecordRoute, Route, Routing};
+pub use routing::{Path, RecordRoute, Route, Routing, ServiceRoute};
+pub use server::{Products, Server, ServerVal, UserAgent};
+pub use subject::{Organization, Subject};
 pub use timer::{MinSe, Refresher, SessionExpires};
+pub use timestamp::Timestamp;
 pub use via::Via;
+pub use warning::Warning;
+
+/// Serializes a header value decoded as the typed header of the same name, `None` if the name is
+/// unknown or the value invalid. Values of CSV headers are serialized as list.
+#[cfg(feature = "serde")]
+pub(crate) fn serialize_typed<S: serde::Serializer>(
+    name: &crate::Name,
+    value: &bytesstr::BytesStr,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use crate::header::{Header, Kind};
+    use crate::parse::{diagnostic, Parser};
+    use crate::Name;
+
+    fn decoded<H, S>(value: &bytesstr::BytesStr, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        H: Header<Kind = Kind> + serde::Serialize,
+        S: serde::Serializer,
+    {
+        match diagnostic::decode::<H>(Parser::default(), value) {
+            Ok(decoded) if matches!(H::kind(), Kind::CSV) => serializer.serialize_some(&decoded),
+            Ok(decoded) => serializer.serialize_some(&decoded[0]),
+            Err(_) => serializer.serialize_none(),
+        }
+    }
+
+    macro_rules! typed {
+        ($($konst:ident => $ty:ty,)*) => {
+            $(
+            if *name == Name::$konst {
+                return decoded::<$ty, S>(value, serializer);
+            }
+            )*
+        };
+    }
+
+    typed! {
+        ACCEPT => Accept,
+        ACCEPT_ENCODING => AcceptEncoding,
+        ACCEPT_LANGUAGE => AcceptLanguage,
+        ALERT_INFO => AlertInfo,
+        ALLOW => Allow,
+        ALLOW_EVENTS => AllowEvents,
+        AUTHORIZATION => Authorization,
+        CALL_ID => CallID,
+        CALL_INFO => CallInfo,
+        CONTACT => Contact,
+        CONTENT_DISPOSITION => ContentDisposition,
+        CONTENT_LENGTH => ContentLength,
+        CONTENT_TYPE => ContentType,
+        CSEQ => CSeq,
+        DATE => Date,
+        DIVERSION => Diversion,
+        ERROR_INFO => ErrorInfo,
+        EVENT => Event,
+        EXPIRES => Expires,
+        FLOW_TIMER => FlowTimer,
+        FROM => From,
+        HISTORY_INFO => HistoryInfo,
+        IN_REPLY_TO => InReplyTo,
+        INFO_PACKAGE => InfoPackage,
+        MAX_FORWARDS => MaxForwards,
+        MIN_EXPIRES => MinExpires,
+        MIN_SE => MinSe,
+        ORGANIZATION => Organization,
+        P_ASSERTED_IDENTITY => PAssertedIdentity,
+        P_PREFERRED_IDENTITY => PPreferredIdentity,
+        PATH => Path,
+        PRIORITY => Priority,
+        PRIVACY => Privacy,
+        PROXY_AUTHENTICATE => ProxyAuthenticate,
+        PROXY_AUTHORIZATION => ProxyAuthorization,
+        RACK => RAck,
+        REASON => Reason,
+        RECORD_ROUTE => RecordRoute,
+        RECV_INFO => RecvInfo,
+        REPLACES => Replaces,
+        REPLY_TO => ReplyTo,
+        REQUIRE => Require,
+        RETRY_AFTER => RetryAfter,
+        ROUTE => Route,
+        RSEQ => RSeq,
+        SERVER => Server,
+        SERVICE_ROUTE => ServiceRoute,
+        SESSION_EXPIRES => SessionExpires,
+        SIP_ETAG => SipETag,
+        SIP_IF_MATCH => SipIfMatch,
+        SUBJECT => Subject,
+        SUPPORTED => Supported,
+        TIMESTAMP => Timestamp,
+        TO => To,
+        UNSUPPORTED => Unsupported,
+        USER_AGENT => UserAgent,
+        VIA => Via,
+        WARNING => Warning,
+        WWW_AUTHENTICATE => WWWAuthenticate,
+    }
+
+    serializer.serialize_none()
+}
@@ -13,6 +13,7 @@ use std::str::FromStr;
 decl_from_str_header!(
     /// `RSeq` header
     #[derive(Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
     RSeq,
     u32,
     Single,
@@ -21,6 +22,7 @@ decl_from_str_header!(
 
 /// `RAck` header
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RAck {
     pub rack: u32,
     pub cseq: u32,
@@ -0,0 +1,96 @@
+use crate::header::name::Name;
+use crate::parse::{token, ParseCtx};
+use crate::print::{Print, PrintCtx};
+use bytesstr::BytesStr;
+use nom::bytes::complete::take_while1;
+use nom::combinator::map;
+use nom::IResult;
+use std::fmt;
+
+/// `Priority` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Priority {
+    Emergency,
+    Urgent,
+    Normal,
+    NonUrgent,
+    Other(BytesStr),
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Priority {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Emergency => "emergency",
+            Self::Urgent => "urgent",
+            Self::Normal => "normal",
+            Self::NonUrgent => "non-urgent",
+            Self::Other(other) => other,
+        }
+    }
+
+    pub(crate) fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            map(take_while1(token), |priority: &str| {
+                if priority.eq_ignore_ascii_case("emergency") {
+                    Self::Emergency
+                } else if priority.eq_ignore_ascii_case("urgent") {
+                    Self::Urgent
+                } else if priority.eq_ignore_ascii_case("normal") {
+                    Self::Normal
+                } else if priority.eq_ignore_ascii_case("non-urgent") {
+                    Self::NonUrgent
+                } else {
+                    Self::Other(BytesStr::from_parse(ctx.src, priority))
+                }
+            })(i)
+        }
+    }
+}
+
+impl Print for Priority {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+__impl_header!(Priority, Single, Name::PRIORITY);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::print::AppendCtx;
+
+    #[test]
+    fn priority() {
+        let input = BytesStr::from_static("Non-Urgent");
+
+        let (rem, priority) = Priority::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(priority, Priority::NonUrgent);
+    }
+
+    #[test]
+    fn priority_other() {
+        let input = BytesStr::from_static("whenever");
+
+        let (_, priority) = Priority::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert_eq!(priority, Priority::Other(BytesStr::from_static("whenever")));
+    }
+
+    #[test]
+    fn priority_print() {
+        assert_eq!(
+            Priority::Emergency.default_print_ctx().to_string(),
+            "emergency"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::code::Code;
+use crate::header::name::Name;
+use crate::parse::{token, ParseCtx};
+use crate::print::{Print, PrintCtx};
+use crate::uri::params::{Params, CPS};
+use bytesstr::BytesStr;
+use nom::bytes::complete::take_while1;
+use nom::combinator::map;
+use nom::sequence::tuple;
+use nom::IResult;
+use std::fmt;
+
+/// `Reason` header (RFC 3326)
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Reason {
+    /// Protocol the cause belongs to, e.g. `SIP` or `Q.850`
+    pub protocol: BytesStr,
+    pub cause: Option<u16>,
+    /// Text without the enclosing quotes, escape sequences are kept as is
+    pub text: Option<BytesStr>,
+    pub params: Params<CPS>,
+}
+
+impl Reason {
+    pub fn new<P>(protocol: P, cause: Option<u16>) -> Self
+    where
+        P: Into<BytesStr>,
+    {
+        Self {
+            protocol: protocol.into(),
+            cause,
+            text: None,
+            params: Params::new(),
+        }
+    }
+
+    /// Create a `SIP` reason from a status code, using the code's default reason phrase as text
+    pub fn sip(code: Code) -> Self {
+        Self {
+            text: code.text().map(BytesStr::from_static),
+            ..Self::new("SIP", Some(code.into_u16()))
+        }
+    }
+
+    pub fn with_text<T>(mut self, text: T) -> Self
+    where
+        T: Into<BytesStr>,
+    {
+        self.text = Some(text.into());
+        self
+    }
+
+    pub(crate) fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            map(
+                tuple((take_while1(token), Params::<CPS>::parse(ctx))),
+                |(protocol, mut params)| {
+                    // Keep unparsable causes in the params so they are not lost when printing
+                    let cause = params.get_val("cause").and_then(|cause| cause.parse().ok());
+
+                    if cause.is_some() {
+                        params.take("cause");
+                    }
+
+                    Reason {
+                        protocol: BytesStr::from_parse(ctx.src, protocol),
+                        cause,
+                        text: params.take("text"),
+                        params,
+                    }
+                },
+            )(i)
+        }
+    }
+}
+
+impl Print for Reason {
+    fn print(&self, f: &mut fmt::Formatter<'_>, ctx: PrintCtx<'_>) -> fmt::Result {
+        write!(f, "{}", self.protocol)?;
+
+        if let Some(cause) = self.cause {
+            write!(f, ";cause={}", cause)?;
+        }
+
+        if let Some(text) = &self.text {
+            write!(f, ";text=\"{}\"", text)?;
+        }
+
+        self.params.print(f, ctx)
+    }
+}
+
+__impl_header!(Reason, CSV, Name::REASON);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::Header;
+    use crate::print::AppendCtx;
+    use std::iter::once;
+
+    #[test]
+    fn reason() {
+        let input = BytesStr::from_static("SIP ;cause=200 ;text=\"Call completed elsewhere\"");
+
+        let (rem, reason) = Reason::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(reason.protocol, "SIP");
+        assert_eq!(reason.cause, Some(200));
+        assert_eq!(reason.text.unwrap(), "Call completed elsewhere");
+        assert!(reason.params.is_empty());
+    }
+
+    #[test]
+    fn reason_multiple() {
+        let input = BytesStr::from_static("Q.850;cause=16;text=\"Terminated\", SIP;cause=600");
+
+        let (rem, reasons) = Vec::<Reason>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(reasons[0].protocol, "Q.850");
+        assert_eq!(reasons[0].cause, Some(16));
+        assert_eq!(reasons[1].protocol, "SIP");
+        assert_eq!(reasons[1].cause, Some(600));
+        assert!(reasons[1].text.is_none());
+    }
+
+    #[test]
+    fn reason_invalid_cause() {
+        let input = BytesStr::from_static("SIP;cause=abc");
+
+        let (_, reason) = Reason::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert_eq!(reason.cause, None);
+        assert_eq!(reason.params.get_val("cause").unwrap(), "abc");
+    }
+
+    #[test]
+    fn reason_print() {
+        let reason = Reason::sip(Code::BUSY_EVERYWHERE);
+
+        assert_eq!(
+            reason.default_print_ctx().to_string(),
+            "SIP;cause=600;text=\"Busy Everywhere\""
+        );
+    }
+}
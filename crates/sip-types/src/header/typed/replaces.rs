@@ -12,6 +12,7 @@ use nom::{bytes::complete::take_while1, IResult};
 use std::fmt;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Replaces {
     pub call_id: BytesStr,
     pub to_tag: BytesStr,
@@ -0,0 +1,79 @@
+use crate::header::name::Name;
+use crate::parse::ParseCtx;
+use crate::print::{Print, PrintCtx, UriContext};
+use crate::uri::params::{Params, CPS};
+use crate::uri::NameAddr;
+use nom::combinator::map;
+use nom::sequence::tuple;
+use nom::IResult;
+use std::fmt;
+
+/// `Reply-To` header, contains the address replies should be sent to
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReplyTo {
+    pub uri: NameAddr,
+    pub params: Params<CPS>,
+}
+
+impl ReplyTo {
+    pub fn new(uri: NameAddr) -> Self {
+        Self {
+            uri,
+            params: Params::new(),
+        }
+    }
+
+    pub(crate) fn parse<'p>(ctx: ParseCtx<'p>) -> impl Fn(&'p str) -> IResult<&'p str, Self> + 'p {
+        move |i| {
+            map(
+                tuple((NameAddr::parse_no_params(ctx), Params::<CPS>::parse(ctx))),
+                |(uri, params)| ReplyTo { uri, params },
+            )(i)
+        }
+    }
+}
+
+impl Print for ReplyTo {
+    fn print(&self, f: &mut fmt::Formatter<'_>, mut ctx: PrintCtx<'_>) -> fmt::Result {
+        ctx.uri = Some(UriContext::FromTo);
+        self.uri.print(f, ctx)?;
+        self.params.print(f, ctx)
+    }
+}
+
+__impl_header!(ReplyTo, Single, Name::REPLY_TO);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::host::HostPort;
+    use crate::print::AppendCtx;
+    use crate::uri::sip::SipUri;
+    use bytesstr::BytesStr;
+
+    #[test]
+    fn reply_to() {
+        let input = BytesStr::from_static("Bob <sip:bob@biloxi.com>;x=y");
+
+        let (rem, reply_to) = ReplyTo::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(reply_to.uri.name.as_ref().unwrap(), "Bob");
+        assert_eq!(reply_to.params.get_val("x").unwrap(), "y");
+        assert!(reply_to.uri.uri.downcast_ref::<SipUri>().is_some());
+    }
+
+    #[test]
+    fn reply_to_print() {
+        let reply_to = ReplyTo::new(NameAddr::new(
+            "Bob",
+            SipUri::new(HostPort::host_name("biloxi.com")),
+        ));
+
+        assert_eq!(
+            reply_to.default_print_ctx().to_string(),
+            "\"Bob\"<sip:biloxi.com>"
+        );
+    }
+}
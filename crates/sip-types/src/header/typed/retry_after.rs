@@ -18,6 +18,7 @@ use std::str::FromStr;
 /// `Retry-After` header
 ///
 /// Currently only supports seconds representation
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RetryAfter {
     pub value: u32,
     pub params: Params<CPS>,
@@ -10,6 +10,7 @@ use std::fmt;
 
 /// Implementation for all Route-related headers.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Routing {
     pub uri: NameAddr,
     pub params: Params<CPS>,
@@ -50,6 +51,22 @@ impl_wrap_header!(
     Name::RECORD_ROUTE
 );
 
+impl_wrap_header!(
+    /// `Path` header. Wraps [`Routing`]. Contains only one route. To get all routes use [`Vec`].
+    Routing,
+    Path,
+    CSV,
+    Name::PATH
+);
+
+impl_wrap_header!(
+    /// `Service-Route` header. Wraps [`Routing`]. Contains only one route. To get all routes use [`Vec`].
+    Routing,
+    ServiceRoute,
+    CSV,
+    Name::SERVICE_ROUTE
+);
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -99,6 +116,20 @@ mod test {
         let _r2 = &routing[1];
     }
 
+    #[test]
+    fn service_route_multiple() {
+        let input =
+            BytesStr::from_static("<sip:P2.HOME.EXAMPLE.COM;lr>,<sip:HSP.HOME.EXAMPLE.COM;lr>");
+        let (rem, routes) =
+            Vec::<ServiceRoute>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(routes.len(), 2);
+
+        let sip_uri: &SipUri = routes[1].uri.uri.downcast_ref().unwrap();
+        assert!(matches!(&sip_uri.host_port.host, Host::Name(n) if n == "HSP.HOME.EXAMPLE.COM"));
+    }
+
     #[test]
     fn routing_print() {
         let routing = Routing {
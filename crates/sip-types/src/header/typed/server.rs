@@ -0,0 +1,228 @@
+use crate::header::name::Name;
+use crate::parse::{token, whitespace, ParseCtx};
+use crate::print::{Print, PrintCtx};
+use bytesstr::BytesStr;
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while, take_while1};
+use nom::combinator::{map, opt};
+use nom::error::{Error, ErrorKind};
+use nom::multi::many1;
+use nom::sequence::{preceded, tuple};
+use nom::{Err, IResult};
+use std::fmt;
+
+/// Single entry of [`Products`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ServerVal {
+    /// Product name with an optional version, e.g. `ezk/0.1.0`
+    Product {
+        name: BytesStr,
+        version: Option<BytesStr>,
+    },
+    /// Comment without the enclosing parentheses, e.g. `Linux x86_64`
+    Comment(BytesStr),
+}
+
+impl ServerVal {
+    fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            alt((
+                map(parse_comment, |comment| {
+                    ServerVal::Comment(BytesStr::from_parse(ctx.src, comment))
+                }),
+                map(
+                    tuple((
+                        take_while1(token),
+                        opt(preceded(tag("/"), take_while1(token))),
+                    )),
+                    |(name, version)| ServerVal::Product {
+                        name: BytesStr::from_parse(ctx.src, name),
+                        version: version.map(|version| BytesStr::from_parse(ctx.src, version)),
+                    },
+                ),
+            ))(i)
+        }
+    }
+}
+
+impl fmt::Display for ServerVal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerVal::Product {
+                name,
+                version: None,
+            } => write!(f, "{}", name),
+            ServerVal::Product {
+                name,
+                version: Some(version),
+            } => write!(f, "{}/{}", name, version),
+            ServerVal::Comment(comment) => write!(f, "({})", comment),
+        }
+    }
+}
+
+/// Parse a comment which may contain nested comments, returns its content without the outer parentheses
+fn parse_comment(i: &str) -> IResult<&str, &str> {
+    let (comment, _) = tag("(")(i)?;
+
+    let mut depth = 0;
+    let mut escaped = false;
+
+    for (idx, c) in comment.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '(' => depth += 1,
+            ')' if depth == 0 => return Ok((&comment[idx + 1..], &comment[..idx])),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    Err(Err::Error(Error::new(i, ErrorKind::Char)))
+}
+
+/// Type wrapped by [`Server`] and [`UserAgent`], a list of products and comments
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct Products(pub Vec<ServerVal>);
+
+impl Products {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_product<N, V>(mut self, name: N, version: Option<V>) -> Self
+    where
+        N: Into<BytesStr>,
+        V: Into<BytesStr>,
+    {
+        self.0.push(ServerVal::Product {
+            name: name.into(),
+            version: version.map(Into::into),
+        });
+        self
+    }
+
+    pub fn with_comment<C>(mut self, comment: C) -> Self
+    where
+        C: Into<BytesStr>,
+    {
+        self.0.push(ServerVal::Comment(comment.into()));
+        self
+    }
+
+    /// Returns the version of the product with the given name, `Some(None)` if it has no version
+    pub fn version_of(&self, product: &str) -> Option<Option<&BytesStr>> {
+        self.0.iter().find_map(|val| match val {
+            ServerVal::Product { name, version } if name.eq_ignore_ascii_case(product) => {
+                Some(version.as_ref())
+            }
+            _ => None,
+        })
+    }
+
+    pub(crate) fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            map(
+                many1(preceded(take_while(whitespace), ServerVal::parse(ctx))),
+                Products,
+            )(i)
+        }
+    }
+}
+
+impl Print for Products {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
+        let mut vals = self.0.iter();
+
+        if let Some(val) = vals.next() {
+            write!(f, "{}", val)?;
+        }
+
+        for val in vals {
+            write!(f, " {}", val)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl_wrap_header!(
+    /// `Server` header. Wraps [`Products`].
+    Products,
+    Server,
+    Single,
+    Name::SERVER
+);
+
+impl_wrap_header!(
+    /// `User-Agent` header. Wraps [`Products`].
+    Products,
+    UserAgent,
+    Single,
+    Name::USER_AGENT
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::Header;
+    use crate::print::AppendCtx;
+    use std::iter::once;
+
+    #[test]
+    fn user_agent() {
+        let input = BytesStr::from_static("Softphone/Beta1.5 (Linux; x86_64 (64bit)) libsip");
+
+        let (rem, user_agent) = UserAgent::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(
+            user_agent.0 .0,
+            vec![
+                ServerVal::Product {
+                    name: BytesStr::from_static("Softphone"),
+                    version: Some(BytesStr::from_static("Beta1.5")),
+                },
+                ServerVal::Comment(BytesStr::from_static("Linux; x86_64 (64bit)")),
+                ServerVal::Product {
+                    name: BytesStr::from_static("libsip"),
+                    version: None,
+                },
+            ]
+        );
+
+        assert_eq!(
+            user_agent.version_of("softphone"),
+            Some(Some(&BytesStr::from_static("Beta1.5")))
+        );
+        assert_eq!(user_agent.version_of("libsip"), Some(None));
+        assert_eq!(user_agent.version_of("other"), None);
+    }
+
+    #[test]
+    fn server_unclosed_comment() {
+        let input = BytesStr::from_static("HomeServer/2 (unclosed");
+
+        let (rem, _) = Products::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert_eq!(rem, " (unclosed");
+    }
+
+    #[test]
+    fn server_print() {
+        let server = Server::from(
+            Products::new()
+                .with_product("ezk", Some("0.1.0"))
+                .with_comment("Linux"),
+        );
+
+        assert_eq!(server.default_print_ctx().to_string(), "ezk/0.1.0 (Linux)");
+    }
+}
@@ -0,0 +1,46 @@
+use crate::header::name::Name;
+use crate::parse::text::{LineTextSpec, Text};
+use bytesstr::BytesStr;
+
+impl_wrap_header!(
+    /// `Subject` header, contains a free text summary of the call
+    Text<LineTextSpec>,
+    BytesStr,
+    Subject,
+    Single,
+    Name::SUBJECT
+);
+
+impl_wrap_header!(
+    /// `Organization` header, contains the name of the organization the sender belongs to
+    Text<LineTextSpec>,
+    BytesStr,
+    Organization,
+    Single,
+    Name::ORGANIZATION
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::Header;
+    use crate::print::AppendCtx;
+    use std::iter::once;
+
+    #[test]
+    fn subject() {
+        let input = BytesStr::from_static(" Need more boxes, please ");
+
+        let (rem, subject) = Subject::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(subject.0, "Need more boxes, please");
+    }
+
+    #[test]
+    fn organization_print() {
+        let organization = Organization::from("Boxes by Bob");
+
+        assert_eq!(organization.default_print_ctx().to_string(), "Boxes by Bob");
+    }
+}
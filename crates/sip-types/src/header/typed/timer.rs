@@ -12,6 +12,7 @@ use std::str::FromStr;
 
 decl_from_str_header!(
     /// `Min-SE` header
+    #[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
     MinSe,
     u32,
     Single,
@@ -19,6 +20,11 @@ decl_from_str_header!(
 );
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum Refresher {
     Unspecified,
     Uas,
@@ -27,6 +33,7 @@ pub enum Refresher {
 
 /// Session-Expires header
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SessionExpires {
     pub delta_secs: u32,
     pub refresher: Refresher,
@@ -0,0 +1,102 @@
+use crate::header::name::Name;
+use crate::parse::{whitespace, ParseCtx};
+use crate::print::{Print, PrintCtx};
+use nom::bytes::complete::{tag, take_while, take_while1};
+use nom::combinator::{map, map_res, opt, recognize};
+use nom::sequence::{preceded, tuple};
+use nom::IResult;
+use std::fmt;
+use std::str::FromStr;
+
+/// `Timestamp` header
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Timestamp {
+    /// Time the request was sent by the client, the unit is chosen by the client
+    pub value: f64,
+    /// Time between receiving the request and sending the response, added by the UAS
+    pub delay: Option<f64>,
+}
+
+impl Timestamp {
+    pub fn new(value: f64) -> Self {
+        Self { value, delay: None }
+    }
+
+    pub(crate) fn parse(_: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            map(
+                tuple((
+                    parse_number,
+                    opt(preceded(take_while1(whitespace), parse_number)),
+                )),
+                |(value, delay)| Timestamp { value, delay },
+            )(i)
+        }
+    }
+}
+
+fn parse_number(i: &str) -> IResult<&str, f64> {
+    let digit = |c: char| c.is_ascii_digit();
+
+    map_res(
+        recognize(tuple((
+            take_while1(digit),
+            opt(tuple((tag("."), take_while(digit)))),
+        ))),
+        f64::from_str,
+    )(i)
+}
+
+impl Print for Timestamp {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)?;
+
+        if let Some(delay) = self.delay {
+            write!(f, " {}", delay)?;
+        }
+
+        Ok(())
+    }
+}
+
+__impl_header!(Timestamp, Single, Name::TIMESTAMP);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::print::AppendCtx;
+    use bytesstr::BytesStr;
+
+    #[test]
+    fn timestamp() {
+        let input = BytesStr::from_static("54");
+
+        let (rem, timestamp) = Timestamp::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(timestamp.value, 54.0);
+        assert_eq!(timestamp.delay, None);
+    }
+
+    #[test]
+    fn timestamp_delay() {
+        let input = BytesStr::from_static("54.25 0.5");
+
+        let (rem, timestamp) = Timestamp::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(timestamp.value, 54.25);
+        assert_eq!(timestamp.delay, Some(0.5));
+    }
+
+    #[test]
+    fn timestamp_print() {
+        let timestamp = Timestamp {
+            value: 54.25,
+            delay: Some(0.5),
+        };
+
+        assert_eq!(timestamp.default_print_ctx().to_string(), "54.25 0.5");
+    }
+}
@@ -13,6 +13,7 @@ use std::fmt;
 
 /// `Via` header
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Via {
     pub transport: BytesStr,
     pub sent_by: HostPort,
@@ -0,0 +1,120 @@
+use crate::header::name::Name;
+use crate::parse::{parse_quoted, whitespace, ParseCtx};
+use crate::print::{Print, PrintCtx};
+use bytesstr::BytesStr;
+use nom::bytes::complete::{take_while1, take_while_m_n};
+use nom::combinator::{map, map_res};
+use nom::sequence::tuple;
+use nom::IResult;
+use std::fmt;
+use std::str::FromStr;
+
+/// `Warning` header. Contains only one warning. To get all warnings use [`Vec`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Warning {
+    /// Three digit warning code, e.g. `399` (miscellaneous warning)
+    pub code: u16,
+    /// Host and port or pseudonym of the entity which added the warning
+    pub agent: BytesStr,
+    /// Warning text, quoted-pairs are left escaped
+    pub text: BytesStr,
+}
+
+impl Warning {
+    pub fn new<A, T>(code: u16, agent: A, text: T) -> Self
+    where
+        A: Into<BytesStr>,
+        T: Into<BytesStr>,
+    {
+        Self {
+            code,
+            agent: agent.into(),
+            text: text.into(),
+        }
+    }
+
+    pub(crate) fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            map(
+                tuple((
+                    map_res(
+                        take_while_m_n(3, 3, |c: char| c.is_ascii_digit()),
+                        FromStr::from_str,
+                    ),
+                    take_while1(whitespace),
+                    take_while1(|c| !whitespace(c) && c != ','),
+                    take_while1(whitespace),
+                    parse_quoted,
+                )),
+                |(code, _, agent, _, text)| Warning {
+                    code,
+                    agent: BytesStr::from_parse(ctx.src, agent),
+                    text: BytesStr::from_parse(ctx.src, text),
+                },
+            )(i)
+        }
+    }
+}
+
+impl Print for Warning {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
+        write!(f, "{:03} {} \"{}\"", self.code, self.agent, self.text)
+    }
+}
+
+__impl_header!(Warning, CSV, Name::WARNING);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::Header;
+    use crate::print::AppendCtx;
+    use std::iter::once;
+
+    #[test]
+    fn warning() {
+        let input =
+            BytesStr::from_static(r#"307 isi.edu "Session parameter 'foo' not understood""#);
+
+        let (rem, warning) = Warning::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(warning.code, 307);
+        assert_eq!(warning.agent, "isi.edu");
+        assert_eq!(warning.text, "Session parameter 'foo' not understood");
+    }
+
+    #[test]
+    fn warning_multiple() {
+        let input = BytesStr::from_static(
+            r#"301 isi.edu "Incompatible network address type 'E.164'", 399 [::1]:5060 "Say \"hi\"""#,
+        );
+
+        let (rem, warnings) =
+            Vec::<Warning>::decode(Default::default(), &mut once(&input)).unwrap();
+
+        assert!(rem.is_none());
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[1].code, 399);
+        assert_eq!(warnings[1].agent, "[::1]:5060");
+        assert_eq!(warnings[1].text, r#"Say \"hi\""#);
+    }
+
+    #[test]
+    fn warning_invalid_code() {
+        let input = BytesStr::from_static(r#"30 isi.edu "Too short""#);
+
+        assert!(Warning::parse(ParseCtx::default(&input))(&input).is_err());
+    }
+
+    #[test]
+    fn warning_print() {
+        let warning = Warning::new(399, "example.com", "Miscellaneous warning");
+
+        assert_eq!(
+            warning.default_print_ctx().to_string(),
+            r#"399 example.com "Miscellaneous warning""#
+        );
+    }
+}
@@ -25,6 +25,13 @@ pub enum Host {
     Name(BytesStr),
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Host {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 impl Host {
     pub fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
         move |i| {
@@ -87,6 +94,7 @@ pub(crate) fn probe_host(c: char) -> bool {
 
 /// Contains [Host] paired with an optional port
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct HostPort {
     pub host: Host,
     pub port: Option<u16>,
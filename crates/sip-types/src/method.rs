@@ -3,7 +3,7 @@ use bytes::Bytes;
 use bytesstr::BytesStr;
 use nom::branch::alt;
 use nom::bytes::complete::{tag_no_case, take_while};
-use nom::combinator::map;
+use nom::combinator::{all_consuming, map};
 use nom::IResult;
 use std::fmt;
 
@@ -25,6 +25,13 @@ use std::fmt;
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct Method(Repr);
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Method {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 macro_rules! methods {
     ($($(#[$comments:meta])* $print:literal, $ident:ident;)+) => {
 
@@ -39,11 +46,11 @@ macro_rules! methods {
             $(pub const $ident : Self = Self(Repr :: $ident );)+
 
             fn from_parse(src: &Bytes, slice: &str) -> Self {
-                if let Ok((_, repr)) = alt((
+                if let Ok((_, repr)) = all_consuming(alt((
                    $(
                    map(tag_no_case($print), |_| Repr::$ident),
                    )*
-                ))(slice) as IResult<&str, Repr> {
+                )))(slice) as IResult<&str, Repr> {
                     Self(repr)
                 } else {
                     Self(Repr::Other(BytesStr::from_parse(src, slice)))
@@ -72,6 +79,8 @@ methods! {
     "UPDATE",      UPDATE;
     "PRACK",       PRACK;
     "OPTIONS",     OPTIONS;
+    "PUBLISH",     PUBLISH;
+    "INFO",        INFO;
 }
 
 impl Method {
@@ -119,4 +128,11 @@ mod test {
 
         assert_eq!(method.to_string(), "SOMEOBSCUREMETHOD");
     }
+
+    #[test]
+    fn other_method_with_known_prefix() {
+        let method = Method::from("INFORM");
+
+        assert_eq!(method, Method(Repr::Other("INFORM".into())));
+    }
 }
@@ -1,13 +1,16 @@
 //! Contains SIP message parts and parser
 
 use crate::code::Code;
+use crate::header::typed::{CSeq, ContentLength};
 use crate::method::Method;
-use crate::parse::{token, whitespace, ParseCtx};
-use crate::print::{AppendCtx, Print, PrintCtx};
+use crate::parse::diagnostic::check_header;
+use crate::parse::{token, whitespace, Diagnostic, ParseCtx, ParseMode, Parser};
+use crate::print::{AppendCtx, Print, PrintCtx, PrintMode};
+use crate::uri::sip::SipUri;
 use crate::uri::Uri;
-use crate::Name;
+use crate::{Headers, Name};
 use anyhow::Result;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use bytesstr::BytesStr;
 use internal::ws;
 use memchr::memchr2;
@@ -17,8 +20,9 @@ use nom::character::complete::char;
 use nom::combinator::{map, map_res, opt};
 use nom::sequence::{preceded, separated_pair, terminated, tuple};
 use nom::{AsChar, IResult};
-use std::fmt;
-use std::str::FromStr;
+use std::fmt::{self, Write};
+use std::str::{from_utf8, FromStr};
+use thiserror::Error;
 
 fn not_newline(c: char) -> bool {
     !matches!(c, '\n' | '\r')
@@ -84,7 +88,12 @@ impl Line {
 }
 
 /// The leading line of any SIP message
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum MessageLine {
     Request(RequestLine),
     Response(StatusLine),
@@ -126,7 +135,8 @@ impl Print for MessageLine {
 }
 
 /// The leading line of a SIP request message
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RequestLine {
     pub method: Method,
     pub uri: Box<dyn Uri>,
@@ -157,7 +167,8 @@ impl RequestLine {
 }
 
 /// The leading line of a SIP response message
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StatusLine {
     pub code: Code,
     pub reason: Option<BytesStr>,
@@ -201,10 +212,294 @@ impl StatusLine {
     }
 }
 
+/// Error returned by [`SipMessage::parse`]
+#[derive(Debug, Error)]
+pub enum ParseMessageError {
+    #[error("Message Incomplete")]
+    Incomplete,
+    #[error("Message Head is not valid UTF-8")]
+    InvalidUtf8,
+    #[error("Invalid Request/Status Line")]
+    InvalidMessageLine,
+    #[error("Invalid Header Line")]
+    InvalidHeaderLine,
+    #[error("Message Body Incomplete")]
+    BodyIncomplete,
+    #[error("Message Rejected: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Rejected(Vec<Diagnostic>),
+}
+
+/// A complete SIP request or response
+///
+/// Can be used to parse and print SIP messages outside of any transport, e.g. when reading them from logs or test fixtures.
+///
+/// # Example
+///
+/// ```
+/// use ezk_sip_types::msg::SipMessage;
+/// use ezk_sip_types::Method;
+/// use bytes::Bytes;
+///
+/// let msg = Bytes::from_static(b"MESSAGE sip:bob@example.com SIP/2.0\r
+/// Call-ID: a84b4c76e66710\r
+/// Content-Length: 5\r
+/// \r
+/// Hello");
+///
+/// let message = SipMessage::parse(&msg).unwrap();
+///
+/// assert_eq!(message.line.request_method(), Some(&Method::MESSAGE));
+/// assert_eq!(message.body, "Hello");
+/// assert_eq!(message.to_bytes(), msg);
+/// ```
+///
+/// # Serialization
+///
+/// With the `serde` feature messages implement `Serialize`. The representation is lossless and stable, e.g. as JSON:
+///
+/// - [`Method`], [`Name`] and [`Host`](crate::host::Host) are strings, [`Code`] is a number
+/// - [`Headers`] are an ordered list of `{"name", "value", "typed"}` objects. `typed` contains the
+///   decoded header if its name is known and its value valid, `null` otherwise. Values of comma
+///   separated headers (e.g. `Via`) are always decoded as a list.
+/// - URIs are tagged with their type: `{"sip": {..}}`, `{"tel": {..}}`, `{"absolute": {..}}` or
+///   `{"other": "<printed uri>"}`
+/// - Parameters are an ordered list of `{"name", "value"}` objects
+/// - Bodies are `{"encoding": "utf-8", "data": ".."}` or, if they contain binary data,
+///   `{"encoding": "base64", "data": ".."}`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SipMessage {
+    pub line: MessageLine,
+    pub headers: Headers,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_body"))]
+    pub body: Bytes,
+}
+
+impl SipMessage {
+    pub fn new(line: MessageLine, headers: Headers, body: Bytes) -> Self {
+        Self {
+            line,
+            headers,
+            body,
+        }
+    }
+
+    pub fn is_request(&self) -> bool {
+        self.line.is_request()
+    }
+
+    /// Parse a complete SIP message using the default [`Parser`]
+    pub fn parse(src: &Bytes) -> Result<Self, ParseMessageError> {
+        Self::parse_with(src, Parser::default())
+    }
+
+    /// Parse a complete SIP message
+    ///
+    /// The body is sliced using the `Content-Length` header. If it is missing or invalid
+    /// everything after the message head is considered to be the body.
+    ///
+    /// Using [`ParseMode::Strict`] the message is rejected if any [`Diagnostic`] is an error.
+    pub fn parse_with(src: &Bytes, parser: Parser) -> Result<Self, ParseMessageError> {
+        match parser.mode {
+            ParseMode::Lenient => Self::parse_inner(src, parser, None),
+            ParseMode::Strict => {
+                let (message, diagnostics) = Self::parse_with_diagnostics(src, parser)?;
+
+                if diagnostics.iter().any(Diagnostic::is_error) {
+                    Err(ParseMessageError::Rejected(diagnostics))
+                } else {
+                    Ok(message)
+                }
+            }
+        }
+    }
+
+    /// Parse a complete SIP message like [`ParseMode::Lenient`] and return all defects found in it
+    ///
+    /// Every header is parsed, so this is considerably slower than [`SipMessage::parse`].
+    pub fn parse_with_diagnostics(
+        src: &Bytes,
+        parser: Parser,
+    ) -> Result<(Self, Vec<Diagnostic>), ParseMessageError> {
+        let mut diagnostics = vec![];
+
+        let message = Self::parse_inner(src, parser, Some(&mut diagnostics))?;
+
+        message.check(&mut diagnostics);
+
+        Ok((message, diagnostics))
+    }
+
+    fn parse_inner(
+        src: &Bytes,
+        parser: Parser,
+        mut diagnostics: Option<&mut Vec<Diagnostic>>,
+    ) -> Result<Self, ParseMessageError> {
+        let mut pull_parser = PullParser::new(src, 0);
+
+        let mut message_line = None;
+        let mut headers = Headers::new();
+
+        for item in &mut pull_parser {
+            let line = item.map_err(|_| ParseMessageError::Incomplete)?;
+            let line = from_utf8(line).map_err(|_| ParseMessageError::InvalidUtf8)?;
+
+            if message_line.is_none() {
+                let (_, parsed) = MessageLine::parse(ParseCtx::new(src, parser))(line)
+                    .map_err(|_| ParseMessageError::InvalidMessageLine)?;
+
+                if let Some(diagnostics) = &mut diagnostics {
+                    // Request lines use exactly one SP as separator (RFC 3261 Section 7.1)
+                    if parsed.is_request()
+                        && (line.split(' ').count() != 3 || line.split(' ').any(str::is_empty))
+                    {
+                        diagnostics.push(Diagnostic::RequestLineWhitespace);
+                    }
+                }
+
+                message_line = Some(parsed);
+            } else {
+                let (_, line) =
+                    Line::parse(src)(line).map_err(|_| ParseMessageError::InvalidHeaderLine)?;
+
+                if let Some(diagnostics) = &mut diagnostics {
+                    diagnostics.extend(check_header(parser, &line.name, &line.value));
+                }
+
+                headers.insert(line.name, line.value);
+            }
+        }
+
+        let line = message_line.ok_or(ParseMessageError::InvalidMessageLine)?;
+
+        let head_end = pull_parser.head_end();
+
+        let body = match headers.get::<ContentLength>() {
+            Ok(ContentLength(0)) => Bytes::new(),
+            Ok(ContentLength(len)) => {
+                if src.len() >= head_end + len {
+                    src.slice(head_end..head_end + len)
+                } else {
+                    return Err(ParseMessageError::BodyIncomplete);
+                }
+            }
+            Err(_) => src.slice(head_end..),
+        };
+
+        Ok(Self {
+            line,
+            headers,
+            body,
+        })
+    }
+
+    /// Check the parsed message for defects which aren't local to a single header
+    fn check(&self, diagnostics: &mut Vec<Diagnostic>) {
+        let mut required = vec![Name::FROM, Name::TO, Name::CALL_ID, Name::CSEQ, Name::VIA];
+
+        if let MessageLine::Request(line) = &self.line {
+            required.push(Name::MAX_FORWARDS);
+
+            if let Some(sip_uri) = line.uri.downcast_ref::<SipUri>() {
+                if sip_uri.header_params.iter().next().is_some() {
+                    diagnostics.push(Diagnostic::RequestUriHeaders);
+                }
+            }
+
+            if let Ok(cseq) = self.headers.get::<CSeq>() {
+                if cseq.method != line.method {
+                    diagnostics.push(Diagnostic::CSeqMethodMismatch);
+                }
+            }
+        }
+
+        for name in required {
+            if !self.headers.iter().any(|(n, _)| *n == name) {
+                diagnostics.push(Diagnostic::MissingHeader(name));
+            }
+        }
+
+        let single = [
+            Name::CALL_ID,
+            Name::CSEQ,
+            Name::FROM,
+            Name::TO,
+            Name::MAX_FORWARDS,
+            Name::CONTENT_LENGTH,
+        ];
+
+        for name in single {
+            if self.headers.iter().filter(|(n, _)| **n == name).count() > 1 {
+                diagnostics.push(Diagnostic::RepeatedHeader(name));
+            }
+        }
+    }
+
+    /// Set the `Content-Length` header to the length of the body, replacing any existing one
+    pub fn set_content_length(&mut self) {
+        self.headers.remove(&Name::CONTENT_LENGTH);
+        self.headers
+            .insert(Name::CONTENT_LENGTH, self.body.len().to_string());
+    }
+
+    /// Print the message into a buffer
+    ///
+    /// Headers are printed as they are, use [`SipMessage::set_content_length`] before
+    /// printing a message with a modified body.
+    pub fn to_bytes(&self) -> Bytes {
+        self.to_bytes_with(PrintMode::Normal)
+    }
+
+    /// Print the message into a buffer using the given [`PrintMode`]
+    pub fn to_bytes_with(&self, mode: PrintMode) -> Bytes {
+        let ctx = PrintCtx {
+            method: self.line.request_method(),
+            uri: None,
+        };
+
+        let mut buf = BytesMut::new();
+
+        write!(
+            buf,
+            "{}\r\n{}\r\n",
+            self.line.print_ctx(ctx),
+            self.headers.display(mode)
+        )
+        .expect("writing to BytesMut never fails");
+
+        buf.extend_from_slice(&self.body);
+        buf.freeze()
+    }
+}
+
+/// Serializes a message body as UTF-8 string if possible or base64 encoded otherwise,
+/// see [`SipMessage`]'s serialization
+#[cfg(feature = "serde")]
+pub fn serialize_body<S: serde::Serializer>(body: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::ser::SerializeStruct;
+
+    let mut s = serializer.serialize_struct("Body", 2)?;
+
+    match from_utf8(body) {
+        Ok(data) => {
+            s.serialize_field("encoding", "utf-8")?;
+            s.serialize_field("data", data)?;
+        }
+        Err(_) => {
+            s.serialize_field("encoding", "base64")?;
+            s.serialize_field("data", &STANDARD.encode(body))?;
+        }
+    }
+
+    s.end()
+}
+
 /// Simple pull parser which returns all lines in a SIP message.
 ///
 /// > __Note:__ Lines are terminated with either `\n` or `\r\n` followed by anything but a whitespace.
-/// This is a SIP message feature allowing multi-line headers.
+/// > This is a SIP message feature allowing multi-line headers.
 ///
 /// # Examples
 ///
@@ -364,3 +659,286 @@ impl<'i> Iterator for PullParser<'i> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::typed::CallID;
+
+    #[test]
+    fn sip_message_response_without_content_length() {
+        let src = Bytes::from_static(
+            b"SIP/2.0 200 OK\r\nCall-ID: a84b4c76e66710\r\nCSeq: 1 OPTIONS\r\n\r\nbody",
+        );
+
+        let message = SipMessage::parse(&src).unwrap();
+
+        assert!(!message.is_request());
+        assert!(matches!(&message.line, MessageLine::Response(line) if line.code == Code::OK));
+        assert_eq!(message.headers.get::<CallID>().unwrap().0, "a84b4c76e66710");
+        assert_eq!(message.body, "body");
+    }
+
+    #[test]
+    fn sip_message_body_incomplete() {
+        let src = Bytes::from_static(
+            b"MESSAGE sip:bob@example.com SIP/2.0\r\nContent-Length: 10\r\n\r\nHello",
+        );
+
+        assert!(matches!(
+            SipMessage::parse(&src),
+            Err(ParseMessageError::BodyIncomplete)
+        ));
+    }
+
+    #[test]
+    fn sip_message_invalid_line() {
+        let src = Bytes::from_static(b"not a sip message\r\n\r\n");
+
+        assert!(matches!(
+            SipMessage::parse(&src),
+            Err(ParseMessageError::InvalidMessageLine)
+        ));
+    }
+
+    #[test]
+    fn sip_message_set_content_length() {
+        let src = Bytes::from_static(
+            b"MESSAGE sip:bob@example.com SIP/2.0\r\nContent-Length: 5\r\n\r\nHello",
+        );
+
+        let mut message = SipMessage::parse(&src).unwrap();
+        message.body = Bytes::from_static(b"Hello World");
+        message.set_content_length();
+
+        assert_eq!(
+            message.to_bytes(),
+            "MESSAGE sip:bob@example.com SIP/2.0\r\nContent-Length: 11\r\n\r\nHello World"
+        );
+    }
+
+    #[test]
+    fn sip_message_compact() {
+        let src = Bytes::from_static(
+            b"MESSAGE sip:bob@example.com SIP/2.0\r\n\
+            Via: SIP/2.0/UDP example.com;branch=z9hG4bK123\r\n\
+            Call-ID: abc\r\n\
+            CSeq: 1 MESSAGE\r\n\
+            Content-Length: 5\r\n\
+            \r\n\
+            Hello",
+        );
+
+        let message = SipMessage::parse(&src).unwrap();
+        let compact = message.to_bytes_with(PrintMode::Compact);
+
+        assert_eq!(
+            compact,
+            "MESSAGE sip:bob@example.com SIP/2.0\r\n\
+            v:SIP/2.0/UDP example.com;branch=z9hG4bK123\r\n\
+            i:abc\r\n\
+            CSeq:1 MESSAGE\r\n\
+            l:5\r\n\
+            \r\n\
+            Hello"
+        );
+
+        let reparsed = SipMessage::parse(&compact).unwrap();
+
+        assert_eq!(reparsed.to_bytes(), src);
+    }
+
+    #[cfg(feature = "serde")]
+    mod serialize {
+        use super::*;
+        use serde_json::json;
+
+        #[test]
+        fn request() {
+            let src = Bytes::from_static(
+                b"INVITE sip:bob@example.com;transport=tcp SIP/2.0\r\n\
+                Via: SIP/2.0/UDP 10.0.0.1:5060;branch=z9hG4bK1, SIP/2.0/TCP proxy.example.com\r\n\
+                From: \"Alice\" <sip:alice@example.com>;tag=1234\r\n\
+                To: <tel:+4912345>\r\n\
+                CSeq: 1 INVITE\r\n\
+                X-Custom: custom value\r\n\
+                Content-Length: 5\r\n\
+                \r\n\
+                Hello",
+            );
+
+            let message = SipMessage::parse(&src).unwrap();
+
+            assert_eq!(
+                serde_json::to_value(&message).unwrap(),
+                json!({
+                    "line": {
+                        "request": {
+                            "method": "INVITE",
+                            "uri": {
+                                "sip": {
+                                    "sips": false,
+                                    "user": "bob",
+                                    "password": null,
+                                    "host_port": { "host": "example.com", "port": null },
+                                    "uri_params": [{ "name": "transport", "value": "tcp" }],
+                                    "header_params": []
+                                }
+                            }
+                        }
+                    },
+                    "headers": [
+                        {
+                            "name": "Via",
+                            "value": "SIP/2.0/UDP 10.0.0.1:5060;branch=z9hG4bK1, SIP/2.0/TCP proxy.example.com",
+                            "typed": [
+                                {
+                                    "transport": "UDP",
+                                    "sent_by": { "host": "10.0.0.1", "port": 5060 },
+                                    "params": [{ "name": "branch", "value": "z9hG4bK1" }]
+                                },
+                                {
+                                    "transport": "TCP",
+                                    "sent_by": { "host": "proxy.example.com", "port": null },
+                                    "params": []
+                                }
+                            ]
+                        },
+                        {
+                            "name": "From",
+                            "value": "\"Alice\" <sip:alice@example.com>;tag=1234",
+                            "typed": {
+                                "uri": {
+                                    "name": "Alice",
+                                    "uri": {
+                                        "sip": {
+                                            "sips": false,
+                                            "user": "alice",
+                                            "password": null,
+                                            "host_port": { "host": "example.com", "port": null },
+                                            "uri_params": [],
+                                            "header_params": []
+                                        }
+                                    }
+                                },
+                                "tag": "1234",
+                                "params": []
+                            }
+                        },
+                        {
+                            "name": "To",
+                            "value": "<tel:+4912345>",
+                            "typed": {
+                                "uri": {
+                                    "name": null,
+                                    "uri": {
+                                        "tel": {
+                                            "number": "+4912345",
+                                            "phone_context": null,
+                                            "ext": null,
+                                            "isub": null,
+                                            "params": []
+                                        }
+                                    }
+                                },
+                                "tag": null,
+                                "params": []
+                            }
+                        },
+                        {
+                            "name": "CSeq",
+                            "value": "1 INVITE",
+                            "typed": { "cseq": 1, "method": "INVITE" }
+                        },
+                        {
+                            "name": "X-Custom",
+                            "value": "custom value",
+                            "typed": null
+                        },
+                        {
+                            "name": "Content-Length",
+                            "value": "5",
+                            "typed": 5
+                        }
+                    ],
+                    "body": { "encoding": "utf-8", "data": "Hello" }
+                })
+            );
+        }
+
+        #[test]
+        fn response_binary_body() {
+            let src = Bytes::from_static(
+                b"SIP/2.0 200 OK\r\nCSeq: x INVITE\r\nContent-Length: 3\r\n\r\n\xff\x00\x01",
+            );
+
+            let message = SipMessage::parse(&src).unwrap();
+
+            assert_eq!(
+                serde_json::to_value(&message).unwrap(),
+                json!({
+                    "line": { "response": { "code": 200, "reason": "OK" } },
+                    "headers": [
+                        { "name": "CSeq", "value": "x INVITE", "typed": null },
+                        { "name": "Content-Length", "value": "3", "typed": 3 }
+                    ],
+                    "body": { "encoding": "base64", "data": "/wAB" }
+                })
+            );
+        }
+
+        #[test]
+        fn response_informational_headers() {
+            let src = Bytes::from_static(
+                b"SIP/2.0 603 Decline\r\n\
+                Date: Sat, 13 Nov 2010 23:29:00 GMT\r\n\
+                Server: ezk/0.1 (Linux)\r\n\
+                Warning: 370 proxy.example.com \"Insufficient bandwidth\"\r\n\
+                Reason: SIP;cause=603;text=\"Decline\"\r\n\
+                \r\n",
+            );
+
+            let message = SipMessage::parse(&src).unwrap();
+            let json = serde_json::to_value(&message).unwrap();
+
+            assert_eq!(json["headers"][0]["typed"], "Sat, 13 Nov 2010 23:29:00 GMT");
+            assert_eq!(
+                json["headers"][1]["typed"],
+                json!([
+                    { "product": { "name": "ezk", "version": "0.1" } },
+                    { "comment": "Linux" }
+                ])
+            );
+            assert_eq!(
+                json["headers"][2]["typed"],
+                json!([{ "code": 370, "agent": "proxy.example.com", "text": "Insufficient bandwidth" }])
+            );
+            assert_eq!(
+                json["headers"][3]["typed"],
+                json!([{ "protocol": "SIP", "cause": 603, "text": "Decline", "params": [] }])
+            );
+        }
+
+        #[test]
+        fn request_history_info() {
+            let src = Bytes::from_static(
+                b"INVITE sip:vm@example.com SIP/2.0\r\n\
+                History-Info: <sip:bob@example.com>;index=1, <sip:vm@example.com>;index=1.1;mp=1\r\n\
+                \r\n",
+            );
+
+            let message = SipMessage::parse(&src).unwrap();
+            let json = serde_json::to_value(&message).unwrap();
+
+            let typed = &json["headers"][0]["typed"];
+            assert_eq!(typed[0]["index"], "1");
+            assert_eq!(typed[0]["tag"], json!(null));
+            assert_eq!(typed[1]["index"], "1.1");
+            assert_eq!(typed[1]["tag"], json!({ "mp": "1" }));
+            assert_eq!(typed[1]["uri"]["uri"]["sip"]["user"], "vm");
+        }
+    }
+}
+
+#[cfg(test)]
+mod torture;
@@ -0,0 +1,623 @@
+//! Torture test messages from [RFC4475](https://datatracker.ietf.org/doc/html/rfc4475)
+
+use super::*;
+use crate::header::typed::{
+    Accept, Authorization, CallID, Contact, ContentType, From, MaxForwards, Require, To, Via,
+};
+use crate::uri::absolute::AbsoluteUri;
+use crate::uri::params::Param;
+
+macro_rules! torture {
+    ($name:literal) => {
+        Bytes::from_static(include_bytes!(concat!("torture/", $name, ".dat")))
+    };
+}
+
+/// Valid messages must be accepted in strict mode and must not produce any error diagnostic
+fn valid(src: Bytes) -> SipMessage {
+    let (_, diagnostics) = SipMessage::parse_with_diagnostics(&src, Parser::default()).unwrap();
+
+    assert!(
+        !diagnostics.iter().any(Diagnostic::is_error),
+        "{:?}",
+        diagnostics
+    );
+
+    SipMessage::parse_with(&src, Parser::strict()).unwrap()
+}
+
+/// Invalid messages are accepted in lenient mode, but rejected in strict mode
+fn invalid(src: Bytes) -> Vec<Diagnostic> {
+    SipMessage::parse(&src).unwrap();
+
+    match SipMessage::parse_with(&src, Parser::strict()) {
+        Err(ParseMessageError::Rejected(diagnostics)) => diagnostics,
+        res => panic!("message was not rejected: {:?}", res),
+    }
+}
+
+/// Messages which cannot be parsed in any mode
+fn unparsable(src: Bytes) -> ParseMessageError {
+    SipMessage::parse_with(&src, Parser::strict()).unwrap_err();
+    SipMessage::parse(&src).unwrap_err()
+}
+
+fn has_malformed(diagnostics: &[Diagnostic], name: Name) -> bool {
+    diagnostics
+        .iter()
+        .any(|d| matches!(d, Diagnostic::MalformedHeader(n) if *n == name))
+}
+
+// 3.1.1 Valid Messages
+
+#[test]
+fn wsinv() {
+    let message = valid(torture!("wsinv"));
+
+    assert_eq!(message.line.request_method(), Some(&Method::INVITE));
+    assert_eq!(message.body.len(), 150);
+
+    let to = message.headers.get::<To>().unwrap();
+    assert_eq!(to.tag.as_deref(), Some("1918181833n"));
+
+    let from = message.headers.get::<From>().unwrap();
+    assert_eq!(from.uri.name.as_deref(), Some(r#"J Rosenberg \\\""#));
+    assert_eq!(from.tag.as_deref(), Some("98asjd8"));
+
+    assert_eq!(message.headers.get::<MaxForwards>().unwrap().0, 68);
+
+    let cseq = message.headers.get::<CSeq>().unwrap();
+    assert_eq!(cseq.cseq, 9);
+    assert_eq!(cseq.method, Method::INVITE);
+
+    let vias = message.headers.get::<Vec<Via>>().unwrap();
+    assert_eq!(vias.len(), 3);
+    assert_eq!(vias[0].transport, "UDP");
+    assert_eq!(vias[1].transport, "TCP");
+    assert_eq!(vias[1].params.get_val("branch").unwrap(), "z9hG4bK9ikj8");
+    assert_eq!(vias[2].params.get_val("branch").unwrap(), "z9hG4bK30239");
+
+    let contact = message.headers.get::<Contact>().unwrap();
+    assert_eq!(contact.params.get_val("newparam").unwrap(), "newvalue");
+    assert_eq!(
+        contact.params.get("secondparam"),
+        Some(&Param::name("secondparam"))
+    );
+    assert_eq!(contact.params.get_val("q").unwrap(), "0.33");
+}
+
+#[test]
+fn intmeth() {
+    let message = valid(torture!("intmeth"));
+
+    let method = message.line.request_method().unwrap();
+    assert_eq!(
+        method.to_string(),
+        "!interesting-Method0123456789_*+`.%indeed'~"
+    );
+
+    let to = message.headers.get::<To>().unwrap();
+    assert_eq!(
+        to.uri.name.as_deref(),
+        Some("BEL:\\\x07 NUL:\\\x00 DEL:\\\x7F")
+    );
+
+    let call_id = message.headers.get::<CallID>().unwrap();
+    assert_eq!(call_id.0, "intmeth.word%ZK-!.*_+'@word`~)(><:\\/\"][?}{");
+}
+
+#[test]
+fn esc01() {
+    let message = valid(torture!("esc01"));
+
+    assert_eq!(message.body.len(), 150);
+    assert!(message
+        .headers
+        .get::<Contact>()
+        .unwrap()
+        .uri
+        .uri
+        .downcast_ref::<SipUri>()
+        .is_some());
+}
+
+#[test]
+fn escnull() {
+    let message = valid(torture!("escnull"));
+
+    assert_eq!(message.headers.get::<Vec<Contact>>().unwrap().len(), 2);
+}
+
+#[test]
+fn esc02() {
+    let message = valid(torture!("esc02"));
+
+    assert_eq!(
+        message.line.request_method().unwrap().to_string(),
+        "RE%47IST%45R"
+    );
+    assert_eq!(
+        message.headers.get::<To>().unwrap().uri.name.as_deref(),
+        Some("%Z%45")
+    );
+    assert_eq!(message.headers.get::<Vec<Contact>>().unwrap().len(), 3);
+}
+
+#[test]
+fn lwsdisp() {
+    let message = valid(torture!("lwsdisp"));
+
+    let from = message.headers.get::<From>().unwrap();
+    assert_eq!(from.uri.name.as_deref(), Some("caller"));
+}
+
+#[test]
+fn longreq() {
+    let message = valid(torture!("longreq"));
+
+    assert_eq!(message.headers.get::<Vec<Via>>().unwrap().len(), 29);
+    assert_eq!(message.body.len(), 150);
+}
+
+#[test]
+fn dblreq() {
+    let message = valid(torture!("dblreq"));
+
+    // The trailing INVITE must be ignored
+    assert!(message.body.is_empty());
+}
+
+#[test]
+fn semiuri() {
+    let message = valid(torture!("semiuri"));
+
+    let MessageLine::Request(line) = &message.line else {
+        panic!("expected request");
+    };
+
+    let uri: &SipUri = line.uri.downcast_ref().unwrap();
+    assert!(uri.uri_params.is_empty());
+
+    assert_eq!(message.headers.get::<Vec<Accept>>().unwrap().len(), 6);
+}
+
+#[test]
+fn transports() {
+    let message = valid(torture!("transports"));
+
+    let transports: Vec<_> = message
+        .headers
+        .get::<Vec<Via>>()
+        .unwrap()
+        .into_iter()
+        .map(|via| via.transport)
+        .collect();
+
+    assert_eq!(transports, ["UDP", "SCTP", "TLS", "UNKNOWN", "TCP"]);
+}
+
+#[test]
+fn mpart01() {
+    let message = valid(torture!("mpart01"));
+
+    let content_type = message.headers.get::<ContentType>().unwrap();
+    let multipart = crate::multipart::MultipartBody::parse(&content_type, &message.body).unwrap();
+
+    assert_eq!(multipart.parts.len(), 2);
+}
+
+#[test]
+fn unreason() {
+    let message = valid(torture!("unreason"));
+
+    let MessageLine::Response(line) = &message.line else {
+        panic!("expected response");
+    };
+
+    assert_eq!(line.code, Code::OK);
+    assert_eq!(
+        line.reason.as_deref(),
+        Some("= 2**3 * 5**2 но сто девяносто девять - простое")
+    );
+    assert_eq!(message.body.len(), 154);
+}
+
+#[test]
+fn noreason() {
+    let message = valid(torture!("noreason"));
+
+    let MessageLine::Response(line) = &message.line else {
+        panic!("expected response");
+    };
+
+    assert_eq!(line.code, Code::TRYING);
+    assert!(line.reason.is_none());
+}
+
+// 3.1.2 Invalid Messages
+
+#[test]
+fn badinv01() {
+    let diagnostics = invalid(torture!("badinv01"));
+
+    assert!(has_malformed(&diagnostics, Name::VIA));
+    assert!(has_malformed(&diagnostics, Name::CONTACT));
+}
+
+#[test]
+fn clerr() {
+    assert!(matches!(
+        unparsable(torture!("clerr")),
+        ParseMessageError::BodyIncomplete
+    ));
+}
+
+#[test]
+fn ncl() {
+    let diagnostics = invalid(torture!("ncl"));
+
+    assert!(has_malformed(&diagnostics, Name::CONTENT_LENGTH));
+}
+
+#[test]
+fn scalar02() {
+    let diagnostics = invalid(torture!("scalar02"));
+
+    assert!(has_malformed(&diagnostics, Name::CSEQ));
+    assert!(has_malformed(&diagnostics, Name::EXPIRES));
+    assert!(diagnostics
+        .iter()
+        .any(|d| matches!(d, Diagnostic::ValueOutOfRange(n) if *n == Name::MAX_FORWARDS)));
+}
+
+#[test]
+fn scalarlg() {
+    let diagnostics = invalid(torture!("scalarlg"));
+
+    assert!(has_malformed(&diagnostics, Name::CSEQ));
+}
+
+#[test]
+fn quotbal() {
+    let diagnostics = invalid(torture!("quotbal"));
+
+    assert!(has_malformed(&diagnostics, Name::TO));
+}
+
+#[test]
+fn ltgtruri() {
+    assert!(matches!(
+        unparsable(torture!("ltgtruri")),
+        ParseMessageError::InvalidMessageLine
+    ));
+}
+
+#[test]
+fn lwsruri() {
+    let diagnostics = invalid(torture!("lwsruri"));
+
+    assert!(matches!(
+        diagnostics[..],
+        [Diagnostic::RequestLineWhitespace]
+    ));
+}
+
+#[test]
+fn lwsstart() {
+    let diagnostics = invalid(torture!("lwsstart"));
+
+    assert!(matches!(
+        diagnostics[..],
+        [Diagnostic::RequestLineWhitespace]
+    ));
+}
+
+#[test]
+fn trws() {
+    let diagnostics = invalid(torture!("trws"));
+
+    assert!(matches!(
+        diagnostics[..],
+        [Diagnostic::RequestLineWhitespace]
+    ));
+}
+
+#[test]
+fn escruri() {
+    let diagnostics = invalid(torture!("escruri"));
+
+    assert!(matches!(diagnostics[..], [Diagnostic::RequestUriHeaders]));
+}
+
+#[test]
+fn baddate() {
+    let diagnostics = invalid(torture!("baddate"));
+
+    assert!(has_malformed(&diagnostics, Name::DATE));
+}
+
+#[test]
+fn regbadct() {
+    let diagnostics = invalid(torture!("regbadct"));
+
+    assert!(has_malformed(&diagnostics, Name::CONTACT));
+}
+
+#[test]
+fn badaspec() {
+    let diagnostics = invalid(torture!("badaspec"));
+
+    assert!(has_malformed(&diagnostics, Name::TO));
+}
+
+#[test]
+fn baddn() {
+    let diagnostics = invalid(torture!("baddn"));
+
+    assert!(has_malformed(&diagnostics, Name::FROM));
+    assert!(has_malformed(&diagnostics, Name::TO));
+}
+
+#[test]
+fn badvers() {
+    assert!(matches!(
+        unparsable(torture!("badvers")),
+        ParseMessageError::InvalidMessageLine
+    ));
+}
+
+#[test]
+fn mismatch01() {
+    let diagnostics = invalid(torture!("mismatch01"));
+
+    assert!(matches!(diagnostics[..], [Diagnostic::CSeqMethodMismatch]));
+}
+
+#[test]
+fn mismatch02() {
+    let diagnostics = invalid(torture!("mismatch02"));
+
+    assert!(matches!(diagnostics[..], [Diagnostic::CSeqMethodMismatch]));
+}
+
+#[test]
+fn bigcode() {
+    assert!(matches!(
+        unparsable(torture!("bigcode")),
+        ParseMessageError::InvalidMessageLine
+    ));
+}
+
+#[test]
+fn insuf() {
+    let diagnostics = invalid(torture!("insuf"));
+
+    let missing: Vec<_> = diagnostics
+        .iter()
+        .filter_map(|d| match d {
+            Diagnostic::MissingHeader(name) => Some(name.as_print_str()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(missing, ["From", "To", "Call-ID", "Max-Forwards"]);
+}
+
+// 3.2 Transaction Layer Semantics
+
+#[test]
+fn badbranch() {
+    let message = valid(torture!("badbranch"));
+
+    // The branch only consists of the magic cookie, but is still a valid token
+    let via = message.headers.get::<Via>().unwrap();
+    assert_eq!(via.params.get_val("branch").unwrap(), "z9hG4bK");
+}
+
+// 3.3 Application-Layer Semantics
+
+#[test]
+fn unksm() {
+    let message = valid(torture!("unksm"));
+
+    let MessageLine::Request(line) = &message.line else {
+        panic!("expected request");
+    };
+
+    let uri: &AbsoluteUri = line.uri.downcast_ref().unwrap();
+    assert_eq!(uri.scheme, "nobodyKnowsThisScheme");
+}
+
+#[test]
+fn novelsc() {
+    let message = valid(torture!("novelsc"));
+
+    let MessageLine::Request(line) = &message.line else {
+        panic!("expected request");
+    };
+
+    let uri: &AbsoluteUri = line.uri.downcast_ref().unwrap();
+    assert_eq!(uri.scheme, "soap.beep");
+    assert_eq!(uri.scheme_specific, "//192.0.2.103:3002");
+}
+
+#[test]
+fn unksm2() {
+    let message = valid(torture!("unksm2"));
+
+    let to = message.headers.get::<To>().unwrap();
+    let uri: &AbsoluteUri = to.uri.uri.downcast_ref().unwrap();
+    assert_eq!(uri.scheme, "isbn");
+}
+
+#[test]
+fn bext01() {
+    let message = valid(torture!("bext01"));
+
+    let require: Vec<_> = message
+        .headers
+        .get::<Vec<Require>>()
+        .unwrap()
+        .into_iter()
+        .map(|require| require.0)
+        .collect();
+
+    assert_eq!(
+        require,
+        ["nothingSupportsThis", "nothingSupportsThisEither"]
+    );
+}
+
+#[test]
+fn invut() {
+    let message = valid(torture!("invut"));
+
+    let content_type = message.headers.get::<ContentType>().unwrap();
+    assert!(content_type.matches("application/unknownformat"));
+    assert_eq!(message.body.len(), 40);
+}
+
+#[test]
+fn regaut01() {
+    let message = valid(torture!("regaut01"));
+
+    let authorization = message.headers.get::<Authorization>().unwrap();
+    assert_eq!(authorization.token, "NoOneKnowsThisScheme");
+}
+
+fn repeated(diagnostics: &[Diagnostic]) -> Vec<&str> {
+    diagnostics
+        .iter()
+        .filter_map(|d| match d {
+            Diagnostic::RepeatedHeader(name) => Some(name.as_print_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn multi01() {
+    let diagnostics = invalid(torture!("multi01"));
+
+    assert_eq!(
+        repeated(&diagnostics),
+        ["Call-ID", "From", "To", "Max-Forwards"]
+    );
+}
+
+#[test]
+fn mcl01() {
+    let diagnostics = invalid(torture!("mcl01"));
+
+    assert_eq!(repeated(&diagnostics), ["Content-Length"]);
+}
+
+#[test]
+fn bcast() {
+    let message = valid(torture!("bcast"));
+
+    let vias = message.headers.get::<Vec<Via>>().unwrap();
+    assert_eq!(vias.len(), 2);
+    assert_eq!(vias[1].sent_by.host.to_string(), "255.255.255.255");
+}
+
+#[test]
+fn zeromf() {
+    let message = valid(torture!("zeromf"));
+
+    assert_eq!(message.headers.get::<MaxForwards>().unwrap().0, 0);
+}
+
+#[test]
+fn cparam01() {
+    let message = valid(torture!("cparam01"));
+
+    let contact = message.headers.get::<Contact>().unwrap();
+    let uri: &SipUri = contact.uri.uri.downcast_ref().unwrap();
+
+    assert!(uri.uri_params.is_empty());
+    assert_eq!(
+        contact.params.get("unknownparam"),
+        Some(&Param::name("unknownparam"))
+    );
+}
+
+#[test]
+fn cparam02() {
+    let message = valid(torture!("cparam02"));
+
+    let contact = message.headers.get::<Contact>().unwrap();
+    let uri: &SipUri = contact.uri.uri.downcast_ref().unwrap();
+
+    assert_eq!(
+        uri.uri_params.get("unknownparam"),
+        Some(&Param::name("unknownparam"))
+    );
+    assert!(contact.params.get("unknownparam").is_none());
+}
+
+#[test]
+fn regescrt() {
+    let message = valid(torture!("regescrt"));
+
+    let contact = message.headers.get::<Contact>().unwrap();
+    let uri: &SipUri = contact.uri.uri.downcast_ref().unwrap();
+
+    assert_eq!(
+        uri.header_params.get_val("Route").unwrap(),
+        "<sip:sip.example.com>"
+    );
+}
+
+#[test]
+fn sdp01() {
+    let message = valid(torture!("sdp01"));
+
+    let accept = message.headers.get::<Vec<Accept>>().unwrap();
+    assert_eq!(accept.len(), 1);
+    assert_eq!(accept[0].0, "text/nobodyKnowsThis");
+    assert_eq!(message.body.len(), 150);
+}
+
+// 3.4 Backward Compatibility
+
+#[test]
+fn inv2543() {
+    // RFC 2543 did not require Max-Forwards, which is only accepted in lenient mode
+    let diagnostics = invalid(torture!("inv2543"));
+
+    assert!(matches!(
+        diagnostics[..],
+        [Diagnostic::MissingHeader(ref name)] if *name == Name::MAX_FORWARDS
+    ));
+
+    let message = SipMessage::parse(&torture!("inv2543")).unwrap();
+
+    let from = message.headers.get::<From>().unwrap();
+    assert!(from.tag.is_none());
+
+    let via = message.headers.get::<Via>().unwrap();
+    assert!(via.params.get("branch").is_none());
+}
+
+// Unknown headers are reported, but never make a message invalid
+
+#[test]
+fn unknown_headers() {
+    let src = torture!("wsinv");
+
+    let (_, diagnostics) = SipMessage::parse_with_diagnostics(&src, Parser::default()).unwrap();
+
+    let unknown: Vec<_> = diagnostics
+        .iter()
+        .filter_map(|d| match d {
+            Diagnostic::UnknownHeader(name) => Some(name.as_print_str()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        unknown,
+        ["NewFangledHeader", "UnknownHeaderWithUnusualValue"]
+    );
+}
@@ -0,0 +1,307 @@
+//! Multipart MIME bodies as described in [RFC2046](https://datatracker.ietf.org/doc/html/rfc2046#section-5.1)
+//!
+//! Used to carry multiple bodies in a single SIP message (e.g. SDP together with ISUP or a location object).
+
+use crate::header::typed::{ContentDisposition, ContentType};
+use crate::header::HeaderError;
+use crate::msg::PullParser;
+use crate::Headers;
+use bytes::{BufMut, Bytes, BytesMut};
+use bytesstr::BytesStr;
+use memchr::memmem;
+use std::str::from_utf8;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MultipartError {
+    #[error("content type is not multipart")]
+    NotMultipart,
+    #[error("content type is missing the boundary parameter")]
+    MissingBoundary,
+    #[error("body does not contain the boundary delimiter")]
+    MissingDelimiter,
+    #[error("body part is incomplete")]
+    Incomplete,
+    #[error("invalid body part header")]
+    InvalidHeader,
+}
+
+/// Single part of a [`MultipartBody`]
+#[derive(Debug, Clone)]
+pub struct BodyPart {
+    /// Headers of the part, usually `Content-Type` and `Content-Disposition`
+    pub headers: Headers,
+    pub body: Bytes,
+}
+
+impl BodyPart {
+    pub fn new(content_type: ContentType, body: Bytes) -> Self {
+        let mut headers = Headers::new();
+        headers.insert_type(&content_type);
+
+        Self { headers, body }
+    }
+
+    /// Set the `Content-Disposition` of the part
+    pub fn with_disposition(mut self, disposition: ContentDisposition) -> Self {
+        self.headers.insert_type(&disposition);
+        self
+    }
+
+    /// Returns the content type of the part, which defaults to `text/plain` if missing
+    pub fn content_type(&self) -> Result<ContentType, HeaderError> {
+        match self.headers.try_get() {
+            Some(content_type) => content_type,
+            None => Ok(ContentType::from_static("text/plain")),
+        }
+    }
+
+    pub fn content_disposition(&self) -> Option<Result<ContentDisposition, HeaderError>> {
+        self.headers.try_get()
+    }
+
+    fn parse(src: &Bytes) -> Result<Self, MultipartError> {
+        let mut headers = Headers::new();
+
+        // A part without headers starts with the empty line
+        let body = if let Some(body) = src.strip_prefix(b"\r\n") {
+            src.slice_ref(body)
+        } else {
+            let mut parser = PullParser::new(src, 0);
+
+            for line in &mut parser {
+                let line = line.map_err(|_| MultipartError::Incomplete)?;
+                let line = from_utf8(line).map_err(|_| MultipartError::InvalidHeader)?;
+
+                let (name, value) = line.split_once(':').ok_or(MultipartError::InvalidHeader)?;
+
+                let name = BytesStr::from_parse(src, name.trim());
+                let value = BytesStr::from_parse(src, value.trim());
+
+                headers.insert(name, value);
+            }
+
+            src.slice(parser.head_end()..)
+        };
+
+        Ok(Self { headers, body })
+    }
+}
+
+/// Multipart body (e.g. `multipart/mixed`, `multipart/related` or `multipart/alternative`)
+#[derive(Debug, Clone)]
+pub struct MultipartBody {
+    /// Content type of the whole body, always contains the `boundary` parameter
+    content_type: ContentType,
+    pub parts: Vec<BodyPart>,
+}
+
+impl MultipartBody {
+    /// Create an empty multipart body with the given subtype (e.g. `mixed`)
+    ///
+    /// The boundary must not occur inside any of the parts, so it should be a random string.
+    pub fn new<S, B>(sub_type: S, boundary: B) -> Self
+    where
+        S: Into<BytesStr>,
+        B: Into<BytesStr> + AsRef<str>,
+    {
+        Self {
+            content_type: ContentType::new("multipart", sub_type)
+                .with_value_param("boundary", boundary),
+            parts: vec![],
+        }
+    }
+
+    pub fn with_part(mut self, part: BodyPart) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    /// Content type of the whole body, to be sent in the `Content-Type` header
+    pub fn content_type(&self) -> &ContentType {
+        &self.content_type
+    }
+
+    pub fn boundary(&self) -> &BytesStr {
+        self.content_type
+            .boundary()
+            .expect("content type is only created with a boundary")
+    }
+
+    /// Parse a multipart body using the boundary of the given content type
+    pub fn parse(content_type: &ContentType, body: &Bytes) -> Result<Self, MultipartError> {
+        if !content_type.is_multipart() {
+            return Err(MultipartError::NotMultipart);
+        }
+
+        let boundary = content_type
+            .boundary()
+            .ok_or(MultipartError::MissingBoundary)?;
+
+        let delimiter = format!("\r\n--{}", boundary);
+        let finder = memmem::Finder::new(delimiter.as_bytes());
+
+        // The first delimiter may be at the very beginning, without the leading CRLF
+        let mut pos = if body.starts_with(&delimiter.as_bytes()[2..]) {
+            delimiter.len() - 2
+        } else {
+            finder
+                .find(body)
+                .map(|pos| pos + delimiter.len())
+                .ok_or(MultipartError::MissingDelimiter)?
+        };
+
+        let mut parts = vec![];
+
+        loop {
+            let rest = &body[pos..];
+
+            // The close delimiter ends the body, the epilogue is ignored
+            if rest.starts_with(b"--") {
+                break;
+            }
+
+            // Skip transport padding up to the end of the delimiter line
+            let line_end = memchr::memchr(b'\n', rest).ok_or(MultipartError::Incomplete)?;
+            let part_start = pos + line_end + 1;
+
+            let part_len = finder
+                .find(&body[part_start..])
+                .ok_or(MultipartError::Incomplete)?;
+
+            parts.push(BodyPart::parse(
+                &body.slice(part_start..part_start + part_len),
+            )?);
+
+            pos = part_start + part_len + delimiter.len();
+        }
+
+        Ok(Self {
+            content_type: content_type.clone(),
+            parts,
+        })
+    }
+
+    /// Encode the multipart body, to be sent with [`MultipartBody::content_type`]
+    pub fn to_bytes(&self) -> Bytes {
+        let boundary = self.boundary();
+
+        let mut buf = BytesMut::new();
+
+        for part in &self.parts {
+            buf.put_slice(b"--");
+            buf.put_slice(boundary.as_bytes());
+            buf.put_slice(b"\r\n");
+
+            for (name, value) in part.headers.iter() {
+                buf.put_slice(name.as_print_str().as_bytes());
+                buf.put_slice(b": ");
+                buf.put_slice(value.as_bytes());
+                buf.put_slice(b"\r\n");
+            }
+
+            buf.put_slice(b"\r\n");
+            buf.put_slice(&part.body);
+            buf.put_slice(b"\r\n");
+        }
+
+        buf.put_slice(b"--");
+        buf.put_slice(boundary.as_bytes());
+        buf.put_slice(b"--\r\n");
+
+        buf.freeze()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BODY: &[u8] = b"preamble\r\n--boundary1\r\n\
+Content-Type: application/sdp\r\n\
+\r\n\
+v=0\r\n\
+o=- 0 0 IN IP4 192.0.2.1\r\n\
+\r\n\
+--boundary1 \r\n\
+Content-Type: application/ISUP;version=nxv3;base=etsi121\r\n\
+Content-Disposition: signal;handling=optional\r\n\
+\r\n\
+01 00 49\r\n\
+--boundary1--\r\n\
+epilogue";
+
+    #[test]
+    fn multipart_parse() {
+        let content_type = "multipart/mixed;boundary=boundary1".parse().unwrap();
+
+        let multipart = MultipartBody::parse(&content_type, &Bytes::from_static(BODY)).unwrap();
+
+        assert_eq!(multipart.parts.len(), 2);
+
+        let sdp = &multipart.parts[0];
+        assert!(sdp.content_type().unwrap().matches("application/sdp"));
+        assert!(sdp.content_disposition().is_none());
+        assert_eq!(sdp.body, "v=0\r\no=- 0 0 IN IP4 192.0.2.1\r\n");
+
+        let isup = &multipart.parts[1];
+        assert!(isup.content_type().unwrap().matches("application/isup"));
+        assert!(isup.content_disposition().unwrap().unwrap().is_optional());
+        assert_eq!(isup.body, "01 00 49");
+    }
+
+    #[test]
+    fn multipart_part_without_headers() {
+        let content_type = "multipart/alternative;boundary=b".parse().unwrap();
+        let body = Bytes::from_static(b"--b\r\n\r\nhello\r\n--b--");
+
+        let multipart = MultipartBody::parse(&content_type, &body).unwrap();
+
+        assert_eq!(multipart.parts.len(), 1);
+        assert!(multipart.parts[0]
+            .content_type()
+            .unwrap()
+            .matches("text/plain"));
+        assert_eq!(multipart.parts[0].body, "hello");
+    }
+
+    #[test]
+    fn multipart_roundtrip() {
+        let multipart = MultipartBody::new("mixed", "unique-boundary-1")
+            .with_part(BodyPart::new(
+                ContentType::from_static("application/sdp"),
+                Bytes::from_static(b"v=0\r\n"),
+            ))
+            .with_part(
+                BodyPart::new(
+                    ContentType::from_static("application/pidf+xml"),
+                    Bytes::from_static(b"<presence/>"),
+                )
+                .with_disposition(ContentDisposition::new("render")),
+            );
+
+        let bytes = multipart.to_bytes();
+
+        assert_eq!(
+            bytes,
+            "--unique-boundary-1\r\n\
+Content-Type: application/sdp\r\n\
+\r\n\
+v=0\r\n\
+\r\n\
+--unique-boundary-1\r\n\
+Content-Type: application/pidf+xml\r\n\
+Content-Disposition: render\r\n\
+\r\n\
+<presence/>\r\n\
+--unique-boundary-1--\r\n"
+        );
+
+        let parsed = MultipartBody::parse(multipart.content_type(), &bytes).unwrap();
+
+        assert_eq!(parsed.boundary(), "unique-boundary-1");
+        assert_eq!(parsed.parts.len(), 2);
+        assert_eq!(parsed.parts[0].body, "v=0\r\n");
+        assert_eq!(parsed.parts[1].body, "<presence/>");
+    }
+}
@@ -0,0 +1,191 @@
+use crate::header::typed::*;
+use crate::header::{Header, Kind};
+use crate::parse::{ParseMode, Parser};
+use crate::Name;
+use bytesstr::BytesStr;
+use std::fmt;
+use std::iter::once;
+
+/// Defect found when checking a message against the SIP grammar
+///
+/// Returned by [`SipMessage::parse_with_diagnostics`](crate::msg::SipMessage::parse_with_diagnostics).
+#[derive(Debug, Clone)]
+pub enum Diagnostic {
+    /// The request line contains additional or trailing whitespace
+    RequestLineWhitespace,
+    /// The Request-URI contains escaped headers
+    RequestUriHeaders,
+    /// The header name is not known to this library, its value cannot be checked
+    UnknownHeader(Name),
+    /// The header value does not match its grammar
+    MalformedHeader(Name),
+    /// The header value is outside its legal range
+    ValueOutOfRange(Name),
+    /// A header required in every message is missing
+    MissingHeader(Name),
+    /// A header which must appear at most once is repeated
+    RepeatedHeader(Name),
+    /// The method of the CSeq header does not match the request method
+    CSeqMethodMismatch,
+}
+
+impl Diagnostic {
+    /// Returns if the diagnostic makes the message invalid, all others are informational
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Self::UnknownHeader(..))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RequestLineWhitespace => f.write_str("invalid whitespace in request line"),
+            Self::RequestUriHeaders => f.write_str("request uri contains headers"),
+            Self::UnknownHeader(name) => write!(f, "unknown header {}", name.as_print_str()),
+            Self::MalformedHeader(name) => write!(f, "malformed header {}", name.as_print_str()),
+            Self::ValueOutOfRange(name) => {
+                write!(f, "value of header {} out of range", name.as_print_str())
+            }
+            Self::MissingHeader(name) => write!(f, "missing header {}", name.as_print_str()),
+            Self::RepeatedHeader(name) => write!(f, "repeated header {}", name.as_print_str()),
+            Self::CSeqMethodMismatch => f.write_str("CSeq method does not match request method"),
+        }
+    }
+}
+
+type CheckFn = fn(Parser, &BytesStr) -> Result<(), Diagnostic>;
+
+static CHECKS: &[(Name, CheckFn)] = &[
+    (Name::ACCEPT, check::<Accept>),
+    (Name::ACCEPT_ENCODING, check::<AcceptEncoding>),
+    (Name::ACCEPT_LANGUAGE, check::<AcceptLanguage>),
+    (Name::ALERT_INFO, check::<AlertInfo>),
+    (Name::ALLOW, check::<Allow>),
+    (Name::ALLOW_EVENTS, check::<AllowEvents>),
+    (Name::AUTHORIZATION, check::<Authorization>),
+    (Name::CALL_ID, check::<CallID>),
+    (Name::CALL_INFO, check::<CallInfo>),
+    (Name::CONTACT, check::<Contact>),
+    (Name::CONTENT_DISPOSITION, check::<ContentDisposition>),
+    (Name::CONTENT_LENGTH, check::<ContentLength>),
+    (Name::CONTENT_TYPE, check::<ContentType>),
+    (Name::CSEQ, check_cseq),
+    (Name::DATE, check::<Date>),
+    (Name::DIVERSION, check::<Diversion>),
+    (Name::ERROR_INFO, check::<ErrorInfo>),
+    (Name::EVENT, check::<Event>),
+    (Name::EXPIRES, check::<Expires>),
+    (Name::FLOW_TIMER, check::<FlowTimer>),
+    (Name::FROM, check::<From>),
+    (Name::HISTORY_INFO, check::<HistoryInfo>),
+    (Name::INFO_PACKAGE, check::<InfoPackage>),
+    (Name::IN_REPLY_TO, check::<InReplyTo>),
+    (Name::MAX_FORWARDS, check_max_forwards),
+    (Name::MIN_EXPIRES, check::<MinExpires>),
+    (Name::MIN_SE, check::<MinSe>),
+    (Name::ORGANIZATION, check::<Organization>),
+    (Name::PATH, check::<Path>),
+    (Name::PRIORITY, check::<Priority>),
+    (Name::PRIVACY, check::<Privacy>),
+    (Name::PROXY_AUTHENTICATE, check::<ProxyAuthenticate>),
+    (Name::PROXY_AUTHORIZATION, check::<ProxyAuthorization>),
+    (Name::P_ASSERTED_IDENTITY, check::<PAssertedIdentity>),
+    (Name::P_PREFERRED_IDENTITY, check::<PPreferredIdentity>),
+    (Name::RACK, check::<RAck>),
+    (Name::REASON, check::<Reason>),
+    (Name::RECORD_ROUTE, check::<RecordRoute>),
+    (Name::RECV_INFO, check::<RecvInfo>),
+    (Name::REPLACES, check::<Replaces>),
+    (Name::REPLY_TO, check::<ReplyTo>),
+    (Name::REQUIRE, check::<Require>),
+    (Name::RETRY_AFTER, check::<RetryAfter>),
+    (Name::ROUTE, check::<Route>),
+    (Name::RSEQ, check::<RSeq>),
+    (Name::SERVER, check::<Server>),
+    (Name::SERVICE_ROUTE, check::<ServiceRoute>),
+    (Name::SESSION_EXPIRES, check::<SessionExpires>),
+    (Name::SIP_ETAG, check::<SipETag>),
+    (Name::SIP_IF_MATCH, check::<SipIfMatch>),
+    (Name::SUBJECT, check::<Subject>),
+    (Name::SUPPORTED, check::<Supported>),
+    (Name::TIMESTAMP, check::<Timestamp>),
+    (Name::TO, check::<To>),
+    (Name::UNSUPPORTED, check::<Unsupported>),
+    (Name::USER_AGENT, check::<UserAgent>),
+    (Name::VIA, check::<Via>),
+    (Name::WARNING, check::<Warning>),
+    (Name::WWW_AUTHENTICATE, check::<WWWAuthenticate>),
+];
+
+/// Check a single header value, headers without a typed representation are only checked for their name
+///
+/// Values are always parsed using [`ParseMode::Strict`].
+pub(crate) fn check_header(parser: Parser, name: &Name, value: &BytesStr) -> Option<Diagnostic> {
+    if name.as_parse_strs().is_none() {
+        return Some(Diagnostic::UnknownHeader(name.clone()));
+    }
+
+    let (_, check) = CHECKS.iter().find(|(n, _)| n == name)?;
+
+    let parser = Parser {
+        mode: ParseMode::Strict,
+        ..parser
+    };
+
+    check(parser, value).err()
+}
+
+/// Decode the complete value as `H`, comma separated lists are only allowed for [`Kind::CSV`] headers
+pub(crate) fn decode<H>(parser: Parser, value: &BytesStr) -> Result<Vec<H>, Diagnostic>
+where
+    H: Header<Kind = Kind>,
+{
+    let malformed = || Diagnostic::MalformedHeader(H::name().clone());
+
+    let mut value = value.clone();
+    let mut decoded = vec![];
+
+    loop {
+        let (rem, header) = H::decode(parser, &mut once(&value)).map_err(|_| malformed())?;
+
+        decoded.push(header);
+
+        let rem = match rem.map(str::trim_start) {
+            None | Some("") => return Ok(decoded),
+            Some(rem) => rem,
+        };
+
+        match (H::kind(), rem.strip_prefix(',')) {
+            (Kind::CSV, Some(next)) => value = value.slice_ref(next.trim_start()),
+            _ => return Err(malformed()),
+        }
+    }
+}
+
+fn check<H>(parser: Parser, value: &BytesStr) -> Result<(), Diagnostic>
+where
+    H: Header<Kind = Kind>,
+{
+    decode::<H>(parser, value).map(|_| ())
+}
+
+fn check_cseq(parser: Parser, value: &BytesStr) -> Result<(), Diagnostic> {
+    for cseq in decode::<CSeq>(parser, value)? {
+        // RFC 3261 Section 8.1.1.5
+        if cseq.cseq >= 1 << 31 {
+            return Err(Diagnostic::ValueOutOfRange(Name::CSEQ));
+        }
+    }
+
+    Ok(())
+}
+
+fn check_max_forwards(parser: Parser, value: &BytesStr) -> Result<(), Diagnostic> {
+    for max_forwards in decode::<MaxForwards>(parser, value)? {
+        if max_forwards.0 > 255 {
+            return Err(Diagnostic::ValueOutOfRange(Name::MAX_FORWARDS));
+        }
+    }
+
+    Ok(())
+}
@@ -2,20 +2,38 @@
 
 #![allow(unused_parens)]
 
+use crate::uri::absolute;
 use crate::uri::sip::SipUri;
+use crate::uri::tel::TelUri;
 use crate::uri::Uri;
 use bytes::Bytes;
 use nom::branch::alt;
-use nom::bytes::complete::{escaped, is_not};
 use nom::character::complete::char;
 use nom::combinator::map;
-use nom::sequence::delimited;
+use nom::error::ErrorKind;
 use nom::IResult;
 
+pub(crate) mod diagnostic;
 pub(crate) mod text;
 
+pub use diagnostic::Diagnostic;
+
+/// Parse a quoted string, returns its content without the surrounding quotes and with quoted-pairs left escaped
 pub(crate) fn parse_quoted(i: &str) -> IResult<&str, &str> {
-    delimited(char('"'), escaped(is_not("\""), '\\', char('"')), char('"'))(i)
+    let (quoted, _) = char('"')(i)?;
+
+    let mut escaped = false;
+
+    for (idx, c) in quoted.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Ok((&quoted[idx + 1..], &quoted[..idx])),
+            _ => {}
+        }
+    }
+
+    Err(nom::Err::Error(nom::error::Error::new(i, ErrorKind::Char)))
 }
 
 pub(crate) fn whitespace(c: char) -> bool {
@@ -28,29 +46,51 @@ pub(crate) fn token(c: char) -> bool {
 
 /// Can be used to extend the parsing capabilities of this library.
 ///
-/// Currently this can be used to register nom parsers for custom URI types
+/// Currently this can be used to register nom parsers for custom URI types.
+/// SIP and tel URIs are always parsed, before trying the custom parsers.
+/// The default parsers accept any absolute URI as [`AbsoluteUri`](crate::uri::absolute::AbsoluteUri).
+///
+/// It also selects the [`ParseMode`] used when parsing complete messages.
 #[derive(Copy, Clone)]
 pub struct Parser {
     pub parse_other_uri: fn(&str) -> IResult<&str, Box<dyn Uri>>,
     pub parse_other_uri_no_params: fn(&str) -> IResult<&str, Box<dyn Uri>>,
+    pub mode: ParseMode,
 }
 
-fn fail(i: &str) -> IResult<&str, Box<dyn Uri>> {
-    Err(nom::Err::Error(nom::error::Error::new(
-        i,
-        nom::error::ErrorKind::Alt,
-    )))
+impl Parser {
+    /// Returns the default parser using [`ParseMode::Strict`]
+    pub fn strict() -> Self {
+        Self {
+            mode: ParseMode::Strict,
+            ..Self::default()
+        }
+    }
 }
 
 impl Default for Parser {
     fn default() -> Self {
         Self {
-            parse_other_uri: fail,
-            parse_other_uri_no_params: fail,
+            parse_other_uri: absolute::parse_other,
+            parse_other_uri_no_params: absolute::parse_other_no_params,
+            mode: ParseMode::Lenient,
         }
     }
 }
 
+/// Selects how [`SipMessage::parse_with`](crate::msg::SipMessage::parse_with) treats messages
+/// which do not conform to the SIP grammar
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Accept every message which has a valid message line, malformed headers are only
+    /// noticed once they are accessed. Required to interoperate with buggy devices.
+    #[default]
+    Lenient,
+
+    /// Check the whole message and reject it if any [`Diagnostic`] is an error
+    Strict,
+}
+
 /// Contains the source buffer and a parser
 #[derive(Copy, Clone)]
 pub struct ParseCtx<'p> {
@@ -77,6 +117,7 @@ impl<'p> ParseCtx<'p> {
         move |i| {
             alt((
                 map(SipUri::parse(self), |uri| -> Box<dyn Uri> { Box::new(uri) }),
+                map(TelUri::parse(self), |uri| -> Box<dyn Uri> { Box::new(uri) }),
                 self.parser.parse_other_uri,
             ))(i)
         }
@@ -88,6 +129,9 @@ impl<'p> ParseCtx<'p> {
                 map(SipUri::parse_no_params(self), |uri| -> Box<dyn Uri> {
                     Box::new(uri)
                 }),
+                map(TelUri::parse_no_params(self), |uri| -> Box<dyn Uri> {
+                    Box::new(uri)
+                }),
                 self.parser.parse_other_uri_no_params,
             ))(i)
         }
@@ -104,5 +148,10 @@ mod test {
         assert_eq!(parse_quoted(r#""Bob" "#), Ok((" ", "Bob")));
 
         assert_eq!(parse_quoted(r#""Bob" "Alice""#), Ok((r#" "Alice""#, "Bob")));
+
+        assert_eq!(
+            parse_quoted(r#""Bob \"B\" \\" x"#),
+            Ok((" x", r#"Bob \"B\" \\"#))
+        );
     }
 }
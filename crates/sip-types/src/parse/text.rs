@@ -1,12 +1,12 @@
 use crate::parse::ParseCtx;
 use bytesstr::BytesStr;
-use nom::bytes::complete::take_while1;
-use nom::combinator::map;
+use nom::bytes::complete::{take_while, take_while1};
 use nom::IResult;
 use std::marker::PhantomData;
 
 pub trait TextSpec {
     const SPEC: fn(char) -> bool;
+    const ALLOW_EMPTY: bool = false;
 }
 
 pub enum CsvTextSpec {}
@@ -21,6 +21,14 @@ impl TextSpec for SingleTextSpec {
     const SPEC: fn(char) -> bool = |c| !c.is_ascii_whitespace();
 }
 
+/// Takes the complete value, used for free text headers like `Subject` which may also be empty
+pub enum LineTextSpec {}
+
+impl TextSpec for LineTextSpec {
+    const SPEC: fn(char) -> bool = |_| true;
+    const ALLOW_EMPTY: bool = true;
+}
+
 pub struct Text<S> {
     m: PhantomData<S>,
 }
@@ -28,9 +36,13 @@ pub struct Text<S> {
 impl<S: TextSpec> Text<S> {
     pub fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, BytesStr> + '_ {
         move |i| {
-            map(take_while1(S::SPEC), move |slice| {
-                BytesStr::from_parse(ctx.src, str::trim(slice))
-            })(i)
+            let (rem, slice) = if S::ALLOW_EMPTY {
+                take_while(S::SPEC)(i)?
+            } else {
+                take_while1(S::SPEC)(i)?
+            };
+
+            Ok((rem, BytesStr::from_parse(ctx.src, str::trim(slice))))
         }
     }
 }
@@ -29,6 +29,18 @@ pub struct PrintCtx<'a> {
     pub uri: Option<UriContext>,
 }
 
+/// Selects how [`Headers`](crate::Headers) are printed
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum PrintMode {
+    /// Print the full header names, e.g. `Content-Length: 0`
+    #[default]
+    Normal,
+
+    /// Print the compact form of header names where one exists and drop the space after the colon, e.g. `l:0`.
+    /// Used to keep messages sent over UDP below the path MTU.
+    Compact,
+}
+
 /// Implements [`fmt::Display`] where `T` implements [`Print`] and passes its context to [`Print::print`]
 ///
 /// Constructed using [`AppendCtx::print_ctx`].
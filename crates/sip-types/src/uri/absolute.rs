@@ -0,0 +1,255 @@
+use crate::host::HostPort;
+use crate::parse::ParseCtx;
+use crate::print::{AppendCtx, Print, PrintCtx};
+use crate::uri::Uri;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use nom::bytes::complete::{take_while, take_while1};
+use nom::character::complete::{char, satisfy};
+use nom::combinator::{map, recognize, verify};
+use nom::sequence::{pair, separated_pair};
+use nom::IResult;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Absolute URI of any scheme as described in [RFC3986](https://datatracker.ietf.org/doc/html/rfc3986#section-4.3)
+///
+/// Used as fallback for all URIs which are neither SIP nor tel URIs (e.g. `urn:service:sos`,
+/// `mailto:` or `http:`). The scheme specific part is kept as is, so the URI is printed unmodified.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AbsoluteUri {
+    /// Scheme of the URI, without the trailing colon
+    pub scheme: BytesStr,
+
+    /// Everything after the colon
+    pub scheme_specific: BytesStr,
+}
+
+impl AbsoluteUri {
+    pub fn new<S, P>(scheme: S, scheme_specific: P) -> Self
+    where
+        S: Into<BytesStr>,
+        P: Into<BytesStr>,
+    {
+        Self {
+            scheme: scheme.into(),
+            scheme_specific: scheme_specific.into(),
+        }
+    }
+
+    /// Returns the host and port of the authority component, if the URI has one (e.g. `http://example.com/`)
+    pub fn authority(&self) -> Option<HostPort> {
+        let authority = self.scheme_specific.strip_prefix("//")?;
+        let authority = authority.split(['/', '?', '#']).next().unwrap_or_default();
+
+        // Strip the userinfo
+        let host_port = authority.rsplit('@').next().unwrap_or_default();
+
+        let src = BytesStr::from(host_port);
+        let (rem, host_port) = HostPort::parse(ParseCtx::default(&src))(&src).ok()?;
+
+        if rem.is_empty() {
+            Some(host_port)
+        } else {
+            None
+        }
+    }
+
+    /// Scheme names are compared case-insensitive, the scheme specific part must match exactly
+    pub fn compare(&self, other: &Self) -> bool {
+        self.scheme.eq_ignore_ascii_case(&other.scheme)
+            && self.scheme_specific == other.scheme_specific
+    }
+
+    pub fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| parse(ctx.src, uri_char)(i)
+    }
+
+    /// Parse the URI up to the first parameter delimiter, as parameters belong to the surrounding header
+    pub fn parse_no_params(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| parse(ctx.src, uri_char_no_params)(i)
+    }
+}
+
+fn parse(src: &Bytes, spec: fn(char) -> bool) -> impl Fn(&str) -> IResult<&str, AbsoluteUri> + '_ {
+    move |i| {
+        map(split(spec), |(scheme, scheme_specific)| AbsoluteUri {
+            scheme: BytesStr::from_parse(src, scheme),
+            scheme_specific: BytesStr::from_parse(src, scheme_specific),
+        })(i)
+    }
+}
+
+/// Split the URI into scheme and scheme specific part
+///
+/// SIP, SIPS and tel URIs are rejected, so malformed ones are not accepted by the fallback.
+fn split(spec: fn(char) -> bool) -> impl Fn(&str) -> IResult<&str, (&str, &str)> {
+    move |i| {
+        separated_pair(
+            verify(
+                recognize(pair(
+                    satisfy(|c| c.is_ascii_alphabetic()),
+                    take_while(scheme_char),
+                )),
+                |scheme: &str| !is_dedicated_scheme(scheme),
+            ),
+            char(':'),
+            take_while1(spec),
+        )(i)
+    }
+}
+
+/// Fallback parser for [`Parser::default`](crate::parse::Parser), which has no access to the source buffer
+pub(crate) fn parse_other(i: &str) -> IResult<&str, Box<dyn Uri>> {
+    parse_other_with(i, uri_char)
+}
+
+/// Fallback parser for [`Parser::default`](crate::parse::Parser), which has no access to the source buffer
+pub(crate) fn parse_other_no_params(i: &str) -> IResult<&str, Box<dyn Uri>> {
+    parse_other_with(i, uri_char_no_params)
+}
+
+fn parse_other_with(i: &str, spec: fn(char) -> bool) -> IResult<&str, Box<dyn Uri>> {
+    let (rem, (scheme, scheme_specific)) = split(spec)(i)?;
+
+    Ok((rem, Box::new(AbsoluteUri::new(scheme, scheme_specific))))
+}
+
+/// Returns if the scheme has its own URI type
+fn is_dedicated_scheme(scheme: &str) -> bool {
+    ["sip", "sips", "tel"]
+        .iter()
+        .any(|dedicated| scheme.eq_ignore_ascii_case(dedicated))
+}
+
+fn scheme_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')
+}
+
+fn uri_char(c: char) -> bool {
+    lookup_table!(c => alpha; num; '-', '.', '_', '~', ':', '/', '?', '#', '[', ']', '@', '!', '$', '&', '\'', '(', ')', '*', '+', ',', ';', '=', '%')
+}
+
+fn uri_char_no_params(c: char) -> bool {
+    uri_char(c) && !matches!(c, ';' | '?' | ',')
+}
+
+impl fmt::Debug for AbsoluteUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.print_ctx(PrintCtx::default()))
+    }
+}
+
+impl Print for AbsoluteUri {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.scheme, self.scheme_specific)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid absolute uri")]
+pub struct InvalidAbsoluteUri(());
+
+impl FromStr for AbsoluteUri {
+    type Err = InvalidAbsoluteUri;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = BytesStr::from(s);
+
+        let ctx = ParseCtx::default(&s);
+
+        let res = Self::parse(ctx)(s.as_ref())
+            .map(|(_, uri)| uri)
+            .map_err(|_| InvalidAbsoluteUri(()));
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::header::typed::Contact;
+    use crate::host::Host;
+    use crate::uri::NameAddr;
+
+    #[test]
+    fn absolute_urn() {
+        let uri: AbsoluteUri = "urn:service:sos".parse().unwrap();
+
+        assert_eq!(uri.scheme, "urn");
+        assert_eq!(uri.scheme_specific, "service:sos");
+        assert!(uri.authority().is_none());
+        assert_eq!(uri.default_print_ctx().to_string(), "urn:service:sos");
+    }
+
+    #[test]
+    fn absolute_http_authority() {
+        let uri: AbsoluteUri = "http://user@www.example.com:8080/photo.png?size=1"
+            .parse()
+            .unwrap();
+
+        let host_port = uri.authority().unwrap();
+
+        assert_eq!(
+            host_port,
+            HostPort {
+                host: Host::Name(BytesStr::from_static("www.example.com")),
+                port: Some(8080),
+            }
+        );
+    }
+
+    #[test]
+    fn absolute_compare() {
+        let a: AbsoluteUri = "MAILTO:alice@example.com".parse().unwrap();
+        let b: AbsoluteUri = "mailto:alice@example.com".parse().unwrap();
+
+        assert!(a.compare(&b));
+    }
+
+    #[test]
+    fn absolute_fallback_in_name_addr() {
+        let input = BytesStr::from_static("<http://www.example.com/alice/photo.jpg>;purpose=icon");
+
+        let (rem, contact) = Contact::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(contact.params.get_val("purpose").unwrap(), "icon");
+
+        let uri: &AbsoluteUri = contact.uri.uri.downcast_ref().unwrap();
+        assert_eq!(uri.scheme, "http");
+        assert_eq!(uri.scheme_specific, "//www.example.com/alice/photo.jpg");
+    }
+
+    #[test]
+    fn absolute_fallback_no_params() {
+        let input = BytesStr::from_static("im:alice@example.com;foo=bar");
+
+        let (rem, name_addr) =
+            NameAddr::parse_no_params(ParseCtx::default(&input))(&input).unwrap();
+
+        assert_eq!(rem, ";foo=bar");
+        assert_eq!(
+            name_addr.uri.default_print_ctx().to_string(),
+            "im:alice@example.com"
+        );
+    }
+
+    #[test]
+    fn absolute_rejects_dedicated_schemes() {
+        assert!("sip:alice@example.com".parse::<AbsoluteUri>().is_err());
+        assert!("SIPS:alice@example.com".parse::<AbsoluteUri>().is_err());
+        assert!("tel:+1-201-555-0123".parse::<AbsoluteUri>().is_err());
+        assert!("sipx:alice@example.com".parse::<AbsoluteUri>().is_ok());
+    }
+
+    #[test]
+    fn absolute_fallback_rejects_malformed_sip_uri() {
+        let input = BytesStr::from_static("<sip:alice@[::1>");
+
+        assert!(Contact::parse(ParseCtx::default(&input))(&input).is_err());
+    }
+}
@@ -1,16 +1,21 @@
-//! Contains the URI trait, SIP and NameAddr implementation
+//! Contains the URI trait, SIP, tel, generic absolute URI and NameAddr implementation
 
 use crate::host::HostPort;
 use crate::print::{Print, PrintCtx};
+use crate::uri::absolute::AbsoluteUri;
 use crate::uri::sip::SipUri;
+use crate::uri::tel::TelUri;
+use bytesstr::BytesStr;
 use downcast_rs::Downcast;
 use std::borrow::Cow;
 use std::fmt;
 
 #[macro_use]
 pub mod params;
+pub mod absolute;
 mod name_addr;
 pub mod sip;
+pub mod tel;
 
 pub use name_addr::NameAddr;
 
@@ -40,6 +45,33 @@ impl Print for Box<dyn Uri> {
     }
 }
 
+/// Serialized tagged with the URI's type, see [`SipMessage`](crate::msg::SipMessage)
+#[cfg(feature = "serde")]
+impl serde::Serialize for dyn Uri {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        /// Prints URIs of unknown types
+        struct Printed<'u>(&'u dyn Uri);
+
+        impl fmt::Display for Printed<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.print(f, PrintCtx::default())
+            }
+        }
+
+        if let Some(uri) = self.downcast_ref::<SipUri>() {
+            serializer.serialize_newtype_variant("Uri", 0, "sip", uri)
+        } else if let Some(uri) = self.downcast_ref::<TelUri>() {
+            serializer.serialize_newtype_variant("Uri", 1, "tel", uri)
+        } else if let Some(uri) = self.downcast_ref::<AbsoluteUri>() {
+            serializer.serialize_newtype_variant("Uri", 2, "absolute", uri)
+        } else {
+            let printed = Printed(self).to_string();
+
+            serializer.serialize_newtype_variant("Uri", 3, "other", &printed)
+        }
+    }
+}
+
 impl Uri for sip::SipUri {
     fn info(&self) -> UriInfo<'_> {
         UriInfo {
@@ -65,6 +97,61 @@ impl Uri for sip::SipUri {
     }
 }
 
+impl Uri for TelUri {
+    /// tel URIs don't describe a host to send requests to, the returned host is the
+    /// `phone-context` if it is a domain name, else empty. Requests to tel URIs must
+    /// contain a Route header.
+    fn info(&self) -> UriInfo<'_> {
+        let host = match &self.phone_context {
+            Some(phone_context) if !phone_context.starts_with('+') => phone_context.clone(),
+            _ => BytesStr::from_static(""),
+        };
+
+        UriInfo {
+            transport: None,
+            secure: false,
+            host_port: HostPort::host_name(host),
+        }
+    }
+
+    fn compare(&self, other: &dyn Uri) -> bool {
+        if let Some(other) = other.downcast_ref::<Self>() {
+            self.compare(other)
+        } else {
+            false
+        }
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Uri> {
+        Box::new(TelUri::clone(self))
+    }
+}
+
+impl Uri for AbsoluteUri {
+    /// The returned host is the host of the authority component, or empty if there is none
+    fn info(&self) -> UriInfo<'_> {
+        UriInfo {
+            transport: None,
+            secure: false,
+            host_port: self
+                .authority()
+                .unwrap_or_else(|| HostPort::host_name(BytesStr::from_static(""))),
+        }
+    }
+
+    fn compare(&self, other: &dyn Uri) -> bool {
+        if let Some(other) = other.downcast_ref::<Self>() {
+            self.compare(other)
+        } else {
+            false
+        }
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Uri> {
+        Box::new(AbsoluteUri::clone(self))
+    }
+}
+
 impl Clone for Box<dyn Uri> {
     fn clone(&self) -> Self {
         self.clone_boxed()
@@ -1,4 +1,4 @@
-use crate::parse::{parse_quoted, whitespace, ParseCtx};
+use crate::parse::{parse_quoted, token, whitespace, ParseCtx, ParseMode};
 use crate::print::{AppendCtx, Print, PrintCtx};
 use crate::uri::Uri;
 use bytesstr::BytesStr;
@@ -13,6 +13,7 @@ use std::fmt;
 /// `(token|"display") <URI> | URI`
 /// Used in From / To Headers
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NameAddr {
     pub name: Option<BytesStr>,
     pub uri: Box<dyn Uri>,
@@ -48,7 +49,7 @@ impl NameAddr {
                 alt((
                     tuple((
                         opt(map_res(
-                            alt((parse_quoted, take_while(display))),
+                            alt((parse_quoted, take_while(display(ctx)))),
                             |mut display| {
                                 display = display.trim();
                                 if display.is_empty() {
@@ -79,7 +80,7 @@ impl NameAddr {
                 alt((
                     tuple((
                         opt(map_res(
-                            alt((parse_quoted, take_while(display))),
+                            alt((parse_quoted, take_while(display(ctx)))),
                             |mut display| {
                                 display = display.trim();
                                 if display.is_empty() {
@@ -113,8 +114,17 @@ impl Print for NameAddr {
     }
 }
 
-fn display(c: char) -> bool {
-    !lookup_table!(c => ':', '\r', '\n', '<')
+/// Characters allowed in an unquoted display name, strict mode only allows `*(token LWS)`
+fn display(ctx: ParseCtx<'_>) -> impl Fn(char) -> bool {
+    let strict = ctx.parser.mode == ParseMode::Strict;
+
+    move |c| {
+        if strict {
+            token(c) || whitespace(c)
+        } else {
+            !lookup_table!(c => ':', '\r', '\n', '<')
+        }
+    }
 }
 
 #[cfg(test)]
@@ -3,7 +3,7 @@ use bytes::Bytes;
 use bytesstr::BytesStr;
 use internal::ws;
 use nom::branch::alt;
-use nom::bytes::complete::{tag, take_while};
+use nom::bytes::complete::{tag, take_while, take_while1};
 use nom::combinator::{map, map_res, opt};
 use nom::multi::many0;
 use nom::IResult;
@@ -20,6 +20,13 @@ pub struct Params<S> {
     marker: PhantomData<S>,
 }
 
+#[cfg(feature = "serde")]
+impl<P> serde::Serialize for Params<P> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.params)
+    }
+}
+
 impl<S: ParamsSpec> Params<S> {
     pub fn new() -> Params<S> {
         Params {
@@ -44,6 +51,11 @@ impl<S: ParamsSpec> Params<S> {
         self.params.push(param);
     }
 
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Param> + '_ {
+        self.params.iter()
+    }
+
     #[inline]
     pub fn get<N>(&self, name: N) -> Option<&Param>
     where
@@ -101,6 +113,25 @@ impl<S: ParamsSpec> Params<S> {
         }
     }
 
+    /// Print the params as header parameters, where values that cannot be represented
+    /// as token are printed as quoted string instead of being percent-encoded
+    pub fn quoted_print(&self) -> QuotedPrint<'_, S, fn(&str) -> bool> {
+        self.quoted_print_if(|_| true)
+    }
+
+    /// Like [`Params::quoted_print`], but only quotes the values of params whose name
+    /// matches the filter (e.g. `+sip.instance="<urn:uuid:...>"`), all other values
+    /// are percent-encoded
+    pub fn quoted_print_if<F>(&self, filter: F) -> QuotedPrint<'_, S, F>
+    where
+        F: Fn(&str) -> bool,
+    {
+        QuotedPrint {
+            params: self,
+            filter,
+        }
+    }
+
     pub fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
         move |i| {
             map(
@@ -178,6 +209,55 @@ where
     }
 }
 
+/// used to print `Params` as header parameters with quoted values
+pub struct QuotedPrint<'p, S, F>
+where
+    S: ParamsSpec,
+    F: Fn(&str) -> bool,
+{
+    params: &'p Params<S>,
+    filter: F,
+}
+
+impl<S, F> fmt::Display for QuotedPrint<'_, S, F>
+where
+    S: ParamsSpec,
+    F: Fn(&str) -> bool,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, param) in self.params.params.iter().enumerate() {
+            if i == 0 {
+                f.write_str(S::FIRST_DELIMITER)?;
+            } else {
+                f.write_str(S::DELIMITER)?;
+            }
+
+            match &param.value {
+                Some(value) if (self.filter)(&param.name) && !value.chars().all(S::CHAR_SPEC) => {
+                    write!(
+                        f,
+                        "{}=\"",
+                        percent_encode(param.name.as_bytes(), S::ENCODE_SET())
+                    )?;
+
+                    for c in value.chars() {
+                        if matches!(c, '"' | '\\') {
+                            f.write_str("\\")?;
+                        }
+
+                        write!(f, "{}", c)?;
+                    }
+
+                    f.write_str("\"")?;
+                }
+                _ => param.write(f, S::ENCODE_SET())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl<S> Default for Params<S> {
     fn default() -> Self {
         Params {
@@ -200,7 +280,7 @@ pub trait ParamsSpec: std::fmt::Debug {
 pub enum HPS {}
 
 fn header_char(c: char) -> bool {
-    lookup_table!(c => alpha; num; '[', ']', '/', /*'=',*/ ':', '+', '$', '-', '_', '.', '!', '~', '*', '\'', '(', ')')
+    lookup_table!(c => alpha; num; '%', '[', ']', '/', /*'=',*/ ':', '+', '$', '-', '_', '.', '!', '~', '*', '\'', '(', ')')
 }
 
 encode_set!(header_char, HPS_SET);
@@ -231,6 +311,7 @@ impl ParamsSpec for CPS {
 
 /// Represents a Parameter `name[=(value|"value")]`
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Param {
     pub name: BytesStr,
     pub value: Option<BytesStr>,
@@ -279,7 +360,7 @@ impl Param {
         move |i| {
             map_res(
                 ws((
-                    take_while(spec),
+                    take_while1(spec),
                     opt(ws((tag("="), alt((parse_quoted, take_while(spec)))))),
                 )),
                 move |(name, value)| -> Result<_, Utf8Error> {
@@ -314,7 +395,7 @@ macro_rules! impl_with_params {
         where
             N: Into<bytesstr::BytesStr> + AsRef<str>,
         {
-            self.$field.push(crate::uri::params::Param::name(name));
+            self.$field.push($crate::uri::params::Param::name(name));
             self
         }
 
@@ -41,6 +41,29 @@ pub struct SipUri {
     pub header_params: Params<HPS>,
 }
 
+/// Serialized with `user` and `password` flattened into the URI
+#[cfg(feature = "serde")]
+impl serde::Serialize for SipUri {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let (user, password) = match &self.user_part {
+            UserPart::Empty => (None, None),
+            UserPart::User(user) => (Some(user), None),
+            UserPart::UserPw(user_pw) => (Some(&user_pw.user), Some(&user_pw.password)),
+        };
+
+        let mut s = serializer.serialize_struct("SipUri", 6)?;
+        s.serialize_field("sips", &self.sips)?;
+        s.serialize_field("user", &user)?;
+        s.serialize_field("password", &password)?;
+        s.serialize_field("host_port", &self.host_port)?;
+        s.serialize_field("uri_params", &self.uri_params)?;
+        s.serialize_field("header_params", &self.header_params)?;
+        s.end()
+    }
+}
+
 impl SipUri {
     pub fn new(host_port: HostPort) -> Self {
         SipUri {
@@ -0,0 +1,334 @@
+use crate::host::HostPort;
+use crate::parse::ParseCtx;
+use crate::print::{AppendCtx, Print, PrintCtx};
+use crate::uri::params::{Params, CPS};
+use crate::uri::sip::{SipUri, UserPart};
+use bytesstr::BytesStr;
+use nom::bytes::complete::{tag_no_case, take_while1};
+use nom::combinator::{map, map_res};
+use nom::sequence::{preceded, tuple};
+use nom::IResult;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// `tel` URI as described in [RFC3966](https://datatracker.ietf.org/doc/html/rfc3966)
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TelUri {
+    /// The telephone number including visual separators, global numbers start with `+`
+    pub number: BytesStr,
+
+    /// `phone-context` parameter, required for local numbers
+    pub phone_context: Option<BytesStr>,
+
+    /// `ext` parameter, the extension of the number
+    pub ext: Option<BytesStr>,
+
+    /// `isub` parameter, the ISDN subaddress
+    pub isub: Option<BytesStr>,
+
+    /// All other parameters
+    pub params: Params<CPS>,
+}
+
+impl TelUri {
+    /// Create a new tel URI, global numbers must start with `+`
+    pub fn new<N>(number: N) -> Self
+    where
+        N: Into<BytesStr>,
+    {
+        Self {
+            number: number.into(),
+            phone_context: None,
+            ext: None,
+            isub: None,
+            params: Params::new(),
+        }
+    }
+
+    pub fn with_phone_context<C>(mut self, phone_context: C) -> Self
+    where
+        C: Into<BytesStr>,
+    {
+        self.phone_context = Some(phone_context.into());
+        self
+    }
+
+    /// Returns if the number is a global number (starting with `+`)
+    pub fn is_global(&self) -> bool {
+        self.number.starts_with('+')
+    }
+
+    /// Compare two tel URIs as described in RFC 3966 section 4
+    ///
+    /// Visual separators in the number, extension and numeric phone contexts are ignored.
+    pub fn compare(&self, other: &Self) -> bool {
+        fn opt_eq(a: &Option<BytesStr>, b: &Option<BytesStr>, eq: fn(&str, &str) -> bool) -> bool {
+            match (a, b) {
+                (Some(a), Some(b)) => eq(a, b),
+                (None, None) => true,
+                _ => false,
+            }
+        }
+
+        let params_eq = self.params.iter().count() == other.params.iter().count()
+            && self
+                .params
+                .iter()
+                .all(|param| match other.params.get(&param.name) {
+                    Some(other) => {
+                        opt_eq(&param.value, &other.value, |a, b| a.eq_ignore_ascii_case(b))
+                    }
+                    None => false,
+                });
+
+        eq_digits(&self.number, &other.number)
+            && opt_eq(&self.ext, &other.ext, eq_digits)
+            && opt_eq(&self.isub, &other.isub, |a, b| a.eq_ignore_ascii_case(b))
+            && opt_eq(&self.phone_context, &other.phone_context, eq_context)
+            && params_eq
+    }
+
+    /// Convert the tel URI to a SIP URI with `user=phone` as described in RFC 3261 section 19.1.6
+    pub fn to_sip_uri(&self, host_port: HostPort) -> SipUri {
+        let mut user = self.default_print_ctx().to_string();
+        user.drain(.."tel:".len());
+
+        SipUri::new(host_port)
+            .user(user.into())
+            .uri_param_value("user", BytesStr::from_static("phone"))
+    }
+
+    /// Convert a SIP URI with `user=phone` into a tel URI
+    ///
+    /// Returns `None` if the SIP URI does not contain the `user=phone` parameter or
+    /// its user part isn't a valid telephone number.
+    pub fn from_sip_uri(sip_uri: &SipUri) -> Option<Self> {
+        if sip_uri.uri_params.get_val("user")? != "phone" {
+            return None;
+        }
+
+        let user = match &sip_uri.user_part {
+            UserPart::User(user) => user,
+            UserPart::UserPw(user_pw) => &user_pw.user,
+            UserPart::Empty => return None,
+        };
+
+        format!("tel:{}", user).parse().ok()
+    }
+
+    pub fn parse(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| {
+            map_res(
+                tuple((parse_number(ctx), Params::<CPS>::parse(ctx))),
+                |(number, mut params)| -> Result<Self, InvalidTelUri> {
+                    let phone_context = params.take("phone-context");
+
+                    // Local numbers must contain a phone-context
+                    if !number.starts_with('+') && phone_context.is_none() {
+                        return Err(InvalidTelUri(()));
+                    }
+
+                    Ok(Self {
+                        number,
+                        phone_context,
+                        ext: params.take("ext"),
+                        isub: params.take("isub"),
+                        params,
+                    })
+                },
+            )(i)
+        }
+    }
+
+    /// Parse only the number, parameters are left unparsed as they belong to the surrounding header
+    pub fn parse_no_params(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, Self> + '_ {
+        move |i| map(parse_number(ctx), Self::new)(i)
+    }
+}
+
+fn parse_number(ctx: ParseCtx<'_>) -> impl Fn(&str) -> IResult<&str, BytesStr> + '_ {
+    move |i| {
+        map_res(
+            preceded(tag_no_case("tel:"), take_while1(phone_digit)),
+            |number: &str| -> Result<BytesStr, InvalidTelUri> {
+                let digits = number.strip_prefix('+').unwrap_or(number);
+
+                let valid = if number.starts_with('+') {
+                    digits
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || visual_separator(c))
+                } else {
+                    !digits.contains('+')
+                };
+
+                if valid && digits.chars().any(|c| !visual_separator(c)) {
+                    Ok(BytesStr::from_parse(ctx.src, number))
+                } else {
+                    Err(InvalidTelUri(()))
+                }
+            },
+        )(i)
+    }
+}
+
+fn phone_digit(c: char) -> bool {
+    c.is_ascii_hexdigit() || matches!(c, '*' | '#' | '+') || visual_separator(c)
+}
+
+fn visual_separator(c: char) -> bool {
+    matches!(c, '-' | '.' | '(' | ')')
+}
+
+fn eq_digits(a: &str, b: &str) -> bool {
+    let strip = |s: &str| -> String {
+        s.chars()
+            .filter(|&c| !visual_separator(c))
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    };
+
+    strip(a) == strip(b)
+}
+
+/// Phone contexts are either global numbers or domain names
+fn eq_context(a: &str, b: &str) -> bool {
+    if a.starts_with('+') {
+        eq_digits(a, b)
+    } else {
+        a.eq_ignore_ascii_case(b)
+    }
+}
+
+impl fmt::Debug for TelUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.print_ctx(PrintCtx::default()))
+    }
+}
+
+impl Print for TelUri {
+    fn print(&self, f: &mut fmt::Formatter<'_>, _: PrintCtx<'_>) -> fmt::Result {
+        write!(f, "tel:{}", self.number)?;
+
+        if let Some(ext) = &self.ext {
+            write!(f, ";ext={}", ext)?;
+        }
+
+        if let Some(isub) = &self.isub {
+            write!(f, ";isub={}", isub)?;
+        }
+
+        if let Some(phone_context) = &self.phone_context {
+            write!(f, ";phone-context={}", phone_context)?;
+        }
+
+        write!(f, "{}", self.params)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid tel uri")]
+pub struct InvalidTelUri(());
+
+impl FromStr for TelUri {
+    type Err = InvalidTelUri;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = BytesStr::from(s);
+
+        let ctx = ParseCtx::default(&s);
+
+        let res = Self::parse(ctx)(s.as_ref())
+            .map(|(_, uri)| uri)
+            .map_err(|_| InvalidTelUri(()));
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::uri::NameAddr;
+
+    #[test]
+    fn tel_global() {
+        let uri: TelUri = "tel:+1-201-555-0123;ext=1234".parse().unwrap();
+
+        assert!(uri.is_global());
+        assert_eq!(uri.number, "+1-201-555-0123");
+        assert_eq!(uri.ext.as_deref(), Some("1234"));
+        assert!(uri.phone_context.is_none());
+    }
+
+    #[test]
+    fn tel_local() {
+        let uri: TelUri = "tel:7042;phone-context=example.com;foo=bar"
+            .parse()
+            .unwrap();
+
+        assert!(!uri.is_global());
+        assert_eq!(uri.number, "7042");
+        assert_eq!(uri.phone_context.as_deref(), Some("example.com"));
+        assert_eq!(uri.params.get_val("foo").unwrap(), "bar");
+    }
+
+    #[test]
+    fn tel_local_without_context() {
+        assert!("tel:7042".parse::<TelUri>().is_err());
+    }
+
+    #[test]
+    fn tel_compare() {
+        let a: TelUri = "tel:+1-201-555-0123".parse().unwrap();
+        let b: TelUri = "tel:+1(201)5550123".parse().unwrap();
+        let c: TelUri = "tel:+1-201-555-0124".parse().unwrap();
+
+        assert!(a.compare(&b));
+        assert!(!a.compare(&c));
+
+        let d: TelUri = "tel:863-1234;phone-context=+1-914-555".parse().unwrap();
+        let e: TelUri = "tel:8631234;phone-context=+1914555".parse().unwrap();
+
+        assert!(d.compare(&e));
+    }
+
+    #[test]
+    fn tel_print() {
+        let uri = TelUri::new("863-1234").with_phone_context("+1-914-555");
+
+        assert_eq!(
+            uri.default_print_ctx().to_string(),
+            "tel:863-1234;phone-context=+1-914-555"
+        );
+    }
+
+    #[test]
+    fn tel_sip_conversion() {
+        let uri: TelUri = "tel:+358-555-1234567;isub=1411".parse().unwrap();
+
+        let sip_uri = uri.to_sip_uri(HostPort::host_name("foo.com"));
+
+        assert_eq!(
+            sip_uri.default_print_ctx().to_string(),
+            "sip:+358-555-1234567;isub=1411@foo.com;user=phone"
+        );
+
+        let tel_uri = TelUri::from_sip_uri(&sip_uri).unwrap();
+
+        assert!(tel_uri.compare(&uri));
+    }
+
+    #[test]
+    fn tel_in_name_addr() {
+        let input = BytesStr::from_static("<tel:+1-201-555-0123>");
+
+        let (rem, name_addr) = NameAddr::parse(ParseCtx::default(&input))(&input).unwrap();
+
+        assert!(rem.is_empty());
+
+        let tel_uri: &TelUri = name_addr.uri.downcast_ref().unwrap();
+        assert_eq!(tel_uri.number, "+1-201-555-0123");
+    }
+}
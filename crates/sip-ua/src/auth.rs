@@ -0,0 +1,655 @@
+//! Digest authentication (RFC 2617) for outgoing requests
+
+use crate::util::random_string;
+use bytesstr::BytesStr;
+use sip_core::transaction::TsxResponse;
+use sip_core::{Endpoint, Request, Result, WithStatus};
+use sip_types::header::typed::{
+    Auth, AuthParam, Authorization, ProxyAuthenticate, ProxyAuthorization, WWWAuthenticate,
+};
+use sip_types::print::{AppendCtx, PrintCtx, UriContext};
+use sip_types::{Code, Headers};
+use std::collections::HashMap;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("response contains no digest challenge")]
+    NoChallenge,
+    #[error("digest challenge is missing the {0} parameter")]
+    MissingParam(&'static str),
+    #[error("unsupported digest algorithm {0}")]
+    UnsupportedAlgorithm(BytesStr),
+    #[error("no credentials for realm {0}")]
+    MissingCredentials(BytesStr),
+    #[error("credentials for realm {0} have been rejected")]
+    FailedToAuthenticate(BytesStr),
+}
+
+/// Username and password used to authenticate
+#[derive(Debug, Clone)]
+pub struct DigestCredentials {
+    pub user: BytesStr,
+    pub password: BytesStr,
+}
+
+impl DigestCredentials {
+    pub fn new<U, P>(user: U, password: P) -> Self
+    where
+        U: Into<BytesStr>,
+        P: Into<BytesStr>,
+    {
+        Self {
+            user: user.into(),
+            password: password.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Md5Sess,
+}
+
+/// Challenge received in a `WWW-Authenticate` or `Proxy-Authenticate` header
+#[derive(Debug)]
+struct Challenge {
+    proxy: bool,
+    realm: BytesStr,
+    nonce: BytesStr,
+    opaque: Option<BytesStr>,
+    algorithm: Algorithm,
+    qop_auth: bool,
+
+    /// Client nonce and the amount of times the nonce has been used
+    cnonce: BytesStr,
+    nc: u32,
+}
+
+/// Answers digest challenges of `401 Unauthorized` and `407 Proxy Authentication Required` responses
+///
+/// Remembers all received challenges, to authorize all following requests using [`DigestAuthenticator::authorize_request`].
+#[derive(Debug, Default)]
+pub struct DigestAuthenticator {
+    /// Credentials for specific realms
+    credentials: HashMap<BytesStr, DigestCredentials>,
+
+    /// Credentials used for all realms without specific credentials
+    default_credentials: Option<DigestCredentials>,
+
+    challenges: Vec<Challenge>,
+}
+
+impl DigestAuthenticator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add credentials to use for the given realm
+    pub fn add_credentials<R>(&mut self, realm: R, credentials: DigestCredentials)
+    where
+        R: Into<BytesStr>,
+    {
+        self.credentials.insert(realm.into(), credentials);
+    }
+
+    /// Set the credentials used for all realms without specific credentials
+    pub fn set_default_credentials(&mut self, credentials: DigestCredentials) {
+        self.default_credentials = Some(credentials);
+    }
+
+    fn credentials_for(&self, realm: &BytesStr) -> Option<&DigestCredentials> {
+        self.credentials
+            .get(realm)
+            .or(self.default_credentials.as_ref())
+    }
+
+    /// Read all digest challenges from the given `401` or `407` response
+    ///
+    /// Returns an error if the response contains no usable challenge, or if the challenge
+    /// was answered before and the credentials have been rejected.
+    pub fn handle_rejection(&mut self, response: &TsxResponse) -> Result<(), AuthError> {
+        self.handle_challenges(&response.headers)
+    }
+
+    fn handle_challenges(&mut self, headers: &Headers) -> Result<(), AuthError> {
+        let www_authenticate = headers
+            .get::<Vec<WWWAuthenticate>>()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|header| (false, header.0));
+
+        let proxy_authenticate = headers
+            .get::<Vec<ProxyAuthenticate>>()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|header| (true, header.0));
+
+        let mut found_challenge = false;
+
+        for (proxy, auth) in www_authenticate.chain(proxy_authenticate) {
+            if !auth.token.eq_ignore_ascii_case("Digest") {
+                continue;
+            }
+
+            self.handle_challenge(proxy, auth)?;
+            found_challenge = true;
+        }
+
+        if found_challenge {
+            Ok(())
+        } else {
+            Err(AuthError::NoChallenge)
+        }
+    }
+
+    fn handle_challenge(&mut self, proxy: bool, auth: Auth) -> Result<(), AuthError> {
+        let param = |name: &str| {
+            auth.params
+                .iter()
+                .find(|param| param.name.eq_ignore_ascii_case(name))
+                .map(|param| param.value.clone())
+        };
+
+        let realm = param("realm").ok_or(AuthError::MissingParam("realm"))?;
+        let nonce = param("nonce").ok_or(AuthError::MissingParam("nonce"))?;
+
+        let algorithm = match param("algorithm") {
+            None => Algorithm::Md5,
+            Some(algorithm) if algorithm.eq_ignore_ascii_case("MD5") => Algorithm::Md5,
+            Some(algorithm) if algorithm.eq_ignore_ascii_case("MD5-sess") => Algorithm::Md5Sess,
+            Some(algorithm) => return Err(AuthError::UnsupportedAlgorithm(algorithm)),
+        };
+
+        let qop_auth = param("qop")
+            .map(|qop| qop.split(',').any(|qop| qop.trim() == "auth"))
+            .unwrap_or_default();
+
+        let stale = param("stale")
+            .map(|stale| stale.eq_ignore_ascii_case("true"))
+            .unwrap_or_default();
+
+        if self.credentials_for(&realm).is_none() {
+            return Err(AuthError::MissingCredentials(realm));
+        }
+
+        let previous = self
+            .challenges
+            .iter()
+            .position(|challenge| challenge.proxy == proxy && challenge.realm == realm);
+
+        if let Some(previous) = previous {
+            // Being challenged again for the same realm means the credentials were wrong,
+            // unless the server only wants us to use a new nonce
+            if !stale {
+                return Err(AuthError::FailedToAuthenticate(realm));
+            }
+
+            self.challenges.remove(previous);
+        }
+
+        self.challenges.push(Challenge {
+            proxy,
+            realm,
+            nonce,
+            opaque: param("opaque"),
+            algorithm,
+            qop_auth,
+            cnonce: random_string(),
+            nc: 0,
+        });
+
+        Ok(())
+    }
+
+    /// Add `Authorization` and `Proxy-Authorization` headers answering all received challenges to the request.
+    ///
+    /// Previously set authorization headers are removed.
+    pub fn authorize_request(&mut self, request: &mut Request) {
+        if self.challenges.is_empty() {
+            return;
+        }
+
+        request.headers.remove_type::<Authorization>();
+        request.headers.remove_type::<ProxyAuthorization>();
+
+        let method = request.line.method.to_string();
+        let uri = request
+            .line
+            .uri
+            .print_ctx(PrintCtx {
+                method: Some(&request.line.method),
+                uri: Some(UriContext::ReqUri),
+            })
+            .to_string();
+
+        for i in 0..self.challenges.len() {
+            let credentials = self
+                .credentials_for(&self.challenges[i].realm)
+                // Challenges are only stored if credentials are available
+                .expect("missing credentials")
+                .clone();
+
+            let challenge = &mut self.challenges[i];
+            challenge.nc += 1;
+
+            let auth = challenge.respond(&credentials, &method, &uri);
+
+            if challenge.proxy {
+                request.headers.insert_type(&ProxyAuthorization(auth));
+            } else {
+                request.headers.insert_type(&Authorization(auth));
+            }
+        }
+    }
+}
+
+impl Challenge {
+    fn respond(&self, credentials: &DigestCredentials, method: &str, uri: &str) -> Auth {
+        let nc = format!("{:08x}", self.nc);
+
+        let mut ha1 = md5_hex(&format!(
+            "{}:{}:{}",
+            credentials.user, self.realm, credentials.password
+        ));
+
+        if self.algorithm == Algorithm::Md5Sess {
+            ha1 = md5_hex(&format!("{}:{}:{}", ha1, self.nonce, self.cnonce));
+        }
+
+        let ha2 = md5_hex(&format!("{}:{}", method, uri));
+
+        let response = if self.qop_auth {
+            md5_hex(&format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1, self.nonce, nc, self.cnonce, ha2
+            ))
+        } else {
+            md5_hex(&format!("{}:{}:{}", ha1, self.nonce, ha2))
+        };
+
+        let mut params = vec![
+            AuthParam::new("username", credentials.user.clone()),
+            AuthParam::new("realm", self.realm.clone()),
+            AuthParam::new("nonce", self.nonce.clone()),
+            AuthParam::new("uri", uri.to_string()),
+            AuthParam::new("response", response),
+        ];
+
+        if self.algorithm == Algorithm::Md5Sess {
+            params.push(AuthParam::new("algorithm", "MD5-sess"));
+        } else {
+            params.push(AuthParam::new("algorithm", "MD5"));
+        }
+
+        if let Some(opaque) = &self.opaque {
+            params.push(AuthParam::new("opaque", opaque.clone()));
+        }
+
+        if self.qop_auth {
+            params.push(AuthParam::new("qop", "auth"));
+            params.push(AuthParam::new("nc", nc));
+            params.push(AuthParam::new("cnonce", self.cnonce.clone()));
+        }
+
+        Auth {
+            token: "Digest".into(),
+            params,
+        }
+    }
+}
+
+fn md5_hex(input: &str) -> String {
+    format!("{:x}", md5::compute(input))
+}
+
+/// Send the request created by `create_request` and, when challenged, retry it with
+/// credentials provided by the `authenticator`.
+///
+/// `create_request` is called for every attempt, as every attempt requires a new CSeq number.
+/// Returns the final response of the last attempt.
+pub async fn send_request_with_auth<F>(
+    endpoint: &Endpoint,
+    authenticator: &mut DigestAuthenticator,
+    mut create_request: F,
+) -> Result<TsxResponse>
+where
+    F: FnMut() -> Request,
+{
+    loop {
+        let mut request = create_request();
+
+        authenticator.authorize_request(&mut request);
+
+        let transaction = endpoint.send_request(request).await?;
+        let response = transaction.receive_final().await?;
+
+        match response.line.code {
+            Code::UNAUTHORIZED | Code::PROXY_AUTHENTICATION_REQUIRED => {
+                authenticator
+                    .handle_rejection(&response)
+                    .status(response.line.code)?;
+            }
+            _ => return Ok(response),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::{MessageLayer, MessageSender};
+    use crate::test_util::{bind_loopback, uri};
+    use sip_types::header::typed::ContentType;
+    use sip_types::uri::sip::SipUri;
+    use sip_types::uri::NameAddr;
+    use sip_types::{Method, Name};
+
+    const NONCE: &str = "dcd98b7102dd2f0e8b11d0f600bfb0c093";
+    const CNONCE: &str = "0a4f113b";
+
+    fn credentials() -> DigestCredentials {
+        DigestCredentials::new("Mufasa", "Circle Of Life")
+    }
+
+    fn challenge(algorithm: Algorithm, qop_auth: bool) -> Challenge {
+        Challenge {
+            proxy: false,
+            realm: "testrealm@host.com".into(),
+            nonce: NONCE.into(),
+            opaque: Some("5ccc069c403ebaf9f0171e9517f40e41".into()),
+            algorithm,
+            qop_auth,
+            cnonce: CNONCE.into(),
+            nc: 1,
+        }
+    }
+
+    fn param<'a>(auth: &'a Auth, name: &str) -> Option<&'a str> {
+        auth.params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| param.value.as_ref())
+    }
+
+    fn headers(name: Name, value: &'static str) -> Headers {
+        let mut headers = Headers::new();
+        headers.insert(name, value);
+        headers
+    }
+
+    // RFC 2617 Section 3.5
+    #[test]
+    fn rfc2617_example() {
+        let auth =
+            challenge(Algorithm::Md5, true).respond(&credentials(), "GET", "/dir/index.html");
+
+        assert_eq!(
+            param(&auth, "response"),
+            Some("6629fae49393a05397450978507c4ef1")
+        );
+        assert_eq!(param(&auth, "nc"), Some("00000001"));
+        assert_eq!(param(&auth, "cnonce"), Some(CNONCE));
+        assert_eq!(param(&auth, "qop"), Some("auth"));
+        assert_eq!(
+            param(&auth, "opaque"),
+            Some("5ccc069c403ebaf9f0171e9517f40e41")
+        );
+    }
+
+    // RFC 2069 compatible response without qop
+    #[test]
+    fn response_without_qop() {
+        let auth =
+            challenge(Algorithm::Md5, false).respond(&credentials(), "GET", "/dir/index.html");
+
+        assert_eq!(
+            param(&auth, "response"),
+            Some("670fd8c2df070c60b045671b8b24ff02")
+        );
+        assert_eq!(param(&auth, "nc"), None);
+        assert_eq!(param(&auth, "cnonce"), None);
+    }
+
+    #[test]
+    fn response_md5_sess() {
+        let auth =
+            challenge(Algorithm::Md5Sess, true).respond(&credentials(), "GET", "/dir/index.html");
+
+        assert_eq!(
+            param(&auth, "response"),
+            Some("8e3825c57e897f5a0dec6c2d4e5059d0")
+        );
+        assert_eq!(param(&auth, "algorithm"), Some("MD5-sess"));
+    }
+
+    #[test]
+    fn authorize_request() {
+        let mut authenticator = DigestAuthenticator::new();
+        authenticator.add_credentials("testrealm@host.com", credentials());
+
+        authenticator
+            .handle_challenges(&headers(
+                Name::WWW_AUTHENTICATE,
+                "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+                nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\"",
+            ))
+            .unwrap();
+
+        authenticator.challenges[0].cnonce = CNONCE.into();
+
+        let uri: SipUri = "sip:example.com".parse().unwrap();
+        let mut request = Request::new(Method::REGISTER, uri);
+
+        authenticator.authorize_request(&mut request);
+        authenticator.authorize_request(&mut request);
+
+        // Previous header has been replaced
+        let authorization = request.headers.get::<Vec<Authorization>>().unwrap();
+        assert_eq!(authorization.len(), 1);
+
+        let auth = &authorization[0].0;
+        assert_eq!(param(auth, "uri"), Some("sip:example.com"));
+        assert_eq!(param(auth, "nc"), Some("00000002"));
+        assert_eq!(
+            param(auth, "response"),
+            Some("b8c38daa03fe233c68e1f6a166675fd4")
+        );
+        assert!(request.headers.get::<ProxyAuthorization>().is_err());
+    }
+
+    #[test]
+    fn proxy_challenge() {
+        let mut authenticator = DigestAuthenticator::new();
+        authenticator.set_default_credentials(credentials());
+
+        authenticator
+            .handle_challenges(&headers(
+                Name::PROXY_AUTHENTICATE,
+                "Digest realm=\"proxy\", nonce=\"abc\"",
+            ))
+            .unwrap();
+
+        let uri: SipUri = "sip:example.com".parse().unwrap();
+        let mut request = Request::new(Method::INVITE, uri);
+
+        authenticator.authorize_request(&mut request);
+
+        let auth = request.headers.get::<ProxyAuthorization>().unwrap().0;
+        assert_eq!(param(&auth, "realm"), Some("proxy"));
+        assert!(request.headers.get::<Authorization>().is_err());
+    }
+
+    #[test]
+    fn rejected_credentials() {
+        let mut authenticator = DigestAuthenticator::new();
+        authenticator.add_credentials("realm", credentials());
+
+        let challenge = headers(
+            Name::WWW_AUTHENTICATE,
+            "Digest realm=\"realm\", nonce=\"abc\"",
+        );
+
+        authenticator.handle_challenges(&challenge).unwrap();
+
+        assert!(matches!(
+            authenticator.handle_challenges(&challenge),
+            Err(AuthError::FailedToAuthenticate(realm)) if realm == "realm"
+        ));
+    }
+
+    #[test]
+    fn stale_nonce() {
+        let mut authenticator = DigestAuthenticator::new();
+        authenticator.add_credentials("realm", credentials());
+
+        authenticator
+            .handle_challenges(&headers(
+                Name::WWW_AUTHENTICATE,
+                "Digest realm=\"realm\", nonce=\"abc\"",
+            ))
+            .unwrap();
+
+        authenticator
+            .handle_challenges(&headers(
+                Name::WWW_AUTHENTICATE,
+                "Digest realm=\"realm\", nonce=\"def\", stale=true",
+            ))
+            .unwrap();
+
+        assert_eq!(authenticator.challenges.len(), 1);
+        assert_eq!(authenticator.challenges[0].nonce, "def");
+    }
+
+    #[test]
+    fn invalid_challenges() {
+        let mut authenticator = DigestAuthenticator::new();
+        authenticator.add_credentials("realm", credentials());
+
+        assert!(matches!(
+            authenticator.handle_challenges(&Headers::new()),
+            Err(AuthError::NoChallenge)
+        ));
+        assert!(matches!(
+            authenticator
+                .handle_challenges(&headers(Name::WWW_AUTHENTICATE, "Basic realm=\"realm\"")),
+            Err(AuthError::NoChallenge)
+        ));
+        assert!(matches!(
+            authenticator
+                .handle_challenges(&headers(Name::WWW_AUTHENTICATE, "Digest realm=\"realm\"")),
+            Err(AuthError::MissingParam("nonce"))
+        ));
+        assert!(matches!(
+            authenticator.handle_challenges(&headers(
+                Name::WWW_AUTHENTICATE,
+                "Digest realm=\"realm\", nonce=\"abc\", algorithm=SHA-256"
+            )),
+            Err(AuthError::UnsupportedAlgorithm(_))
+        ));
+        assert!(matches!(
+            authenticator.handle_challenges(&headers(
+                Name::WWW_AUTHENTICATE,
+                "Digest realm=\"other\", nonce=\"abc\""
+            )),
+            Err(AuthError::MissingCredentials(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn send_with_auth() {
+        let mut builder = Endpoint::builder();
+        let (message_layer, mut messages) = MessageLayer::new();
+        builder.add_layer(message_layer);
+        let server_addr = bind_loopback(&mut builder).await;
+        let _server = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let client_addr = bind_loopback(&mut builder).await;
+        let client = builder.build();
+
+        let server = tokio::spawn(async move {
+            let message = messages.recv().await.unwrap();
+            assert!(message.request.headers.get::<Authorization>().is_err());
+
+            let mut response = message
+                .create_response(Code::UNAUTHORIZED, None)
+                .await
+                .unwrap();
+            response.msg.headers.insert(
+                Name::WWW_AUTHENTICATE,
+                "Digest realm=\"testrealm@host.com\", qop=\"auth\", nonce=\"abc\"",
+            );
+            message.respond(response).await.unwrap();
+
+            let message = messages.recv().await.unwrap();
+            let auth = message.request.headers.get::<Authorization>().unwrap().0;
+            assert_eq!(param(&auth, "username"), Some("Mufasa"));
+            assert_eq!(param(&auth, "nc"), Some("00000001"));
+
+            message.respond_with(Code::OK).await.unwrap();
+        });
+
+        let mut sender = MessageSender::new(
+            NameAddr::uri(uri("alice", client_addr)),
+            Box::new(uri("bob", server_addr)),
+        );
+        sender.authenticator.set_default_credentials(credentials());
+
+        let response = sender
+            .send(
+                &client,
+                ContentType::from_static("text/plain"),
+                "Hello".into(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.line.code, Code::OK);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn send_with_rejected_auth() {
+        let mut builder = Endpoint::builder();
+        let (message_layer, mut messages) = MessageLayer::new();
+        builder.add_layer(message_layer);
+        let server_addr = bind_loopback(&mut builder).await;
+        let _server = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let client_addr = bind_loopback(&mut builder).await;
+        let client = builder.build();
+
+        tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                let mut response = message
+                    .create_response(Code::UNAUTHORIZED, None)
+                    .await
+                    .unwrap();
+                response.msg.headers.insert(
+                    Name::WWW_AUTHENTICATE,
+                    "Digest realm=\"testrealm@host.com\", nonce=\"abc\"",
+                );
+                message.respond(response).await.unwrap();
+            }
+        });
+
+        let mut sender = MessageSender::new(
+            NameAddr::uri(uri("alice", client_addr)),
+            Box::new(uri("bob", server_addr)),
+        );
+        sender.authenticator.set_default_credentials(credentials());
+
+        let error = sender
+            .send(
+                &client,
+                ContentType::from_static("text/plain"),
+                "Hello".into(),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.status, Code::UNAUTHORIZED);
+    }
+}
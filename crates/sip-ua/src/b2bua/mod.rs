@@ -0,0 +1,674 @@
+//! Back-to-back user agent, bridging an incoming call leg with an outgoing call leg
+//!
+//! Both legs are independent dialogs with their own Call-ID, tags and Via headers, so no
+//! topology information of one leg is visible on the other leg. Headers are only copied
+//! between legs as allowed by the [`HeaderPolicy`].
+
+use crate::dialog::DialogLayer;
+use crate::invite::acceptor::Acceptor;
+use crate::invite::initiator::{Initiator, Response};
+use crate::invite::session::{Event, Session};
+use crate::invite::InviteLayer;
+use bytes::Bytes;
+use sip_core::transaction::TsxResponse;
+use sip_core::transport::OutgoingResponse;
+use sip_core::{Endpoint, Error, IncomingRequest, LayerKey, Request, Result};
+use sip_types::header::typed::{Contact, ContentType};
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Code, CodeKind, Headers, Method, Name};
+use std::sync::Arc;
+use tokio::select;
+
+/// Identifies one of the two legs of a bridged call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Leg {
+    /// The incoming leg, created from the received INVITE
+    A,
+
+    /// The outgoing leg, created by the B2BUA
+    B,
+}
+
+impl Leg {
+    pub fn other(self) -> Self {
+        match self {
+            Leg::A => Leg::B,
+            Leg::B => Leg::A,
+        }
+    }
+}
+
+/// Hooks to modify the content relayed between the legs
+pub trait B2buaHooks: Send + Sync {
+    /// Rewrite a body (usually SDP) received on the leg `from`, before it is sent on the other leg
+    fn rewrite_body(&self, from: Leg, content_type: &ContentType, body: Bytes) -> Bytes {
+        let _ = (from, content_type);
+
+        body
+    }
+}
+
+/// Hooks which relay all content unmodified
+pub struct NoHooks;
+
+impl B2buaHooks for NoHooks {}
+
+/// Headers which are never copied between legs, as they belong to the dialog or
+/// transaction of a single leg or would reveal its topology
+const LEG_HEADERS: [Name; 22] = [
+    Name::VIA,
+    Name::FROM,
+    Name::TO,
+    Name::CALL_ID,
+    Name::CSEQ,
+    Name::CONTACT,
+    Name::RECORD_ROUTE,
+    Name::ROUTE,
+    Name::MAX_FORWARDS,
+    Name::CONTENT_LENGTH,
+    Name::CONTENT_TYPE,
+    Name::ALLOW,
+    Name::SUPPORTED,
+    Name::REQUIRE,
+    Name::SESSION_EXPIRES,
+    Name::MIN_SE,
+    Name::RSEQ,
+    Name::RACK,
+    Name::AUTHORIZATION,
+    Name::PROXY_AUTHORIZATION,
+    Name::WWW_AUTHENTICATE,
+    Name::PROXY_AUTHENTICATE,
+];
+
+/// Defines which headers of the initial INVITE and its responses are copied to the other leg
+#[derive(Debug, Clone, Default)]
+pub enum HeaderPolicy {
+    /// Copy no headers
+    #[default]
+    None,
+
+    /// Copy only the given headers
+    Only(Vec<Name>),
+
+    /// Copy all headers except the given ones
+    AllExcept(Vec<Name>),
+}
+
+impl HeaderPolicy {
+    fn copy(&self, from: &Headers, to: &mut Headers) {
+        for (name, value) in from.iter() {
+            if LEG_HEADERS.contains(name) {
+                continue;
+            }
+
+            let pass = match self {
+                HeaderPolicy::None => false,
+                HeaderPolicy::Only(names) => names.contains(name),
+                HeaderPolicy::AllExcept(names) => !names.contains(name),
+            };
+
+            if pass {
+                to.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Identity and target of the outgoing leg
+pub struct OutgoingLeg {
+    pub id: NameAddr,
+    pub contact: Contact,
+    pub target: Box<dyn Uri>,
+}
+
+/// Creates bridged calls from incoming INVITE requests
+pub struct B2bua {
+    endpoint: Endpoint,
+    dialog_layer: LayerKey<DialogLayer>,
+    invite_layer: LayerKey<InviteLayer>,
+
+    pub header_policy: HeaderPolicy,
+    pub hooks: Arc<dyn B2buaHooks>,
+}
+
+impl B2bua {
+    pub fn new(
+        endpoint: Endpoint,
+        dialog_layer: LayerKey<DialogLayer>,
+        invite_layer: LayerKey<InviteLayer>,
+    ) -> Self {
+        Self {
+            endpoint,
+            dialog_layer,
+            invite_layer,
+            header_policy: HeaderPolicy::default(),
+            hooks: Arc::new(NoHooks),
+        }
+    }
+
+    /// Bridge the incoming `invite` to a new outgoing leg
+    ///
+    /// Provisional and final responses of the outgoing leg are relayed to the incoming leg.
+    /// If the incoming INVITE is cancelled the outgoing INVITE is cancelled as well.
+    /// Returns an error with the status of the final response if the outgoing leg
+    /// could not be established.
+    pub async fn bridge(
+        &self,
+        invite: IncomingRequest,
+        local_contact: Contact,
+        outgoing: OutgoingLeg,
+    ) -> Result<BridgedCall> {
+        let mut initiator = Initiator::new(
+            self.endpoint.clone(),
+            self.dialog_layer,
+            self.invite_layer,
+            outgoing.id,
+            outgoing.contact,
+            outgoing.target,
+        );
+
+        let mut b_invite = initiator.create_invite();
+        self.header_policy
+            .copy(&invite.headers, &mut b_invite.headers);
+        relay_body(
+            &*self.hooks,
+            Leg::A,
+            &invite.headers,
+            &invite.body,
+            &mut b_invite,
+        );
+
+        let mut acceptor = Acceptor::new(
+            self.endpoint.clone(),
+            self.dialog_layer,
+            self.invite_layer,
+            invite,
+            local_contact,
+        )?;
+
+        initiator.send_invite(b_invite).await?;
+
+        let mut cancelled = false;
+
+        loop {
+            let response = select! {
+                response = initiator.receive() => response?,
+                _ = acceptor.cancelled(), if !cancelled => {
+                    cancelled = true;
+                    initiator.cancel().await?;
+                    continue;
+                }
+            };
+
+            match response {
+                Response::Provisional(response) | Response::EarlyMedia(response) => {
+                    if response.line.code == Code::TRYING || cancelled {
+                        continue;
+                    }
+
+                    let response = self.relay_response(&acceptor, &response).await?;
+
+                    acceptor.respond_provisional(response).await?;
+                }
+                Response::ReliableProvisional(response) => {
+                    let prack = initiator.create_prack(&response)?;
+                    initiator.send_prack(prack).await?;
+
+                    if cancelled {
+                        continue;
+                    }
+
+                    let relayed = self.relay_response(&acceptor, &response).await?;
+
+                    acceptor.respond_provisional(relayed).await?;
+                }
+                Response::Failure(response) => {
+                    if !cancelled {
+                        let relayed = self.relay_response(&acceptor, &response).await?;
+                        acceptor.respond_failure(relayed).await?;
+                    }
+
+                    return Err(Error::new(response.line.code));
+                }
+                Response::Session(mut b, response) => {
+                    if cancelled {
+                        b.terminate().await?;
+                        return Err(Error::new(Code::REQUEST_TERMINATED));
+                    }
+
+                    let relayed = self.relay_response(&acceptor, &response).await?;
+
+                    return match acceptor.respond_success(relayed).await {
+                        Ok((a, _ack)) => Ok(BridgedCall {
+                            a,
+                            b,
+                            hooks: self.hooks.clone(),
+                        }),
+                        Err(e) => {
+                            b.terminate().await?;
+                            Err(e)
+                        }
+                    };
+                }
+                Response::Finished => {
+                    if !cancelled {
+                        let response = acceptor
+                            .create_response(Code::REQUEST_TIMEOUT, None)
+                            .await?;
+
+                        acceptor.respond_failure(response).await?;
+                    }
+
+                    return Err(Error::new(Code::REQUEST_TIMEOUT));
+                }
+            }
+        }
+    }
+
+    /// Create a response on the incoming leg mirroring a response received on the outgoing leg
+    async fn relay_response(
+        &self,
+        acceptor: &Acceptor,
+        response: &TsxResponse,
+    ) -> Result<OutgoingResponse> {
+        let mut relayed = acceptor
+            .create_response(response.line.code, response.line.reason.clone())
+            .await?;
+
+        self.header_policy
+            .copy(&response.headers, &mut relayed.msg.headers);
+        relay_response_body(&*self.hooks, Leg::B, response, &mut relayed);
+
+        Ok(relayed)
+    }
+}
+
+/// Two established sessions which are bridged by the [`B2bua`]
+pub struct BridgedCall {
+    /// Session of the incoming leg
+    pub a: Session,
+
+    /// Session of the outgoing leg
+    pub b: Session,
+
+    hooks: Arc<dyn B2buaHooks>,
+}
+
+impl BridgedCall {
+    /// Relay requests between the legs until one of them is terminated,
+    /// which also terminates the other leg
+    ///
+    /// RE-INVITE, UPDATE and INFO requests are forwarded to the other leg and its
+    /// responses relayed back. Session refreshes are handled on each leg individually.
+    ///
+    /// If relaying fails, both legs are terminated before the error is returned.
+    pub async fn run(mut self) -> Result<()> {
+        let result = self.relay().await;
+
+        if let Err(e) = &result {
+            log::warn!("Failed to relay between legs, terminating call, {:?}", e);
+
+            for session in [&mut self.a, &mut self.b] {
+                if session.is_terminated().await {
+                    continue;
+                }
+
+                if let Err(e) = session.terminate().await {
+                    log::debug!("Failed to terminate leg, {:?}", e);
+                }
+            }
+        }
+
+        result
+    }
+
+    async fn relay(&mut self) -> Result<()> {
+        loop {
+            let hooks = &*self.hooks;
+
+            let terminated = select! {
+                event = self.a.drive() => handle_event(hooks, Leg::A, event?, &mut self.b).await?,
+                event = self.b.drive() => handle_event(hooks, Leg::B, event?, &mut self.a).await?,
+            };
+
+            if terminated {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Handle an event of the leg `from`, returns `true` when the call has been terminated
+async fn handle_event(
+    hooks: &dyn B2buaHooks,
+    from: Leg,
+    event: Event<'_>,
+    other: &mut Session,
+) -> Result<bool> {
+    match event {
+        Event::RefreshNeeded(event) => {
+            event.process_default().await?;
+        }
+        Event::ReInviteReceived(event) => {
+            // Reject a too small session interval locally, the other leg is not involved
+            if let Some(min_se) = event.check_session_interval() {
+                event.reject_session_interval(min_se).await?;
+                return Ok(false);
+            }
+
+            let mut request = other.dialog.create_request(Method::INVITE);
+            relay_body(
+                hooks,
+                from,
+                &event.invite.headers,
+                &event.invite.body,
+                &mut request,
+            );
+
+            let response = other.send_reinvite(request).await?;
+
+            let mut relayed = event
+                .create_response(response.line.code, response.line.reason.clone())
+                .await?;
+            relay_response_body(hooks, from.other(), &response, &mut relayed);
+
+            if response.line.code.kind() == CodeKind::Success {
+                event.respond_success(relayed).await?;
+            } else {
+                event.respond_failure(relayed).await?;
+            }
+        }
+        Event::UpdateReceived(event) => {
+            if let Some(min_se) = event.check_session_interval() {
+                event.reject_session_interval(min_se).await?;
+                return Ok(false);
+            }
+
+            let mut request = other.dialog.create_request(Method::UPDATE);
+            relay_body(
+                hooks,
+                from,
+                &event.update.headers,
+                &event.update.body,
+                &mut request,
+            );
+
+            let response = other.send_update(request).await?;
+
+            let mut relayed = event
+                .create_response(response.line.code, response.line.reason.clone())
+                .await?;
+            relay_response_body(hooks, from.other(), &response, &mut relayed);
+
+            event.respond(relayed).await?;
+        }
+        Event::InfoReceived(event) => {
+            let content_type = match event.info.headers.get::<ContentType>() {
+                Ok(content_type) => content_type,
+                Err(_) => {
+                    // Nothing to relay
+                    event.process_default().await?;
+                    return Ok(false);
+                }
+            };
+
+            let result = other
+                .send_info(event.package(), content_type, event.info.body.clone())
+                .await;
+
+            let code = match result {
+                Ok(response) => response.line.code,
+                Err(e) => e.status,
+            };
+
+            event.respond_with(code).await?;
+        }
+        Event::Bye(event) => {
+            event.process_default().await?;
+            other.terminate().await?;
+
+            return Ok(true);
+        }
+        Event::Terminated => {
+            other.terminate().await?;
+
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Returns the rewritten body of a message, or `None` if the message has no body
+fn rewrite_body(
+    hooks: &dyn B2buaHooks,
+    from: Leg,
+    headers: &Headers,
+    body: &Bytes,
+) -> Option<(ContentType, Bytes)> {
+    if body.is_empty() {
+        return None;
+    }
+
+    let content_type = headers.get::<ContentType>().ok()?;
+    let body = hooks.rewrite_body(from, &content_type, body.clone());
+
+    Some((content_type, body))
+}
+
+fn relay_body(
+    hooks: &dyn B2buaHooks,
+    from: Leg,
+    headers: &Headers,
+    body: &Bytes,
+    request: &mut Request,
+) {
+    if let Some((content_type, body)) = rewrite_body(hooks, from, headers, body) {
+        request.headers.insert_type(&content_type);
+        request.body = body;
+    }
+}
+
+fn relay_response_body(
+    hooks: &dyn B2buaHooks,
+    from: Leg,
+    response: &TsxResponse,
+    relayed: &mut OutgoingResponse,
+) {
+    if let Some((content_type, body)) = rewrite_body(hooks, from, &response.headers, &response.body)
+    {
+        relayed.msg.headers.insert_type(&content_type);
+        relayed.msg.body = body;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{bind_loopback, establish, uri, AcceptLayer};
+    use sip_core::{EndpointBuilder, Layer, MayTake};
+    use sip_types::header::typed::{Refresher, SessionExpires};
+    use sip_types::uri::sip::SipUri;
+    use std::net::SocketAddr;
+    use tokio::sync::mpsc;
+
+    /// Bridges all INVITEs to the `target` and passes the result of the bridged call to the test
+    struct BridgeLayer {
+        dialog_layer: LayerKey<DialogLayer>,
+        invite_layer: LayerKey<InviteLayer>,
+        contact: Contact,
+        target: SipUri,
+        results: mpsc::Sender<Result<()>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Layer for BridgeLayer {
+        fn name(&self) -> &'static str {
+            "test-bridge"
+        }
+
+        async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+            if request.line.method != Method::INVITE {
+                return;
+            }
+
+            let b2bua = B2bua::new(endpoint.clone(), self.dialog_layer, self.invite_layer);
+
+            let call = b2bua
+                .bridge(
+                    request.take(),
+                    self.contact.clone(),
+                    OutgoingLeg {
+                        id: NameAddr::uri(self.contact.uri.uri.clone()),
+                        contact: self.contact.clone(),
+                        target: Box::new(self.target.clone()),
+                    },
+                )
+                .await
+                .unwrap();
+
+            self.results.send(call.run().await).await.unwrap();
+        }
+    }
+
+    struct Endpoints {
+        caller: Session,
+        callee: Session,
+        results: mpsc::Receiver<Result<()>>,
+
+        // Keep the endpoints alive
+        _endpoints: Vec<Endpoint>,
+    }
+
+    fn add_invite_layers(
+        builder: &mut EndpointBuilder,
+    ) -> (LayerKey<DialogLayer>, LayerKey<InviteLayer>) {
+        (
+            builder.add_layer(DialogLayer::default()),
+            builder.add_layer(InviteLayer::default()),
+        )
+    }
+
+    fn contact(user: &str, addr: SocketAddr) -> Contact {
+        Contact::new(NameAddr::uri(uri(user, addr)))
+    }
+
+    /// Establish a call from the caller through the B2BUA to the callee
+    async fn setup() -> Endpoints {
+        let mut builder = Endpoint::builder();
+        let (dialog_layer, invite_layer) = add_invite_layers(&mut builder);
+        let (sessions, mut callee_sessions) = mpsc::channel(1);
+        let callee_addr = bind_loopback(&mut builder).await;
+        builder.add_layer(AcceptLayer {
+            dialog_layer,
+            invite_layer,
+            contact: contact("callee", callee_addr),
+            sessions,
+        });
+        let callee = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let (dialog_layer, invite_layer) = add_invite_layers(&mut builder);
+        let (results, call_results) = mpsc::channel(1);
+        let b2bua_addr = bind_loopback(&mut builder).await;
+        builder.add_layer(BridgeLayer {
+            dialog_layer,
+            invite_layer,
+            contact: contact("b2bua", b2bua_addr),
+            target: uri("callee", callee_addr),
+            results,
+        });
+        let b2bua = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let (dialog_layer, invite_layer) = add_invite_layers(&mut builder);
+        let caller_addr = bind_loopback(&mut builder).await;
+        let caller = builder.build();
+
+        let initiator = Initiator::new(
+            caller.clone(),
+            dialog_layer,
+            invite_layer,
+            NameAddr::uri(uri("caller", caller_addr)),
+            contact("caller", caller_addr),
+            Box::new(uri("b2bua", b2bua_addr)),
+        );
+
+        Endpoints {
+            caller: establish(initiator).await,
+            callee: callee_sessions.recv().await.unwrap(),
+            results: call_results,
+            _endpoints: vec![caller, b2bua, callee],
+        }
+    }
+
+    /// Drive the session, answering all requests, until a BYE has been received
+    async fn wait_for_bye(mut session: Session) {
+        loop {
+            match session.drive().await.unwrap() {
+                Event::Bye(event) => {
+                    event.process_default().await.unwrap();
+                    return;
+                }
+                Event::UpdateReceived(event) => event.process_default().await.unwrap(),
+                Event::ReInviteReceived(event) => {
+                    event.process_default().await.unwrap();
+                }
+                Event::InfoReceived(event) => event.process_default().await.unwrap(),
+                Event::RefreshNeeded(event) => event.process_default().await.unwrap(),
+                Event::Terminated => panic!("session terminated without BYE"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn hangup_terminates_other_leg() {
+        let Endpoints {
+            mut caller,
+            callee,
+            mut results,
+            ..
+        } = setup().await;
+
+        caller.terminate().await.unwrap();
+
+        wait_for_bye(callee).await;
+
+        results.recv().await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn session_interval_too_small_keeps_call() {
+        let Endpoints {
+            mut caller,
+            callee,
+            mut results,
+            ..
+        } = setup().await;
+
+        // The callee must never see the rejected UPDATE
+        let callee = tokio::spawn(async move {
+            let mut callee = callee;
+
+            match callee.drive().await.unwrap() {
+                Event::Bye(event) => event.process_default().await.unwrap(),
+                _ => panic!("callee received unexpected event"),
+            }
+        });
+
+        let mut update = caller.dialog.create_request(Method::UPDATE);
+        update.headers.insert_type(&SessionExpires {
+            delta_secs: 30,
+            refresher: Refresher::Uac,
+        });
+
+        let response = caller.send_update(update).await.unwrap();
+        assert_eq!(response.line.code, Code::SESSION_INTERVAL_TOO_SMALL);
+
+        // The rejected UPDATE leaves the call intact on both legs
+        caller.terminate().await.unwrap();
+        callee.await.unwrap();
+
+        results.recv().await.unwrap().unwrap();
+    }
+}
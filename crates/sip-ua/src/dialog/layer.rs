@@ -1,7 +1,9 @@
 use super::key::DialogKey;
+use bytesstr::BytesStr;
 use parking_lot::Mutex;
 use sip_core::{Endpoint, EndpointBuilder, IncomingRequest, Layer, LayerKey, MayTake, Result};
-use sip_types::{Code, Method};
+use sip_types::header::typed::Contact;
+use sip_types::{Code, Headers, Method};
 use slotmap::{DefaultKey, SlotMap};
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
@@ -18,20 +20,34 @@ pub trait Usage: Send + Sync + 'static {
 
 pub(super) struct DialogEntry {
     backlog: BTreeMap<u32, IncomingRequest>,
-    next_peer_cseq: u32,
+    /// Next expected CSeq number, `None` until the peer sent its first request
+    pub(super) next_peer_cseq: Option<u32>,
     usages: SlotMap<DefaultKey, Arc<dyn Usage>>,
+    /// Requests must be received over a secure transport
+    secure: bool,
+    /// Target refresh requests must contain a SIPS Contact
+    ///
+    /// Kept in sync with the remote target by [`Dialog::refresh_target`](super::Dialog::refresh_target)
+    pub(super) sips_target: bool,
 }
 
 impl DialogEntry {
-    pub fn new(peer_cseq: u32) -> Self {
+    pub fn new(peer_cseq: Option<u32>, secure: bool, sips_target: bool) -> Self {
         Self {
             backlog: Default::default(),
-            next_peer_cseq: peer_cseq + 1,
+            next_peer_cseq: peer_cseq.map(|cseq| cseq + 1),
             usages: Default::default(),
+            secure,
+            sips_target,
         }
     }
 }
 
+/// Returns if the method is a target refresh request, which updates the remote target of the dialog
+pub fn is_target_refresh(method: &Method) -> bool {
+    matches!(*method, Method::INVITE | Method::UPDATE)
+}
+
 #[derive(Default)]
 pub struct DialogLayer {
     pub(super) dialogs: Mutex<HashMap<DialogKey, DialogEntry>>,
@@ -56,13 +72,30 @@ impl Layer for DialogLayer {
             }
         };
 
+        let (secure, sips_target) = match self.dialogs.lock().get(&key) {
+            Some(entry) => (entry.secure, entry.sips_target),
+            None => return,
+        };
+
+        if secure && !request.tp_info.transport.secure() {
+            if let Err(e) = self.reject_insecure_request(endpoint, request.take()).await {
+                log::warn!("failed to reject insecure request, {:?}", e);
+            }
+
+            return;
+        }
+
         let (usages, requests) = {
             let mut dialogs = self.dialogs.lock();
 
             if let Some(dialog_entry) = dialogs.get_mut(&key) {
                 let request_cseq = request.base_headers.cseq.cseq;
 
-                match request_cseq.cmp(&dialog_entry.next_peer_cseq) {
+                // The remote sequence number of client dialogs is empty until the
+                // peer sends its first request, which then sets the expected number
+                let next_peer_cseq = *dialog_entry.next_peer_cseq.get_or_insert(request_cseq);
+
+                match request_cseq.cmp(&next_peer_cseq) {
                     Ordering::Less => {
                         // CSeq number is lower than expected. ACK requests have the CSeq number of the initial
                         // INVITE request they acknowledge as they are considered part of the transactions,
@@ -86,7 +119,10 @@ impl Layer for DialogLayer {
                         // in the correct order and distribute it to the usages as well.
                         let mut requests = vec![request.take()];
 
-                        for next_cseq in request_cseq.. {
+                        // The backlog only contains requests above the expected CSeq number,
+                        // so draining starts at the one following this request and stops at
+                        // the first gap.
+                        for next_cseq in request_cseq + 1.. {
                             if let Some(message) = dialog_entry.backlog.remove(&next_cseq) {
                                 requests.push(message);
                             } else {
@@ -96,7 +132,7 @@ impl Layer for DialogLayer {
 
                         // set the next expected cseq to the one of last message we handle + 1
                         dialog_entry.next_peer_cseq =
-                            requests.last().unwrap().base_headers.cseq.cseq + 1;
+                            Some(requests.last().unwrap().base_headers.cseq.cseq + 1);
 
                         (usages, requests)
                     }
@@ -116,6 +152,18 @@ impl Layer for DialogLayer {
         log::debug!("message matches {:?}", key);
 
         for request in requests {
+            // Invalid target refresh requests are rejected after the CSeq check,
+            // as they still consume their CSeq number
+            if is_target_refresh(&request.line.method)
+                && !valid_target_refresh(&request.headers, sips_target)
+            {
+                if let Err(e) = self.reject_invalid_target_refresh(endpoint, request).await {
+                    log::warn!("failed to reject invalid target refresh request, {:?}", e);
+                }
+
+                continue;
+            }
+
             let mut request = Some(request);
 
             for usage in usages.values() {
@@ -127,7 +175,8 @@ impl Layer for DialogLayer {
                     .await;
 
                 if request.is_none() {
-                    return;
+                    // Continue with the requests taken from the backlog
+                    break;
                 }
             }
 
@@ -142,6 +191,66 @@ impl Layer for DialogLayer {
 }
 
 impl DialogLayer {
+    /// Reject a request that was received over a non-secure transport inside a secure dialog
+    async fn reject_insecure_request(
+        &self,
+        endpoint: &Endpoint,
+        request: IncomingRequest,
+    ) -> Result<()> {
+        log::warn!(
+            "rejecting {} received over non-secure transport inside secure dialog",
+            request.line.method
+        );
+
+        if request.line.method == Method::ACK {
+            // Cannot respond to ACK requests
+            return Ok(());
+        }
+
+        let response = endpoint
+            .create_response(
+                &request,
+                Code::TEMPORARILY_UNAVAILABLE,
+                Some(BytesStr::from_static("Secure Transport Required")),
+            )
+            .await?;
+
+        if request.line.method == Method::INVITE {
+            let tsx = endpoint.create_server_inv_tsx(&request);
+
+            tsx.respond_failure(response).await
+        } else {
+            let tsx = endpoint.create_server_tsx(&request);
+
+            tsx.respond(response).await
+        }
+    }
+
+    /// Reject a target refresh request with a missing or invalid Contact
+    async fn reject_invalid_target_refresh(
+        &self,
+        endpoint: &Endpoint,
+        request: IncomingRequest,
+    ) -> Result<()> {
+        let response = endpoint
+            .create_response(
+                &request,
+                Code::BAD_REQUEST,
+                Some(BytesStr::from_static("Invalid Contact")),
+            )
+            .await?;
+
+        if request.line.method == Method::INVITE {
+            let tsx = endpoint.create_server_inv_tsx(&request);
+
+            tsx.respond_failure(response).await
+        } else {
+            let tsx = endpoint.create_server_tsx(&request);
+
+            tsx.respond(response).await
+        }
+    }
+
     async fn handle_unwanted_request(
         &self,
         endpoint: &Endpoint,
@@ -168,6 +277,15 @@ impl DialogLayer {
     }
 }
 
+/// Target refresh requests must contain a valid Contact, which must be
+/// a SIPS URI if the current remote target is one
+fn valid_target_refresh(headers: &Headers, sips_target: bool) -> bool {
+    match headers.get::<Contact>() {
+        Ok(contact) => !sips_target || contact.uri.uri.info().secure,
+        Err(_) => false,
+    }
+}
+
 /// The lifetime of the guard ensures the existence of the
 /// usage inside a dialog. When dropped the usage will be
 /// removed from the dialog.
@@ -217,3 +335,227 @@ where
         usage_key,
     })
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dialog::Dialog;
+    use crate::test_util::{bind_loopback, uri};
+    use sip_types::header::typed::{CSeq, CallID, From, To};
+    use sip_types::uri::sip::SipUri;
+    use sip_types::uri::NameAddr;
+    use sip_types::Name;
+    use std::time::Duration;
+
+    /// Records the CSeq numbers of all received requests and responds with 200
+    struct Recorder(Arc<Mutex<Vec<u32>>>);
+
+    #[async_trait::async_trait]
+    impl Usage for Recorder {
+        fn name(&self) -> &'static str {
+            "test-recorder"
+        }
+
+        async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+            let request = request.take();
+
+            self.0.lock().push(request.base_headers.cseq.cseq);
+
+            let response = endpoint
+                .create_response(&request, Code::OK, None)
+                .await
+                .unwrap();
+
+            endpoint
+                .create_server_tsx(&request)
+                .respond(response)
+                .await
+                .unwrap();
+        }
+    }
+
+    struct Setup {
+        client: Dialog,
+        server: Dialog,
+        received: Arc<Mutex<Vec<u32>>>,
+        _guard: UsageGuard,
+    }
+
+    /// Create a dialog between two endpoints, `server_peer_contact` overrides the
+    /// remote target of the server side
+    async fn setup(server_peer_contact: Option<SipUri>) -> Setup {
+        let mut builder = Endpoint::builder();
+        let client_layer = builder.add_layer(DialogLayer::default());
+        let client_addr = bind_loopback(&mut builder).await;
+        let client_endpoint = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let server_layer = builder.add_layer(DialogLayer::default());
+        let server_addr = bind_loopback(&mut builder).await;
+        let server_endpoint = builder.build();
+
+        let client_uri = uri("client", client_addr);
+        let server_uri = uri("server", server_addr);
+
+        let from = From::new(NameAddr::uri(client_uri.clone()), Some("client".into()));
+        let to = To::new(NameAddr::uri(server_uri.clone()), Some("server".into()));
+        let call_id = CallID::new("dialog-test");
+        let cseq = 100;
+
+        let client = Dialog::new_client(
+            client_endpoint,
+            client_layer,
+            cseq,
+            from.clone(),
+            to.clone(),
+            Contact::new(NameAddr::uri(client_uri.clone())),
+            Contact::new(NameAddr::uri(server_uri.clone())),
+            call_id.clone(),
+            vec![],
+            false,
+        );
+
+        let server = Dialog::new_server(
+            server_endpoint.clone(),
+            server_layer,
+            cseq,
+            from,
+            to,
+            Contact::new(NameAddr::uri(server_uri)),
+            Contact::new(NameAddr::uri(server_peer_contact.unwrap_or(client_uri))),
+            call_id,
+            vec![],
+            false,
+        );
+
+        let received = Arc::new(Mutex::new(vec![]));
+
+        let guard = register_usage(
+            server_endpoint,
+            server_layer,
+            server.key(),
+            Recorder(received.clone()),
+        )
+        .unwrap();
+
+        Setup {
+            client,
+            server,
+            received,
+            _guard: guard,
+        }
+    }
+
+    async fn send(endpoint: Endpoint, request: sip_core::Request) -> Code {
+        let transaction = endpoint.send_request(request).await.unwrap();
+
+        transaction.receive_final().await.unwrap().line.code
+    }
+
+    #[test]
+    fn target_refresh_contact() {
+        let mut headers = Headers::new();
+        assert!(!valid_target_refresh(&headers, false));
+        assert!(!valid_target_refresh(&headers, true));
+
+        headers.insert(Name::CONTACT, "<sip:alice@example.com>");
+        assert!(valid_target_refresh(&headers, false));
+        assert!(!valid_target_refresh(&headers, true));
+
+        let mut headers = Headers::new();
+        headers.insert(Name::CONTACT, "<sips:alice@example.com>");
+        assert!(valid_target_refresh(&headers, false));
+        assert!(valid_target_refresh(&headers, true));
+    }
+
+    #[tokio::test]
+    async fn backlog_is_drained_in_order() {
+        let Setup {
+            client, received, ..
+        } = &mut setup(None).await;
+
+        let first = client.create_request(Method::INFO);
+        let second = client.create_request(Method::INFO);
+
+        let first_cseq = first.headers.get::<CSeq>().unwrap().cseq;
+
+        // The second request is held back until the first one has been received
+        let second = tokio::spawn(send(client.endpoint.clone(), second));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(received.lock().is_empty());
+
+        assert_eq!(send(client.endpoint.clone(), first).await, Code::OK);
+        assert_eq!(second.await.unwrap(), Code::OK);
+
+        assert_eq!(*received.lock(), [first_cseq, first_cseq + 1]);
+    }
+
+    #[tokio::test]
+    async fn target_refresh_without_contact_is_rejected() {
+        let Setup {
+            client, received, ..
+        } = &mut setup(None).await;
+
+        let mut update = client.create_request(Method::UPDATE);
+        update.headers.remove(&Name::CONTACT);
+
+        assert_eq!(
+            send(client.endpoint.clone(), update).await,
+            Code::BAD_REQUEST
+        );
+
+        // The rejected request still consumed its CSeq number
+        let update = client.create_request(Method::UPDATE);
+        assert_eq!(send(client.endpoint.clone(), update).await, Code::OK);
+        assert_eq!(received.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn target_refresh_downgrading_sips_is_rejected() {
+        let mut sips = uri("client", ([127, 0, 0, 1], 5061).into());
+        sips.sips = true;
+
+        let Setup {
+            client,
+            server,
+            received,
+            ..
+        } = &mut setup(Some(sips)).await;
+
+        let update = client.create_request(Method::UPDATE);
+
+        assert_eq!(
+            send(client.endpoint.clone(), update).await,
+            Code::BAD_REQUEST
+        );
+        assert!(received.lock().is_empty());
+        assert!(server.peer_contact.uri.uri.info().secure);
+    }
+
+    #[tokio::test]
+    async fn insecure_request_in_secure_dialog_is_rejected() {
+        let Setup {
+            client,
+            server,
+            received,
+            ..
+        } = &mut setup(None).await;
+
+        server.endpoint[server.dialog_layer]
+            .dialogs
+            .lock()
+            .get_mut(&server.key())
+            .unwrap()
+            .secure = true;
+
+        // The client sends the request over UDP
+        let info = client.create_request(Method::INFO);
+
+        assert_eq!(
+            send(client.endpoint.clone(), info).await,
+            Code::TEMPORARILY_UNAVAILABLE
+        );
+        assert!(received.lock().is_empty());
+    }
+}
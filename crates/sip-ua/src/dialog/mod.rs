@@ -2,15 +2,17 @@ use crate::dialog::layer::DialogEntry;
 use crate::util::random_sequence_number;
 use bytesstr::BytesStr;
 use sip_core::transport::OutgoingResponse;
-use sip_core::{Endpoint, IncomingRequest, LayerKey, Request, Result};
-use sip_types::header::typed::{CSeq, CallID, Contact, From, RecordRoute, To};
-use sip_types::{Code, Method};
+use sip_core::{Endpoint, Error, IncomingRequest, LayerKey, Request, Result};
+use sip_types::header::typed::{CSeq, CallID, Contact, From, RecordRoute, Route, To};
+use sip_types::{Code, CodeKind, Headers, Method};
 
 mod key;
 mod layer;
+mod state;
 
 pub use key::DialogKey;
-pub use layer::{register_usage, DialogLayer, Usage, UsageGuard};
+pub use layer::{is_target_refresh, register_usage, DialogLayer, Usage, UsageGuard};
+pub use state::DialogState;
 
 #[derive(Debug)]
 pub struct Dialog {
@@ -22,6 +24,8 @@ pub struct Dialog {
     pub local_cseq: u32,
 
     /// Remote CSeq number as seen in first request
+    ///
+    /// Is `0` for client dialogs as the peer has not sent any request yet
     pub peer_cseq: u32,
 
     /// From header used to construct requests inside the dialog
@@ -39,18 +43,25 @@ pub struct Dialog {
 
     /// Remote Contact header, used to construct requests inside the dialog
     /// as its the target URI.
+    ///
+    /// Must be modified using [`Dialog::refresh_target`].
     pub peer_contact: Contact,
 
     /// CallID of the Dialog which is part of the dialog key
     pub call_id: CallID,
 
     /// Dialog's Route set, must be set with every request
+    ///
+    /// The route set is fixed once the dialog is confirmed, target refresh requests
+    /// only modify the remote target (`peer_contact`).
     pub route_set: Vec<RecordRoute>,
 
-    /// Was a secure transport used to construct this dialog
-    /// Requires all future requests to also use secure transports
-    // TODO use this
-    pub secure: bool,
+    /// Was a secure transport or a SIPS URI used to construct this dialog
+    ///
+    /// Requires all future requests to also use secure transports. Requests created
+    /// using [`Dialog::create_request`] are never sent over a non-secure transport,
+    /// incoming requests received over a non-secure transport are rejected.
+    secure: bool,
 }
 
 impl Dialog {
@@ -87,7 +98,57 @@ impl Dialog {
             secure,
         };
 
-        let entry = DialogEntry::new(dialog.peer_cseq);
+        let entry = DialogEntry::new(
+            Some(dialog.peer_cseq),
+            dialog.secure,
+            dialog.requires_sips_target(),
+        );
+
+        dialog.endpoint[dialog_layer]
+            .dialogs
+            .lock()
+            .insert(dialog.key(), entry);
+
+        dialog
+    }
+
+    /// Create a dialog from a response to an outgoing request
+    ///
+    /// `local_cseq` must be the last CSeq number used by the local side, which is usually
+    /// the CSeq number of the request that created the dialog.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_client(
+        endpoint: Endpoint,
+        dialog_layer: LayerKey<DialogLayer>,
+        local_cseq: u32,
+        from: From,
+        to: To,
+        local_contact: Contact,
+        peer_contact: Contact,
+        call_id: CallID,
+        mut route_set: Vec<RecordRoute>,
+        secure: bool,
+    ) -> Self {
+        assert!(from.tag.is_some());
+
+        // The route set of client dialogs is the Record-Route of the response in reverse order
+        route_set.reverse();
+
+        let dialog = Self {
+            endpoint,
+            dialog_layer,
+            local_cseq: local_cseq + 1,
+            peer_cseq: 0,
+            from,
+            to,
+            local_contact,
+            peer_contact,
+            call_id,
+            route_set,
+            secure,
+        };
+
+        let entry = DialogEntry::new(None, dialog.secure, dialog.requires_sips_target());
 
         dialog.endpoint[dialog_layer]
             .dialogs
@@ -108,6 +169,7 @@ impl Dialog {
 
     pub fn create_request(&mut self, method: Method) -> Request {
         let mut request = Request::new(method.clone(), self.peer_contact.uri.uri.clone());
+        request.secure = self.secure;
 
         let cseq = CSeq::new(self.local_cseq, method);
         self.local_cseq += 1;
@@ -116,11 +178,67 @@ impl Dialog {
         request.headers.insert_type(&self.to);
         request.headers.insert_type(&self.call_id);
         request.headers.insert_type(&cseq);
-        request.headers.insert_type(&self.route_set);
+
+        let route_set: Vec<Route> = self
+            .route_set
+            .iter()
+            .map(|record_route| Route(record_route.0.clone()))
+            .collect();
+
+        request.headers.insert_type(&route_set);
+
+        // Target refresh requests must contain the local contact
+        if matches!(request.line.method, Method::INVITE | Method::UPDATE) {
+            request.headers.insert_type(&self.local_contact);
+        }
 
         request
     }
 
+    /// Replace the remote target with the Contact of a target refresh request
+    /// (or its successful response) as described in RFC 3261 section 12.2
+    ///
+    /// Messages without Contact leave the remote target unmodified. Returns an error if the
+    /// Contact is invalid or would downgrade the remote target from a SIPS URI.
+    pub fn refresh_target(&mut self, headers: &Headers) -> Result<()> {
+        let contact: Contact = match headers.try_get() {
+            Some(contact) => contact?,
+            None => return Ok(()),
+        };
+
+        if self.requires_sips_target() && !contact.uri.uri.info().secure {
+            log::warn!("target refresh would downgrade the remote target from a SIPS URI");
+
+            return Err(Error::new(Code::BAD_REQUEST));
+        }
+
+        self.peer_contact = contact;
+
+        let sips_target = self.requires_sips_target();
+
+        if let Some(entry) = self.endpoint[self.dialog_layer]
+            .dialogs
+            .lock()
+            .get_mut(&self.key())
+        {
+            entry.sips_target = sips_target;
+        }
+
+        Ok(())
+    }
+
+    /// Was a secure transport or a SIPS URI used to construct this dialog
+    ///
+    /// Requests inside a secure dialog are only sent and accepted over secure transports.
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+
+    /// A dialog with a SIPS remote target can only be refreshed with another SIPS target
+    fn requires_sips_target(&self) -> bool {
+        self.peer_contact.uri.uri.info().secure
+    }
+
     pub async fn create_response(
         &self,
         request: &IncomingRequest,
@@ -152,6 +270,13 @@ impl Dialog {
             }
         }
 
+        if request.line.method == Method::UPDATE
+            && code.kind() == CodeKind::Success
+            && !response.msg.headers.contains::<Contact>()
+        {
+            response.msg.headers.insert_type(&self.local_contact);
+        }
+
         Ok(response)
     }
 }
@@ -164,3 +289,86 @@ impl Drop for Dialog {
             .remove(&self.key());
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::uri;
+    use sip_types::print::AppendCtx;
+    use sip_types::uri::NameAddr;
+    use sip_types::Name;
+
+    fn dialog(peer_contact: &str) -> Dialog {
+        let mut builder = Endpoint::builder();
+        let dialog_layer = builder.add_layer(DialogLayer::default());
+        let endpoint = builder.build();
+
+        let local = uri("alice", ([127, 0, 0, 1], 5060).into());
+
+        let mut headers = Headers::new();
+        headers.insert(Name::CONTACT, peer_contact);
+
+        Dialog::new_client(
+            endpoint,
+            dialog_layer,
+            1,
+            From::new(NameAddr::uri(local.clone()), Some("alice".into())),
+            To::new(NameAddr::uri(local.clone()), Some("bob".into())),
+            Contact::new(NameAddr::uri(local)),
+            headers.get().unwrap(),
+            CallID::new("refresh-test"),
+            vec![],
+            false,
+        )
+    }
+
+    fn target(dialog: &Dialog) -> String {
+        dialog.peer_contact.uri.uri.default_print_ctx().to_string()
+    }
+
+    #[tokio::test]
+    async fn refresh_target() {
+        let mut dialog = dialog("<sip:bob@example.com>");
+
+        // Without Contact the remote target is unchanged
+        dialog.refresh_target(&Headers::new()).unwrap();
+        assert_eq!(target(&dialog), "sip:bob@example.com");
+
+        let mut headers = Headers::new();
+        headers.insert(Name::CONTACT, "<sips:bob@example.org>");
+        dialog.refresh_target(&headers).unwrap();
+        assert_eq!(target(&dialog), "sips:bob@example.org");
+
+        // The dialog layer now requires SIPS targets as well
+        let dialogs = dialog.endpoint[dialog.dialog_layer].dialogs.lock();
+        assert!(dialogs[&dialog.key()].sips_target);
+    }
+
+    #[tokio::test]
+    async fn refresh_target_rejects_sips_downgrade() {
+        let mut dialog = dialog("<sips:bob@example.com>");
+
+        let mut headers = Headers::new();
+        headers.insert(Name::CONTACT, "<sip:bob@example.org>");
+
+        let error = dialog.refresh_target(&headers).unwrap_err();
+        assert_eq!(error.status, Code::BAD_REQUEST);
+        assert_eq!(target(&dialog), "sips:bob@example.com");
+
+        let mut headers = Headers::new();
+        headers.insert(Name::CONTACT, "<sips:bob@example.org>");
+        dialog.refresh_target(&headers).unwrap();
+        assert_eq!(target(&dialog), "sips:bob@example.org");
+    }
+
+    #[tokio::test]
+    async fn refresh_target_invalid_contact() {
+        let mut dialog = dialog("<sip:bob@example.com>");
+
+        let mut headers = Headers::new();
+        headers.insert(Name::CONTACT, "<>");
+
+        assert!(dialog.refresh_target(&headers).is_err());
+        assert_eq!(target(&dialog), "sip:bob@example.com");
+    }
+}
@@ -0,0 +1,210 @@
+use super::layer::DialogEntry;
+use super::{Dialog, DialogLayer};
+use anyhow::anyhow;
+use bytesstr::BytesStr;
+use sip_core::{Endpoint, Error, LayerKey, Result};
+use sip_types::header::typed::{CallID, From, RecordRoute, To};
+use sip_types::header::Header;
+use sip_types::{Code, Headers};
+
+/// State of a [`Dialog`] which can be exported and later restored, e.g. to take over
+/// dialogs after a restart or on another node
+///
+/// Headers are stored as their printed values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DialogState {
+    pub call_id: String,
+    pub local_cseq: u32,
+    /// Last CSeq number received from the peer, `None` if the peer has not sent any request yet
+    pub peer_cseq: Option<u32>,
+    pub from: String,
+    pub to: String,
+    pub local_contact: String,
+    pub peer_contact: String,
+    pub route_set: Vec<String>,
+    pub secure: bool,
+}
+
+impl Dialog {
+    /// Export the current state of the dialog
+    pub fn export(&self) -> DialogState {
+        let peer_cseq = self.endpoint[self.dialog_layer]
+            .dialogs
+            .lock()
+            .get(&self.key())
+            .and_then(|entry| entry.next_peer_cseq)
+            .map(|next_peer_cseq| next_peer_cseq - 1);
+
+        DialogState {
+            call_id: self.call_id.0.to_string(),
+            local_cseq: self.local_cseq,
+            peer_cseq,
+            from: print_header(&self.from),
+            to: print_header(&self.to),
+            local_contact: print_header(&self.local_contact),
+            peer_contact: print_header(&self.peer_contact),
+            route_set: self.route_set.iter().map(print_header).collect(),
+            secure: self.secure,
+        }
+    }
+
+    /// Restore a dialog from a previously exported state into the given endpoint
+    ///
+    /// The route set is restored as is and not reversed again.
+    pub fn restore(
+        endpoint: Endpoint,
+        dialog_layer: LayerKey<DialogLayer>,
+        state: DialogState,
+    ) -> Result<Self> {
+        let from: From = parse_header(&state.from)?;
+        let to: To = parse_header(&state.to)?;
+
+        if from.tag.is_none() {
+            return Err(Error {
+                status: Code::BAD_REQUEST,
+                error: Some(anyhow!("Missing local tag")),
+            });
+        }
+
+        let dialog = Self {
+            endpoint,
+            dialog_layer,
+            local_cseq: state.local_cseq,
+            peer_cseq: state.peer_cseq.unwrap_or_default(),
+            from,
+            to,
+            local_contact: parse_header(&state.local_contact)?,
+            peer_contact: parse_header(&state.peer_contact)?,
+            call_id: CallID::new(state.call_id),
+            route_set: state
+                .route_set
+                .iter()
+                .map(|route| parse_header::<RecordRoute>(route))
+                .collect::<Result<_>>()?,
+            secure: state.secure,
+        };
+
+        let entry = DialogEntry::new(
+            state.peer_cseq,
+            dialog.secure,
+            dialog.requires_sips_target(),
+        );
+
+        dialog.endpoint[dialog_layer]
+            .dialogs
+            .lock()
+            .insert(dialog.key(), entry);
+
+        Ok(dialog)
+    }
+}
+
+fn print_header<H: Header>(header: &H) -> String {
+    let mut headers = Headers::new();
+    headers.insert_type(header);
+
+    let values: Vec<&str> = headers.iter().map(|(_, value)| value.as_str()).collect();
+
+    values.join(", ")
+}
+
+fn parse_header<H: Header>(value: &str) -> Result<H> {
+    let mut headers = Headers::new();
+    headers.insert(H::name().clone(), BytesStr::from(value));
+
+    Ok(headers.get()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sip_types::Name;
+
+    fn new_endpoint() -> (Endpoint, LayerKey<DialogLayer>) {
+        let mut builder = Endpoint::builder();
+        let dialog_layer = builder.add_layer(DialogLayer::default());
+
+        (builder.build(), dialog_layer)
+    }
+
+    fn state() -> DialogState {
+        DialogState {
+            call_id: "state-test".into(),
+            local_cseq: 42,
+            peer_cseq: Some(7),
+            from: "<sips:alice@example.com>;tag=local".into(),
+            to: "\"Bob\"<sips:bob@example.org>;tag=peer".into(),
+            local_contact: "<sips:alice@10.0.0.1:5061;transport=tcp>".into(),
+            peer_contact: "<sips:bob@10.0.0.2:5061;transport=tcp>".into(),
+            route_set: vec![
+                "<sips:proxy1.example.com;lr>".into(),
+                "<sips:proxy2.example.org;lr>".into(),
+            ],
+            secure: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn export_restore() {
+        let (endpoint, dialog_layer) = new_endpoint();
+
+        let mut headers = Headers::new();
+        headers.insert(Name::RECORD_ROUTE, "<sips:proxy1.example.com;lr>");
+        headers.insert(Name::RECORD_ROUTE, "<sips:proxy2.example.org;lr>");
+
+        let dialog = Dialog::new_server(
+            endpoint,
+            dialog_layer,
+            7,
+            parse_header(&state().to).unwrap(),
+            parse_header(&state().from).unwrap(),
+            parse_header(&state().local_contact).unwrap(),
+            parse_header(&state().peer_contact).unwrap(),
+            CallID::new("state-test"),
+            headers.get().unwrap(),
+            true,
+        );
+
+        let mut state = dialog.export();
+        assert_eq!(state.peer_cseq, Some(7));
+        assert_eq!(state.route_set, self::state().route_set);
+        state.local_cseq = 42;
+        assert_eq!(state, self::state());
+
+        drop(dialog);
+
+        // Restore into another endpoint, e.g. on a standby node
+        let (endpoint, dialog_layer) = new_endpoint();
+        let restored = Dialog::restore(endpoint.clone(), dialog_layer, state.clone()).unwrap();
+
+        assert_eq!(restored.export(), state);
+        assert!(endpoint[dialog_layer]
+            .dialogs
+            .lock()
+            .contains_key(&restored.key()));
+    }
+
+    #[tokio::test]
+    async fn export_restore_client_without_peer_request() {
+        let (endpoint, dialog_layer) = new_endpoint();
+
+        let mut state = state();
+        state.peer_cseq = None;
+
+        let restored = Dialog::restore(endpoint, dialog_layer, state.clone()).unwrap();
+        assert_eq!(restored.peer_cseq, 0);
+        assert_eq!(restored.export(), state);
+    }
+
+    #[tokio::test]
+    async fn restore_without_local_tag() {
+        let (endpoint, dialog_layer) = new_endpoint();
+
+        let mut state = state();
+        state.from = "<sips:alice@example.com>".into();
+
+        let error = Dialog::restore(endpoint, dialog_layer, state).unwrap_err();
+        assert_eq!(error.status, Code::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,278 @@
+//! Call diversion, History-Info (RFC 7044) and Diversion (RFC 5806)
+//!
+//! Contains the helpers used by user agents and proxies to record the retargeting of a request,
+//! and by applications like voicemail to find the party which was originally called.
+
+use sip_core::Request;
+use sip_types::header::typed::{
+    Diversion, DiversionReason, HistoryIndex, HistoryInfo, HistoryTag, Reason, To,
+};
+use sip_types::header::HeaderError;
+use sip_types::uri::params::Params;
+use sip_types::uri::sip::SipUri;
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Code, Headers, Name};
+
+/// Retarget a request to `target` and record it in its `History-Info` header
+///
+/// If the request has no History-Info yet, an entry for the current request URI is added first.
+/// The `cause` of the retargeting is escaped as Reason in the entry of the current target and
+/// added as `cause` parameter (RFC 4458) to the new request URI.
+pub fn retarget(
+    request: &mut Request,
+    target: NameAddr,
+    tag: fn(HistoryIndex) -> HistoryTag,
+    cause: Option<Code>,
+) -> Result<(), HeaderError> {
+    let mut history = match request.headers.try_get::<Vec<HistoryInfo>>() {
+        Some(history) => history?,
+        None => vec![],
+    };
+
+    if history.is_empty() {
+        HistoryInfo::retarget(
+            &mut history,
+            NameAddr::uri(request.line.uri.clone()),
+            tag,
+            None,
+        );
+    }
+
+    let reason = cause.map(Reason::sip);
+    let entry = HistoryInfo::retarget(&mut history, target, tag, reason.as_ref());
+
+    if let Some(cause) = cause {
+        entry.set_cause(cause.into_u16());
+    }
+
+    request.line.uri = entry.uri.uri.clone();
+
+    request.headers.remove(&Name::HISTORY_INFO);
+    request.headers.insert_type(&history);
+
+    Ok(())
+}
+
+/// Retarget a request to `target` and record it in its `Diversion` header
+///
+/// The current request URI is added as the diverting party.
+pub fn divert(
+    request: &mut Request,
+    target: Box<dyn Uri>,
+    reason: DiversionReason,
+) -> Result<(), HeaderError> {
+    let mut diversions = match request.headers.try_get::<Vec<Diversion>>() {
+        Some(diversions) => diversions?,
+        None => vec![],
+    };
+
+    let from = std::mem::replace(&mut request.line.uri, target);
+    Diversion::divert(&mut diversions, NameAddr::uri(from), reason);
+
+    request.headers.remove(&Name::DIVERSION);
+    request.headers.insert_type(&diversions);
+
+    Ok(())
+}
+
+/// Returns the party which was originally called by a request
+///
+/// Uses the first `History-Info` entry, the last `Diversion` entry or the `To` header,
+/// whichever is found first. Malformed headers are skipped.
+pub fn original_called_party(headers: &Headers) -> Option<NameAddr> {
+    if let Ok(history) = headers.get::<Vec<HistoryInfo>>() {
+        if let Some(entry) = HistoryInfo::original_target(&history) {
+            let mut uri = entry.uri.clone();
+
+            // Remove the escaped Reason
+            if let Some(sip_uri) = uri.uri.downcast_mut::<SipUri>() {
+                sip_uri.header_params = Params::new();
+            }
+
+            return Some(uri);
+        }
+    }
+
+    if let Ok(diversions) = headers.get::<Vec<Diversion>>() {
+        if let Some(entry) = Diversion::original_target(&diversions) {
+            return Some(entry.uri.clone());
+        }
+    }
+
+    headers.get::<To>().ok().map(|to| to.0.uri)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sip_types::header::typed::HistoryTag;
+    use sip_types::print::{AppendCtx, Print};
+    use sip_types::Method;
+
+    fn sip_uri(user: &str) -> SipUri {
+        format!("sip:{}@example.com", user).parse().unwrap()
+    }
+
+    fn print<P: Print>(p: &P) -> String {
+        p.default_print_ctx().to_string()
+    }
+
+    fn request() -> Request {
+        Request::new(Method::INVITE, sip_uri("bob"))
+    }
+
+    #[test]
+    fn retarget_adds_first_entry() {
+        let mut request = request();
+
+        retarget(
+            &mut request,
+            NameAddr::uri(sip_uri("voicemail")),
+            HistoryTag::Mp,
+            Some(Code::BUSY_HERE),
+        )
+        .unwrap();
+
+        assert_eq!(
+            print(&request.line.uri),
+            "sip:voicemail@example.com;cause=486"
+        );
+
+        let history: Vec<HistoryInfo> = request.headers.get().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            print(&history[0]),
+            "<sip:bob@example.com?Reason=SIP%3Bcause%3D486%3Btext%3D%22Busy%20Here%22>;index=1"
+        );
+        assert_eq!(
+            print(&history[1]),
+            "<sip:voicemail@example.com;cause=486>;index=1.1;mp=1"
+        );
+    }
+
+    #[test]
+    fn retarget_without_cause() {
+        let mut request = request();
+
+        retarget(
+            &mut request,
+            NameAddr::uri(sip_uri("carol")),
+            HistoryTag::Rc,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(print(&request.line.uri), "sip:carol@example.com");
+
+        let history: Vec<HistoryInfo> = request.headers.get().unwrap();
+        assert_eq!(print(&history[0]), "<sip:bob@example.com>;index=1");
+        assert_eq!(print(&history[1]), "<sip:carol@example.com>;index=1.1;rc=1");
+    }
+
+    #[test]
+    fn retarget_existing_history() {
+        let mut request = Request::new(Method::INVITE, sip_uri("carol"));
+        request.headers.insert(
+            Name::HISTORY_INFO,
+            "<sip:bob@example.com>;index=1, <sip:carol@example.com>;index=1.1;rc=1",
+        );
+
+        retarget(
+            &mut request,
+            NameAddr::uri(sip_uri("voicemail")),
+            HistoryTag::Mp,
+            Some(Code::REQUEST_TIMEOUT),
+        )
+        .unwrap();
+
+        let history: Vec<HistoryInfo> = request.headers.get().unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(print(&history[0]), "<sip:bob@example.com>;index=1");
+        assert_eq!(history[1].reason().unwrap().cause, Some(408));
+        assert_eq!(
+            print(&history[2]),
+            "<sip:voicemail@example.com;cause=408>;index=1.1.1;mp=1.1"
+        );
+    }
+
+    #[test]
+    fn divert_inserts_first_entry() {
+        let mut request = request();
+        request.headers.insert(
+            Name::DIVERSION,
+            "<sip:alice@example.com>;reason=unconditional;counter=1",
+        );
+
+        divert(
+            &mut request,
+            Box::new(sip_uri("voicemail")),
+            DiversionReason::UserBusy,
+        )
+        .unwrap();
+
+        assert_eq!(print(&request.line.uri), "sip:voicemail@example.com");
+
+        let diversions: Vec<Diversion> = request.headers.get().unwrap();
+        assert_eq!(diversions.len(), 2);
+        assert_eq!(print(&diversions[0].uri.uri), "sip:bob@example.com");
+        assert_eq!(diversions[0].reason, Some(DiversionReason::UserBusy));
+        assert_eq!(print(&diversions[1].uri.uri), "sip:alice@example.com");
+    }
+
+    fn headers(history_info: Option<&str>, diversion: Option<&str>) -> Headers {
+        let mut headers = Headers::new();
+        headers.insert(Name::TO, "<sip:to@example.com>");
+
+        if let Some(history_info) = history_info {
+            headers.insert(Name::HISTORY_INFO, history_info);
+        }
+
+        if let Some(diversion) = diversion {
+            headers.insert(Name::DIVERSION, diversion);
+        }
+
+        headers
+    }
+
+    fn original(headers: &Headers) -> String {
+        print(&original_called_party(headers).unwrap().uri)
+    }
+
+    #[test]
+    fn original_called_party_priority() {
+        let history_info = "<sip:carol@example.com>;index=1.1;rc=1, \
+            <sip:bob@example.com?Reason=SIP%3Bcause%3D302>;index=1";
+        let diversion = "<sip:carol@example.com>;reason=user-busy, \
+            <sip:alice@example.com>;reason=unconditional";
+
+        assert_eq!(
+            original(&headers(Some(history_info), Some(diversion))),
+            "sip:bob@example.com"
+        );
+        assert_eq!(
+            original(&headers(None, Some(diversion))),
+            "sip:alice@example.com"
+        );
+        assert_eq!(original(&headers(None, None)), "sip:to@example.com");
+    }
+
+    #[test]
+    fn original_called_party_skips_malformed() {
+        let diversion = "<sip:alice@example.com>;reason=unconditional";
+
+        assert_eq!(
+            original(&headers(
+                Some("<sip:bob@example.com>;rc=1"),
+                Some(diversion)
+            )),
+            "sip:alice@example.com"
+        );
+        assert_eq!(
+            original(&headers(
+                Some("<sip:bob@example.com>;rc=1"),
+                Some("not a uri")
+            )),
+            "sip:to@example.com"
+        );
+    }
+}
@@ -11,11 +11,11 @@ use parking_lot as pl;
 use sip_core::transaction::consts::T1;
 use sip_core::transport::OutgoingResponse;
 use sip_core::{Endpoint, Error, IncomingRequest, LayerKey, Result, WithStatus};
-use sip_types::header::typed::{Contact, RSeq, RecordRoute, Require, Supported};
+use sip_types::header::typed::{Contact, RSeq, RecordRoute, RecvInfo, Require, Supported};
 use sip_types::{Code, Method};
 use std::ops::Deref;
 use std::sync::Arc;
-use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex, Notify};
 use tokio::time::timeout;
 
 #[derive(Debug, thiserror::Error)]
@@ -68,6 +68,14 @@ impl Acceptor {
         let peer_supports_timer = supported.iter().any(|ext| ext.deref() == "timer");
         let peer_supports_100rel = supported.iter().any(|ext| ext.deref() == "100rel");
 
+        let peer_recv_info = invite
+            .headers
+            .get::<Vec<RecvInfo>>()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|recv_info| recv_info.0)
+            .collect();
+
         let route_set: Vec<RecordRoute> = invite.headers.get().unwrap_or_default();
 
         let peer_contact: Contact = invite.headers.get()?;
@@ -91,7 +99,7 @@ impl Acceptor {
             peer_contact,
             invite.base_headers.call_id.clone(),
             route_set,
-            invite.line.uri.info().secure,
+            invite.line.uri.info().secure || invite.tp_info.transport.secure(),
         );
 
         // ==== register acceptor usage to dialog
@@ -114,6 +122,8 @@ impl Acceptor {
             }),
             peer_supports_timer,
             peer_supports_100rel,
+            peer_recv_info,
+            cancelled: Notify::new(),
             awaited_ack: pl::Mutex::new(None),
             awaited_prack: pl::Mutex::new(None),
         });
@@ -153,6 +163,13 @@ impl Acceptor {
         self.inner.peer_supports_timer
     }
 
+    /// Wait until the INVITE gets cancelled by the peer
+    ///
+    /// Responding to the INVITE after this returned will fail.
+    pub async fn cancelled(&self) {
+        self.inner.cancelled.notified().await
+    }
+
     pub async fn create_response(
         &self,
         code: Code,
@@ -266,6 +283,11 @@ impl Acceptor {
                 SessionTimer::new_unsupported()
             };
 
+            if !response.msg.headers.contains::<RecvInfo>() {
+                let recv_info = self.endpoint[self.inner.invite_layer].recv_info();
+                response.msg.headers.insert_type(&recv_info);
+            }
+
             let accepted = transaction.respond_success(response).await?;
 
             let ack = super::receive_ack(accepted, ack_recv).await?;
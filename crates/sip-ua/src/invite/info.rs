@@ -0,0 +1,235 @@
+//! DTMF relay using INFO requests
+//!
+//! Supports the `application/dtmf-relay` and `application/dtmf` content types, which are
+//! sent using legacy INFO requests without an `Info-Package` header.
+
+use std::fmt;
+use std::str::from_utf8;
+
+pub const DTMF_RELAY_CONTENT_TYPE: &str = "application/dtmf-relay";
+pub const DTMF_CONTENT_TYPE: &str = "application/dtmf";
+
+/// Default duration of a DTMF tone in milliseconds
+const DEFAULT_DURATION: u32 = 160;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DtmfError {
+    #[error("missing Signal field")]
+    MissingSignal,
+    #[error("invalid DTMF signal {0:?}")]
+    InvalidSignal(String),
+    #[error("invalid duration {0:?}")]
+    InvalidDuration(String),
+    #[error("body is not valid UTF-8")]
+    InvalidUtf8,
+}
+
+/// A single DTMF tone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dtmf {
+    /// One of `0-9`, `*`, `#` and `A-D`
+    pub signal: char,
+
+    /// Duration of the tone in milliseconds
+    pub duration: u32,
+}
+
+impl Dtmf {
+    /// Create a DTMF tone with the default duration of 160ms
+    ///
+    /// Returns `None` if the signal is not a valid DTMF signal
+    pub fn new(signal: char) -> Option<Self> {
+        Self::with_duration(signal, DEFAULT_DURATION)
+    }
+
+    /// Create a DTMF tone with the given duration in milliseconds
+    ///
+    /// Returns `None` if the signal is not a valid DTMF signal
+    pub fn with_duration(signal: char, duration: u32) -> Option<Self> {
+        let signal = signal.to_ascii_uppercase();
+
+        if is_dtmf_signal(signal) {
+            Some(Self { signal, duration })
+        } else {
+            None
+        }
+    }
+
+    /// Parse an `application/dtmf-relay` body
+    ///
+    /// ```text
+    /// Signal=5
+    /// Duration=160
+    /// ```
+    pub fn parse_dtmf_relay(body: &[u8]) -> Result<Self, DtmfError> {
+        let body = from_utf8(body).map_err(|_| DtmfError::InvalidUtf8)?;
+
+        let mut signal = None;
+        let mut duration = DEFAULT_DURATION;
+
+        for line in body.lines() {
+            let (name, value) = match line.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => continue,
+            };
+
+            if name.eq_ignore_ascii_case("Signal") {
+                signal = Some(parse_signal(value)?);
+            } else if name.eq_ignore_ascii_case("Duration") {
+                duration = value
+                    .parse()
+                    .map_err(|_| DtmfError::InvalidDuration(value.into()))?;
+            }
+        }
+
+        let signal = signal.ok_or(DtmfError::MissingSignal)?;
+
+        Ok(Self { signal, duration })
+    }
+
+    /// Parse an `application/dtmf` body, which only contains the signal
+    pub fn parse_dtmf(body: &[u8]) -> Result<Self, DtmfError> {
+        let body = from_utf8(body).map_err(|_| DtmfError::InvalidUtf8)?;
+
+        Ok(Self {
+            signal: parse_signal(body.trim())?,
+            duration: DEFAULT_DURATION,
+        })
+    }
+
+    /// Print the tone as `application/dtmf-relay` body
+    pub fn to_dtmf_relay(&self) -> String {
+        format!("Signal={}\r\nDuration={}\r\n", self.signal, self.duration)
+    }
+
+    /// Print the tone as `application/dtmf` body
+    pub fn to_dtmf(&self) -> String {
+        self.signal.to_string()
+    }
+}
+
+impl fmt::Display for Dtmf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.signal)
+    }
+}
+
+/// Body format used to send DTMF tones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtmfFormat {
+    /// `application/dtmf-relay`
+    DtmfRelay,
+
+    /// `application/dtmf`
+    Dtmf,
+}
+
+fn is_dtmf_signal(c: char) -> bool {
+    matches!(c, '0'..='9' | '*' | '#' | 'A'..='D')
+}
+
+fn parse_signal(value: &str) -> Result<char, DtmfError> {
+    let mut chars = value.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if is_dtmf_signal(c.to_ascii_uppercase()) => Ok(c.to_ascii_uppercase()),
+        _ => Err(DtmfError::InvalidSignal(value.into())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dtmf_relay() {
+        let dtmf = Dtmf::parse_dtmf_relay(b"Signal=5\r\nDuration=250\r\n").unwrap();
+
+        assert_eq!(dtmf.signal, '5');
+        assert_eq!(dtmf.duration, 250);
+    }
+
+    #[test]
+    fn dtmf_relay_lenient() {
+        let dtmf = Dtmf::parse_dtmf_relay(b"signal = #\nunknown\nOther=1").unwrap();
+
+        assert_eq!(dtmf.signal, '#');
+        assert_eq!(dtmf.duration, DEFAULT_DURATION);
+
+        let dtmf = Dtmf::parse_dtmf_relay(b"Signal=a\r\n").unwrap();
+        assert_eq!(dtmf.signal, 'A');
+    }
+
+    #[test]
+    fn dtmf_relay_invalid() {
+        assert!(matches!(
+            Dtmf::parse_dtmf_relay(b"Duration=160\r\n"),
+            Err(DtmfError::MissingSignal)
+        ));
+        assert!(matches!(
+            Dtmf::parse_dtmf_relay(b""),
+            Err(DtmfError::MissingSignal)
+        ));
+        assert!(matches!(
+            Dtmf::parse_dtmf_relay(b"Signal=E\r\n"),
+            Err(DtmfError::InvalidSignal(signal)) if signal == "E"
+        ));
+        assert!(matches!(
+            Dtmf::parse_dtmf_relay(b"Signal=12\r\n"),
+            Err(DtmfError::InvalidSignal(signal)) if signal == "12"
+        ));
+        assert!(matches!(
+            Dtmf::parse_dtmf_relay(b"Signal=1\r\nDuration=-5\r\n"),
+            Err(DtmfError::InvalidDuration(duration)) if duration == "-5"
+        ));
+        assert!(matches!(
+            Dtmf::parse_dtmf_relay(&[0xff, 0xfe]),
+            Err(DtmfError::InvalidUtf8)
+        ));
+    }
+
+    #[test]
+    fn dtmf() {
+        let dtmf = Dtmf::parse_dtmf(b"*\r\n").unwrap();
+
+        assert_eq!(dtmf.signal, '*');
+        assert_eq!(dtmf.duration, DEFAULT_DURATION);
+    }
+
+    #[test]
+    fn dtmf_invalid() {
+        assert!(matches!(
+            Dtmf::parse_dtmf(b""),
+            Err(DtmfError::InvalidSignal(signal)) if signal.is_empty()
+        ));
+        assert!(matches!(
+            Dtmf::parse_dtmf(b"Signal=1"),
+            Err(DtmfError::InvalidSignal(_))
+        ));
+        assert!(matches!(
+            Dtmf::parse_dtmf(&[0xff]),
+            Err(DtmfError::InvalidUtf8)
+        ));
+    }
+
+    #[test]
+    fn print_parse() {
+        let dtmf = Dtmf::with_duration('d', 100).unwrap();
+        assert_eq!(dtmf.signal, 'D');
+
+        assert_eq!(
+            Dtmf::parse_dtmf_relay(dtmf.to_dtmf_relay().as_bytes()).unwrap(),
+            dtmf
+        );
+        assert_eq!(
+            Dtmf::parse_dtmf(dtmf.to_dtmf().as_bytes()).unwrap(),
+            Dtmf::new('D').unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_signal() {
+        assert_eq!(Dtmf::new('x'), None);
+        assert_eq!(Dtmf::new(' '), None);
+    }
+}
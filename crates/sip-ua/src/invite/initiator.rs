@@ -0,0 +1,823 @@
+use super::prack::ReliableProvisionals;
+use super::session::{Role, Session};
+use super::timer::InitiatorTimerConfig;
+use super::{Inner, InviteLayer, InviteSessionState, InviteUsage};
+use crate::dialog::{register_usage, Dialog, DialogLayer};
+use crate::util::{random_sequence_number, random_string};
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use parking_lot as pl;
+use sip_core::transaction::{ClientInvTsx, TsxResponse};
+use sip_core::{Endpoint, Error, LayerKey, Request, Result};
+use sip_types::header::typed::{
+    CSeq, CallID, Contact, ContentType, From, RAck, RSeq, RecordRoute, RecvInfo, Require, Route,
+    Supported, To,
+};
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Code, CodeKind, Method};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex, Notify};
+
+/// Response to an INVITE sent by the [`Initiator`]
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Response {
+    /// Provisional response to the INVITE
+    Provisional(TsxResponse),
+
+    /// Provisional response of an early dialog which contains a session description (early media)
+    ///
+    /// When the INVITE is forked, the provisional responses of every early dialog are returned
+    /// separately, identified by the to-tag. The latest session description of every early dialog
+    /// is also available using [`Initiator::early_dialog`].
+    EarlyMedia(TsxResponse),
+
+    /// Reliable provisional response to the INVITE, which must be acknowledged using a PRACK
+    /// request created with [`Initiator::create_prack`].
+    ///
+    /// The PRACK is not sent automatically, since it must carry the answer if the response
+    /// contained an offer.
+    ///
+    /// Reliable provisional responses are returned in the order of their `RSeq` number,
+    /// retransmissions are not returned. Session descriptions of reliable provisional responses
+    /// are stored in the early dialog as well.
+    ReliableProvisional(TsxResponse),
+
+    /// The INVITE has been rejected with the given response
+    Failure(TsxResponse),
+
+    /// The INVITE has been accepted and a session has been established
+    Session(Session, TsxResponse),
+
+    /// The INVITE transaction has been terminated, no more responses will be received
+    Finished,
+}
+
+/// Early dialog created by a provisional response with a to-tag
+#[derive(Debug)]
+pub struct EarlyDialog {
+    pub dialog: Dialog,
+
+    /// The latest session description received inside the early dialog
+    pub session_description: Option<(ContentType, Bytes)>,
+}
+
+/// Used to create outgoing sessions by sending INVITE requests
+pub struct Initiator {
+    endpoint: Endpoint,
+    dialog_layer: LayerKey<DialogLayer>,
+    invite_layer: LayerKey<InviteLayer>,
+
+    local_contact: Contact,
+    target: Box<dyn Uri>,
+
+    /// Preloaded route set of the INVITE
+    route_set: Vec<Route>,
+
+    from: From,
+    to: To,
+    call_id: CallID,
+
+    /// CSeq number of the current INVITE
+    cseq: u32,
+
+    /// Last CSeq number used in any request sent by the initiator
+    local_cseq: u32,
+
+    /// Configuration for `timer` extension
+    pub timer_config: InitiatorTimerConfig,
+
+    /// The last sent INVITE, used to resend the INVITE when required
+    invite: Option<Request>,
+    transaction: Option<ClientInvTsx>,
+
+    /// State of reliable provisional responses for every early dialog, keyed by the to-tag
+    reliable_provisionals: HashMap<BytesStr, ReliableProvisionals>,
+
+    /// Early dialogs created by provisional responses, keyed by the to-tag
+    early_dialogs: HashMap<BytesStr, EarlyDialog>,
+}
+
+impl Initiator {
+    pub fn new(
+        endpoint: Endpoint,
+        dialog_layer: LayerKey<DialogLayer>,
+        invite_layer: LayerKey<InviteLayer>,
+        id: NameAddr,
+        local_contact: Contact,
+        target: Box<dyn Uri>,
+    ) -> Self {
+        let cseq = random_sequence_number();
+
+        Self {
+            endpoint,
+            dialog_layer,
+            invite_layer,
+            local_contact,
+            to: To::new(NameAddr::uri(target.clone()), None),
+            target,
+            route_set: vec![],
+            from: From::new(id, Some(random_string())),
+            call_id: CallID::new(random_string()),
+            cseq,
+            local_cseq: cseq,
+            timer_config: InitiatorTimerConfig::default(),
+            invite: None,
+            transaction: None,
+            reliable_provisionals: HashMap::new(),
+            early_dialogs: HashMap::new(),
+        }
+    }
+
+    /// Set the preloaded route set of the INVITE, usually the
+    /// [`Registration::route_set`](crate::register::Registration::route_set) of the calling user
+    pub fn set_route_set(&mut self, route_set: Vec<Route>) {
+        self.route_set = route_set;
+    }
+
+    /// Returns the early dialog created by provisional responses with the given to-tag
+    pub fn early_dialog(&self, to_tag: &BytesStr) -> Option<&EarlyDialog> {
+        self.early_dialogs.get(to_tag)
+    }
+
+    /// Returns all early dialogs, one for every to-tag seen in provisional responses
+    pub fn early_dialogs(&self) -> impl Iterator<Item = &EarlyDialog> + '_ {
+        self.early_dialogs.values()
+    }
+
+    /// Create the initial INVITE request, which can be modified (e.g. adding an SDP offer)
+    /// before sending it using [`Initiator::send_invite`]
+    pub fn create_invite(&mut self) -> Request {
+        let mut request = Request::new(Method::INVITE, self.target.clone());
+
+        request.headers.insert_type(&self.from);
+        request.headers.insert_type(&self.to);
+        request.headers.insert_type(&self.call_id);
+        request
+            .headers
+            .insert_type(&CSeq::new(self.cseq, Method::INVITE));
+        request.headers.insert_type(&self.route_set);
+        request.headers.insert_type(&self.local_contact);
+        request.headers.insert_type(self.endpoint.allowed());
+        request.headers.insert_type(self.endpoint.supported());
+        request
+            .headers
+            .insert_type(&self.endpoint[self.invite_layer].recv_info());
+
+        self.timer_config.populate_request(&mut request);
+
+        request
+    }
+
+    pub async fn send_invite(&mut self, request: Request) -> Result<()> {
+        let transaction = self.endpoint.send_invite(request.clone()).await?;
+
+        self.invite = Some(request);
+        self.transaction = Some(transaction);
+
+        Ok(())
+    }
+
+    /// Receive the next response to the sent INVITE
+    ///
+    /// A `422 Session Interval Too Small` response is handled internally, by resending the
+    /// INVITE with the session interval required by the peer.
+    pub async fn receive(&mut self) -> Result<Response> {
+        loop {
+            let held_back = self
+                .reliable_provisionals
+                .values_mut()
+                .find_map(ReliableProvisionals::next_in_order);
+
+            if let Some(response) = held_back {
+                return Ok(Response::ReliableProvisional(response));
+            }
+
+            let transaction = match &mut self.transaction {
+                Some(transaction) => transaction,
+                None => return Ok(Response::Finished),
+            };
+
+            let response = match transaction.receive().await? {
+                Some(response) => response,
+                None => {
+                    self.transaction = None;
+                    return Ok(Response::Finished);
+                }
+            };
+
+            match response.line.code.kind() {
+                CodeKind::Provisional => {
+                    if let Some(response) = self.handle_provisional(response) {
+                        return Ok(response);
+                    }
+                }
+                CodeKind::Success => {
+                    let transaction = self.transaction.take().unwrap();
+                    let session = self.create_session(transaction, &response).await?;
+
+                    return Ok(Response::Session(session, response));
+                }
+                _ => {
+                    self.transaction = None;
+                    self.early_dialogs.clear();
+
+                    if self.timer_config.on_interval_too_small(&response) {
+                        self.resend_invite().await?;
+                        continue;
+                    }
+
+                    return Ok(Response::Failure(response));
+                }
+            }
+        }
+    }
+
+    /// Cancel the sent INVITE
+    ///
+    /// Should only be called after a provisional response has been received. The INVITE
+    /// will usually be answered with `487 Request Terminated` afterwards, which is returned by
+    /// [`Initiator::receive`].
+    pub async fn cancel(&mut self) -> Result<()> {
+        let transaction = match &self.transaction {
+            Some(transaction) => transaction,
+            None => return Ok(()),
+        };
+
+        if let Some(cancel) = transaction.cancel().await? {
+            let response = cancel.receive_final().await?;
+
+            if response.line.code.kind() != CodeKind::Success {
+                log::debug!("CANCEL got rejected with {:?}", response.line.code);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create a PRACK request acknowledging the given reliable provisional response
+    ///
+    /// The PRACK is sent inside the early dialog created by the response, using its remote
+    /// target and route set. Returns an error if the response has no to-tag or did not create
+    /// an early dialog.
+    ///
+    /// The request can be modified before sending it using [`Initiator::send_prack`],
+    /// e.g. to add an SDP answer to an offer received in the provisional response.
+    pub fn create_prack(&mut self, response: &TsxResponse) -> Result<Request> {
+        let rseq: RSeq = response.headers.get()?;
+
+        self.create_prack_in_early_dialog(&response.base_headers.to, rseq)
+    }
+
+    fn create_prack_in_early_dialog(&mut self, to: &To, rseq: RSeq) -> Result<Request> {
+        let early_dialogs = &mut self.early_dialogs;
+
+        let early_dialog = to
+            .tag
+            .as_ref()
+            .and_then(|to_tag| early_dialogs.get_mut(to_tag))
+            .ok_or_else(|| Error::new(Code::CALL_OR_TRANSACTION_DOES_NOT_EXIST))?;
+
+        let mut request = early_dialog.dialog.create_request(Method::PRACK);
+
+        // Keep track of the CSeq number for requests outside of the early dialog
+        self.local_cseq = self.local_cseq.max(early_dialog.dialog.local_cseq - 1);
+
+        request
+            .headers
+            .insert_type(&RAck::new(rseq.0, self.cseq, Method::INVITE));
+
+        Ok(request)
+    }
+
+    /// Send a PRACK request created with [`Initiator::create_prack`] and return the final response to it
+    pub async fn send_prack(&mut self, request: Request) -> Result<TsxResponse> {
+        let transaction = self.endpoint.send_request(request).await?;
+
+        transaction.receive_final().await
+    }
+
+    /// Filter reliable provisional responses, returns `None` if the response must not be delivered
+    fn handle_provisional(&mut self, response: TsxResponse) -> Option<Response> {
+        let has_session_description = self.update_early_dialog(&response);
+
+        let requires_100rel = response
+            .headers
+            .get::<Vec<Require>>()
+            .unwrap_or_default()
+            .iter()
+            .any(|ext| ext.deref() == "100rel");
+
+        let rseq = response.headers.get::<RSeq>().ok();
+        let to_tag = response.base_headers.to.tag.clone();
+
+        match (requires_100rel, rseq, to_tag) {
+            (true, Some(rseq), Some(to_tag)) => self
+                .reliable_provisionals
+                .entry(to_tag)
+                .or_default()
+                .receive(rseq.0, response)
+                .map(Response::ReliableProvisional),
+            _ if has_session_description => Some(Response::EarlyMedia(response)),
+            _ => Some(Response::Provisional(response)),
+        }
+    }
+
+    /// Create or update the early dialog of a provisional response,
+    /// returns if the response contains a session description
+    fn update_early_dialog(&mut self, response: &TsxResponse) -> bool {
+        let to_tag = match &response.base_headers.to.tag {
+            Some(to_tag) => to_tag.clone(),
+            None => return false,
+        };
+
+        if !self.early_dialogs.contains_key(&to_tag) {
+            // Provisional responses without Contact cannot create an early dialog
+            let peer_contact: Contact = match response.headers.get() {
+                Ok(peer_contact) => peer_contact,
+                Err(_) => return false,
+            };
+
+            let route_set: Vec<RecordRoute> = response.headers.get().unwrap_or_default();
+
+            let dialog = Dialog::new_client(
+                self.endpoint.clone(),
+                self.dialog_layer,
+                self.local_cseq,
+                self.from.clone(),
+                response.base_headers.to.clone(),
+                self.local_contact.clone(),
+                peer_contact,
+                self.call_id.clone(),
+                route_set,
+                self.target.info().secure || response.tp_info.transport.secure(),
+            );
+
+            self.early_dialogs.insert(
+                to_tag.clone(),
+                EarlyDialog {
+                    dialog,
+                    session_description: None,
+                },
+            );
+        }
+
+        if response.body.is_empty() {
+            return false;
+        }
+
+        let content_type = match response.headers.get::<ContentType>() {
+            Ok(content_type) => content_type,
+            Err(_) => return false,
+        };
+
+        if let Some(early_dialog) = self.early_dialogs.get_mut(&to_tag) {
+            early_dialog.session_description = Some((content_type, response.body.clone()));
+        }
+
+        true
+    }
+
+    /// Send the last INVITE again with an incremented CSeq and updated `timer` headers
+    async fn resend_invite(&mut self) -> Result<()> {
+        let mut invite = self
+            .invite
+            .take()
+            .expect("resend_invite called without invite");
+
+        self.local_cseq += 1;
+        self.cseq = self.local_cseq;
+        self.reliable_provisionals.clear();
+
+        invite.headers.remove_type::<CSeq>();
+        invite
+            .headers
+            .insert_type(&CSeq::new(self.cseq, Method::INVITE));
+
+        self.timer_config.populate_request(&mut invite);
+
+        self.send_invite(invite).await
+    }
+
+    async fn create_session(
+        &mut self,
+        transaction: ClientInvTsx,
+        response: &TsxResponse,
+    ) -> Result<Session> {
+        let peer_contact: Contact = response.headers.get()?;
+        let route_set: Vec<RecordRoute> = response.headers.get().unwrap_or_default();
+
+        let supported = response.headers.get::<Vec<Supported>>().unwrap_or_default();
+        let required = response.headers.get::<Vec<Require>>().unwrap_or_default();
+
+        let peer_supports = |ext: &str| {
+            supported.iter().any(|s| s.deref() == ext) || required.iter().any(|r| r.deref() == ext)
+        };
+
+        let peer_recv_info = response
+            .headers
+            .get::<Vec<RecvInfo>>()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|recv_info| recv_info.0)
+            .collect();
+
+        let early_dialog = response
+            .base_headers
+            .to
+            .tag
+            .as_ref()
+            .and_then(|to_tag| self.early_dialogs.remove(to_tag));
+
+        // Other early dialogs will never be confirmed
+        self.early_dialogs.clear();
+
+        let mut dialog = if let Some(EarlyDialog { mut dialog, .. }) = early_dialog {
+            // Promote the early dialog, the route set is recomputed from the 2XX response
+            dialog.refresh_target(&response.headers)?;
+            dialog.route_set = route_set.into_iter().rev().collect();
+            dialog.local_cseq = dialog.local_cseq.max(self.local_cseq + 1);
+            dialog
+        } else {
+            Dialog::new_client(
+                self.endpoint.clone(),
+                self.dialog_layer,
+                self.local_cseq,
+                self.from.clone(),
+                response.base_headers.to.clone(),
+                self.local_contact.clone(),
+                peer_contact,
+                self.call_id.clone(),
+                route_set,
+                self.target.info().secure || response.tp_info.transport.secure(),
+            )
+        };
+
+        let (evt_sink, events) = mpsc::channel(4);
+
+        let inner = Arc::new(Inner {
+            invite_layer: self.invite_layer,
+            state: Mutex::new(InviteSessionState::Established { evt_sink }),
+            peer_supports_timer: peer_supports("timer"),
+            peer_supports_100rel: peer_supports("100rel"),
+            peer_recv_info,
+            cancelled: Notify::new(),
+            awaited_ack: pl::Mutex::new(None),
+            awaited_prack: pl::Mutex::new(None),
+        });
+
+        let usage_guard = register_usage(
+            self.endpoint.clone(),
+            self.dialog_layer,
+            dialog.key(),
+            InviteUsage {
+                inner: inner.clone(),
+            },
+        )
+        // Unwrap is safe as we still hold the dialog
+        .unwrap();
+
+        let session_timer = self.timer_config.on_success_response(response);
+
+        let mut ack = super::create_ack(&mut dialog, response.base_headers.cseq.cseq).await?;
+
+        self.endpoint.send_outgoing_request(&mut ack).await?;
+
+        let forks = super::ForkContext {
+            dialog_layer: self.dialog_layer,
+            from: self.from.clone(),
+            local_contact: self.local_contact.clone(),
+            call_id: self.call_id.clone(),
+            secure: self.target.info().secure,
+        };
+
+        super::spawn_ack_retransmitter(
+            self.endpoint.clone(),
+            transaction,
+            ack,
+            response,
+            Some(forks),
+        );
+
+        Ok(Session::new(
+            self.endpoint.clone(),
+            inner,
+            Role::Uac,
+            events,
+            session_timer,
+            usage_guard,
+            dialog,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{bind_loopback, uri};
+    use sip_core::transport::OutgoingResponse;
+    use sip_core::{IncomingRequest, Layer, MayTake};
+    use sip_types::header::typed::Route;
+    use sip_types::print::{AppendCtx, Print};
+    use sip_types::{Headers, Name};
+    use std::net::SocketAddr;
+
+    fn initiator() -> Initiator {
+        let mut builder = Endpoint::builder();
+        let dialog_layer = builder.add_layer(DialogLayer::default());
+        let invite_layer = builder.add_layer(InviteLayer::default());
+        let endpoint = builder.build();
+
+        let local = uri("alice", ([127, 0, 0, 1], 5060).into());
+
+        Initiator::new(
+            endpoint,
+            dialog_layer,
+            invite_layer,
+            NameAddr::uri(local.clone()),
+            Contact::new(NameAddr::uri(local)),
+            Box::new(uri("bob", ([127, 0, 0, 1], 5070).into())),
+        )
+    }
+
+    /// Add the early dialog a provisional response with the given to-tag, Contact and Record-Route would create
+    fn add_early_dialog(initiator: &mut Initiator, to_tag: &str, contact: &str) -> To {
+        let to = To::new(initiator.to.uri.clone(), Some(to_tag.into()));
+
+        let mut headers = Headers::new();
+        headers.insert(Name::RECORD_ROUTE, "<sip:proxy2.example.com;lr>");
+        headers.insert(Name::RECORD_ROUTE, "<sip:proxy1.example.com;lr>");
+
+        let dialog = Dialog::new_client(
+            initiator.endpoint.clone(),
+            initiator.dialog_layer,
+            initiator.local_cseq,
+            initiator.from.clone(),
+            to.clone(),
+            initiator.local_contact.clone(),
+            Contact::new(NameAddr::uri(uri(contact, ([127, 0, 0, 1], 5080).into()))),
+            initiator.call_id.clone(),
+            headers.get().unwrap(),
+            false,
+        );
+
+        initiator.early_dialogs.insert(
+            to_tag.into(),
+            EarlyDialog {
+                dialog,
+                session_description: None,
+            },
+        );
+
+        to
+    }
+
+    fn print<P: Print>(p: &P) -> String {
+        p.default_print_ctx().to_string()
+    }
+
+    #[tokio::test]
+    async fn prack_uses_early_dialog() {
+        let mut initiator = initiator();
+        let invite_cseq = initiator.cseq;
+
+        add_early_dialog(&mut initiator, "fork1", "fork1");
+        let to = add_early_dialog(&mut initiator, "fork2", "fork2");
+
+        let prack = initiator
+            .create_prack_in_early_dialog(&to, RSeq(7))
+            .unwrap();
+
+        // Remote target and route set of the matching early dialog
+        assert_eq!(print(&prack.line.uri), "sip:fork2@127.0.0.1:5080");
+
+        let routes: Vec<Route> = prack.headers.get().unwrap();
+        let routes: Vec<String> = routes.iter().map(|route| print(&route.0)).collect();
+        assert_eq!(
+            routes,
+            ["<sip:proxy1.example.com;lr>", "<sip:proxy2.example.com;lr>"]
+        );
+
+        let to_header: To = prack.headers.get().unwrap();
+        assert_eq!(to_header.tag.as_deref(), Some("fork2"));
+
+        let cseq: CSeq = prack.headers.get().unwrap();
+        assert_eq!(cseq.cseq, invite_cseq + 1);
+        assert_eq!(cseq.method, Method::PRACK);
+
+        let rack: RAck = prack.headers.get().unwrap();
+        assert_eq!(rack.rack, 7);
+        assert_eq!(rack.cseq, invite_cseq);
+
+        // The next PRACK in the same early dialog increments the CSeq number
+        let prack = initiator
+            .create_prack_in_early_dialog(&to, RSeq(8))
+            .unwrap();
+
+        let cseq: CSeq = prack.headers.get().unwrap();
+        assert_eq!(cseq.cseq, invite_cseq + 2);
+        assert_eq!(initiator.local_cseq, invite_cseq + 2);
+    }
+
+    #[tokio::test]
+    async fn prack_without_early_dialog() {
+        let mut initiator = initiator();
+        add_early_dialog(&mut initiator, "fork1", "fork1");
+
+        let unknown = To::new(initiator.to.uri.clone(), Some("unknown".into()));
+        let error = initiator
+            .create_prack_in_early_dialog(&unknown, RSeq(1))
+            .unwrap_err();
+        assert_eq!(error.status, Code::CALL_OR_TRANSACTION_DOES_NOT_EXIST);
+
+        let no_tag = initiator.to.clone();
+        let error = initiator
+            .create_prack_in_early_dialog(&no_tag, RSeq(1))
+            .unwrap_err();
+        assert_eq!(error.status, Code::CALL_OR_TRANSACTION_DOES_NOT_EXIST);
+    }
+
+    const FORKS: [&str; 2] = ["fork-a", "fork-b"];
+
+    /// Answers INVITEs like a forking proxy: every fork creates an early dialog with early media,
+    /// the last fork accepts the INVITE and the first fork sends a late 2XX response
+    struct ForkingLayer {
+        addr: SocketAddr,
+        /// Method and to-tag of all received ACK and BYE requests
+        received: mpsc::UnboundedSender<(Method, BytesStr)>,
+    }
+
+    impl ForkingLayer {
+        async fn response(
+            &self,
+            endpoint: &Endpoint,
+            invite: &IncomingRequest,
+            code: Code,
+            fork: &'static str,
+        ) -> OutgoingResponse {
+            let mut response = endpoint.create_response(invite, code, None).await.unwrap();
+
+            response
+                .msg
+                .headers
+                .edit(|to: &mut To| to.tag = Some(fork.into()))
+                .unwrap();
+            response
+                .msg
+                .headers
+                .insert_type(&Contact::new(NameAddr::uri(uri(fork, self.addr))));
+
+            response
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Layer for ForkingLayer {
+        fn name(&self) -> &'static str {
+            "test-forking"
+        }
+
+        async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+            let request = request.take();
+
+            match request.line.method {
+                Method::INVITE => {
+                    let mut transaction = endpoint.create_server_inv_tsx(&request);
+
+                    for fork in FORKS {
+                        let mut response = self
+                            .response(endpoint, &request, Code::SESSION_PROGRESS, fork)
+                            .await;
+                        response
+                            .msg
+                            .headers
+                            .insert_type(&ContentType::from_static("application/sdp"));
+                        response.msg.body = Bytes::from(format!("{} sdp", fork));
+
+                        transaction
+                            .respond_provisional(&mut response)
+                            .await
+                            .unwrap();
+                    }
+
+                    let response = self.response(endpoint, &request, Code::OK, FORKS[1]).await;
+                    let _accepted = transaction.respond_success(response).await.unwrap();
+
+                    let mut late = self.response(endpoint, &request, Code::OK, FORKS[0]).await;
+                    endpoint.send_outgoing_response(&mut late).await.unwrap();
+                }
+                Method::ACK => {}
+                Method::BYE => {
+                    let response = endpoint
+                        .create_response(&request, Code::OK, None)
+                        .await
+                        .unwrap();
+
+                    endpoint
+                        .create_server_tsx(&request)
+                        .respond(response)
+                        .await
+                        .unwrap();
+                }
+                _ => return,
+            }
+
+            if request.line.method != Method::INVITE {
+                let to_tag = request.base_headers.to.tag.clone().unwrap();
+                self.received.send((request.line.method, to_tag)).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn forked_invite() {
+        let mut builder = Endpoint::builder();
+        let (received, mut requests) = mpsc::unbounded_channel();
+        let forking_addr = bind_loopback(&mut builder).await;
+        builder.add_layer(ForkingLayer {
+            addr: forking_addr,
+            received,
+        });
+        let _forking = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let dialog_layer = builder.add_layer(DialogLayer::default());
+        let invite_layer = builder.add_layer(InviteLayer::default());
+        let local_addr = bind_loopback(&mut builder).await;
+        let endpoint = builder.build();
+
+        let local = uri("alice", local_addr);
+        let mut initiator = Initiator::new(
+            endpoint,
+            dialog_layer,
+            invite_layer,
+            NameAddr::uri(local.clone()),
+            Contact::new(NameAddr::uri(local)),
+            Box::new(uri("bob", forking_addr)),
+        );
+
+        let invite = initiator.create_invite();
+        initiator.send_invite(invite).await.unwrap();
+
+        // Every fork delivers its early media separately
+        for fork in FORKS {
+            let response = loop {
+                match initiator.receive().await.unwrap() {
+                    Response::Provisional(_) => continue,
+                    Response::EarlyMedia(response) => break response,
+                    response => panic!("unexpected response {:?}", response),
+                }
+            };
+
+            assert_eq!(response.base_headers.to.tag.as_deref(), Some(fork));
+            assert_eq!(response.body, format!("{} sdp", fork).as_bytes());
+        }
+
+        // One early dialog for every to-tag, with the session description of its fork
+        assert_eq!(initiator.early_dialogs().count(), 2);
+
+        for fork in FORKS {
+            let early_dialog = initiator.early_dialog(&fork.into()).unwrap();
+
+            let (content_type, body) = early_dialog.session_description.as_ref().unwrap();
+            assert!(content_type.matches("application/sdp"));
+            assert_eq!(body, format!("{} sdp", fork).as_bytes());
+            assert_eq!(
+                print(&early_dialog.dialog.peer_contact.uri.uri),
+                print(&uri(fork, forking_addr))
+            );
+        }
+
+        // The early dialog of the fork sending the 2XX response is promoted
+        let session = match initiator.receive().await.unwrap() {
+            Response::Session(session, _) => session,
+            response => panic!("unexpected response {:?}", response),
+        };
+
+        assert_eq!(session.dialog.to.tag.as_deref(), Some(FORKS[1]));
+        assert_eq!(
+            print(&session.dialog.peer_contact.uri.uri),
+            print(&uri(FORKS[1], forking_addr))
+        );
+        assert_eq!(initiator.early_dialogs().count(), 0);
+
+        // The session's 2XX is acknowledged, the late 2XX is acknowledged and its dialog terminated
+        let mut received = vec![];
+
+        for _ in 0..3 {
+            received.push(requests.recv().await.unwrap());
+        }
+
+        received.sort();
+        assert_eq!(
+            received,
+            [
+                (Method::ACK, BytesStr::from_static(FORKS[0])),
+                (Method::ACK, BytesStr::from_static(FORKS[1])),
+                (Method::BYE, BytesStr::from_static(FORKS[0])),
+            ]
+        );
+    }
+}
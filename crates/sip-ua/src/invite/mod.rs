@@ -1,26 +1,30 @@
-use crate::dialog::{Dialog, Usage};
+use crate::dialog::{Dialog, DialogLayer, Usage};
 use acceptor::CancellableKey;
+use bytesstr::BytesStr;
 use parking_lot as pl;
 use prack::AwaitedPrack;
 use session::UsageEvent;
 use sip_core::transaction::consts::{T1, T2};
-use sip_core::transaction::{Accepted, ServerInvTsx, TsxKey};
+use sip_core::transaction::{Accepted, ClientInvTsx, ServerInvTsx, TsxKey, TsxResponse};
 use sip_core::transport::OutgoingRequest;
 use sip_core::{
     Endpoint, EndpointBuilder, Error, IncomingRequest, Layer, LayerKey, MayTake, Result,
 };
-use sip_types::header::typed::CSeq;
-use sip_types::{Code, Method};
+use sip_types::header::typed::{CSeq, CallID, Contact, From, InfoPackage, RecordRoute, RecvInfo};
+use sip_types::{Code, CodeKind, Method};
 use std::collections::HashMap;
 use std::mem::replace;
 use std::sync::Arc;
 use tokio::sync::mpsc::error::SendError;
-use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex, Notify};
 use tokio::time::timeout;
 
 pub mod acceptor;
+pub mod info;
+pub mod initiator;
 mod prack;
 pub mod session;
+pub mod store;
 mod timer;
 
 #[derive(Debug)]
@@ -39,11 +43,18 @@ struct Inner {
     peer_supports_timer: bool,
     peer_supports_100rel: bool,
 
+    /// Info packages the peer is willing to receive
+    peer_recv_info: Vec<BytesStr>,
+
+    /// Notified when the initial INVITE got cancelled
+    cancelled: Notify,
+
     awaited_ack: pl::Mutex<Option<AwaitedAck>>,
     awaited_prack: pl::Mutex<Option<AwaitedPrack>>,
 }
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 enum InviteSessionState {
     /// Provisional state before a final response was sent
     Provisional {
@@ -118,6 +129,28 @@ impl InviteSessionState {
 #[derive(Default)]
 pub struct InviteLayer {
     cancellables: pl::Mutex<HashMap<CancellableKey, Arc<Inner>>>,
+
+    /// Info packages which can be received inside sessions
+    recv_info: Vec<BytesStr>,
+}
+
+impl InviteLayer {
+    /// Add an info package which can be received inside sessions using INFO requests.
+    ///
+    /// INFO requests for packages which have not been added are rejected with
+    /// `469 Bad Info Package`. INFO requests without an `Info-Package` header (legacy INFO usage)
+    /// are always passed to the session.
+    pub fn add_recv_info<P>(&mut self, package: P)
+    where
+        P: Into<BytesStr>,
+    {
+        self.recv_info.push(package.into());
+    }
+
+    /// Returns the `Recv-Info` headers to add to INVITE requests and their 2XX responses
+    fn recv_info(&self) -> Vec<RecvInfo> {
+        self.recv_info.iter().cloned().map(RecvInfo).collect()
+    }
 }
 
 #[async_trait::async_trait]
@@ -133,6 +166,7 @@ impl Layer for InviteLayer {
         endpoint.add_allow(Method::ACK);
         endpoint.add_allow(Method::CANCEL);
         endpoint.add_allow(Method::PRACK);
+        endpoint.add_allow(Method::INFO);
 
         endpoint.add_supported("100rel");
         endpoint.add_supported("timer");
@@ -176,6 +210,8 @@ impl InviteLayer {
             let cancel_tsx = endpoint.create_server_tsx(&cancel);
 
             if let Some((dialog, invite_tsx, invite)) = inner.state.lock().await.set_cancelled() {
+                inner.cancelled.notify_one();
+
                 let invite_response = dialog
                     .create_response(&invite, Code::REQUEST_TERMINATED, None)
                     .await?;
@@ -226,6 +262,27 @@ impl Usage for InviteUsage {
                     }
                 }
             }
+            Method::UPDATE => {
+                let state = self.inner.state.lock().await;
+
+                if let InviteSessionState::Established { evt_sink } = &*state {
+                    let update = request.inner().take().unwrap();
+
+                    if let Err(SendError(UsageEvent::Update(update))) =
+                        evt_sink.send(UsageEvent::Update(update)).await
+                    {
+                        *request.inner() = Some(update);
+                    }
+                }
+            }
+            Method::INFO => {
+                if let Err(e) = self
+                    .handle_info(endpoint, MayTake::new(request.inner()))
+                    .await
+                {
+                    log::warn!("Failed to handle INFO request {:?}", e);
+                }
+            }
             Method::ACK => {
                 let mut awaited_ack_opt = self.inner.awaited_ack.lock();
 
@@ -296,6 +353,47 @@ impl Usage for InviteUsage {
 }
 
 impl InviteUsage {
+    async fn handle_info(
+        &self,
+        endpoint: &Endpoint,
+        mut request: MayTake<'_, IncomingRequest>,
+    ) -> Result<()> {
+        let state = self.inner.state.lock().await;
+
+        let evt_sink = if let InviteSessionState::Established { evt_sink } = &*state {
+            evt_sink
+        } else {
+            return Ok(());
+        };
+
+        if let Ok(package) = request.headers.get::<InfoPackage>() {
+            let recv_info = &endpoint[self.inner.invite_layer].recv_info;
+
+            if !recv_info.contains(&package.0) {
+                let info = request.take();
+                let transaction = endpoint.create_server_tsx(&info);
+
+                let mut response = endpoint
+                    .create_response(&info, Code::BAD_INFO_PACKAGE, None)
+                    .await?;
+
+                let recv_info = endpoint[self.inner.invite_layer].recv_info();
+                response.msg.headers.insert_type(&recv_info);
+
+                return transaction.respond(response).await;
+            }
+        }
+
+        let info = request.inner().take().unwrap();
+
+        if let Err(SendError(UsageEvent::Info(info))) = evt_sink.send(UsageEvent::Info(info)).await
+        {
+            *request.inner() = Some(info);
+        }
+
+        Ok(())
+    }
+
     async fn handle_bye_in_provisional_state(
         &self,
         endpoint: &Endpoint,
@@ -324,6 +422,10 @@ impl InviteUsage {
 async fn create_ack(dialog: &mut Dialog, cseq_num: u32) -> Result<OutgoingRequest> {
     let mut ack = dialog.create_request(Method::ACK);
 
+    // The ACK carries the CSeq number of the INVITE and must not consume a new one,
+    // else the peer would wait for the skipped number before handling the next request
+    dialog.local_cseq -= 1;
+
     // Set CSeq
     ack.headers.edit(|cseq: &mut CSeq| cseq.cseq = cseq_num)?;
 
@@ -339,6 +441,109 @@ async fn create_ack(dialog: &mut Dialog, cseq_num: u32) -> Result<OutgoingReques
     Ok(ack)
 }
 
+/// Data required to create the dialogs of 2XX responses from other forks of an INVITE
+struct ForkContext {
+    dialog_layer: LayerKey<DialogLayer>,
+    from: From,
+    local_contact: Contact,
+    call_id: CallID,
+    secure: bool,
+}
+
+/// Keep the client INVITE transaction alive after the ACK has been sent,
+/// to resend the ACK for every retransmission of the 2XX response.
+///
+/// With `forks` the dialogs of 2XX responses from other forks of the INVITE are acknowledged
+/// and immediately terminated using a BYE request.
+fn spawn_ack_retransmitter(
+    endpoint: Endpoint,
+    mut transaction: ClientInvTsx,
+    ack: OutgoingRequest,
+    response: &TsxResponse,
+    forks: Option<ForkContext>,
+) {
+    let mut acks = HashMap::new();
+    acks.insert(response.base_headers.to.tag.clone(), ack);
+
+    tokio::spawn(async move {
+        while let Ok(Some(response)) = transaction.receive().await {
+            if response.line.code.kind() != CodeKind::Success {
+                continue;
+            }
+
+            let to_tag = response.base_headers.to.tag.clone();
+
+            if let Some(ack) = acks.get_mut(&to_tag) {
+                if let Err(e) = endpoint.send_outgoing_request(ack).await {
+                    log::warn!("Failed to retransmit ACK {:?}", e);
+                }
+
+                continue;
+            }
+
+            let forks = match &forks {
+                Some(forks) => forks,
+                None => {
+                    log::warn!("ignoring 2XX response from a different dialog");
+                    continue;
+                }
+            };
+
+            match acknowledge_fork(&endpoint, forks, &response).await {
+                Ok((ack, dialog)) => {
+                    acks.insert(to_tag, ack);
+
+                    // Don't hold up ACK retransmissions while waiting for the BYE's response
+                    tokio::spawn(terminate_fork(dialog));
+                }
+                Err(e) => log::warn!("Failed to acknowledge forked dialog {:?}", e),
+            }
+        }
+    });
+}
+
+/// Acknowledge the 2XX response of another fork, returns the sent ACK and the fork's dialog
+async fn acknowledge_fork(
+    endpoint: &Endpoint,
+    forks: &ForkContext,
+    response: &TsxResponse,
+) -> Result<(OutgoingRequest, Dialog)> {
+    let peer_contact: Contact = response.headers.get()?;
+    let route_set: Vec<RecordRoute> = response.headers.get().unwrap_or_default();
+
+    let mut dialog = Dialog::new_client(
+        endpoint.clone(),
+        forks.dialog_layer,
+        response.base_headers.cseq.cseq,
+        forks.from.clone(),
+        response.base_headers.to.clone(),
+        forks.local_contact.clone(),
+        peer_contact,
+        forks.call_id.clone(),
+        route_set,
+        forks.secure || response.tp_info.transport.secure(),
+    );
+
+    let mut ack = create_ack(&mut dialog, response.base_headers.cseq.cseq).await?;
+    endpoint.send_outgoing_request(&mut ack).await?;
+
+    Ok((ack, dialog))
+}
+
+/// Terminate the dialog of an acknowledged fork using a BYE
+async fn terminate_fork(mut dialog: Dialog) {
+    let bye = dialog.create_request(Method::BYE);
+
+    let result = async {
+        let transaction = dialog.endpoint.send_request(bye).await?;
+        transaction.receive_final().await
+    };
+
+    if let Err(e) = result.await {
+        log::warn!("Failed to terminate forked dialog {:?}", e);
+    }
+}
+
 /// Helper function to receive the ACK response from invite-usage
 /// after sending a success-response
 async fn receive_ack(
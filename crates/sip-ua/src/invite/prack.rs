@@ -1,7 +1,9 @@
 use super::InviteUsage;
+use sip_core::transaction::TsxResponse;
 use sip_core::{Endpoint, IncomingRequest, MayTake, Result};
 use sip_types::header::typed::RAck;
 use sip_types::Code;
+use std::collections::BTreeMap;
 use tokio::sync::oneshot;
 
 #[derive(Debug)]
@@ -49,3 +51,130 @@ impl InviteUsage {
         prack_tsx.respond(response).await
     }
 }
+
+/// Tracks the reliable provisional responses received inside a single early dialog.
+///
+/// Drops retransmissions and holds back responses that were received out of order,
+/// so that they can be acknowledged in the order of their `RSeq` number.
+///
+/// The PRACK requests are not sent from here, as a PRACK may have to carry the answer to an
+/// offer received in the provisional response (RFC 3262 Section 5). That answer can only be
+/// created by the user of the [`Initiator`](super::initiator::Initiator).
+#[derive(Debug)]
+pub(super) struct ReliableProvisionals<R = TsxResponse> {
+    /// RSeq number of the last response that has been delivered
+    last_rseq: Option<u32>,
+
+    /// Responses that were received before their predecessor, keyed by their RSeq number
+    out_of_order: BTreeMap<u32, R>,
+}
+
+impl<R> Default for ReliableProvisionals<R> {
+    fn default() -> Self {
+        Self {
+            last_rseq: None,
+            out_of_order: BTreeMap::new(),
+        }
+    }
+}
+
+impl<R> ReliableProvisionals<R> {
+    /// Returns the response if it is the next one in order
+    pub(super) fn receive(&mut self, rseq: u32, response: R) -> Option<R> {
+        match self.last_rseq {
+            None => {
+                self.last_rseq = Some(rseq);
+                Some(response)
+            }
+            Some(last_rseq) if rseq == last_rseq.wrapping_add(1) => {
+                self.last_rseq = Some(rseq);
+                Some(response)
+            }
+            Some(last_rseq) if rseq_is_newer(rseq, last_rseq) => {
+                self.out_of_order.insert(rseq, response);
+                None
+            }
+            // Retransmission of an already delivered response
+            Some(_) => None,
+        }
+    }
+
+    /// Returns the next held back response if its predecessor has been delivered
+    pub(super) fn next_in_order(&mut self) -> Option<R> {
+        let next_rseq = self.last_rseq?.wrapping_add(1);
+        let response = self.out_of_order.remove(&next_rseq)?;
+
+        self.last_rseq = Some(next_rseq);
+
+        Some(response)
+    }
+}
+
+/// Compare RSeq numbers using serial number arithmetic, so the sequence may wrap around
+fn rseq_is_newer(rseq: u32, last_rseq: u32) -> bool {
+    let distance = rseq.wrapping_sub(last_rseq);
+
+    distance != 0 && distance < (1 << 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn in_order() {
+        let mut provisionals = ReliableProvisionals::default();
+
+        assert_eq!(provisionals.receive(1, "a"), Some("a"));
+        assert_eq!(provisionals.receive(2, "b"), Some("b"));
+        assert_eq!(provisionals.next_in_order(), None);
+    }
+
+    #[test]
+    fn retransmission() {
+        let mut provisionals = ReliableProvisionals::default();
+
+        assert_eq!(provisionals.receive(10, "a"), Some("a"));
+        assert_eq!(provisionals.receive(10, "a"), None);
+        assert_eq!(provisionals.receive(11, "b"), Some("b"));
+        assert_eq!(provisionals.receive(10, "a"), None);
+    }
+
+    #[test]
+    fn out_of_order() {
+        let mut provisionals = ReliableProvisionals::default();
+
+        assert_eq!(provisionals.receive(1, "a"), Some("a"));
+        assert_eq!(provisionals.receive(3, "c"), None);
+        assert_eq!(provisionals.receive(4, "d"), None);
+        assert_eq!(provisionals.next_in_order(), None);
+
+        assert_eq!(provisionals.receive(2, "b"), Some("b"));
+        assert_eq!(provisionals.next_in_order(), Some("c"));
+        assert_eq!(provisionals.next_in_order(), Some("d"));
+        assert_eq!(provisionals.next_in_order(), None);
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut provisionals = ReliableProvisionals::default();
+
+        assert_eq!(provisionals.receive(u32::MAX - 1, "a"), Some("a"));
+        assert_eq!(provisionals.receive(0, "c"), None);
+        assert_eq!(provisionals.receive(u32::MAX, "b"), Some("b"));
+        assert_eq!(provisionals.next_in_order(), Some("c"));
+
+        // Old responses from before the wrap around are retransmissions
+        assert_eq!(provisionals.receive(u32::MAX - 1, "a"), None);
+        assert_eq!(provisionals.receive(1, "d"), Some("d"));
+    }
+
+    #[test]
+    fn rseq_comparison() {
+        assert!(rseq_is_newer(2, 1));
+        assert!(rseq_is_newer(0, u32::MAX));
+        assert!(!rseq_is_newer(1, 1));
+        assert!(!rseq_is_newer(1, 2));
+        assert!(!rseq_is_newer(u32::MAX, 0));
+    }
+}
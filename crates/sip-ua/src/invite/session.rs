@@ -1,17 +1,24 @@
+use super::info::{Dtmf, DtmfError, DtmfFormat, DTMF_CONTENT_TYPE, DTMF_RELAY_CONTENT_TYPE};
+use super::store::SessionState;
 use super::timer::SessionTimer;
-use super::Inner;
-use crate::dialog::{Dialog, UsageGuard};
+use super::{Inner, InviteLayer, InviteSessionState, InviteUsage};
+use crate::dialog::{register_usage, Dialog, DialogLayer, UsageGuard};
 use crate::invite::AwaitedAck;
-use sip_core::transaction::{ServerInvTsx, ServerTsx};
-use sip_core::{Endpoint, Error, IncomingRequest, Result};
-use sip_types::header::typed::Refresher;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use parking_lot as pl;
+use sip_core::transaction::{ServerInvTsx, ServerTsx, TsxResponse};
+use sip_core::transport::OutgoingResponse;
+use sip_core::{Endpoint, Error, IncomingRequest, LayerKey, Request, Result};
+use sip_types::header::typed::{ContentType, InfoPackage, MinSe, Refresher};
 use sip_types::{Code, CodeKind, Method};
 use std::sync::Arc;
 use tokio::select;
 use tokio::sync::mpsc::{self, Receiver};
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, Mutex, Notify};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Role {
     Uac,
     Uas,
@@ -34,41 +41,62 @@ pub struct Session {
     pub dialog: Dialog,
 }
 
+/// Method used to refresh a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshMethod {
+    Invite,
+    Update,
+}
+
 pub struct RefreshNeeded<'s> {
     pub session: &'s mut Session,
 }
 
 impl RefreshNeeded<'_> {
+    /// Refresh the session using a RE-INVITE
     pub async fn process_default(self) -> Result<()> {
-        let invite = self.session.dialog.create_request(Method::INVITE);
+        self.process(RefreshMethod::Invite).await
+    }
 
-        let mut transaction = self.session.endpoint.send_invite(invite).await?;
+    /// Refresh the session using the given method
+    ///
+    /// A `422 Session Interval Too Small` response is handled by resending the request
+    /// with the session interval required by the peer.
+    pub async fn process(self, method: RefreshMethod) -> Result<()> {
+        let session = self.session;
 
-        let mut ack = None;
+        loop {
+            let mut request = session.dialog.create_request(match method {
+                RefreshMethod::Invite => Method::INVITE,
+                RefreshMethod::Update => Method::UPDATE,
+            });
+
+            session
+                .session_timer
+                .populate_refresh_request(&mut request, session.role);
+
+            let response = match method {
+                RefreshMethod::Invite => session.send_reinvite(request).await?,
+                RefreshMethod::Update => session.send_update(request).await?,
+            };
 
-        while let Some(response) = transaction.receive().await? {
             match response.line.code.kind() {
-                CodeKind::Provisional => { /* ignore */ }
                 CodeKind::Success => {
-                    let ack = if let Some(ack) = &mut ack {
-                        ack
-                    } else {
-                        let ack_req = super::create_ack(
-                            &mut self.session.dialog,
-                            response.base_headers.cseq.cseq,
-                        )
-                        .await?;
-
-                        ack.insert(ack_req)
-                    };
-
-                    self.session.endpoint.send_outgoing_request(ack).await?;
+                    session
+                        .session_timer
+                        .on_refresh_success(&response, session.role);
+
+                    return Ok(());
+                }
+                _ if session
+                    .session_timer
+                    .on_refresh_interval_too_small(&response) =>
+                {
+                    continue;
                 }
                 _ => return Err(Error::new(response.line.code)),
             }
         }
-
-        Ok(())
     }
 }
 
@@ -80,12 +108,71 @@ pub struct ReInviteReceived<'s> {
 
 impl ReInviteReceived<'_> {
     /// Process the RE-INVITE
-    pub async fn process_default(self) -> Result<IncomingRequest> {
-        let response = self
-            .session
+    ///
+    /// Returns the ACK, or `None` if the RE-INVITE has been rejected (see [`respond_success`](Self::respond_success))
+    pub async fn process_default(self) -> Result<Option<IncomingRequest>> {
+        let response = self.create_response(Code::OK, None).await?;
+
+        self.respond_success(response).await
+    }
+
+    pub async fn create_response(
+        &self,
+        code: Code,
+        reason: Option<BytesStr>,
+    ) -> Result<OutgoingResponse> {
+        self.session
             .dialog
-            .create_response(&self.invite, Code::OK, None)
+            .create_response(&self.invite, code, reason)
+            .await
+    }
+
+    /// Returns the `Min-SE` to reject the RE-INVITE with, if the session interval it requests is too small
+    pub fn check_session_interval(&self) -> Option<MinSe> {
+        self.session
+            .session_timer
+            .check_refresh_request(&self.invite)
+    }
+
+    /// Reject the RE-INVITE with `422 Session Interval Too Small`, the session stays unmodified
+    pub async fn reject_session_interval(self, min_se: MinSe) -> Result<()> {
+        let mut response = self
+            .create_response(Code::SESSION_INTERVAL_TOO_SMALL, None)
             .await?;
+        response.msg.headers.insert_type(&min_se);
+
+        self.respond_failure(response).await
+    }
+
+    /// Respond to the RE-INVITE with a successful response and return the ACK to it
+    ///
+    /// Replaces the remote target of the dialog with the Contact of the RE-INVITE. If the
+    /// Contact is not a valid remote target, the RE-INVITE is rejected and an error is returned.
+    ///
+    /// If the session interval requested by the RE-INVITE is too small, it is rejected with
+    /// `422 Session Interval Too Small` instead and `None` is returned.
+    pub async fn respond_success(
+        self,
+        mut response: OutgoingResponse,
+    ) -> Result<Option<IncomingRequest>> {
+        if let Some(min_se) = self.check_session_interval() {
+            self.reject_session_interval(min_se).await?;
+
+            return Ok(None);
+        }
+
+        if let Err(e) = self.session.dialog.refresh_target(&self.invite.headers) {
+            let response = self.create_response(e.status, None).await?;
+            self.transaction.respond_failure(response).await?;
+
+            return Err(e);
+        }
+
+        self.session.session_timer.on_refresh_request(
+            &self.invite,
+            &mut response,
+            self.session.role,
+        );
 
         let (ack_sender, ack_recv) = oneshot::channel();
 
@@ -96,7 +183,139 @@ impl ReInviteReceived<'_> {
 
         let accepted = self.transaction.respond_success(response).await?;
 
-        super::receive_ack(accepted, ack_recv).await
+        super::receive_ack(accepted, ack_recv).await.map(Some)
+    }
+
+    /// Reject the RE-INVITE with the given response, the session stays unmodified
+    pub async fn respond_failure(self, response: OutgoingResponse) -> Result<()> {
+        self.transaction.respond_failure(response).await
+    }
+}
+
+pub struct UpdateReceived<'s> {
+    pub session: &'s mut Session,
+    pub update: IncomingRequest,
+    pub transaction: ServerTsx,
+}
+
+impl UpdateReceived<'_> {
+    /// Process the UPDATE, respond with a 200 OK
+    pub async fn process_default(self) -> Result<()> {
+        let response = self.create_response(Code::OK, None).await?;
+
+        self.respond(response).await
+    }
+
+    pub async fn create_response(
+        &self,
+        code: Code,
+        reason: Option<BytesStr>,
+    ) -> Result<OutgoingResponse> {
+        self.session
+            .dialog
+            .create_response(&self.update, code, reason)
+            .await
+    }
+
+    /// Returns the `Min-SE` to reject the UPDATE with, if the session interval it requests is too small
+    pub fn check_session_interval(&self) -> Option<MinSe> {
+        self.session
+            .session_timer
+            .check_refresh_request(&self.update)
+    }
+
+    /// Reject the UPDATE with `422 Session Interval Too Small`, the session stays unmodified
+    pub async fn reject_session_interval(self, min_se: MinSe) -> Result<()> {
+        let mut response = self
+            .create_response(Code::SESSION_INTERVAL_TOO_SMALL, None)
+            .await?;
+        response.msg.headers.insert_type(&min_se);
+
+        self.transaction.respond(response).await
+    }
+
+    /// Respond to the UPDATE, successful responses refresh the session timer
+    /// and the remote target of the dialog
+    ///
+    /// If the session interval requested by the UPDATE is too small, it is rejected with
+    /// `422 Session Interval Too Small` instead. If its Contact is not a valid remote target,
+    /// it is rejected and an error is returned.
+    pub async fn respond(self, mut response: OutgoingResponse) -> Result<()> {
+        if response.msg.line.code.kind() == CodeKind::Success {
+            if let Some(min_se) = self.check_session_interval() {
+                return self.reject_session_interval(min_se).await;
+            }
+
+            if let Err(e) = self.session.dialog.refresh_target(&self.update.headers) {
+                let response = self.create_response(e.status, None).await?;
+                self.transaction.respond(response).await?;
+
+                return Err(e);
+            }
+
+            self.session.session_timer.on_refresh_request(
+                &self.update,
+                &mut response,
+                self.session.role,
+            );
+        }
+
+        self.transaction.respond(response).await
+    }
+}
+
+pub struct InfoReceived<'s> {
+    pub session: &'s mut Session,
+    pub info: IncomingRequest,
+    pub transaction: ServerTsx,
+}
+
+impl InfoReceived<'_> {
+    /// Info package of the INFO, `None` for legacy INFO requests without `Info-Package` header
+    pub fn package(&self) -> Option<BytesStr> {
+        self.info.headers.get::<InfoPackage>().ok().map(|p| p.0)
+    }
+
+    /// Decode the DTMF tone carried by the INFO
+    ///
+    /// Returns `None` if the body is neither `application/dtmf-relay` nor `application/dtmf`
+    pub fn dtmf(&self) -> Option<Result<Dtmf, DtmfError>> {
+        let content_type = self.info.headers.get::<ContentType>().ok()?;
+
+        if content_type.matches(DTMF_RELAY_CONTENT_TYPE) {
+            Some(Dtmf::parse_dtmf_relay(&self.info.body))
+        } else if content_type.matches(DTMF_CONTENT_TYPE) {
+            Some(Dtmf::parse_dtmf(&self.info.body))
+        } else {
+            None
+        }
+    }
+
+    /// Process the INFO, respond with a 200 OK
+    pub async fn process_default(self) -> Result<()> {
+        self.respond_with(Code::OK).await
+    }
+
+    pub async fn create_response(
+        &self,
+        code: Code,
+        reason: Option<BytesStr>,
+    ) -> Result<OutgoingResponse> {
+        self.session
+            .dialog
+            .create_response(&self.info, code, reason)
+            .await
+    }
+
+    /// Respond to the INFO with the given code
+    pub async fn respond_with(self, code: Code) -> Result<()> {
+        let response = self.create_response(code, None).await?;
+
+        self.respond(response).await
+    }
+
+    pub async fn respond(self, response: OutgoingResponse) -> Result<()> {
+        self.transaction.respond(response).await
     }
 }
 
@@ -123,6 +342,8 @@ impl ByeEvent<'_> {
 pub enum Event<'s> {
     RefreshNeeded(RefreshNeeded<'s>),
     ReInviteReceived(ReInviteReceived<'s>),
+    UpdateReceived(UpdateReceived<'s>),
+    InfoReceived(InfoReceived<'s>),
     Bye(ByeEvent<'s>),
     Terminated,
 }
@@ -148,6 +369,75 @@ impl Session {
         }
     }
 
+    /// Export the state of the session, which can be restored using [`Session::restore`]
+    ///
+    /// The state contains the current local CSeq number, so it should be exported again
+    /// after sending requests inside the session.
+    pub fn export(&self) -> SessionState {
+        SessionState {
+            dialog: self.dialog.export(),
+            role: self.role,
+            peer_supports_timer: self.inner.peer_supports_timer,
+            peer_supports_100rel: self.inner.peer_supports_100rel,
+            peer_recv_info: self
+                .inner
+                .peer_recv_info
+                .iter()
+                .map(|package| package.to_string())
+                .collect(),
+            session_timer: self.session_timer.export(self.role),
+        }
+    }
+
+    /// Restore an established session from an exported state into the given endpoint,
+    /// e.g. to take over sessions of another node after it failed
+    ///
+    /// The session timer restarts with the full session interval.
+    pub fn restore(
+        endpoint: Endpoint,
+        dialog_layer: LayerKey<DialogLayer>,
+        invite_layer: LayerKey<InviteLayer>,
+        state: SessionState,
+    ) -> Result<Self> {
+        let dialog = Dialog::restore(endpoint.clone(), dialog_layer, state.dialog)?;
+
+        let (evt_sink, events) = mpsc::channel(4);
+
+        let inner = Arc::new(Inner {
+            invite_layer,
+            state: Mutex::new(InviteSessionState::Established { evt_sink }),
+            peer_supports_timer: state.peer_supports_timer,
+            peer_supports_100rel: state.peer_supports_100rel,
+            peer_recv_info: state.peer_recv_info.into_iter().map(Into::into).collect(),
+            cancelled: Notify::new(),
+            awaited_ack: pl::Mutex::new(None),
+            awaited_prack: pl::Mutex::new(None),
+        });
+
+        let usage_guard = register_usage(
+            endpoint.clone(),
+            dialog_layer,
+            dialog.key(),
+            InviteUsage {
+                inner: inner.clone(),
+            },
+        )
+        // Unwrap is safe as we still hold the dialog
+        .unwrap();
+
+        let session_timer = SessionTimer::restore(state.session_timer, state.role);
+
+        Ok(Self::new(
+            endpoint,
+            inner,
+            state.role,
+            events,
+            session_timer,
+            usage_guard,
+            dialog,
+        ))
+    }
+
     pub async fn drive(&mut self) -> Result<Event<'_>> {
         select! {
             _ = self.session_timer.wait() => {
@@ -159,6 +449,14 @@ impl Session {
         }
     }
 
+    /// Returns if the session has been terminated by either side
+    pub async fn is_terminated(&self) -> bool {
+        matches!(
+            *self.inner.state.lock().await,
+            InviteSessionState::Terminated
+        )
+    }
+
     pub async fn terminate(&mut self) -> Result<()> {
         let mut state = self.inner.state.lock().await;
         state.set_terminated();
@@ -173,6 +471,112 @@ impl Session {
         }
     }
 
+    /// Returns if the peer is willing to receive INFO requests for the given info package
+    pub fn peer_accepts_info_package(&self, package: &str) -> bool {
+        self.inner.peer_recv_info.iter().any(|p| p == package)
+    }
+
+    /// Send an INFO request with the given content and return the final response to it
+    ///
+    /// Without `package` a legacy INFO request without `Info-Package` header is sent.
+    /// Returns an error with status `469 Bad Info Package` if the peer did not indicate
+    /// that it is willing to receive the given package.
+    pub async fn send_info(
+        &mut self,
+        package: Option<BytesStr>,
+        content_type: ContentType,
+        body: Bytes,
+    ) -> Result<TsxResponse> {
+        let mut request = self.dialog.create_request(Method::INFO);
+
+        if let Some(package) = package {
+            if !self.peer_accepts_info_package(&package) {
+                return Err(Error::new(Code::BAD_INFO_PACKAGE));
+            }
+
+            request.headers.insert_type(&InfoPackage(package));
+        }
+
+        request.headers.insert_type(&content_type);
+        request.body = body;
+
+        let transaction = self.endpoint.send_request(request).await?;
+        transaction.receive_final().await
+    }
+
+    /// Send a DTMF tone using a legacy INFO request
+    pub async fn send_dtmf(&mut self, dtmf: Dtmf, format: DtmfFormat) -> Result<()> {
+        let (content_type, body) = match format {
+            DtmfFormat::DtmfRelay => (DTMF_RELAY_CONTENT_TYPE, dtmf.to_dtmf_relay()),
+            DtmfFormat::Dtmf => (DTMF_CONTENT_TYPE, dtmf.to_dtmf()),
+        };
+
+        let response = self
+            .send_info(None, ContentType::from_static(content_type), body.into())
+            .await?;
+
+        match response.line.code.kind() {
+            CodeKind::Success => Ok(()),
+            _ => Err(Error::new(response.line.code)),
+        }
+    }
+
+    /// Send a RE-INVITE and return the final response to it, sending the ACK if it was successful
+    ///
+    /// A successful response replaces the remote target of the dialog.
+    pub async fn send_reinvite(&mut self, invite: Request) -> Result<TsxResponse> {
+        let mut transaction = self.endpoint.send_invite(invite).await?;
+
+        while let Some(response) = transaction.receive().await? {
+            match response.line.code.kind() {
+                CodeKind::Provisional => { /* ignore */ }
+                CodeKind::Success => {
+                    self.refresh_target(&response);
+
+                    let mut ack =
+                        super::create_ack(&mut self.dialog, response.base_headers.cseq.cseq)
+                            .await?;
+
+                    self.endpoint.send_outgoing_request(&mut ack).await?;
+
+                    super::spawn_ack_retransmitter(
+                        self.endpoint.clone(),
+                        transaction,
+                        ack,
+                        &response,
+                        None,
+                    );
+
+                    return Ok(response);
+                }
+                _ => return Ok(response),
+            }
+        }
+
+        Err(Error::new(Code::REQUEST_TIMEOUT))
+    }
+
+    /// Send an UPDATE and return the final response to it
+    ///
+    /// A successful response replaces the remote target of the dialog.
+    pub async fn send_update(&mut self, update: Request) -> Result<TsxResponse> {
+        let transaction = self.endpoint.send_request(update).await?;
+        let response = transaction.receive_final().await?;
+
+        if response.line.code.kind() == CodeKind::Success {
+            self.refresh_target(&response);
+        }
+
+        Ok(response)
+    }
+
+    /// Apply the Contact of a successful response to a target refresh request
+    fn refresh_target(&mut self, response: &TsxResponse) {
+        if let Err(e) = self.dialog.refresh_target(&response.headers) {
+            log::warn!("Ignoring invalid remote target in response, {:?}", e);
+        }
+    }
+
     fn handle_usage_event(&mut self, evt: Option<UsageEvent>) -> Result<Event<'_>> {
         let evt = if let Some(evt) = evt {
             evt
@@ -203,6 +607,26 @@ impl Session {
                     transaction,
                 }))
             }
+            UsageEvent::Info(info) => {
+                let transaction = self.endpoint.create_server_tsx(&info);
+
+                Ok(Event::InfoReceived(InfoReceived {
+                    session: self,
+                    info,
+                    transaction,
+                }))
+            }
+            UsageEvent::Update(update) => {
+                self.session_timer.reset();
+
+                let transaction = self.endpoint.create_server_tsx(&update);
+
+                Ok(Event::UpdateReceived(UpdateReceived {
+                    session: self,
+                    update,
+                    transaction,
+                }))
+            }
         }
     }
 
@@ -228,5 +652,7 @@ impl Session {
 
 pub(super) enum UsageEvent {
     ReInvite(IncomingRequest),
+    Update(IncomingRequest),
+    Info(IncomingRequest),
     Bye(IncomingRequest),
 }
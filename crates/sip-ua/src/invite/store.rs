@@ -0,0 +1,216 @@
+//! Export and storage of established sessions, to restore them after a restart or on a standby node
+
+use super::session::Role;
+pub use super::timer::SessionTimerState;
+use crate::dialog::{DialogKey, DialogState};
+use parking_lot as pl;
+use std::collections::HashMap;
+
+/// State of an established [`Session`](super::session::Session), created using
+/// [`Session::export`](super::session::Session::export)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionState {
+    pub dialog: DialogState,
+    pub role: Role,
+    pub peer_supports_timer: bool,
+    pub peer_supports_100rel: bool,
+    pub peer_recv_info: Vec<String>,
+
+    /// State of the session timer, `None` if the session does not expire
+    pub session_timer: Option<SessionTimerState>,
+}
+
+/// Pluggable storage for the state of established sessions
+///
+/// Implementations can persist the states (e.g. serialized using the `serde` feature)
+/// to a database shared with standby nodes, which restore them using
+/// [`Session::restore`](super::session::Session::restore).
+///
+/// Sessions do not use a store on their own. The application saves the state once the
+/// session is established and again after sending requests inside it, and removes it
+/// when the session is terminated:
+///
+/// ```no_run
+/// # use sip_core::{Endpoint, LayerKey};
+/// # use ezk_sip_ua::dialog::DialogLayer;
+/// # use ezk_sip_ua::invite::session::Session;
+/// # use ezk_sip_ua::invite::store::SessionStore;
+/// # use ezk_sip_ua::invite::InviteLayer;
+/// # async fn example(
+/// #     endpoint: Endpoint,
+/// #     dialog_layer: LayerKey<DialogLayer>,
+/// #     invite_layer: LayerKey<InviteLayer>,
+/// #     store: &dyn SessionStore,
+/// #     mut session: Session,
+/// # ) {
+/// let key = session.dialog.key();
+/// store.save(&key, session.export()).await.unwrap();
+///
+/// // After a restart or on a standby node
+/// for state in store.load_all().await.unwrap() {
+///     let session =
+///         Session::restore(endpoint.clone(), dialog_layer, invite_layer, state).unwrap();
+///     // drive the restored session ...
+/// }
+///
+/// session.terminate().await.unwrap();
+/// store.remove(&key).await.unwrap();
+/// # }
+/// ```
+#[async_trait::async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Insert or replace the state of the session with the given dialog key
+    async fn save(&self, key: &DialogKey, state: SessionState) -> anyhow::Result<()>;
+
+    /// Remove the state of a terminated session
+    async fn remove(&self, key: &DialogKey) -> anyhow::Result<()>;
+
+    /// Load the states of all stored sessions
+    async fn load_all(&self) -> anyhow::Result<Vec<SessionState>>;
+}
+
+/// [`SessionStore`] which keeps all states in memory
+#[derive(Default)]
+pub struct MemorySessionStore {
+    states: pl::Mutex<HashMap<DialogKey, SessionState>>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn save(&self, key: &DialogKey, state: SessionState) -> anyhow::Result<()> {
+        self.states.lock().insert(key.clone(), state);
+        Ok(())
+    }
+
+    async fn remove(&self, key: &DialogKey) -> anyhow::Result<()> {
+        self.states.lock().remove(key);
+        Ok(())
+    }
+
+    async fn load_all(&self) -> anyhow::Result<Vec<SessionState>> {
+        Ok(self.states.lock().values().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dialog::DialogLayer;
+    use crate::invite::initiator::Initiator;
+    use crate::invite::session::{Event, Session};
+    use crate::invite::InviteLayer;
+    use crate::test_util::{bind_loopback, establish, uri, AcceptLayer};
+    use sip_core::Endpoint;
+    use sip_types::header::typed::Contact;
+    use sip_types::uri::NameAddr;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn restore_stored_session() {
+        let mut builder = Endpoint::builder();
+        let dialog_layer = builder.add_layer(DialogLayer::default());
+        let invite_layer = builder.add_layer(InviteLayer::default());
+        let (sessions, mut callee_sessions) = mpsc::channel(1);
+        let callee_addr = bind_loopback(&mut builder).await;
+        builder.add_layer(AcceptLayer {
+            dialog_layer,
+            invite_layer,
+            contact: Contact::new(NameAddr::uri(uri("callee", callee_addr))),
+            sessions,
+        });
+        let _callee_endpoint = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let dialog_layer = builder.add_layer(DialogLayer::default());
+        let invite_layer = builder.add_layer(InviteLayer::default());
+        let caller_addr = bind_loopback(&mut builder).await;
+        let caller_endpoint = builder.build();
+
+        let caller = establish(Initiator::new(
+            caller_endpoint.clone(),
+            dialog_layer,
+            invite_layer,
+            NameAddr::uri(uri("caller", caller_addr)),
+            Contact::new(NameAddr::uri(uri("caller", caller_addr))),
+            Box::new(uri("callee", callee_addr)),
+        ))
+        .await;
+        let mut callee = callee_sessions.recv().await.unwrap();
+
+        let store = MemorySessionStore::default();
+
+        let key = caller.dialog.key();
+        let state = caller.export();
+        assert!(state.session_timer.is_some());
+
+        store.save(&key, state.clone()).await.unwrap();
+
+        // The session is lost, e.g. because of a restart
+        drop(caller);
+
+        let mut states = store.load_all().await.unwrap();
+        assert_eq!(states.len(), 1);
+        assert_eq!(states[0], state);
+
+        let mut caller = Session::restore(
+            caller_endpoint,
+            dialog_layer,
+            invite_layer,
+            states.remove(0),
+        )
+        .unwrap();
+        assert_eq!(caller.export(), state);
+
+        // The restored session continues the dialog with the peer
+        let callee = tokio::spawn(async move {
+            match callee.drive().await.unwrap() {
+                Event::Bye(event) => event.process_default().await.unwrap(),
+                _ => panic!("expected BYE"),
+            }
+        });
+
+        caller.terminate().await.unwrap();
+        callee.await.unwrap();
+
+        store.remove(&key).await.unwrap();
+        assert!(store.load_all().await.unwrap().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut state = SessionState {
+            dialog: DialogState {
+                call_id: "serde-test".into(),
+                local_cseq: 42,
+                peer_cseq: Some(7),
+                from: "<sip:alice@example.com>;tag=local".into(),
+                to: "<sip:bob@example.org>;tag=peer".into(),
+                local_contact: "<sip:alice@10.0.0.1:5060>".into(),
+                peer_contact: "<sip:bob@10.0.0.2:5060>".into(),
+                route_set: vec!["<sip:proxy.example.com;lr>".into()],
+                secure: false,
+            },
+            role: Role::Uas,
+            peer_supports_timer: true,
+            peer_supports_100rel: false,
+            peer_recv_info: vec!["dtmf".into()],
+            session_timer: Some(SessionTimerState {
+                delta_secs: 1800,
+                min_se: 90,
+                local_refresher: true,
+            }),
+        };
+
+        let json = serde_json::to_string(&state).unwrap();
+        assert!(json.contains(r#""role":"Uas""#));
+        assert_eq!(serde_json::from_str::<SessionState>(&json).unwrap(), state);
+
+        state.dialog.peer_cseq = None;
+        state.session_timer = None;
+
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(serde_json::from_str::<SessionState>(&json).unwrap(), state);
+    }
+}
@@ -1,12 +1,22 @@
-use sip_core::{transport::OutgoingResponse, IncomingRequest};
-use sip_types::header::typed::{MinSe, Refresher, Require, SessionExpires};
+use super::session::Role;
+use sip_core::transaction::TsxResponse;
+use sip_core::{transport::OutgoingResponse, IncomingRequest, Request};
+use sip_types::header::typed::{MinSe, Refresher, Require, SessionExpires, Supported};
+use sip_types::{Code, Headers};
+use std::ops::Deref;
 use std::{future::pending, pin::Pin, time::Duration};
 use tokio::time::{sleep, Sleep};
 
+/// Smallest `Min-SE` allowed by RFC 4028, used if no other value is configured
+pub const DEFAULT_MIN_SE: u32 = 90;
+
 /// Config of the `timer` extension used by the acceptor
 pub struct AcceptorTimerConfig {
     pub refresher: Refresher,
     pub interval_secs: u32,
+
+    /// Smallest session interval accepted in session refresh requests
+    pub min_se: u32,
 }
 
 impl Default for AcceptorTimerConfig {
@@ -14,6 +24,7 @@ impl Default for AcceptorTimerConfig {
         Self {
             refresher: Refresher::Uac,
             interval_secs: 1800,
+            min_se: DEFAULT_MIN_SE,
         }
     }
 }
@@ -31,21 +42,14 @@ impl AcceptorTimerConfig {
             min_se.0.max(self.interval_secs)
         } else {
             self.interval_secs
-        };
-
-        let real_delta_secs;
+        }
+        .max(self.min_se);
 
         // Map unspecified -> Uac as usually if none is specified
         // the UAC side is responsible for refreshes
         self.refresher = match self.refresher {
-            Refresher::Uas => {
-                real_delta_secs = delta_secs - 10;
-                Refresher::Uas
-            }
-            Refresher::Unspecified | Refresher::Uac => {
-                real_delta_secs = delta_secs + 10;
-                Refresher::Uac
-            }
+            Refresher::Uas => Refresher::Uas,
+            Refresher::Unspecified | Refresher::Uac => Refresher::Uac,
         };
 
         response.msg.headers.insert_type(&Require("timer".into()));
@@ -54,13 +58,108 @@ impl AcceptorTimerConfig {
             refresher: self.refresher,
         });
 
-        let sleep = sleep(Duration::from_secs(real_delta_secs as u64));
+        SessionTimer::new(self.refresher, Role::Uas, delta_secs).with_min_se(self.min_se)
+    }
+}
+
+/// Config of the `timer` extension used by the initiator
+pub struct InitiatorTimerConfig {
+    /// Preferred refresher, sent in the `Session-Expires` header of the initial INVITE.
+    /// Leave it unspecified to let the UAS choose.
+    pub refresher: Refresher,
+
+    /// Requested session interval
+    pub interval_secs: u32,
+
+    /// Smallest session interval that will be accepted, sent as `Min-SE` header
+    pub min_se: u32,
+}
+
+impl Default for InitiatorTimerConfig {
+    fn default() -> Self {
+        Self {
+            refresher: Refresher::Unspecified,
+            interval_secs: 1800,
+            min_se: DEFAULT_MIN_SE,
+        }
+    }
+}
+
+impl InitiatorTimerConfig {
+    /// Populates the given INVITE with the `Supported`, `Session-Expires` and `Min-SE` headers
+    ///
+    /// Replaces previously set values, so it can be used to update requests which are resent.
+    pub fn populate_request(&self, request: &mut Request) {
+        let supported = request.headers.get::<Vec<Supported>>().unwrap_or_default();
 
-        SessionTimer {
+        if !supported.iter().any(|ext| ext.deref() == "timer") {
+            request.headers.insert_type(&Supported("timer".into()));
+        }
+
+        request.headers.remove_type::<SessionExpires>();
+        request.headers.remove_type::<MinSe>();
+
+        request.headers.insert_type(&SessionExpires {
+            delta_secs: self.interval_secs.max(self.min_se),
             refresher: self.refresher,
-            real_delta_secs,
-            interval: RefreshInterval::Sleeping(Box::pin(sleep)),
+        });
+        request.headers.insert_type(&MinSe(self.min_se));
+    }
+
+    /// Handles a `422 Session Interval Too Small` response by raising the `Min-SE` and interval
+    /// to the one required by the peer.
+    ///
+    /// Returns if the request should be retried with the new values.
+    pub fn on_interval_too_small(&mut self, response: &TsxResponse) -> bool {
+        handle_interval_too_small(
+            response.line.code,
+            &response.headers,
+            &mut self.interval_secs,
+            &mut self.min_se,
+        )
+    }
+
+    /// Takes the final successful response to the INVITE and returns a `SessionTimer` used inside
+    /// the session.
+    ///
+    /// If the response contains no `Session-Expires` header the session does not expire.
+    pub fn on_success_response(&self, response: &TsxResponse) -> SessionTimer {
+        match response.headers.get::<SessionExpires>() {
+            Ok(session_expires) => {
+                // The UAS must always specify a refresher,
+                // assume the UAC is responsible if it didn't
+                let refresher = match session_expires.refresher {
+                    Refresher::Uas => Refresher::Uas,
+                    Refresher::Unspecified | Refresher::Uac => Refresher::Uac,
+                };
+
+                SessionTimer::new(refresher, Role::Uac, session_expires.delta_secs)
+                    .with_min_se(self.min_se)
+            }
+            Err(_) => SessionTimer::new_unsupported(),
+        }
+    }
+}
+
+fn handle_interval_too_small(
+    code: Code,
+    headers: &Headers,
+    interval_secs: &mut u32,
+    min_se: &mut u32,
+) -> bool {
+    if code != Code::SESSION_INTERVAL_TOO_SMALL {
+        return false;
+    }
+
+    match headers.get::<MinSe>() {
+        // Only retry if the peer actually demands a larger interval,
+        // otherwise the same request would be rejected again
+        Ok(required) if required.0 > *min_se => {
+            *min_se = required.0;
+            *interval_secs = (*interval_secs).max(required.0);
+            true
         }
+        _ => false,
     }
 }
 
@@ -69,21 +168,51 @@ impl AcceptorTimerConfig {
 #[derive(Debug)]
 pub struct SessionTimer {
     pub refresher: Refresher,
+    pub delta_secs: u32,
+    pub min_se: u32,
     pub real_delta_secs: u32,
     pub interval: RefreshInterval,
 }
 
 impl SessionTimer {
+    /// Create a new session timer, `role` being the role of this endpoint in the
+    /// INVITE transaction that created the session.
+    pub fn new(refresher: Refresher, role: Role, delta_secs: u32) -> Self {
+        let real_delta_secs = real_delta_secs(refresher, role, delta_secs);
+
+        let sleep = sleep(Duration::from_secs(real_delta_secs as u64));
+
+        Self {
+            refresher,
+            delta_secs,
+            min_se: DEFAULT_MIN_SE,
+            real_delta_secs,
+            interval: RefreshInterval::Sleeping(Box::pin(sleep)),
+        }
+    }
+
     /// Create a new session timer that will never expire.
     /// Useful for sessions with peers that do not support the `timer` extension.
     pub fn new_unsupported() -> Self {
         Self {
             refresher: Refresher::Unspecified,
+            delta_secs: 0,
+            min_se: DEFAULT_MIN_SE,
             real_delta_secs: 0,
             interval: RefreshInterval::Unsupported,
         }
     }
 
+    fn with_min_se(mut self, min_se: u32) -> Self {
+        self.min_se = min_se;
+        self
+    }
+
+    /// Returns if the session expires
+    pub fn is_active(&self) -> bool {
+        matches!(self.interval, RefreshInterval::Sleeping(_))
+    }
+
     /// Wait for the session to expire. Will never return if no session expiry is set
     pub async fn wait(&mut self) {
         match &mut self.interval {
@@ -101,6 +230,216 @@ impl SessionTimer {
             }
         }
     }
+
+    /// Populates a session refresh request sent by this endpoint
+    pub(super) fn populate_refresh_request(&self, request: &mut Request, role: Role) {
+        if !self.is_active() {
+            return;
+        }
+
+        request.headers.insert_type(&Supported("timer".into()));
+        request.headers.insert_type(&SessionExpires {
+            delta_secs: self.delta_secs,
+            // We are the UAC of the refresh transaction
+            refresher: if self.is_refresher(role) {
+                Refresher::Uac
+            } else {
+                Refresher::Uas
+            },
+        });
+        request.headers.insert_type(&MinSe(self.min_se));
+    }
+
+    /// Handles a `422 Session Interval Too Small` response to a session refresh request
+    ///
+    /// Returns if the request should be retried.
+    pub(super) fn on_refresh_interval_too_small(&mut self, response: &TsxResponse) -> bool {
+        self.is_active()
+            && handle_interval_too_small(
+                response.line.code,
+                &response.headers,
+                &mut self.delta_secs,
+                &mut self.min_se,
+            )
+    }
+
+    /// Update the timer using the successful response to a session refresh request sent by this endpoint
+    pub(super) fn on_refresh_success(&mut self, response: &TsxResponse, role: Role) {
+        match response.headers.get::<SessionExpires>() {
+            Ok(session_expires) => {
+                // We are the UAC of the refresh transaction
+                let we_refresh = !matches!(session_expires.refresher, Refresher::Uas);
+
+                self.update(session_expires.delta_secs, we_refresh, role);
+            }
+            Err(_) => {
+                // Peer no longer wants a session timer
+                self.interval = RefreshInterval::Unsupported;
+                self.refresher = Refresher::Unspecified;
+            }
+        }
+    }
+
+    /// Returns the `Min-SE` header a received session refresh request must be rejected with
+    /// (using `422 Session Interval Too Small`), if its interval is below the local `Min-SE`
+    pub(super) fn check_refresh_request(&self, request: &IncomingRequest) -> Option<MinSe> {
+        if !self.is_active() {
+            return None;
+        }
+
+        let session_expires = request.headers.get::<SessionExpires>().ok();
+
+        if interval_too_small(session_expires.as_ref(), self.min_se) {
+            Some(MinSe(self.min_se))
+        } else {
+            None
+        }
+    }
+
+    /// Update the timer using the session refresh request received by this endpoint and
+    /// populate the successful response to it
+    ///
+    /// The request must have been checked using [`SessionTimer::check_refresh_request`].
+    pub(super) fn on_refresh_request(
+        &mut self,
+        request: &IncomingRequest,
+        response: &mut OutgoingResponse,
+        role: Role,
+    ) {
+        if !self.is_active() {
+            return;
+        }
+
+        let peer_supports_timer = request
+            .headers
+            .get::<Vec<Supported>>()
+            .unwrap_or_default()
+            .iter()
+            .any(|ext| ext.deref() == "timer");
+
+        let (delta_secs, we_refresh) = refresh_request_interval(
+            request.headers.get::<SessionExpires>().ok().as_ref(),
+            peer_supports_timer,
+            self.is_refresher(role),
+            self.delta_secs,
+        );
+
+        self.update(delta_secs, we_refresh, role);
+
+        // RFC 4028 Section 9, a UAC which doesn't support the timer must not be required to
+        if peer_supports_timer {
+            response.msg.headers.insert_type(&Require("timer".into()));
+        }
+
+        response.msg.headers.insert_type(&SessionExpires {
+            delta_secs: self.delta_secs,
+            refresher: if self.is_refresher(role) {
+                Refresher::Uas
+            } else {
+                Refresher::Uac
+            },
+        });
+    }
+
+    /// Export the state of an active timer, `None` if the session does not expire
+    pub(super) fn export(&self, role: Role) -> Option<SessionTimerState> {
+        if !self.is_active() {
+            return None;
+        }
+
+        Some(SessionTimerState {
+            delta_secs: self.delta_secs,
+            min_se: self.min_se,
+            local_refresher: self.is_refresher(role),
+        })
+    }
+
+    /// Restore a timer from an exported state, the timer restarts with the full interval
+    pub(super) fn restore(state: Option<SessionTimerState>, role: Role) -> Self {
+        match state {
+            Some(state) => {
+                let refresher = refresher_for(role, state.local_refresher);
+
+                SessionTimer::new(refresher, role, state.delta_secs).with_min_se(state.min_se)
+            }
+            None => SessionTimer::new_unsupported(),
+        }
+    }
+
+    /// Returns if this endpoint is responsible for refreshing the session
+    pub(super) fn is_refresher(&self, role: Role) -> bool {
+        matches!(
+            (role, self.refresher),
+            (Role::Uac, Refresher::Uac) | (Role::Uas, Refresher::Uas)
+        )
+    }
+
+    fn update(&mut self, delta_secs: u32, we_refresh: bool, role: Role) {
+        self.refresher = refresher_for(role, we_refresh);
+
+        self.delta_secs = delta_secs;
+        self.real_delta_secs = real_delta_secs(self.refresher, role, delta_secs);
+        self.reset();
+    }
+}
+
+/// Map whether this endpoint refreshes the session to the refresher of the session
+fn refresher_for(role: Role, we_refresh: bool) -> Refresher {
+    match (role, we_refresh) {
+        (Role::Uac, true) | (Role::Uas, false) => Refresher::Uac,
+        (Role::Uac, false) | (Role::Uas, true) => Refresher::Uas,
+    }
+}
+
+/// Returns if the interval of a received session refresh request is below `min_se`
+fn interval_too_small(session_expires: Option<&SessionExpires>, min_se: u32) -> bool {
+    session_expires.is_some_and(|session_expires| session_expires.delta_secs < min_se)
+}
+
+/// Returns the new interval and if this endpoint refreshes the session after receiving a
+/// session refresh request
+///
+/// Peers which don't support the timer extension cannot refresh the session, so this
+/// endpoint must do it.
+fn refresh_request_interval(
+    session_expires: Option<&SessionExpires>,
+    peer_supports_timer: bool,
+    is_refresher: bool,
+    delta_secs: u32,
+) -> (u32, bool) {
+    let Some(session_expires) = session_expires else {
+        return (delta_secs, is_refresher || !peer_supports_timer);
+    };
+
+    // The peer is the UAC of the refresh transaction
+    let we_refresh = !peer_supports_timer
+        || match session_expires.refresher {
+            Refresher::Unspecified => is_refresher,
+            Refresher::Uac => false,
+            Refresher::Uas => true,
+        };
+
+    (session_expires.delta_secs, we_refresh)
+}
+
+/// Calculate the time until the timer fires. The refresher must refresh before the session
+/// expires, the other side waits a bit longer before assuming the session is gone.
+fn real_delta_secs(refresher: Refresher, role: Role, delta_secs: u32) -> u32 {
+    match (role, refresher) {
+        (Role::Uac, Refresher::Uac) | (Role::Uas, Refresher::Uas) => delta_secs.saturating_sub(10),
+        _ => delta_secs + 10,
+    }
+}
+
+/// Exported state of an active [`SessionTimer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionTimerState {
+    pub delta_secs: u32,
+    pub min_se: u32,
+
+    /// This endpoint is responsible for refreshing the session
+    pub local_refresher: bool,
 }
 
 #[derive(Debug)]
@@ -108,3 +447,191 @@ pub enum RefreshInterval {
     Unsupported,
     Sleeping(Pin<Box<Sleep>>),
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn session_expires(delta_secs: u32, refresher: Refresher) -> SessionExpires {
+        SessionExpires {
+            delta_secs,
+            refresher,
+        }
+    }
+
+    #[test]
+    fn interval_too_small_raises_min_se() {
+        let mut headers = Headers::new();
+        headers.insert_type(&MinSe(300));
+
+        let mut interval_secs = 200;
+        let mut min_se = DEFAULT_MIN_SE;
+
+        assert!(handle_interval_too_small(
+            Code::SESSION_INTERVAL_TOO_SMALL,
+            &headers,
+            &mut interval_secs,
+            &mut min_se
+        ));
+        assert_eq!(interval_secs, 300);
+        assert_eq!(min_se, 300);
+
+        // Same Min-SE again, retrying would be rejected again
+        assert!(!handle_interval_too_small(
+            Code::SESSION_INTERVAL_TOO_SMALL,
+            &headers,
+            &mut interval_secs,
+            &mut min_se
+        ));
+    }
+
+    #[test]
+    fn interval_too_small_keeps_larger_interval() {
+        let mut headers = Headers::new();
+        headers.insert_type(&MinSe(300));
+
+        let mut interval_secs = 1800;
+        let mut min_se = DEFAULT_MIN_SE;
+
+        assert!(handle_interval_too_small(
+            Code::SESSION_INTERVAL_TOO_SMALL,
+            &headers,
+            &mut interval_secs,
+            &mut min_se
+        ));
+        assert_eq!(interval_secs, 1800);
+        assert_eq!(min_se, 300);
+    }
+
+    #[test]
+    fn interval_too_small_ignores_other_responses() {
+        let mut headers = Headers::new();
+
+        let mut interval_secs = 1800;
+        let mut min_se = DEFAULT_MIN_SE;
+
+        // Missing Min-SE
+        assert!(!handle_interval_too_small(
+            Code::SESSION_INTERVAL_TOO_SMALL,
+            &headers,
+            &mut interval_secs,
+            &mut min_se
+        ));
+
+        headers.insert_type(&MinSe(300));
+
+        assert!(!handle_interval_too_small(
+            Code::BAD_REQUEST,
+            &headers,
+            &mut interval_secs,
+            &mut min_se
+        ));
+        assert_eq!(interval_secs, 1800);
+        assert_eq!(min_se, DEFAULT_MIN_SE);
+    }
+
+    #[test]
+    fn refresh_request_below_min_se() {
+        assert!(interval_too_small(
+            Some(&session_expires(60, Refresher::Uac)),
+            DEFAULT_MIN_SE
+        ));
+        assert!(!interval_too_small(
+            Some(&session_expires(90, Refresher::Uac)),
+            DEFAULT_MIN_SE
+        ));
+        assert!(!interval_too_small(None, DEFAULT_MIN_SE));
+    }
+
+    #[test]
+    fn refresh_request_refresher() {
+        let uac = session_expires(600, Refresher::Uac);
+        let uas = session_expires(600, Refresher::Uas);
+        let unspecified = session_expires(600, Refresher::Unspecified);
+
+        assert_eq!(
+            refresh_request_interval(Some(&uac), true, true, 1800),
+            (600, false)
+        );
+        assert_eq!(
+            refresh_request_interval(Some(&uas), true, false, 1800),
+            (600, true)
+        );
+        assert_eq!(
+            refresh_request_interval(Some(&unspecified), true, false, 1800),
+            (600, false)
+        );
+        assert_eq!(
+            refresh_request_interval(Some(&unspecified), true, true, 1800),
+            (600, true)
+        );
+        assert_eq!(
+            refresh_request_interval(None, true, false, 1800),
+            (1800, false)
+        );
+    }
+
+    #[test]
+    fn refresh_request_without_timer_support() {
+        let uac = session_expires(600, Refresher::Uac);
+
+        // The peer cannot refresh the session if it doesn't support the extension
+        assert_eq!(
+            refresh_request_interval(Some(&uac), false, false, 1800),
+            (600, true)
+        );
+        assert_eq!(
+            refresh_request_interval(None, false, false, 1800),
+            (1800, true)
+        );
+    }
+
+    #[test]
+    fn refresher_mapping() {
+        assert_eq!(refresher_for(Role::Uac, true), Refresher::Uac);
+        assert_eq!(refresher_for(Role::Uac, false), Refresher::Uas);
+        assert_eq!(refresher_for(Role::Uas, true), Refresher::Uas);
+        assert_eq!(refresher_for(Role::Uas, false), Refresher::Uac);
+    }
+
+    #[test]
+    fn real_delta() {
+        assert_eq!(real_delta_secs(Refresher::Uac, Role::Uac, 1800), 1790);
+        assert_eq!(real_delta_secs(Refresher::Uas, Role::Uas, 1800), 1790);
+        assert_eq!(real_delta_secs(Refresher::Uas, Role::Uac, 1800), 1810);
+        assert_eq!(real_delta_secs(Refresher::Uas, Role::Uas, 5), 0);
+    }
+
+    #[tokio::test]
+    async fn export_restore() {
+        for (refresher, role) in [
+            (Refresher::Uac, Role::Uac),
+            (Refresher::Uac, Role::Uas),
+            (Refresher::Uas, Role::Uac),
+            (Refresher::Uas, Role::Uas),
+        ] {
+            let timer = SessionTimer::new(refresher, role, 600).with_min_se(120);
+
+            let state = timer.export(role).unwrap();
+            assert_eq!(state.delta_secs, 600);
+            assert_eq!(state.min_se, 120);
+            assert_eq!(state.local_refresher, timer.is_refresher(role));
+
+            let restored = SessionTimer::restore(Some(state.clone()), role);
+            assert!(restored.is_active());
+            assert_eq!(restored.refresher, refresher);
+            assert_eq!(restored.real_delta_secs, timer.real_delta_secs);
+            assert_eq!(restored.export(role), Some(state));
+        }
+    }
+
+    #[tokio::test]
+    async fn export_restore_unsupported() {
+        let timer = SessionTimer::new_unsupported();
+        assert_eq!(timer.export(Role::Uac), None);
+
+        let restored = SessionTimer::restore(None, Role::Uac);
+        assert!(!restored.is_active());
+        assert_eq!(restored.export(Role::Uac), None);
+    }
+}
@@ -1,4 +1,14 @@
+pub mod auth;
+pub mod b2bua;
 pub mod dialog;
+pub mod diversion;
 pub mod invite;
+pub mod message;
+pub mod options;
+pub mod privacy;
+pub mod publish;
 pub mod register;
 mod util;
+
+#[cfg(test)]
+mod test_util;
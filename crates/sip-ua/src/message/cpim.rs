@@ -0,0 +1,269 @@
+//! Common Presence and Instant Messaging message format (RFC 3862)
+
+use bytes::{BufMut, Bytes, BytesMut};
+use bytesstr::BytesStr;
+use std::str::{from_utf8, Utf8Error};
+
+pub const CONTENT_TYPE: &str = "message/cpim";
+
+#[derive(Debug, thiserror::Error)]
+pub enum CpimError {
+    #[error("message is missing the empty line after the {0} headers")]
+    MissingSeparator(&'static str),
+    #[error("invalid header line {0:?}")]
+    InvalidHeader(String),
+    #[error(transparent)]
+    Utf8(#[from] Utf8Error),
+}
+
+/// Message in the `message/cpim` format
+///
+/// Headers are kept in the order they were received or added.
+#[derive(Debug, Clone, Default)]
+pub struct CpimMessage {
+    /// Message headers like `From`, `To`, `DateTime` or `NS`.
+    ///
+    /// Names of message headers are case sensitive.
+    pub headers: Vec<(BytesStr, BytesStr)>,
+
+    /// MIME headers describing the encapsulated content, like `Content-Type`
+    pub content_headers: Vec<(BytesStr, BytesStr)>,
+
+    /// The encapsulated content
+    pub body: Bytes,
+}
+
+impl CpimMessage {
+    /// Create a message wrapping the `body` of the given content type
+    pub fn new<C, B>(content_type: C, body: B) -> Self
+    where
+        C: Into<BytesStr>,
+        B: Into<Bytes>,
+    {
+        Self {
+            headers: vec![],
+            content_headers: vec![("Content-Type".into(), content_type.into())],
+            body: body.into(),
+        }
+    }
+
+    /// Add a message header
+    pub fn add_header<N, V>(&mut self, name: N, value: V)
+    where
+        N: Into<BytesStr>,
+        V: Into<BytesStr>,
+    {
+        self.headers.push((name.into(), value.into()));
+    }
+
+    /// Returns the first message header with the given name
+    pub fn header(&self, name: &str) -> Option<&BytesStr> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the first content header with the given name, compared case insensitive
+    pub fn content_header(&self, name: &str) -> Option<&BytesStr> {
+        self.content_headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the content type of the encapsulated content
+    pub fn content_type(&self) -> Option<&BytesStr> {
+        self.content_header("Content-Type")
+    }
+
+    /// Returns the namespace prefix declared for the given namespace URN using the `NS` header
+    pub fn namespace_prefix(&self, urn: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .filter(|(name, _)| name == "NS")
+            .find_map(|(_, value)| {
+                let (prefix, ns) = value.split_once('<')?;
+                let ns = ns.strip_suffix('>')?;
+
+                if ns.trim() == urn {
+                    Some(prefix.trim())
+                } else {
+                    None
+                }
+            })
+    }
+
+    pub fn parse(src: &Bytes) -> Result<Self, CpimError> {
+        let (headers, rem) = parse_headers(src, 0).ok_or(CpimError::MissingSeparator("message"))?;
+        let (content_headers, body_start) =
+            parse_headers(src, rem).ok_or(CpimError::MissingSeparator("content"))?;
+
+        Ok(Self {
+            headers: split_headers(src, headers)?,
+            content_headers: split_headers(src, content_headers)?,
+            body: src.slice(body_start..),
+        })
+    }
+
+    /// Print the message into a buffer which can be used as body of a request
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+
+        for (name, value) in &self.headers {
+            put_header(&mut buf, name, value);
+        }
+
+        buf.put_slice(b"\r\n");
+
+        for (name, value) in &self.content_headers {
+            put_header(&mut buf, name, value);
+        }
+
+        buf.put_slice(b"\r\n");
+        buf.put_slice(&self.body);
+
+        buf.freeze()
+    }
+}
+
+fn put_header(buf: &mut BytesMut, name: &str, value: &str) {
+    buf.put_slice(name.as_bytes());
+
+    // Header parameters must directly follow the colon
+    if value.starts_with(';') {
+        buf.put_slice(b":");
+    } else {
+        buf.put_slice(b": ");
+    }
+
+    buf.put_slice(value.as_bytes());
+    buf.put_slice(b"\r\n");
+}
+
+/// Find the header block starting at `start`, returns the range of the header block
+/// and the position after the empty line terminating it
+fn parse_headers(src: &Bytes, start: usize) -> Option<((usize, usize), usize)> {
+    let mut pos = start;
+
+    loop {
+        let line_end = pos + src[pos..].iter().position(|&b| b == b'\n')?;
+
+        let line = &src[pos..line_end];
+
+        if line.is_empty() || line == b"\r" {
+            return Some(((start, pos), line_end + 1));
+        }
+
+        pos = line_end + 1;
+    }
+}
+
+fn split_headers(
+    src: &Bytes,
+    (start, end): (usize, usize),
+) -> Result<Vec<(BytesStr, BytesStr)>, CpimError> {
+    let block = from_utf8(&src[start..end])?;
+
+    block
+        .lines()
+        .map(|line| {
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| CpimError::InvalidHeader(line.into()))?;
+
+            Ok((
+                BytesStr::from_parse(src, name.trim()),
+                BytesStr::from_parse(src, value.trim()),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // RFC 3862 Section 6.3
+    const EXAMPLE: &str = "From: MR SANDERS <im:piglet@100akerwood.com>\r\n\
+To: Depressed Donkey <im:eeyore@100akerwood.com>\r\n\
+DateTime: 2000-12-13T13:40:00-08:00\r\n\
+Subject: the weather will be fine today\r\n\
+Subject:;lang=fr beau temps prevu pour aujourd'hui\r\n\
+NS: MyFeatures <mid:MessageFeatures@id.foo.com>\r\n\
+Require: MyFeatures.VitalMessageOption\r\n\
+MyFeatures.VitalMessageOption: Confirmation-requested\r\n\
+MyFeatures.WackyMessageOption: Use-silly-font\r\n\
+\r\n\
+Content-type: text/xml; charset=utf-8\r\n\
+Content-ID: <1234567890@foo.com>\r\n\
+\r\n\
+<body>\r\n\
+Here is the text of my message.\r\n\
+</body>\r\n";
+
+    #[test]
+    fn parse_rfc3862_example() {
+        let message = CpimMessage::parse(&Bytes::from_static(EXAMPLE.as_bytes())).unwrap();
+
+        assert_eq!(message.headers.len(), 9);
+        assert_eq!(
+            message.header("From").unwrap(),
+            "MR SANDERS <im:piglet@100akerwood.com>"
+        );
+        assert_eq!(
+            message.header("Subject").unwrap(),
+            "the weather will be fine today"
+        );
+        assert_eq!(
+            message.namespace_prefix("mid:MessageFeatures@id.foo.com"),
+            Some("MyFeatures")
+        );
+        assert_eq!(message.namespace_prefix("urn:other"), None);
+        assert_eq!(message.content_type().unwrap(), "text/xml; charset=utf-8");
+        assert_eq!(
+            message.content_header("content-id").unwrap(),
+            "<1234567890@foo.com>"
+        );
+        assert_eq!(
+            &message.body[..],
+            b"<body>\r\nHere is the text of my message.\r\n</body>\r\n"
+        );
+    }
+
+    #[test]
+    fn print_parse() {
+        let message = CpimMessage::parse(&Bytes::from_static(EXAMPLE.as_bytes())).unwrap();
+
+        assert_eq!(&message.to_bytes()[..], EXAMPLE.as_bytes());
+    }
+
+    #[test]
+    fn parse_lf_only() {
+        let src = Bytes::from_static(b"From: <im:a@b>\n\nContent-Type: text/plain\n\nHello");
+
+        let message = CpimMessage::parse(&src).unwrap();
+
+        assert_eq!(message.header("From").unwrap(), "<im:a@b>");
+        assert_eq!(message.content_type().unwrap(), "text/plain");
+        assert_eq!(&message.body[..], b"Hello");
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(matches!(
+            CpimMessage::parse(&Bytes::from_static(b"From: <im:a@b>\r\n")),
+            Err(CpimError::MissingSeparator("message"))
+        ));
+        assert!(matches!(
+            CpimMessage::parse(&Bytes::from_static(
+                b"From: <im:a@b>\r\n\r\nContent-Type: text/plain\r\n"
+            )),
+            Err(CpimError::MissingSeparator("content"))
+        ));
+        assert!(matches!(
+            CpimMessage::parse(&Bytes::from_static(b"From <a>\r\n\r\n\r\n")),
+            Err(CpimError::InvalidHeader(line)) if line == "From <a>"
+        ));
+    }
+}
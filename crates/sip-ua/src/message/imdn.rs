@@ -0,0 +1,615 @@
+//! Instant Message Disposition Notification (RFC 5438)
+
+use super::cpim::CpimMessage;
+use bytesstr::BytesStr;
+use std::fmt::{self, Write};
+
+pub const CONTENT_TYPE: &str = "message/imdn+xml";
+
+/// Namespace URN of the IMDN CPIM headers
+pub const NAMESPACE: &str = "urn:ietf:params:imdn";
+
+const XML_NAMESPACE: &str = "urn:ietf:params:xml:ns:imdn";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImdnError {
+    #[error("missing element <{0}>")]
+    MissingElement(&'static str),
+    #[error("unknown notification status {0:?}")]
+    UnknownStatus(String),
+}
+
+/// Kind of disposition notification which can be requested by the sender of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// Notify when the message has been delivered to the recipient
+    Delivery,
+
+    /// Notify when the message has been displayed to the recipient
+    Display,
+
+    /// Notify when the message has been processed by an intermediary
+    Processing,
+}
+
+impl NotificationKind {
+    fn element(self) -> &'static str {
+        match self {
+            NotificationKind::Delivery => "delivery-notification",
+            NotificationKind::Display => "display-notification",
+            NotificationKind::Processing => "processing-notification",
+        }
+    }
+
+    fn request_token(self, positive: bool) -> &'static str {
+        match (self, positive) {
+            (NotificationKind::Delivery, true) => "positive-delivery",
+            (NotificationKind::Delivery, false) => "negative-delivery",
+            (NotificationKind::Display, _) => "display",
+            (NotificationKind::Processing, _) => "processing",
+        }
+    }
+}
+
+/// Status reported by a disposition notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationStatus {
+    Delivered,
+    Failed,
+    Displayed,
+    Processed,
+    Stored,
+    Forbidden,
+    Error,
+}
+
+impl NotificationStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            NotificationStatus::Delivered => "delivered",
+            NotificationStatus::Failed => "failed",
+            NotificationStatus::Displayed => "displayed",
+            NotificationStatus::Processed => "processed",
+            NotificationStatus::Stored => "stored",
+            NotificationStatus::Forbidden => "forbidden",
+            NotificationStatus::Error => "error",
+        }
+    }
+
+    fn from_element(element: &str) -> Option<Self> {
+        let status = match element {
+            "delivered" => NotificationStatus::Delivered,
+            "failed" => NotificationStatus::Failed,
+            "displayed" => NotificationStatus::Displayed,
+            "processed" => NotificationStatus::Processed,
+            "stored" => NotificationStatus::Stored,
+            "forbidden" => NotificationStatus::Forbidden,
+            "error" => NotificationStatus::Error,
+            _ => return None,
+        };
+
+        Some(status)
+    }
+}
+
+/// Notifications requested by the sender of a message using the `Disposition-Notification` CPIM header
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestedNotifications {
+    pub positive_delivery: bool,
+    pub negative_delivery: bool,
+    pub display: bool,
+    pub processing: bool,
+}
+
+impl RequestedNotifications {
+    fn parse(value: &str) -> Self {
+        let mut requested = Self::default();
+
+        for token in value.split(',') {
+            match token.trim() {
+                "positive-delivery" => requested.positive_delivery = true,
+                "negative-delivery" => requested.negative_delivery = true,
+                "display" => requested.display = true,
+                "processing" => requested.processing = true,
+                _ => {}
+            }
+        }
+
+        requested
+    }
+
+    fn print(&self) -> String {
+        let tokens = [
+            (self.positive_delivery, NotificationKind::Delivery, true),
+            (self.negative_delivery, NotificationKind::Delivery, false),
+            (self.display, NotificationKind::Display, true),
+            (self.processing, NotificationKind::Processing, true),
+        ];
+
+        tokens
+            .iter()
+            .filter(|(requested, ..)| *requested)
+            .map(|(_, kind, positive)| kind.request_token(*positive))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl CpimMessage {
+    /// Request disposition notifications for this message, which is identified by `message_id`.
+    ///
+    /// `datetime` must be the same as in the `DateTime` header, which will be set as well.
+    pub fn request_notifications<M, D>(
+        &mut self,
+        message_id: M,
+        datetime: D,
+        requested: &RequestedNotifications,
+    ) where
+        M: Into<BytesStr>,
+        D: Into<BytesStr>,
+    {
+        let prefix = self.imdn_prefix_or_declare();
+
+        self.headers.retain(|(name, _)| name != "DateTime");
+        self.add_header("DateTime", datetime);
+        self.add_header(format!("{}.Message-ID", prefix), message_id);
+        self.add_header(
+            format!("{}.Disposition-Notification", prefix),
+            requested.print(),
+        );
+    }
+
+    /// Returns the IMDN message id of this message
+    pub fn imdn_message_id(&self) -> Option<&BytesStr> {
+        let prefix = self.namespace_prefix(NAMESPACE)?;
+
+        self.header(&format!("{}.Message-ID", prefix))
+    }
+
+    /// Returns the disposition notifications requested by the sender of this message
+    pub fn requested_notifications(&self) -> Option<RequestedNotifications> {
+        let prefix = self.namespace_prefix(NAMESPACE)?;
+        let value = self.header(&format!("{}.Disposition-Notification", prefix))?;
+
+        Some(RequestedNotifications::parse(value))
+    }
+
+    fn imdn_prefix_or_declare(&mut self) -> String {
+        if let Some(prefix) = self.namespace_prefix(NAMESPACE) {
+            return prefix.to_string();
+        }
+
+        self.add_header("NS", format!("imdn <{}>", NAMESPACE));
+
+        "imdn".into()
+    }
+}
+
+/// Disposition notification (`message/imdn+xml`) reporting the status of a previously sent message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispositionNotification {
+    /// IMDN message id of the message this notification refers to
+    pub message_id: BytesStr,
+
+    /// `DateTime` of the message this notification refers to
+    pub datetime: BytesStr,
+
+    pub recipient_uri: Option<BytesStr>,
+    pub original_recipient_uri: Option<BytesStr>,
+
+    pub kind: NotificationKind,
+    pub status: NotificationStatus,
+}
+
+impl DispositionNotification {
+    /// Create a notification for the given message, which must have requested notifications
+    pub fn for_message(
+        message: &CpimMessage,
+        kind: NotificationKind,
+        status: NotificationStatus,
+    ) -> Option<Self> {
+        Some(Self {
+            message_id: message.imdn_message_id()?.clone(),
+            datetime: message.header("DateTime")?.clone(),
+            recipient_uri: message.header("To").map(cpim_uri),
+            original_recipient_uri: None,
+            kind,
+            status,
+        })
+    }
+
+    /// Wrap the notification into a CPIM message which can be sent as MESSAGE request.
+    ///
+    /// `message_id` is the IMDN message id of the notification itself.
+    pub fn to_cpim<F, T, M, D>(&self, from: F, to: T, message_id: M, datetime: D) -> CpimMessage
+    where
+        F: Into<BytesStr>,
+        T: Into<BytesStr>,
+        M: Into<BytesStr>,
+        D: Into<BytesStr>,
+    {
+        let mut cpim = CpimMessage::new(CONTENT_TYPE, self.to_string());
+
+        cpim.add_header("From", from);
+        cpim.add_header("To", to);
+        cpim.add_header("NS", format!("imdn <{}>", NAMESPACE));
+        cpim.add_header("imdn.Message-ID", message_id);
+        cpim.add_header("DateTime", datetime);
+
+        cpim.content_headers
+            .push(("Content-Disposition".into(), "notification".into()));
+
+        cpim
+    }
+
+    /// Parse a `message/imdn+xml` body
+    pub fn parse(xml: &str) -> Result<Self, ImdnError> {
+        let message_id =
+            element(xml, "message-id").ok_or(ImdnError::MissingElement("message-id"))?;
+        let datetime = element(xml, "datetime").ok_or(ImdnError::MissingElement("datetime"))?;
+
+        let (kind, notification) = [
+            NotificationKind::Delivery,
+            NotificationKind::Display,
+            NotificationKind::Processing,
+        ]
+        .iter()
+        .find_map(|kind| Some((*kind, element(xml, kind.element())?)))
+        .ok_or(ImdnError::MissingElement("delivery-notification"))?;
+
+        let status = element(notification, "status").ok_or(ImdnError::MissingElement("status"))?;
+        let status_name = first_element(status)
+            .map(|(name, _)| local_name(name))
+            .ok_or_else(|| ImdnError::UnknownStatus(status.trim().into()))?;
+
+        let status = NotificationStatus::from_element(status_name)
+            .ok_or_else(|| ImdnError::UnknownStatus(status_name.into()))?;
+
+        Ok(Self {
+            message_id: unescape(message_id).into(),
+            datetime: unescape(datetime).into(),
+            recipient_uri: element(xml, "recipient-uri").map(|uri| unescape(uri).into()),
+            original_recipient_uri: element(xml, "original-recipient-uri")
+                .map(|uri| unescape(uri).into()),
+            kind,
+            status,
+        })
+    }
+}
+
+impl fmt::Display for DispositionNotification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(f, r#"<imdn xmlns="{}">"#, XML_NAMESPACE)?;
+        writeln!(f, "<message-id>{}</message-id>", Escape(&self.message_id))?;
+        writeln!(f, "<datetime>{}</datetime>", Escape(&self.datetime))?;
+
+        if let Some(uri) = &self.recipient_uri {
+            writeln!(f, "<recipient-uri>{}</recipient-uri>", Escape(uri))?;
+        }
+
+        if let Some(uri) = &self.original_recipient_uri {
+            writeln!(
+                f,
+                "<original-recipient-uri>{}</original-recipient-uri>",
+                Escape(uri)
+            )?;
+        }
+
+        writeln!(
+            f,
+            "<{kind}><status><{status}/></status></{kind}>",
+            kind = self.kind.element(),
+            status = self.status.as_str()
+        )?;
+
+        write!(f, "</imdn>")
+    }
+}
+
+/// Returns the URI of a CPIM `From` or `To` header value, which may contain a display name
+fn cpim_uri(value: &BytesStr) -> BytesStr {
+    match value.split_once('<') {
+        Some((_, uri)) => value.slice_ref(uri.trim_end_matches('>').trim()),
+        None => value.clone(),
+    }
+}
+
+/// Returns the content of the first element with the given local name
+///
+/// Namespace prefixes and attributes of the element are ignored, self-closing elements
+/// have no content. This is not a complete XML parser, but sufficient for the flat
+/// documents of RFC 5438.
+fn element<'x>(xml: &'x str, name: &str) -> Option<&'x str> {
+    let mut pos = 0;
+
+    loop {
+        let (qualified_name, tag) = first_element(&xml[pos..])?;
+        let content_start = pos + tag.end;
+
+        if local_name(qualified_name) != name {
+            pos = content_start;
+            continue;
+        }
+
+        if tag.self_closing {
+            return Some(&xml[content_start..content_start]);
+        }
+
+        let content_end = content_start + closing_tag(&xml[content_start..], qualified_name)?;
+
+        return Some(&xml[content_start..content_end]);
+    }
+}
+
+/// Position of a start tag found by [`first_element`]
+struct StartTag {
+    /// Position after the closing `>`
+    end: usize,
+    self_closing: bool,
+}
+
+/// Returns the qualified name of the first start tag, skipping end tags, comments and
+/// processing instructions
+fn first_element(xml: &str) -> Option<(&str, StartTag)> {
+    let mut pos = 0;
+
+    loop {
+        let start = pos + xml[pos..].find('<')? + 1;
+        let end = start + xml[start..].find('>')?;
+        let tag = &xml[start..end];
+
+        pos = end + 1;
+
+        if tag.starts_with(['/', '?', '!']) {
+            continue;
+        }
+
+        let name_end = tag
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(tag.len());
+
+        return Some((
+            &tag[..name_end],
+            StartTag {
+                end: pos,
+                self_closing: tag.ends_with('/'),
+            },
+        ));
+    }
+}
+
+/// Returns the position of the end tag with the given qualified name
+fn closing_tag(xml: &str, qualified_name: &str) -> Option<usize> {
+    let mut pos = 0;
+
+    loop {
+        let start = pos + xml[pos..].find("</")?;
+        let name_start = start + 2;
+        let end = name_start + xml[name_start..].find('>')?;
+
+        if xml[name_start..end].trim_end() == qualified_name {
+            return Some(start);
+        }
+
+        pos = end;
+    }
+}
+
+/// Strip the namespace prefix of an element name
+fn local_name(qualified_name: &str) -> &str {
+    qualified_name
+        .rsplit_once(':')
+        .map(|(_, local_name)| local_name)
+        .unwrap_or(qualified_name)
+}
+
+fn unescape(text: &str) -> String {
+    text.trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+struct Escape<'s>(&'s str);
+
+impl fmt::Display for Escape<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '&' => f.write_str("&amp;")?,
+                '"' => f.write_str("&quot;")?,
+                c => f.write_char(c)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn notification() -> DispositionNotification {
+        DispositionNotification {
+            message_id: "34jk324j".into(),
+            datetime: "2008-04-04T12:16:49-05:00".into(),
+            recipient_uri: Some("im:bob@example.com".into()),
+            original_recipient_uri: None,
+            kind: NotificationKind::Display,
+            status: NotificationStatus::Displayed,
+        }
+    }
+
+    #[test]
+    fn print_parse() {
+        let notification = notification();
+
+        let xml = notification.to_string();
+
+        assert_eq!(DispositionNotification::parse(&xml).unwrap(), notification);
+    }
+
+    #[test]
+    fn print_parse_escaped() {
+        let mut notification = notification();
+        notification.message_id = "<a&b>".into();
+
+        let xml = notification.to_string();
+        assert!(xml.contains("<message-id>&lt;a&amp;b&gt;</message-id>"));
+
+        assert_eq!(DispositionNotification::parse(&xml).unwrap(), notification);
+    }
+
+    // RFC 5438 Section 7.2.1.1
+    #[test]
+    fn parse_rfc5438_example() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<imdn xmlns="urn:ietf:params:xml:ns:imdn">
+  <message-id>34jk324j</message-id>
+  <datetime>2008-04-04T12:16:49-05:00</datetime>
+  <recipient-uri>im:bob@example.com</recipient-uri>
+  <original-recipient-uri>im:bob@example.com</original-recipient-uri>
+  <delivery-notification>
+    <status>
+      <delivered/>
+    </status>
+  </delivery-notification>
+</imdn>"#;
+
+        let notification = DispositionNotification::parse(xml).unwrap();
+
+        assert_eq!(notification.message_id, "34jk324j");
+        assert_eq!(notification.datetime, "2008-04-04T12:16:49-05:00");
+        assert_eq!(
+            notification.original_recipient_uri.as_deref(),
+            Some("im:bob@example.com")
+        );
+        assert_eq!(notification.kind, NotificationKind::Delivery);
+        assert_eq!(notification.status, NotificationStatus::Delivered);
+    }
+
+    #[test]
+    fn parse_prefixed() {
+        let xml = r#"<imdn:imdn xmlns:imdn="urn:ietf:params:xml:ns:imdn">
+  <imdn:message-id xml:lang="en">abc</imdn:message-id>
+  <imdn:datetime>2008-04-04T12:16:49-05:00</imdn:datetime>
+  <imdn:display-notification>
+    <imdn:status><imdn:displayed /></imdn:status>
+  </imdn:display-notification>
+</imdn:imdn>"#;
+
+        let notification = DispositionNotification::parse(xml).unwrap();
+
+        assert_eq!(notification.message_id, "abc");
+        assert_eq!(notification.recipient_uri, None);
+        assert_eq!(notification.kind, NotificationKind::Display);
+        assert_eq!(notification.status, NotificationStatus::Displayed);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(matches!(
+            DispositionNotification::parse("<imdn><datetime>x</datetime></imdn>"),
+            Err(ImdnError::MissingElement("message-id"))
+        ));
+
+        assert!(matches!(
+            DispositionNotification::parse(
+                "<imdn><message-id>a</message-id><datetime>x</datetime></imdn>"
+            ),
+            Err(ImdnError::MissingElement("delivery-notification"))
+        ));
+
+        assert!(matches!(
+            DispositionNotification::parse(
+                "<imdn><message-id>a</message-id><datetime>x</datetime>\
+                <delivery-notification><status><lost/></status></delivery-notification></imdn>"
+            ),
+            Err(ImdnError::UnknownStatus(status)) if status == "lost"
+        ));
+    }
+
+    #[test]
+    fn element_boundary() {
+        let xml = "<message-ids>wrong</message-ids><message-id>right</message-id>";
+
+        assert_eq!(element(xml, "message-id"), Some("right"));
+        assert_eq!(element("<a><b/></a>", "b"), Some(""));
+        assert_eq!(element("<a>", "a"), None);
+    }
+
+    #[test]
+    fn request_notifications() {
+        let mut message = CpimMessage::new("text/plain", "Hello");
+
+        message.request_notifications(
+            "34jk324j",
+            "2008-04-04T12:16:49-05:00",
+            &RequestedNotifications {
+                positive_delivery: true,
+                display: true,
+                ..Default::default()
+            },
+        );
+
+        let message = CpimMessage::parse(&message.to_bytes()).unwrap();
+
+        assert_eq!(message.namespace_prefix(NAMESPACE), Some("imdn"));
+        assert_eq!(message.imdn_message_id().unwrap(), "34jk324j");
+        assert_eq!(
+            message.header("imdn.Disposition-Notification").unwrap(),
+            "positive-delivery, display"
+        );
+        assert_eq!(
+            message.requested_notifications(),
+            Some(RequestedNotifications {
+                positive_delivery: true,
+                display: true,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn notification_for_message() {
+        let mut message = CpimMessage::new("text/plain", "Hello");
+        message.add_header("To", "Bob <im:bob@example.com>");
+        message.request_notifications(
+            "34jk324j",
+            "2008-04-04T12:16:49-05:00",
+            &RequestedNotifications {
+                display: true,
+                ..Default::default()
+            },
+        );
+
+        let notification = DispositionNotification::for_message(
+            &message,
+            NotificationKind::Display,
+            NotificationStatus::Displayed,
+        )
+        .unwrap();
+
+        assert_eq!(notification, self::notification());
+
+        let cpim = notification.to_cpim(
+            "im:bob@example.com",
+            "im:alice@example.com",
+            "dfgh",
+            "2008-04-04T12:16:50-05:00",
+        );
+
+        assert_eq!(cpim.content_type().unwrap(), CONTENT_TYPE);
+        assert_eq!(cpim.imdn_message_id().unwrap(), "dfgh");
+        assert_eq!(
+            cpim.content_header("content-disposition").unwrap(),
+            "notification"
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! Instant messaging using MESSAGE requests (RFC 3428)
+
+use crate::auth::{send_request_with_auth, DigestAuthenticator};
+use crate::dialog::{register_usage, Dialog, DialogLayer, Usage, UsageGuard};
+use crate::util::{random_sequence_number, random_string};
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use cpim::CpimMessage;
+use imdn::DispositionNotification;
+use sip_core::transaction::{ServerTsx, TsxResponse};
+use sip_core::transport::OutgoingResponse;
+use sip_core::{
+    Endpoint, EndpointBuilder, IncomingRequest, Layer, LayerKey, MayTake, Request, Result,
+    WithStatus,
+};
+use sip_types::header::typed::{CSeq, CallID, ContentType, From, Route, To};
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Code, Method};
+use std::str::from_utf8;
+use tokio::sync::mpsc;
+
+pub mod cpim;
+pub mod imdn;
+
+/// Sends MESSAGE requests outside of a dialog to a single target
+pub struct MessageSender {
+    target: Box<dyn Uri>,
+
+    /// Preloaded route set of every MESSAGE
+    route_set: Vec<Route>,
+
+    from: From,
+    to: To,
+    call_id: CallID,
+    cseq: u32,
+
+    /// Used to authenticate messages when challenged
+    pub authenticator: DigestAuthenticator,
+}
+
+impl MessageSender {
+    pub fn new(id: NameAddr, target: Box<dyn Uri>) -> Self {
+        Self {
+            to: To::new(NameAddr::uri(target.clone()), None),
+            target,
+            route_set: vec![],
+            from: From::new(id, Some(random_string())),
+            call_id: CallID::new(random_string()),
+            cseq: random_sequence_number(),
+            authenticator: DigestAuthenticator::new(),
+        }
+    }
+
+    /// Route all messages through the given preloaded route set
+    /// (e.g. the service route learned by a [`Registration`](crate::register::Registration))
+    pub fn set_route_set(&mut self, route_set: Vec<Route>) {
+        self.route_set = route_set;
+    }
+
+    /// Create a MESSAGE request with the given content
+    pub fn create_message(&mut self, content_type: ContentType, body: Bytes) -> Request {
+        let mut request = Request::new(Method::MESSAGE, self.target.clone());
+
+        self.cseq += 1;
+
+        request.headers.insert_type(&self.from);
+        request.headers.insert_type(&self.to);
+        request.headers.insert_type(&self.call_id);
+        request
+            .headers
+            .insert_type(&CSeq::new(self.cseq, Method::MESSAGE));
+        request.headers.insert_type(&self.route_set);
+        request.headers.insert_type(&content_type);
+        request.body = body;
+
+        request
+    }
+
+    /// Send a message with the given content and return the final response.
+    ///
+    /// Retries the request with credentials if the request is challenged.
+    pub async fn send(
+        &mut self,
+        endpoint: &Endpoint,
+        content_type: ContentType,
+        body: Bytes,
+    ) -> Result<TsxResponse> {
+        let mut authenticator = std::mem::take(&mut self.authenticator);
+
+        let result = send_request_with_auth(endpoint, &mut authenticator, || {
+            self.create_message(content_type.clone(), body.clone())
+        })
+        .await;
+
+        self.authenticator = authenticator;
+
+        result
+    }
+}
+
+/// Send a MESSAGE request with the given content inside the dialog and return the final response.
+///
+/// Retries the request with credentials if the request is challenged.
+pub async fn send_in_dialog(
+    dialog: &mut Dialog,
+    authenticator: &mut DigestAuthenticator,
+    content_type: ContentType,
+    body: Bytes,
+) -> Result<TsxResponse> {
+    let endpoint = dialog.endpoint.clone();
+
+    send_request_with_auth(&endpoint, authenticator, || {
+        let mut request = dialog.create_request(Method::MESSAGE);
+
+        request.headers.insert_type(&content_type);
+        request.body = body.clone();
+
+        request
+    })
+    .await
+}
+
+/// Typed content of a received MESSAGE request
+#[derive(Debug)]
+pub enum MessageContent {
+    /// `text/plain` message
+    Text(BytesStr),
+
+    /// `message/cpim` message, which contains no disposition notification
+    Cpim(CpimMessage),
+
+    /// Disposition notification wrapped inside a `message/cpim` message
+    DispositionNotification(CpimMessage, DispositionNotification),
+
+    /// Message of any other content type, available as body of the request
+    Other,
+}
+
+impl MessageContent {
+    fn from_request(content_type: Option<&ContentType>, body: &Bytes) -> Result<Self> {
+        let content_type = match content_type {
+            Some(content_type) => content_type,
+            None => return Ok(MessageContent::Other),
+        };
+
+        if content_type.matches("text/plain") {
+            let text = BytesStr::from_utf8_bytes(body.clone())?;
+
+            return Ok(MessageContent::Text(text));
+        }
+
+        if !content_type.matches(cpim::CONTENT_TYPE) {
+            return Ok(MessageContent::Other);
+        }
+
+        let cpim = CpimMessage::parse(body).status(Code::BAD_REQUEST)?;
+
+        let is_notification = cpim
+            .content_type()
+            .and_then(|content_type| content_type.parse::<ContentType>().ok())
+            .map(|content_type| content_type.matches(imdn::CONTENT_TYPE))
+            .unwrap_or_default();
+
+        if is_notification {
+            let xml = from_utf8(&cpim.body)?;
+            let notification = DispositionNotification::parse(xml).status(Code::BAD_REQUEST)?;
+
+            Ok(MessageContent::DispositionNotification(cpim, notification))
+        } else {
+            Ok(MessageContent::Cpim(cpim))
+        }
+    }
+}
+
+/// A MESSAGE request received by the [`MessageLayer`]
+#[derive(Debug)]
+pub struct MessageEvent {
+    pub endpoint: Endpoint,
+    pub request: IncomingRequest,
+    pub transaction: ServerTsx,
+
+    pub content_type: Option<ContentType>,
+    pub content: MessageContent,
+}
+
+impl MessageEvent {
+    /// Body of the received message
+    pub fn body(&self) -> &Bytes {
+        &self.request.body
+    }
+
+    pub async fn create_response(
+        &self,
+        code: Code,
+        reason: Option<BytesStr>,
+    ) -> Result<OutgoingResponse> {
+        self.endpoint
+            .create_response(&self.request, code, reason)
+            .await
+    }
+
+    pub async fn respond(self, response: OutgoingResponse) -> Result<()> {
+        self.transaction.respond(response).await
+    }
+
+    /// Respond to the message with the given code
+    pub async fn respond_with(self, code: Code) -> Result<()> {
+        let response = self.create_response(code, None).await?;
+
+        self.respond(response).await
+    }
+}
+
+/// Layer which receives MESSAGE requests outside of dialogs and passes them as [`MessageEvent`]s
+/// to the receiver returned by [`MessageLayer::new`]
+///
+/// MESSAGE requests inside a dialog are passed to the layer when the dialog has a
+/// usage registered using [`register_message_usage`].
+pub struct MessageLayer {
+    events: mpsc::Sender<MessageEvent>,
+}
+
+impl MessageLayer {
+    pub fn new() -> (Self, mpsc::Receiver<MessageEvent>) {
+        let (events, receiver) = mpsc::channel(16);
+
+        (Self { events }, receiver)
+    }
+
+    async fn handle_message(&self, endpoint: &Endpoint, message: IncomingRequest) -> Result<()> {
+        let transaction = endpoint.create_server_tsx(&message);
+
+        let content_type = message.headers.get::<ContentType>().ok();
+
+        let content = match MessageContent::from_request(content_type.as_ref(), &message.body) {
+            Ok(content) => content,
+            Err(e) => {
+                log::debug!("Failed to read MESSAGE content, {:?}", e);
+
+                let response = endpoint.create_response(&message, e.status, None).await?;
+
+                return transaction.respond(response).await;
+            }
+        };
+
+        let event = MessageEvent {
+            endpoint: endpoint.clone(),
+            request: message,
+            transaction,
+            content_type,
+            content,
+        };
+
+        if let Err(mpsc::error::SendError(event)) = self.events.send(event).await {
+            log::warn!("Message receiver has been dropped, rejecting MESSAGE");
+
+            event.respond_with(Code::TEMPORARILY_UNAVAILABLE).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Layer for MessageLayer {
+    fn name(&self) -> &'static str {
+        "message"
+    }
+
+    fn init(&mut self, endpoint: &mut EndpointBuilder) {
+        endpoint.add_allow(Method::MESSAGE);
+    }
+
+    async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::MESSAGE {
+            return;
+        }
+
+        if let Err(e) = self.handle_message(endpoint, request.take()).await {
+            log::warn!("Failed to handle MESSAGE request {:?}", e);
+        }
+    }
+}
+
+struct MessageUsage {
+    message_layer: LayerKey<MessageLayer>,
+}
+
+#[async_trait::async_trait]
+impl Usage for MessageUsage {
+    fn name(&self) -> &'static str {
+        "message-usage"
+    }
+
+    async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::MESSAGE {
+            return;
+        }
+
+        if let Err(e) = endpoint[self.message_layer]
+            .handle_message(endpoint, request.take())
+            .await
+        {
+            log::warn!("Failed to handle MESSAGE request {:?}", e);
+        }
+    }
+}
+
+/// Register a usage inside the dialog which passes all MESSAGE requests received
+/// inside the dialog to the [`MessageLayer`]
+///
+/// Returns `None` if the dialog no longer exists
+pub fn register_message_usage(
+    endpoint: Endpoint,
+    dialog_layer: LayerKey<DialogLayer>,
+    message_layer: LayerKey<MessageLayer>,
+    dialog: &Dialog,
+) -> Option<UsageGuard> {
+    register_usage(
+        endpoint,
+        dialog_layer,
+        dialog.key(),
+        MessageUsage { message_layer },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::imdn::{NotificationKind, NotificationStatus};
+    use super::*;
+
+    fn from_request(content_type: Option<&'static str>, body: Bytes) -> Result<MessageContent> {
+        let content_type = content_type.map(ContentType::from_static);
+
+        MessageContent::from_request(content_type.as_ref(), &body)
+    }
+
+    #[test]
+    fn content_text() {
+        let content = from_request(Some("Text/Plain"), "Hello".into()).unwrap();
+
+        assert!(matches!(content, MessageContent::Text(text) if text == "Hello"));
+    }
+
+    #[test]
+    fn content_cpim() {
+        let cpim = CpimMessage::new("text/plain", "Hello");
+
+        let content = from_request(Some(cpim::CONTENT_TYPE), cpim.to_bytes()).unwrap();
+
+        assert!(matches!(content, MessageContent::Cpim(cpim) if &cpim.body[..] == b"Hello"));
+    }
+
+    #[test]
+    fn content_notification() {
+        let notification = DispositionNotification {
+            message_id: "34jk324j".into(),
+            datetime: "2008-04-04T12:16:49-05:00".into(),
+            recipient_uri: None,
+            original_recipient_uri: None,
+            kind: NotificationKind::Delivery,
+            status: NotificationStatus::Delivered,
+        };
+
+        let mut cpim = notification.to_cpim("im:bob@b", "im:alice@a", "dfgh", "now");
+        cpim.content_headers[0].1 = "message/imdn+xml; charset=utf-8".into();
+
+        let content = from_request(Some(cpim::CONTENT_TYPE), cpim.to_bytes()).unwrap();
+
+        match content {
+            MessageContent::DispositionNotification(_, parsed) => {
+                assert_eq!(parsed, notification)
+            }
+            content => panic!("unexpected content {:?}", content),
+        }
+    }
+
+    #[test]
+    fn content_other() {
+        assert!(matches!(
+            from_request(None, "Hello".into()).unwrap(),
+            MessageContent::Other
+        ));
+        assert!(matches!(
+            from_request(Some("application/json"), "{}".into()).unwrap(),
+            MessageContent::Other
+        ));
+    }
+
+    #[test]
+    fn content_invalid() {
+        let error = from_request(Some(cpim::CONTENT_TYPE), "no headers".into()).unwrap_err();
+        assert_eq!(error.status, Code::BAD_REQUEST);
+
+        let mut cpim = CpimMessage::new(imdn::CONTENT_TYPE, "<imdn></imdn>");
+        cpim.add_header("From", "<im:a@b>");
+
+        let error = from_request(Some(cpim::CONTENT_TYPE), cpim.to_bytes()).unwrap_err();
+        assert_eq!(error.status, Code::BAD_REQUEST);
+
+        assert!(from_request(Some("text/plain"), Bytes::from_static(&[0xff])).is_err());
+    }
+}
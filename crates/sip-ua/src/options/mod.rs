@@ -0,0 +1,102 @@
+//! Responding to and sending OPTIONS requests
+
+use sip_core::{Endpoint, EndpointBuilder, IncomingRequest, Layer, MayTake, Result};
+use sip_types::{Code, Method};
+
+mod pinger;
+
+pub use pinger::{OptionsPinger, PeerState, PeerStateChanged, PingerConfig};
+
+/// Layer which responds to OPTIONS requests outside of dialogs with the
+/// capabilities of the endpoint (`Allow`, `Accept` and `Supported`)
+#[derive(Default)]
+pub struct OptionsLayer {}
+
+impl OptionsLayer {
+    async fn handle_options(&self, endpoint: &Endpoint, request: IncomingRequest) -> Result<()> {
+        let transaction = endpoint.create_server_tsx(&request);
+
+        let mut response = endpoint.create_response(&request, Code::OK, None).await?;
+
+        response.msg.headers.insert_type(endpoint.allowed());
+        response.msg.headers.insert_type(endpoint.accepted());
+        response.msg.headers.insert_type(endpoint.supported());
+
+        transaction.respond(response).await
+    }
+}
+
+#[async_trait::async_trait]
+impl Layer for OptionsLayer {
+    fn name(&self) -> &'static str {
+        "options"
+    }
+
+    fn init(&mut self, endpoint: &mut EndpointBuilder) {
+        endpoint.add_allow(Method::OPTIONS);
+    }
+
+    async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::OPTIONS {
+            return;
+        }
+
+        if let Err(e) = self.handle_options(endpoint, request.take()).await {
+            log::warn!("Failed to handle OPTIONS request {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{bind_loopback, uri};
+    use sip_core::Request;
+    use sip_types::header::typed::{Accept, Allow, CSeq, CallID, From, Supported, To};
+    use sip_types::uri::NameAddr;
+
+    #[tokio::test]
+    async fn respond_with_capabilities() {
+        let mut builder = Endpoint::builder();
+        builder.add_layer(OptionsLayer::default());
+        builder.add_allow(Method::INVITE);
+        builder.add_accept(Accept::from("application/sdp"));
+        builder.add_supported(Supported::from("timer"));
+        let server_addr = bind_loopback(&mut builder).await;
+        let _server = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let client_addr = bind_loopback(&mut builder).await;
+        let client = builder.build();
+
+        let target = uri("bob", server_addr);
+
+        let mut request = Request::new(Method::OPTIONS, target.clone());
+        request.headers.insert_type(&From::new(
+            NameAddr::uri(uri("alice", client_addr)),
+            Some("1234".into()),
+        ));
+        request
+            .headers
+            .insert_type(&To::new(NameAddr::uri(target), None));
+        request.headers.insert_type(&CallID::new("options-test"));
+        request.headers.insert_type(&CSeq::new(1, Method::OPTIONS));
+
+        let transaction = client.send_request(request).await.unwrap();
+        let response = transaction.receive_final().await.unwrap();
+
+        assert_eq!(response.line.code, Code::OK);
+
+        let allow: Vec<Allow> = response.headers.get().unwrap();
+        let allow: Vec<Method> = allow.into_iter().map(|allow| allow.0).collect();
+        assert_eq!(allow, [Method::INVITE, Method::OPTIONS]);
+
+        let accept: Vec<Accept> = response.headers.get().unwrap();
+        assert_eq!(accept.len(), 1);
+        assert_eq!(accept[0].0, "application/sdp");
+
+        let supported: Vec<Supported> = response.headers.get().unwrap();
+        assert_eq!(supported.len(), 1);
+        assert_eq!(supported[0].0, "timer");
+    }
+}
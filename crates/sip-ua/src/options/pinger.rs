@@ -0,0 +1,350 @@
+use crate::util::{random_sequence_number, random_string};
+use parking_lot as pl;
+use sip_core::{Endpoint, Request};
+use sip_types::header::typed::{CSeq, CallID, From, Route, To};
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Code, Method};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+
+/// Configuration of the [`OptionsPinger`]
+#[derive(Debug, Clone)]
+pub struct PingerConfig {
+    /// Identity used in the `From` header of the OPTIONS requests
+    pub id: NameAddr,
+
+    /// Preloaded route set of the OPTIONS requests, e.g. the
+    /// [`Registration::route_set`](crate::register::Registration::route_set) of the registration of `id`
+    pub route_set: Vec<Route>,
+
+    /// Time between two OPTIONS requests sent to a peer
+    pub interval: Duration,
+
+    /// Amount of consecutive failed requests after which a peer is considered down
+    pub down_threshold: u32,
+
+    /// Amount of consecutive successful requests after which a peer is considered up
+    pub up_threshold: u32,
+}
+
+impl PingerConfig {
+    pub fn new(id: NameAddr) -> Self {
+        Self {
+            id,
+            route_set: vec![],
+            interval: Duration::from_secs(30),
+            down_threshold: 3,
+            up_threshold: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerState {
+    /// No decision has been made yet
+    Unknown,
+    Up,
+    Down,
+}
+
+/// Emitted by the [`OptionsPinger`] when the state of a peer changes
+#[derive(Debug, Clone)]
+pub struct PeerStateChanged {
+    /// Index of the peer in the list passed to [`OptionsPinger::start`]
+    pub index: usize,
+    pub peer: Box<dyn Uri>,
+    pub state: PeerState,
+}
+
+/// Tracks consecutive results of OPTIONS requests to decide the state of a peer
+#[derive(Debug)]
+struct PeerStatus {
+    state: PeerState,
+    successes: u32,
+    failures: u32,
+}
+
+impl PeerStatus {
+    fn new() -> Self {
+        Self {
+            state: PeerState::Unknown,
+            successes: 0,
+            failures: 0,
+        }
+    }
+
+    /// Record the result of a request, returns the new state if it changed
+    fn record(&mut self, alive: bool, config: &PingerConfig) -> Option<PeerState> {
+        if alive {
+            self.successes += 1;
+            self.failures = 0;
+
+            if self.state != PeerState::Up && self.successes >= config.up_threshold {
+                self.state = PeerState::Up;
+                return Some(PeerState::Up);
+            }
+        } else {
+            self.failures += 1;
+            self.successes = 0;
+
+            if self.state != PeerState::Down && self.failures >= config.down_threshold {
+                self.state = PeerState::Down;
+                return Some(PeerState::Down);
+            }
+        }
+
+        None
+    }
+}
+
+/// Periodically sends OPTIONS requests to a set of peers, to keep track of their availability
+///
+/// A peer is considered alive when it responds with any final response, except
+/// `408 Request Timeout` and `503 Service Unavailable`.
+/// Pinging stops when the pinger is dropped.
+pub struct OptionsPinger {
+    peers: Vec<Box<dyn Uri>>,
+    status: Arc<pl::Mutex<Vec<PeerStatus>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for OptionsPinger {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl OptionsPinger {
+    /// Start sending OPTIONS requests to all `peers`
+    ///
+    /// Returns the pinger and a receiver for all changes to the state of the peers.
+    pub fn start(
+        endpoint: Endpoint,
+        config: PingerConfig,
+        peers: Vec<Box<dyn Uri>>,
+    ) -> (Self, mpsc::UnboundedReceiver<PeerStateChanged>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+
+        let status = Arc::new(pl::Mutex::new(
+            peers.iter().map(|_| PeerStatus::new()).collect::<Vec<_>>(),
+        ));
+
+        let tasks = peers
+            .iter()
+            .enumerate()
+            .map(|(index, peer)| {
+                tokio::spawn(ping_task(
+                    endpoint.clone(),
+                    config.clone(),
+                    index,
+                    peer.clone(),
+                    status.clone(),
+                    events.clone(),
+                ))
+            })
+            .collect();
+
+        (
+            Self {
+                peers,
+                status,
+                tasks,
+            },
+            receiver,
+        )
+    }
+
+    /// Returns the current state of all peers, in the order they were passed to [`OptionsPinger::start`]
+    pub fn states(&self) -> Vec<(&dyn Uri, PeerState)> {
+        let status = self.status.lock();
+
+        self.peers
+            .iter()
+            .zip(status.iter())
+            .map(|(peer, status)| (&**peer, status.state))
+            .collect()
+    }
+
+    /// Returns the current state of the peer at the given index
+    pub fn state(&self, index: usize) -> Option<PeerState> {
+        self.status.lock().get(index).map(|status| status.state)
+    }
+}
+
+async fn ping_task(
+    endpoint: Endpoint,
+    config: PingerConfig,
+    index: usize,
+    peer: Box<dyn Uri>,
+    status: Arc<pl::Mutex<Vec<PeerStatus>>>,
+    events: mpsc::UnboundedSender<PeerStateChanged>,
+) {
+    let from = From::new(config.id.clone(), Some(random_string()));
+    let to = To::new(NameAddr::uri(peer.clone()), None);
+    let call_id = CallID::new(random_string());
+    let mut cseq = random_sequence_number();
+
+    let mut interval = interval(config.interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        cseq += 1;
+
+        let mut request = Request::new(Method::OPTIONS, peer.clone());
+        request.headers.insert_type(&from);
+        request.headers.insert_type(&to);
+        request.headers.insert_type(&call_id);
+        request
+            .headers
+            .insert_type(&CSeq::new(cseq, Method::OPTIONS));
+        request.headers.insert_type(&config.route_set);
+
+        let alive = match ping(&endpoint, request).await {
+            Ok(code) => is_alive(code),
+            Err(e) => {
+                log::debug!("OPTIONS to peer {} failed, {:?}", index, e);
+                false
+            }
+        };
+
+        let new_state = status.lock()[index].record(alive, &config);
+
+        if let Some(state) = new_state {
+            let event = PeerStateChanged {
+                index,
+                peer: peer.clone(),
+                state,
+            };
+
+            if events.send(event).is_err() {
+                log::debug!("Peer state receiver has been dropped");
+            }
+        }
+    }
+}
+
+/// Returns if the final response to an OPTIONS request shows that the peer is alive
+fn is_alive(code: Code) -> bool {
+    !matches!(code, Code::REQUEST_TIMEOUT | Code::SERVICE_UNAVAILABLE)
+}
+
+/// Send the OPTIONS request and return the code of the final response
+async fn ping(endpoint: &Endpoint, request: Request) -> sip_core::Result<Code> {
+    let transaction = endpoint.send_request(request).await?;
+    let response = transaction.receive_final().await?;
+
+    Ok(response.line.code)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::options::OptionsLayer;
+    use crate::test_util::{bind_loopback, uri};
+    use sip_types::uri::sip::SipUri;
+    use tokio::time::timeout;
+
+    fn config(up_threshold: u32, down_threshold: u32) -> PingerConfig {
+        let id: SipUri = "sip:pinger@example.com".parse().unwrap();
+
+        PingerConfig {
+            up_threshold,
+            down_threshold,
+            ..PingerConfig::new(NameAddr::uri(id))
+        }
+    }
+
+    #[test]
+    fn up_after_threshold() {
+        let config = config(2, 3);
+        let mut status = PeerStatus::new();
+
+        assert_eq!(status.record(true, &config), None);
+        assert_eq!(status.state, PeerState::Unknown);
+        assert_eq!(status.record(true, &config), Some(PeerState::Up));
+        assert_eq!(status.record(true, &config), None);
+        assert_eq!(status.state, PeerState::Up);
+    }
+
+    #[test]
+    fn down_after_threshold() {
+        let config = config(1, 3);
+        let mut status = PeerStatus::new();
+
+        assert_eq!(status.record(true, &config), Some(PeerState::Up));
+        assert_eq!(status.record(false, &config), None);
+        assert_eq!(status.record(false, &config), None);
+        assert_eq!(status.state, PeerState::Up);
+        assert_eq!(status.record(false, &config), Some(PeerState::Down));
+        assert_eq!(status.record(false, &config), None);
+        assert_eq!(status.state, PeerState::Down);
+    }
+
+    #[test]
+    fn counters_reset() {
+        let config = config(2, 2);
+        let mut status = PeerStatus::new();
+
+        // Alternating results never reach a threshold
+        for _ in 0..4 {
+            assert_eq!(status.record(false, &config), None);
+            assert_eq!(status.record(true, &config), None);
+        }
+
+        assert_eq!(status.state, PeerState::Unknown);
+
+        assert_eq!(status.record(true, &config), Some(PeerState::Up));
+        assert_eq!(status.record(false, &config), None);
+        assert_eq!(status.record(false, &config), Some(PeerState::Down));
+        assert_eq!(status.record(true, &config), None);
+        assert_eq!(status.record(true, &config), Some(PeerState::Up));
+    }
+
+    #[test]
+    fn alive_codes() {
+        assert!(is_alive(Code::OK));
+        assert!(is_alive(Code::NOT_FOUND));
+        assert!(is_alive(Code::METHOD_NOT_ALLOWED));
+        assert!(!is_alive(Code::REQUEST_TIMEOUT));
+        assert!(!is_alive(Code::SERVICE_UNAVAILABLE));
+    }
+
+    #[tokio::test]
+    async fn peer_up_after_ping() {
+        let mut builder = Endpoint::builder();
+        builder.add_layer(OptionsLayer::default());
+        let peer_addr = bind_loopback(&mut builder).await;
+        let _peer = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let pinger_addr = bind_loopback(&mut builder).await;
+        let endpoint = builder.build();
+
+        let config = PingerConfig {
+            interval: Duration::from_millis(50),
+            ..PingerConfig::new(NameAddr::uri(uri("pinger", pinger_addr)))
+        };
+
+        let (pinger, mut events) =
+            OptionsPinger::start(endpoint, config, vec![Box::new(uri("peer", peer_addr))]);
+
+        assert_eq!(pinger.state(0), Some(PeerState::Unknown));
+
+        let event = timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(event.index, 0);
+        assert_eq!(event.state, PeerState::Up);
+        assert!(event.peer.compare(&uri("peer", peer_addr)));
+        assert_eq!(pinger.state(0), Some(PeerState::Up));
+    }
+}
@@ -0,0 +1,436 @@
+//! Asserted identity (RFC 3325) and privacy (RFC 3323)
+//!
+//! The [`TrustDomainLayer`] keeps network asserted identities inside the trust domain
+//! and applies the privacy requested by the user to messages leaving it.
+
+use sip_core::transport::OutgoingResponse;
+use sip_core::{Endpoint, IncomingRequest, Layer, MayTake, Request};
+use sip_types::header::typed::{
+    Contact, From, PAssertedIdentity, PPreferredIdentity, Privacy, PrivacyValue, Route,
+};
+use sip_types::host::{Host, HostPort};
+use sip_types::uri::sip::{SipUri, UserPart};
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Headers, Name};
+use std::net::{IpAddr, SocketAddr};
+
+/// Headers removed from messages leaving the trust domain when `Privacy: header` is requested
+const IDENTIFYING_HEADERS: [Name; 8] = [
+    Name::CALL_INFO,
+    Name::IN_REPLY_TO,
+    Name::ORGANIZATION,
+    Name::REPLY_TO,
+    Name::SERVER,
+    Name::SUBJECT,
+    Name::USER_AGENT,
+    Name::WARNING,
+];
+
+/// Create the anonymous identity recommended by RFC 3323 section 4.1.1.3
+pub fn anonymous_identity() -> NameAddr {
+    let mut uri = SipUri::new(HostPort::host_name("anonymous.invalid"));
+    uri.user_part = UserPart::User("anonymous".into());
+
+    NameAddr::new("Anonymous", uri)
+}
+
+/// Layer which enforces the boundary of a trust domain
+///
+/// Incoming requests from peers outside the trust domain have their `P-Asserted-Identity`
+/// headers removed. Outgoing messages must be passed through [`TrustDomainLayer::prepare_request`]
+/// or [`TrustDomainLayer::prepare_response`] before sending them.
+///
+/// Must be added to the endpoint before any layer that inspects the asserted identity.
+pub struct TrustDomainLayer {
+    trusted: Vec<Host>,
+}
+
+impl TrustDomainLayer {
+    /// Create a trust domain consisting of the given hosts
+    ///
+    /// Incoming requests are matched by their source address, so only IP addresses
+    /// can be used to trust incoming requests.
+    pub fn new(trusted: Vec<Host>) -> Self {
+        Self { trusted }
+    }
+
+    /// Returns if a message received from the address originates from inside the trust domain
+    pub fn is_trusted_source(&self, source: SocketAddr) -> bool {
+        self.trusted.iter().any(|host| match (host, source.ip()) {
+            (Host::IP4(trusted), IpAddr::V4(ip)) => *trusted == ip,
+            (Host::IP6(trusted), IpAddr::V6(ip)) => *trusted == ip,
+            _ => false,
+        })
+    }
+
+    /// Returns if the host of the URI is inside the trust domain
+    pub fn is_trusted_uri(&self, uri: &dyn Uri) -> bool {
+        match uri.downcast_ref::<SipUri>() {
+            Some(sip_uri) => self.trusted.contains(&sip_uri.host_port.host),
+            None => false,
+        }
+    }
+
+    /// Prepare a request before sending it
+    ///
+    /// The next hop is determined by the topmost Route header or the request URI.
+    /// If it is trusted the `asserted_identity` is inserted (unless the request already
+    /// contains one) in place of any `P-Preferred-Identity`, otherwise the privacy
+    /// requested by the `Privacy` header is applied.
+    pub fn prepare_request(&self, request: &mut Request, asserted_identity: Option<NameAddr>) {
+        let trusted = match request.headers.get::<Vec<Route>>() {
+            Ok(routes) if !routes.is_empty() => self.is_trusted_uri(&*routes[0].uri.uri),
+            _ => self.is_trusted_uri(&*request.line.uri),
+        };
+
+        if trusted {
+            insert_asserted_identity(&mut request.headers, asserted_identity);
+        } else {
+            apply_privacy(&mut request.headers);
+        }
+    }
+
+    /// Prepare a response to the `request` before sending it
+    ///
+    /// Applies the same rules as [`TrustDomainLayer::prepare_request`], using the source of the request as next hop.
+    pub fn prepare_response(
+        &self,
+        request: &IncomingRequest,
+        response: &mut OutgoingResponse,
+        asserted_identity: Option<NameAddr>,
+    ) {
+        if self.is_trusted_source(request.tp_info.source) {
+            insert_asserted_identity(&mut response.msg.headers, asserted_identity);
+        } else {
+            apply_privacy(&mut response.msg.headers);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Layer for TrustDomainLayer {
+    fn name(&self) -> &'static str {
+        "trust-domain"
+    }
+
+    async fn receive(&self, _: &Endpoint, mut request: MayTake<'_, IncomingRequest>) {
+        if self.is_trusted_source(request.tp_info.source) {
+            return;
+        }
+
+        if request.headers.remove_type::<PAssertedIdentity>().is_some() {
+            log::debug!(
+                "removed P-Asserted-Identity received from untrusted peer {}",
+                request.tp_info.source
+            );
+        }
+    }
+}
+
+fn insert_asserted_identity(headers: &mut Headers, asserted_identity: Option<NameAddr>) {
+    if let Some(asserted_identity) = asserted_identity {
+        if headers.try_get::<PAssertedIdentity>().is_none() {
+            headers.insert_type(&PAssertedIdentity(asserted_identity));
+        }
+    }
+
+    if headers.try_get::<PAssertedIdentity>().is_some() {
+        headers.remove_type::<PPreferredIdentity>();
+    }
+}
+
+/// Apply the privacy requested in the `Privacy` header to a message leaving the trust domain
+fn apply_privacy(headers: &mut Headers) {
+    // Identities are only meaningful inside the trust domain
+    headers.remove_type::<PPreferredIdentity>();
+
+    let privacy = match headers.get::<Privacy>() {
+        Ok(privacy) => privacy,
+        Err(_) => return,
+    };
+
+    if privacy.contains(&PrivacyValue::None) {
+        return;
+    }
+
+    if privacy.contains(&PrivacyValue::Id) {
+        headers.remove_type::<PAssertedIdentity>();
+    }
+
+    if privacy.contains(&PrivacyValue::User) {
+        let _ = headers.edit(|from: &mut From| from.uri = anonymous_identity());
+    }
+
+    if privacy.contains(&PrivacyValue::Header) {
+        for name in &IDENTIFYING_HEADERS {
+            headers.remove(name);
+        }
+
+        let _ = headers.edit(|contact: &mut Contact| {
+            contact.uri.name = None;
+
+            if let Some(sip_uri) = contact.uri.uri.downcast_mut::<SipUri>() {
+                sip_uri.user_part = UserPart::Empty;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{bind_loopback, uri};
+    use sip_core::LayerKey;
+    use sip_types::header::typed::{CSeq, CallID, To};
+    use sip_types::print::{AppendCtx, Print};
+    use sip_types::{Code, Method};
+    use std::net::Ipv4Addr;
+    use tokio::sync::mpsc;
+
+    fn trust_domain() -> TrustDomainLayer {
+        TrustDomainLayer::new(vec![Host::Name("proxy.trusted.example.com".into())])
+    }
+
+    fn request(privacy: &str, route: Option<&str>) -> Request {
+        let uri: SipUri = "sip:bob@example.com".parse().unwrap();
+        let mut request = Request::new(Method::INVITE, uri);
+
+        if let Some(route) = route {
+            request.headers.insert(Name::ROUTE, route);
+        }
+
+        request
+            .headers
+            .insert(Name::FROM, "Alice <sip:alice@example.com>;tag=1234");
+        request
+            .headers
+            .insert(Name::CONTACT, "Alice <sip:alice@192.0.2.1>");
+        request.headers.insert(Name::SUBJECT, "Lunch");
+        request.headers.insert(Name::USER_AGENT, "ezk");
+        request
+            .headers
+            .insert(Name::P_ASSERTED_IDENTITY, "<sip:alice@example.com>");
+        request
+            .headers
+            .insert(Name::P_PREFERRED_IDENTITY, "<sip:alice@example.com>");
+        request.headers.insert(Name::PRIVACY, privacy);
+
+        request
+    }
+
+    fn has(headers: &Headers, name: Name) -> bool {
+        headers.iter().any(|(n, _)| *n == name)
+    }
+
+    fn print<P: Print>(p: &P) -> String {
+        p.default_print_ctx().to_string()
+    }
+
+    #[test]
+    fn untrusted_privacy_id() {
+        let mut request = request("id", None);
+        trust_domain().prepare_request(&mut request, None);
+
+        assert!(!has(&request.headers, Name::P_ASSERTED_IDENTITY));
+        assert!(!has(&request.headers, Name::P_PREFERRED_IDENTITY));
+        assert!(has(&request.headers, Name::SUBJECT));
+
+        let from: From = request.headers.get().unwrap();
+        assert_eq!(print(&from.uri.uri), "sip:alice@example.com");
+    }
+
+    #[test]
+    fn untrusted_privacy_header() {
+        let mut request = request("header", None);
+        trust_domain().prepare_request(&mut request, None);
+
+        assert!(!has(&request.headers, Name::SUBJECT));
+        assert!(!has(&request.headers, Name::USER_AGENT));
+        assert!(has(&request.headers, Name::P_ASSERTED_IDENTITY));
+
+        let contact: Contact = request.headers.get().unwrap();
+        assert!(contact.uri.name.is_none());
+        assert_eq!(print(&contact.uri.uri), "sip:192.0.2.1");
+    }
+
+    #[test]
+    fn untrusted_privacy_user() {
+        let mut request = request("user", None);
+        trust_domain().prepare_request(&mut request, None);
+
+        let from: From = request.headers.get().unwrap();
+        assert_eq!(from.uri.name.as_deref(), Some("Anonymous"));
+        assert_eq!(print(&from.uri.uri), "sip:anonymous@anonymous.invalid");
+        assert_eq!(from.tag.as_deref(), Some("1234"));
+
+        assert!(has(&request.headers, Name::SUBJECT));
+        assert!(has(&request.headers, Name::P_ASSERTED_IDENTITY));
+    }
+
+    #[test]
+    fn untrusted_privacy_none() {
+        let mut request = request("none", None);
+        trust_domain().prepare_request(&mut request, None);
+
+        assert!(has(&request.headers, Name::P_ASSERTED_IDENTITY));
+        assert!(has(&request.headers, Name::SUBJECT));
+        assert!(!has(&request.headers, Name::P_PREFERRED_IDENTITY));
+
+        let from: From = request.headers.get().unwrap();
+        assert_eq!(print(&from.uri.uri), "sip:alice@example.com");
+    }
+
+    #[test]
+    fn untrusted_multiple_privacy_values() {
+        let mut request = request("id;header;user", None);
+        trust_domain().prepare_request(&mut request, None);
+
+        assert!(!has(&request.headers, Name::P_ASSERTED_IDENTITY));
+        assert!(!has(&request.headers, Name::SUBJECT));
+
+        let from: From = request.headers.get().unwrap();
+        assert_eq!(print(&from.uri.uri), "sip:anonymous@anonymous.invalid");
+    }
+
+    #[test]
+    fn trusted_route_keeps_identity() {
+        let mut request = request("id;header;user", Some("<sip:proxy.trusted.example.com;lr>"));
+        trust_domain().prepare_request(&mut request, None);
+
+        assert!(has(&request.headers, Name::P_ASSERTED_IDENTITY));
+        assert!(has(&request.headers, Name::SUBJECT));
+        assert!(!has(&request.headers, Name::P_PREFERRED_IDENTITY));
+
+        let from: From = request.headers.get().unwrap();
+        assert_eq!(print(&from.uri.uri), "sip:alice@example.com");
+    }
+
+    #[test]
+    fn trusted_request_uri_inserts_identity() {
+        let uri: SipUri = "sip:bob@proxy.trusted.example.com".parse().unwrap();
+        let mut request = Request::new(Method::INVITE, uri);
+        request
+            .headers
+            .insert(Name::P_PREFERRED_IDENTITY, "<sip:alice@example.com>");
+
+        let identity: SipUri = "sip:alice@trusted.example.com".parse().unwrap();
+        trust_domain().prepare_request(&mut request, Some(NameAddr::uri(identity)));
+
+        let asserted: PAssertedIdentity = request.headers.get().unwrap();
+        assert_eq!(print(&asserted.0.uri), "sip:alice@trusted.example.com");
+        assert!(!has(&request.headers, Name::P_PREFERRED_IDENTITY));
+    }
+
+    #[test]
+    fn untrusted_route_before_trusted_request_uri() {
+        let uri: SipUri = "sip:bob@proxy.trusted.example.com".parse().unwrap();
+        let mut request = Request::new(Method::INVITE, uri);
+        request
+            .headers
+            .insert(Name::ROUTE, "<sip:proxy.example.com;lr>");
+        request
+            .headers
+            .insert(Name::P_ASSERTED_IDENTITY, "<sip:alice@example.com>");
+        request.headers.insert(Name::PRIVACY, "id");
+
+        let identity: SipUri = "sip:alice@trusted.example.com".parse().unwrap();
+        trust_domain().prepare_request(&mut request, Some(NameAddr::uri(identity)));
+
+        assert!(!has(&request.headers, Name::P_ASSERTED_IDENTITY));
+    }
+
+    /// Answers all requests with a response requesting `Privacy: id`
+    struct Responder {
+        trust_domain: LayerKey<TrustDomainLayer>,
+        /// Reports if the request still contained its `P-Asserted-Identity`
+        asserted: mpsc::UnboundedSender<bool>,
+    }
+
+    #[async_trait::async_trait]
+    impl Layer for Responder {
+        fn name(&self) -> &'static str {
+            "test-responder"
+        }
+
+        async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+            let request = request.take();
+
+            let asserted = request.headers.try_get::<PAssertedIdentity>().is_some();
+            self.asserted.send(asserted).unwrap();
+
+            let mut response = endpoint
+                .create_response(&request, Code::OK, None)
+                .await
+                .unwrap();
+            response
+                .msg
+                .headers
+                .insert(Name::P_ASSERTED_IDENTITY, "<sip:bob@example.com>");
+            response.msg.headers.insert(Name::PRIVACY, "id");
+
+            endpoint[self.trust_domain].prepare_response(&request, &mut response, None);
+
+            endpoint
+                .create_server_tsx(&request)
+                .respond(response)
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Send an OPTIONS request with `P-Asserted-Identity` to an endpoint with the given trust domain
+    ///
+    /// Returns if the identity was kept in the request and in the response.
+    async fn exchange(trusted: Vec<Host>) -> (bool, bool) {
+        let (asserted, mut asserted_rx) = mpsc::unbounded_channel();
+
+        let mut builder = Endpoint::builder();
+        let server_addr = bind_loopback(&mut builder).await;
+        let trust_domain = builder.add_layer(TrustDomainLayer::new(trusted));
+        builder.add_layer(Responder {
+            trust_domain,
+            asserted,
+        });
+        let _server = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let client_addr = bind_loopback(&mut builder).await;
+        let client = builder.build();
+
+        let mut request = Request::new(Method::OPTIONS, uri("bob", server_addr));
+        request.headers.insert_type(&From::new(
+            NameAddr::uri(uri("alice", client_addr)),
+            Some("1234".into()),
+        ));
+        request
+            .headers
+            .insert_type(&To::new(NameAddr::uri(uri("bob", server_addr)), None));
+        request.headers.insert_type(&CallID::new("privacy-test"));
+        request.headers.insert_type(&CSeq::new(1, Method::OPTIONS));
+        request
+            .headers
+            .insert(Name::P_ASSERTED_IDENTITY, "<sip:alice@example.com>");
+
+        let transaction = client.send_request(request).await.unwrap();
+        let response = transaction.receive_final().await.unwrap();
+        assert_eq!(response.line.code, Code::OK);
+
+        (
+            asserted_rx.recv().await.unwrap(),
+            has(&response.headers, Name::P_ASSERTED_IDENTITY),
+        )
+    }
+
+    #[tokio::test]
+    async fn untrusted_peer() {
+        assert_eq!(exchange(vec![]).await, (false, false));
+    }
+
+    #[tokio::test]
+    async fn trusted_peer() {
+        assert_eq!(
+            exchange(vec![Host::IP4(Ipv4Addr::LOCALHOST)]).await,
+            (true, true)
+        );
+    }
+}
@@ -0,0 +1,624 @@
+use crate::util::random_string;
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use parking_lot as pl;
+use sip_core::{Endpoint, EndpointBuilder, IncomingRequest, Layer, MayTake, Result};
+use sip_types::header::typed::{
+    AllowEvents, ContentType, Event, Expires, MinExpires, SipETag, SipIfMatch,
+};
+use sip_types::print::{AppendCtx, PrintCtx, UriContext};
+use sip_types::{Code, Method};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep_until, Instant};
+
+/// State published for a resource, identified by its entity-tag
+#[derive(Debug, Clone)]
+pub struct PublishedState {
+    /// Request-URI of the PUBLISH request
+    pub resource: BytesStr,
+
+    /// Event package of the state
+    pub event: BytesStr,
+
+    pub etag: BytesStr,
+
+    pub content_type: Option<ContentType>,
+    pub body: Bytes,
+
+    pub expires_at: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateChangeKind {
+    /// New state has been published
+    Published,
+
+    /// Published state has been replaced
+    Modified,
+
+    /// Published state has been refreshed and got a new entity-tag
+    Refreshed,
+
+    /// Published state has been removed or has expired
+    Removed,
+}
+
+/// Change to the state stored in the [`EscLayer`]
+#[derive(Debug, Clone)]
+pub struct StateChange {
+    pub kind: StateChangeKind,
+    pub state: PublishedState,
+
+    /// The entity-tag the state had before the change
+    pub previous_etag: Option<BytesStr>,
+}
+
+/// Event state compositor which accepts PUBLISH requests (RFC 3903)
+///
+/// Published state is stored by its entity-tag and can be queried using [`EscLayer::states`].
+/// Changes to the stored state are broadcast to receivers created with [`EscLayer::subscribe`].
+///
+/// Once the endpoint is built, expired state is removed when it expires, even if it is never queried.
+pub struct EscLayer {
+    /// Event packages accepted by the layer
+    packages: Vec<BytesStr>,
+
+    /// Publications with a shorter expiry are rejected
+    pub min_expires: u32,
+
+    /// Publications with a longer expiry are granted this expiry
+    pub max_expires: u32,
+
+    store: Arc<Store>,
+
+    /// Task removing expired state, spawned when the endpoint is built
+    expiry_task: Option<JoinHandle<()>>,
+}
+
+impl Drop for EscLayer {
+    fn drop(&mut self) {
+        if let Some(task) = &self.expiry_task {
+            task.abort();
+        }
+    }
+}
+
+/// Published state shared between the [`EscLayer`] and its expiry task
+struct Store {
+    /// Published state keyed by the entity-tag
+    states: pl::Mutex<HashMap<BytesStr, PublishedState>>,
+
+    changes: broadcast::Sender<StateChange>,
+
+    /// Wakes the expiry task when new state has been stored
+    stored: Notify,
+}
+
+impl Store {
+    /// Remove all expired state, returns when the next remaining state expires
+    fn remove_expired(&self, states: &mut HashMap<BytesStr, PublishedState>) -> Option<Instant> {
+        let now = Instant::now();
+
+        let expired: Vec<BytesStr> = states
+            .values()
+            .filter(|state| state.expires_at <= now)
+            .map(|state| state.etag.clone())
+            .collect();
+
+        for etag in expired {
+            if let Some(state) = states.remove(&etag) {
+                self.notify(StateChangeKind::Removed, state, Some(etag));
+            }
+        }
+
+        states.values().map(|state| state.expires_at).min()
+    }
+
+    fn notify(
+        &self,
+        kind: StateChangeKind,
+        state: PublishedState,
+        previous_etag: Option<BytesStr>,
+    ) {
+        // Error only means that there are currently no receivers
+        let _ = self.changes.send(StateChange {
+            kind,
+            state,
+            previous_etag,
+        });
+    }
+}
+
+async fn expiry_task(store: Arc<Store>) {
+    loop {
+        let next_expiry = store.remove_expired(&mut store.states.lock());
+
+        match next_expiry {
+            Some(next_expiry) => {
+                tokio::select! {
+                    _ = sleep_until(next_expiry) => {}
+                    _ = store.stored.notified() => {}
+                }
+            }
+            None => store.stored.notified().await,
+        }
+    }
+}
+
+impl EscLayer {
+    /// Create a layer which accepts publications for the given event packages
+    pub fn new<I, P>(packages: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<BytesStr>,
+    {
+        let (changes, _) = broadcast::channel(32);
+
+        Self {
+            packages: packages.into_iter().map(Into::into).collect(),
+            min_expires: 60,
+            max_expires: 3600,
+            store: Arc::new(Store {
+                states: Default::default(),
+                changes,
+                stored: Notify::new(),
+            }),
+            expiry_task: None,
+        }
+    }
+
+    /// Returns a receiver for all changes to the published state
+    pub fn subscribe(&self) -> broadcast::Receiver<StateChange> {
+        self.store.changes.subscribe()
+    }
+
+    /// Returns all unexpired state published for the resource and event package
+    pub fn states(&self, resource: &str, event: &str) -> Vec<PublishedState> {
+        let mut states = self.store.states.lock();
+
+        self.store.remove_expired(&mut states);
+
+        states
+            .values()
+            .filter(|state| state.resource == resource && state.event == event)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the unexpired state with the given entity-tag
+    pub fn state(&self, etag: &BytesStr) -> Option<PublishedState> {
+        let mut states = self.store.states.lock();
+
+        self.store.remove_expired(&mut states);
+
+        states.get(etag).cloned()
+    }
+
+    async fn handle_publish(&self, endpoint: &Endpoint, request: IncomingRequest) -> Result<()> {
+        let transaction = endpoint.create_server_tsx(&request);
+
+        let event = match request.headers.get::<Event>() {
+            Ok(event) if self.packages.contains(&event.package) => event,
+            _ => {
+                let mut response = endpoint
+                    .create_response(&request, Code::BAD_EVENT, None)
+                    .await?;
+
+                let allow_events: Vec<AllowEvents> = self
+                    .packages
+                    .iter()
+                    .cloned()
+                    .map(AllowEvents::from)
+                    .collect();
+
+                response.msg.headers.insert_type(&allow_events);
+
+                return transaction.respond(response).await;
+            }
+        };
+
+        let expires = request
+            .headers
+            .get::<Expires>()
+            .map(|expires| expires.0)
+            .unwrap_or(self.max_expires);
+
+        if expires != 0 && expires < self.min_expires {
+            let mut response = endpoint
+                .create_response(&request, Code::INTERVAL_TOO_BRIEF, None)
+                .await?;
+
+            response
+                .msg
+                .headers
+                .insert_type(&MinExpires(self.min_expires));
+
+            return transaction.respond(response).await;
+        }
+
+        let expires = expires.min(self.max_expires);
+
+        let resource: BytesStr = request
+            .line
+            .uri
+            .print_ctx(PrintCtx {
+                method: Some(&request.line.method),
+                uri: Some(UriContext::ReqUri),
+            })
+            .to_string()
+            .into();
+
+        let content_type = request.headers.get::<ContentType>().ok();
+
+        let result = match request.headers.get::<SipIfMatch>() {
+            Ok(if_match) => self.update_state(
+                &resource,
+                &event,
+                if_match.0,
+                expires,
+                content_type,
+                request.body.clone(),
+            ),
+            Err(_) if request.body.is_empty() => Err(Code::BAD_REQUEST),
+            Err(_) => Ok(self.insert_state(
+                resource,
+                event.package,
+                expires,
+                content_type,
+                request.body.clone(),
+            )),
+        };
+
+        let response = match result {
+            Ok(etag) => {
+                let mut response = endpoint.create_response(&request, Code::OK, None).await?;
+
+                if let Some(etag) = etag {
+                    response.msg.headers.insert_type(&SipETag(etag));
+                }
+
+                response.msg.headers.insert_type(&Expires(expires));
+
+                response
+            }
+            Err(code) => endpoint.create_response(&request, code, None).await?,
+        };
+
+        transaction.respond(response).await
+    }
+
+    /// Store new state, returns the entity-tag assigned to it
+    fn insert_state(
+        &self,
+        resource: BytesStr,
+        event: BytesStr,
+        expires: u32,
+        content_type: Option<ContentType>,
+        body: Bytes,
+    ) -> Option<BytesStr> {
+        if expires == 0 {
+            // Nothing to store, the state would expire immediately
+            return None;
+        }
+
+        let state = PublishedState {
+            resource,
+            event,
+            etag: random_string(),
+            content_type,
+            body,
+            expires_at: Instant::now() + Duration::from_secs(expires.into()),
+        };
+
+        self.store
+            .states
+            .lock()
+            .insert(state.etag.clone(), state.clone());
+        self.store.stored.notify_one();
+
+        let etag = state.etag.clone();
+
+        self.store.notify(StateChangeKind::Published, state, None);
+
+        Some(etag)
+    }
+
+    /// Refresh, modify or remove the state identified by `etag`
+    ///
+    /// Returns the new entity-tag of the state, or `None` if it has been removed
+    fn update_state(
+        &self,
+        resource: &BytesStr,
+        event: &Event,
+        etag: BytesStr,
+        expires: u32,
+        content_type: Option<ContentType>,
+        body: Bytes,
+    ) -> Result<Option<BytesStr>, Code> {
+        let mut states = self.store.states.lock();
+
+        self.store.remove_expired(&mut states);
+
+        let matches = states
+            .get(&etag)
+            .map(|state| state.resource == *resource && state.event == event.package);
+
+        if matches != Some(true) {
+            return Err(Code::CONDITIONAL_REQUEST_FAILED);
+        }
+
+        // Unwrap is safe as the existence of the state has just been checked
+        let mut state = states.remove(&etag).unwrap();
+
+        if expires == 0 {
+            drop(states);
+
+            self.store
+                .notify(StateChangeKind::Removed, state, Some(etag));
+
+            return Ok(None);
+        }
+
+        let kind = if body.is_empty() {
+            StateChangeKind::Refreshed
+        } else {
+            state.content_type = content_type;
+            state.body = body;
+
+            StateChangeKind::Modified
+        };
+
+        // Every successful refresh or modification assigns a new entity-tag
+        state.etag = random_string();
+        state.expires_at = Instant::now() + Duration::from_secs(expires.into());
+
+        states.insert(state.etag.clone(), state.clone());
+        drop(states);
+        self.store.stored.notify_one();
+
+        let new_etag = state.etag.clone();
+
+        self.store.notify(kind, state, Some(etag));
+
+        Ok(Some(new_etag))
+    }
+}
+
+#[async_trait::async_trait]
+impl Layer for EscLayer {
+    fn name(&self) -> &'static str {
+        "esc"
+    }
+
+    fn init(&mut self, endpoint: &mut EndpointBuilder) {
+        endpoint.add_allow(Method::PUBLISH);
+
+        self.expiry_task = Some(tokio::spawn(expiry_task(self.store.clone())));
+    }
+
+    async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::PUBLISH {
+            return;
+        }
+
+        if let Err(e) = self.handle_publish(endpoint, request.take()).await {
+            log::warn!("Failed to handle PUBLISH request {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    const RESOURCE: &str = "sip:bob@example.com";
+
+    fn esc() -> (EscLayer, broadcast::Receiver<StateChange>) {
+        let esc = EscLayer::new(["presence"]);
+        let changes = esc.subscribe();
+
+        (esc, changes)
+    }
+
+    fn publish(esc: &EscLayer, body: &'static str) -> BytesStr {
+        esc.insert_state(
+            RESOURCE.into(),
+            "presence".into(),
+            60,
+            Some(ContentType::from_static("application/pidf+xml")),
+            Bytes::from_static(body.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn update(
+        esc: &EscLayer,
+        etag: &BytesStr,
+        expires: u32,
+        body: &'static str,
+    ) -> Result<Option<BytesStr>, Code> {
+        esc.update_state(
+            &RESOURCE.into(),
+            &Event::new("presence"),
+            etag.clone(),
+            expires,
+            None,
+            Bytes::from_static(body.as_bytes()),
+        )
+    }
+
+    #[tokio::test]
+    async fn create() {
+        let (esc, mut changes) = esc();
+
+        let etag = publish(&esc, "open");
+
+        let state = esc.state(&etag).unwrap();
+        assert_eq!(state.resource, RESOURCE);
+        assert_eq!(&state.body[..], b"open");
+        assert_eq!(esc.states(RESOURCE, "presence").len(), 1);
+        assert!(esc.states(RESOURCE, "dialog").is_empty());
+
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.kind, StateChangeKind::Published);
+        assert_eq!(change.previous_etag, None);
+    }
+
+    #[tokio::test]
+    async fn create_expired() {
+        let (esc, _changes) = esc();
+
+        let etag = esc.insert_state(RESOURCE.into(), "presence".into(), 0, None, Bytes::new());
+
+        assert_eq!(etag, None);
+        assert!(esc.states(RESOURCE, "presence").is_empty());
+    }
+
+    #[tokio::test]
+    async fn refresh() {
+        let (esc, mut changes) = esc();
+
+        let etag = publish(&esc, "open");
+        changes.try_recv().unwrap();
+
+        let new_etag = update(&esc, &etag, 60, "").unwrap().unwrap();
+
+        assert_ne!(etag, new_etag);
+        assert!(esc.state(&etag).is_none());
+        assert_eq!(&esc.state(&new_etag).unwrap().body[..], b"open");
+
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.kind, StateChangeKind::Refreshed);
+        assert_eq!(change.previous_etag, Some(etag));
+    }
+
+    #[tokio::test]
+    async fn modify() {
+        let (esc, mut changes) = esc();
+
+        let etag = publish(&esc, "open");
+        changes.try_recv().unwrap();
+
+        let new_etag = update(&esc, &etag, 60, "closed").unwrap().unwrap();
+
+        assert_eq!(&esc.state(&new_etag).unwrap().body[..], b"closed");
+        assert_eq!(esc.states(RESOURCE, "presence").len(), 1);
+
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.kind, StateChangeKind::Modified);
+    }
+
+    #[tokio::test]
+    async fn remove() {
+        let (esc, mut changes) = esc();
+
+        let etag = publish(&esc, "open");
+        changes.try_recv().unwrap();
+
+        assert_eq!(update(&esc, &etag, 0, ""), Ok(None));
+        assert!(esc.states(RESOURCE, "presence").is_empty());
+
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.kind, StateChangeKind::Removed);
+        assert_eq!(change.previous_etag, Some(etag.clone()));
+
+        // The entity-tag is no longer valid
+        assert_eq!(
+            update(&esc, &etag, 60, ""),
+            Err(Code::CONDITIONAL_REQUEST_FAILED)
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_etag() {
+        let (esc, _changes) = esc();
+
+        let etag = publish(&esc, "open");
+
+        assert_eq!(
+            update(&esc, &"unknown".into(), 60, ""),
+            Err(Code::CONDITIONAL_REQUEST_FAILED)
+        );
+
+        // Entity-tags are only valid for the resource and event package they were created for
+        assert_eq!(
+            esc.update_state(
+                &"sip:alice@example.com".into(),
+                &Event::new("presence"),
+                etag.clone(),
+                60,
+                None,
+                Bytes::new(),
+            ),
+            Err(Code::CONDITIONAL_REQUEST_FAILED)
+        );
+        assert_eq!(
+            esc.update_state(
+                &RESOURCE.into(),
+                &Event::new("dialog"),
+                etag.clone(),
+                60,
+                None,
+                Bytes::new(),
+            ),
+            Err(Code::CONDITIONAL_REQUEST_FAILED)
+        );
+
+        assert!(esc.state(&etag).is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn expiry() {
+        let (esc, mut changes) = esc();
+
+        let etag = publish(&esc, "open");
+        changes.try_recv().unwrap();
+
+        tokio::time::advance(Duration::from_secs(59)).await;
+        let etag = update(&esc, &etag, 60, "").unwrap().unwrap();
+        changes.try_recv().unwrap();
+
+        tokio::time::advance(Duration::from_secs(59)).await;
+        assert!(esc.state(&etag).is_some());
+        assert_eq!(changes.try_recv().unwrap_err(), TryRecvError::Empty);
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(esc.states(RESOURCE, "presence").is_empty());
+
+        let change = changes.try_recv().unwrap();
+        assert_eq!(change.kind, StateChangeKind::Removed);
+
+        assert_eq!(
+            update(&esc, &etag, 60, ""),
+            Err(Code::CONDITIONAL_REQUEST_FAILED)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn expiry_without_query() {
+        let mut builder = Endpoint::builder();
+        let esc = builder.add_layer(EscLayer::new(["presence"]));
+        let endpoint = builder.build();
+
+        let mut changes = endpoint[esc].subscribe();
+
+        let etag = publish(&endpoint[esc], "open");
+        assert_eq!(
+            changes.recv().await.unwrap().kind,
+            StateChangeKind::Published
+        );
+
+        let start = Instant::now();
+
+        // The expiry task removes the state without it being queried
+        let change = changes.recv().await.unwrap();
+        assert_eq!(change.kind, StateChangeKind::Removed);
+        assert_eq!(change.previous_etag, Some(etag));
+        assert_eq!(start.elapsed(), Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,394 @@
+//! Event state publication using PUBLISH requests (RFC 3903)
+
+use crate::auth::{send_request_with_auth, DigestAuthenticator};
+use crate::util::{create_refresh_interval, random_sequence_number, random_string};
+use bytes::Bytes;
+use bytesstr::BytesStr;
+use sip_core::transaction::TsxResponse;
+use sip_core::{Endpoint, Error, Request, Result};
+use sip_types::header::typed::{
+    CSeq, CallID, ContentType, Event, Expires, From, MinExpires, Route, SipETag, SipIfMatch, To,
+};
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::{Code, CodeKind, Method};
+use tokio::time::Interval;
+
+mod esc;
+
+pub use esc::{EscLayer, PublishedState, StateChange, StateChangeKind};
+
+/// Publishes the state of a single event package for a resource
+///
+/// Keeps track of the entity-tag assigned by the event state compositor and the last
+/// published state, to refresh, modify or remove the publication.
+pub struct Publication {
+    target: Box<dyn Uri>,
+
+    /// Preloaded route set of every PUBLISH
+    route_set: Vec<Route>,
+
+    from: From,
+    to: To,
+    call_id: CallID,
+    cseq: u32,
+
+    event: Event,
+
+    /// Requested amount of seconds until the publication expires
+    expires: u32,
+
+    /// Amount of seconds granted by the event state compositor
+    granted_expires: u32,
+
+    /// Refresh interval, is set to `granted_expires - 10`
+    refresh_interval: Interval,
+
+    /// Entity-tag of the published state, `None` if nothing is published
+    etag: Option<BytesStr>,
+
+    /// Last published state, used to republish the state when the event state compositor lost it
+    state: Option<(ContentType, Bytes)>,
+
+    /// Set when `state` has not been published yet
+    modified: bool,
+
+    /// Used to authenticate requests when challenged
+    pub authenticator: DigestAuthenticator,
+}
+
+impl Publication {
+    /// Create a publication of the `event` state of the resource `target`, published as `id`
+    pub fn new(id: NameAddr, target: Box<dyn Uri>, event: Event) -> Self {
+        let expires = 3600;
+
+        Self {
+            to: To::new(NameAddr::uri(target.clone()), None),
+            target,
+            route_set: vec![],
+            from: From::new(id, Some(random_string())),
+            call_id: CallID::new(random_string()),
+            cseq: random_sequence_number(),
+            event,
+            expires,
+            granted_expires: expires,
+            refresh_interval: create_refresh_interval(expires),
+            etag: None,
+            state: None,
+            modified: false,
+            authenticator: DigestAuthenticator::new(),
+        }
+    }
+
+    /// Set the amount of seconds requested for the publication to be valid
+    pub fn set_expires(&mut self, expires: u32) {
+        self.expires = expires;
+    }
+
+    /// Set the preloaded route set of all PUBLISH requests
+    pub fn set_route_set(&mut self, route_set: Vec<Route>) {
+        self.route_set = route_set;
+    }
+
+    /// Entity-tag of the currently published state
+    pub fn etag(&self) -> Option<&BytesStr> {
+        self.etag.as_ref()
+    }
+
+    /// Returns if any state is currently published
+    pub fn is_published(&self) -> bool {
+        self.etag.is_some()
+    }
+
+    /// Create a PUBLISH request
+    ///
+    /// - With `state` and no published state this is the initial publication
+    /// - With `state` and published state this is a modification
+    /// - Without `state` this is a refresh of the published state
+    ///
+    /// `remove` creates a request with `Expires: 0` which removes the published state.
+    pub fn create_publish(&mut self, state: Option<(ContentType, Bytes)>, remove: bool) -> Request {
+        let mut request = Request::new(Method::PUBLISH, self.target.clone());
+
+        self.cseq += 1;
+
+        request.headers.insert_type(&self.from);
+        request.headers.insert_type(&self.to);
+        request.headers.insert_type(&self.call_id);
+        request
+            .headers
+            .insert_type(&CSeq::new(self.cseq, Method::PUBLISH));
+        request.headers.insert_type(&self.route_set);
+        request.headers.insert_type(&self.event);
+
+        if let Some(etag) = &self.etag {
+            request.headers.insert_type(&SipIfMatch(etag.clone()));
+        }
+
+        let expires = if remove { 0 } else { self.expires };
+        request.headers.insert_type(&Expires(expires));
+
+        if let Some((content_type, body)) = state {
+            request.headers.insert_type(&content_type);
+            request.body = body;
+        }
+
+        request
+    }
+
+    /// Read the entity-tag and granted expiry from a successful response
+    pub fn receive_success_response(&mut self, response: &TsxResponse) {
+        assert_eq!(response.line.code.kind(), CodeKind::Success);
+
+        let expires = response
+            .headers
+            .get::<Expires>()
+            .map(|expires| expires.0)
+            .unwrap_or(self.expires);
+
+        if expires == 0 {
+            self.etag = None;
+            self.state = None;
+            return;
+        }
+
+        if let Ok(etag) = response.headers.get::<SipETag>() {
+            self.etag = Some(etag.0);
+        }
+
+        if self.granted_expires != expires {
+            self.refresh_interval = create_refresh_interval(expires);
+            self.granted_expires = expires;
+        }
+    }
+
+    /// Publish new state, either initially or as modification of the published state
+    ///
+    /// Returns an error if the publication has been rejected.
+    pub async fn publish(
+        &mut self,
+        endpoint: &Endpoint,
+        content_type: ContentType,
+        body: Bytes,
+    ) -> Result<TsxResponse> {
+        self.state = Some((content_type, body));
+        self.modified = true;
+
+        self.send(endpoint, false).await
+    }
+
+    /// Refresh the published state without modifying it
+    ///
+    /// If the event state compositor no longer knows the published state,
+    /// the last published state is published again.
+    pub async fn refresh(&mut self, endpoint: &Endpoint) -> Result<TsxResponse> {
+        self.send(endpoint, false).await
+    }
+
+    /// Remove the published state
+    pub async fn remove(&mut self, endpoint: &Endpoint) -> Result<TsxResponse> {
+        self.send(endpoint, true).await
+    }
+
+    /// Wait until the published state must be refreshed using [`Publication::refresh`]
+    pub async fn wait_for_expiry(&mut self) {
+        self.refresh_interval.tick().await;
+    }
+
+    async fn send(&mut self, endpoint: &Endpoint, remove: bool) -> Result<TsxResponse> {
+        loop {
+            // The state is only sent when it changed or is not published yet
+            let state = if remove || (self.etag.is_some() && !self.modified) {
+                None
+            } else {
+                self.state.clone()
+            };
+
+            if state.is_none() && self.etag.is_none() {
+                return Err(Error::new(Code::CONDITIONAL_REQUEST_FAILED));
+            }
+
+            let mut authenticator = std::mem::take(&mut self.authenticator);
+
+            let result = send_request_with_auth(endpoint, &mut authenticator, || {
+                self.create_publish(state.clone(), remove)
+            })
+            .await;
+
+            self.authenticator = authenticator;
+
+            let response = result?;
+
+            match response.line.code {
+                code if code.kind() == CodeKind::Success => {
+                    self.modified = false;
+                    self.receive_success_response(&response);
+
+                    if remove {
+                        self.etag = None;
+                        self.state = None;
+                    }
+
+                    return Ok(response);
+                }
+                Code::CONDITIONAL_REQUEST_FAILED if self.etag.is_some() && !remove => {
+                    // The event state compositor lost the published state, publish it again
+                    self.etag = None;
+                }
+                Code::INTERVAL_TOO_BRIEF => match response.headers.get::<MinExpires>() {
+                    Ok(min_expires) if min_expires.0 > self.expires => {
+                        self.expires = min_expires.0;
+                    }
+                    _ => return Err(Error::new(response.line.code)),
+                },
+                code => {
+                    if code == Code::CONDITIONAL_REQUEST_FAILED {
+                        self.etag = None;
+                    }
+
+                    return Err(Error::new(code));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::{bind_loopback, uri};
+    use sip_core::LayerKey;
+    use sip_types::print::AppendCtx;
+
+    struct Setup {
+        server: Endpoint,
+        client: Endpoint,
+        esc: LayerKey<EscLayer>,
+        target: BytesStr,
+        publication: Publication,
+    }
+
+    async fn setup() -> Setup {
+        let mut builder = Endpoint::builder();
+        let esc = builder.add_layer(EscLayer::new(["presence"]));
+        let server_addr = bind_loopback(&mut builder).await;
+        let server = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let client_addr = bind_loopback(&mut builder).await;
+        let client = builder.build();
+
+        let target = uri("bob", server_addr);
+
+        let publication = Publication::new(
+            NameAddr::uri(uri("bob", client_addr)),
+            Box::new(target.clone()),
+            Event::new("presence"),
+        );
+
+        Setup {
+            server,
+            client,
+            esc,
+            target: target.default_print_ctx().to_string().into(),
+            publication,
+        }
+    }
+
+    fn pidf(body: &'static str) -> (ContentType, Bytes) {
+        (
+            ContentType::from_static("application/pidf+xml"),
+            Bytes::from_static(body.as_bytes()),
+        )
+    }
+
+    #[tokio::test]
+    async fn publish_refresh_remove() {
+        let Setup {
+            server,
+            client,
+            esc,
+            target,
+            mut publication,
+        } = setup().await;
+
+        let (content_type, body) = pidf("open");
+        publication
+            .publish(&client, content_type, body)
+            .await
+            .unwrap();
+
+        let etag = publication.etag().unwrap().clone();
+        assert!(server[esc].state(&etag).is_some());
+
+        publication.refresh(&client).await.unwrap();
+
+        let refreshed_etag = publication.etag().unwrap().clone();
+        assert_ne!(etag, refreshed_etag);
+
+        let states = server[esc].states(&target, "presence");
+        assert_eq!(states.len(), 1);
+        assert_eq!(&states[0].body[..], b"open");
+
+        publication.remove(&client).await.unwrap();
+
+        assert!(!publication.is_published());
+        assert!(server[esc].states(&target, "presence").is_empty());
+    }
+
+    #[tokio::test]
+    async fn republish_after_conditional_request_failed() {
+        let Setup {
+            server,
+            client,
+            esc,
+            target,
+            mut publication,
+        } = setup().await;
+
+        let (content_type, body) = pidf("open");
+        publication
+            .publish(&client, content_type, body)
+            .await
+            .unwrap();
+
+        // Remove the state from the compositor using a second publication with the same entity-tag
+        let mut other = Publication::new(
+            publication.from.uri.clone(),
+            publication.target.clone(),
+            Event::new("presence"),
+        );
+        other.etag = publication.etag.clone();
+        other.remove(&client).await.unwrap();
+
+        assert!(server[esc].states(&target, "presence").is_empty());
+
+        // Refresh is rejected with 412, the last published state is published again
+        let response = publication.refresh(&client).await.unwrap();
+        assert_eq!(response.line.code, Code::OK);
+
+        let states = server[esc].states(&target, "presence");
+        assert_eq!(states.len(), 1);
+        assert_eq!(&states[0].body[..], b"open");
+        assert_eq!(publication.etag(), Some(&states[0].etag));
+    }
+
+    #[tokio::test]
+    async fn conditional_request_failed_without_state() {
+        let Setup {
+            client,
+            mut publication,
+            ..
+        } = setup().await;
+
+        // Nothing has been published, so nothing can be refreshed
+        let error = publication.refresh(&client).await.unwrap_err();
+        assert_eq!(error.status, Code::CONDITIONAL_REQUEST_FAILED);
+
+        // Unknown entity-tag and no state to publish again
+        publication.etag = Some("unknown".into());
+
+        let error = publication.refresh(&client).await.unwrap_err();
+        assert_eq!(error.status, Code::CONDITIONAL_REQUEST_FAILED);
+        assert!(!publication.is_published());
+    }
+}
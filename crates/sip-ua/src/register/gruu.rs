@@ -0,0 +1,173 @@
+//! Globally Routable User Agent URIs (GRUU, RFC 5627)
+//!
+//! Contains the helpers used by registrations and registrars to create and handle GRUUs.
+
+use bytesstr::BytesStr;
+use rand::{thread_rng, Rng};
+use sip_types::header::typed::{Contact, Supported};
+use sip_types::uri::sip::{SipUri, UserPart};
+use sip_types::Headers;
+use std::ops::Deref;
+
+/// Contact parameter containing the instance id of the user agent
+pub const INSTANCE_PARAM: &str = "+sip.instance";
+
+/// URI parameter identifying the instance in a GRUU
+pub const GR_PARAM: &str = "gr";
+
+/// Create a random instance id in the form of `urn:uuid:<uuid>`
+///
+/// The instance id must stay the same for a device across restarts,
+/// so it should be created once and stored.
+pub fn create_instance_id() -> BytesStr {
+    let mut bytes: [u8; 16] = thread_rng().gen();
+
+    // UUID version 4, variant RFC 4122
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    format!(
+        "urn:uuid:{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+    .into()
+}
+
+/// Returns the instance id of a contact, without the enclosing angle brackets
+pub fn instance_id(contact: &Contact) -> Option<&str> {
+    let instance = contact.params.get_val(INSTANCE_PARAM)?;
+
+    Some(instance.trim_start_matches('<').trim_end_matches('>'))
+}
+
+/// Returns if the message contains `Supported: gruu`
+pub fn supports_gruu(headers: &Headers) -> bool {
+    headers
+        .get::<Vec<Supported>>()
+        .unwrap_or_default()
+        .iter()
+        .any(|ext| ext.deref() == "gruu")
+}
+
+/// Create the public GRUU of an instance registered to the address-of-record `aor`
+///
+/// The public GRUU is the AOR with the `gr` parameter set to the instance id.
+pub fn create_pub_gruu(aor: &SipUri, instance_id: &str) -> SipUri {
+    let mut gruu = SipUri::new(aor.host_port.clone()).sips(aor.sips);
+    gruu.user_part = aor.user_part.clone();
+
+    gruu.uri_param_value(GR_PARAM, BytesStr::from(instance_id))
+}
+
+/// Create a new temporary GRUU for an instance registered to the address-of-record `aor`
+///
+/// The registrar must remember the mapping from the returned URI's user part to the binding,
+/// for as long as the registration exists.
+pub fn create_temp_gruu(aor: &SipUri) -> SipUri {
+    let user: String = thread_rng()
+        .sample_iter(rand::distributions::Alphanumeric)
+        .take(24)
+        .map(char::from)
+        .collect();
+
+    let mut gruu = SipUri::new(aor.host_port.clone()).sips(aor.sips);
+    gruu.user_part = UserPart::User(format!("tgruu.{}", user).into());
+
+    gruu.uri_param_key(GR_PARAM)
+}
+
+/// Add the `pub-gruu` and `temp-gruu` parameters to a contact of the response to a REGISTER request
+pub fn add_gruus(contact: &mut Contact, pub_gruu: &SipUri, temp_gruu: Option<&SipUri>) {
+    contact.params.push_or_edit("pub-gruu", print_uri(pub_gruu));
+
+    if let Some(temp_gruu) = temp_gruu {
+        contact
+            .params
+            .push_or_edit("temp-gruu", print_uri(temp_gruu));
+    }
+}
+
+fn print_uri(uri: &SipUri) -> BytesStr {
+    use sip_types::print::AppendCtx;
+
+    uri.default_print_ctx().to_string().into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sip_types::print::AppendCtx;
+    use sip_types::uri::NameAddr;
+
+    const INSTANCE_ID: &str = "urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6";
+
+    #[test]
+    fn instance_id_is_uuid_v4() {
+        for _ in 0..100 {
+            let instance_id = create_instance_id();
+            let uuid = instance_id.strip_prefix("urn:uuid:").unwrap();
+
+            let groups: Vec<&str> = uuid.split('-').collect();
+            let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+            assert_eq!(lengths, [8, 4, 4, 4, 12]);
+            assert!(uuid
+                .chars()
+                .all(|c| c == '-' || c.is_ascii_digit() || ('a'..='f').contains(&c)));
+
+            // Version nibble is 4, the variant bits are 10
+            assert!(groups[2].starts_with('4'));
+            assert!(matches!(groups[3].as_bytes()[0], b'8' | b'9' | b'a' | b'b'));
+        }
+
+        assert_ne!(create_instance_id(), create_instance_id());
+    }
+
+    #[test]
+    fn instance_id_of_contact() {
+        let uri: SipUri = "sip:alice@192.0.2.4".parse().unwrap();
+        let mut contact = Contact::new(NameAddr::uri(uri));
+        assert_eq!(instance_id(&contact), None);
+
+        contact
+            .params
+            .push_or_edit(INSTANCE_PARAM, format!("<{}>", INSTANCE_ID));
+        assert_eq!(instance_id(&contact), Some(INSTANCE_ID));
+    }
+
+    #[test]
+    fn pub_gruu() {
+        let aor: SipUri = "sips:alice@example.com".parse().unwrap();
+
+        let gruu = create_pub_gruu(&aor, INSTANCE_ID);
+        assert_eq!(
+            gruu.default_print_ctx().to_string(),
+            format!("sips:alice@example.com;gr={}", INSTANCE_ID)
+        );
+    }
+
+    #[test]
+    fn temp_gruu() {
+        let aor: SipUri = "sip:alice@example.com".parse().unwrap();
+
+        let gruu = create_temp_gruu(&aor);
+        let printed = gruu.default_print_ctx().to_string();
+
+        let user = printed
+            .strip_prefix("sip:")
+            .and_then(|uri| uri.strip_suffix("@example.com;gr"))
+            .unwrap();
+        let random = user.strip_prefix("tgruu.").unwrap();
+        assert_eq!(random.len(), 24);
+        assert!(random.chars().all(|c| c.is_ascii_alphanumeric()));
+
+        // Every temporary GRUU is different
+        let other = create_temp_gruu(&aor).default_print_ctx().to_string();
+        assert_ne!(other, printed);
+    }
+}
@@ -1,11 +1,20 @@
-use crate::util::{random_sequence_number, random_string};
+use crate::util::{create_refresh_interval, random_sequence_number, random_string};
+use bytesstr::BytesStr;
 use sip_core::transaction::TsxResponse;
+use sip_core::transport::TpKey;
 use sip_core::Request;
-use sip_types::header::typed::{CSeq, CallID, Contact, Expires, From, To};
+use sip_types::header::typed::{
+    CSeq, CallID, Contact, Expires, FlowTimer, From, Require, Route, Routing, ServiceRoute,
+    Supported, To,
+};
+use sip_types::uri::sip::SipUri;
 use sip_types::uri::{NameAddr, Uri};
 use sip_types::{CodeKind, Method};
-use std::time::Duration;
-use tokio::time::{interval_at, Instant, Interval};
+use tokio::time::Interval;
+
+pub mod gruu;
+pub mod outbound;
+pub mod path;
 
 pub struct Registration {
     registrar: Box<dyn Uri>,
@@ -22,6 +31,31 @@ pub struct Registration {
 
     /// Re-registration interval, is set to `expires - 10`
     register_interval: Interval,
+
+    /// Instance id sent as `+sip.instance` contact parameter
+    instance_id: Option<BytesStr>,
+
+    /// GRUUs assigned by the registrar
+    pub_gruu: Option<SipUri>,
+    temp_gruu: Option<SipUri>,
+
+    /// Registration id sent as `reg-id` contact parameter when using SIP Outbound
+    reg_id: Option<u32>,
+
+    /// Proxy the REGISTER is routed through, added as preloaded Route header
+    outbound_proxy: Option<Route>,
+
+    /// Connection oriented flow the registration has been established over
+    flow: Option<TpKey>,
+
+    /// Keep-alive interval of the flow in seconds, as requested by the registrar
+    flow_timer: Option<u32>,
+
+    /// Did the registrar confirm support for SIP Outbound (`Require: outbound`)
+    outbound: bool,
+
+    /// Service route learned from the last successful response (RFC 3608)
+    service_route: Vec<ServiceRoute>,
 }
 
 impl Registration {
@@ -37,7 +71,137 @@ impl Registration {
             contact: Contact::new(id),
 
             expires: duration_secs,
-            register_interval: create_reg_interval(duration_secs),
+            register_interval: create_refresh_interval(duration_secs),
+
+            instance_id: None,
+            pub_gruu: None,
+            temp_gruu: None,
+            reg_id: None,
+            outbound_proxy: None,
+            flow: None,
+            flow_timer: None,
+            outbound: false,
+            service_route: vec![],
+        }
+    }
+
+    /// Set the instance id of this user agent (e.g. created using [`gruu::create_instance_id`]),
+    /// which must be stable across restarts
+    ///
+    /// Enables GRUU support: the registration is sent with `Supported: gruu` and the
+    /// `+sip.instance` contact parameter.
+    pub fn set_instance_id(&mut self, instance_id: BytesStr) {
+        self.contact
+            .params
+            .push_or_edit(gruu::INSTANCE_PARAM, format!("<{}>", instance_id));
+
+        self.instance_id = Some(instance_id);
+    }
+
+    /// Set the registration id of this registration, as described in RFC 5626
+    ///
+    /// Enables SIP Outbound: the registration is sent with `Supported: outbound` and the
+    /// `reg-id` contact parameter. Requires an instance id to be set using [`Registration::set_instance_id`].
+    /// Each flow of a user agent must use its own registration with a distinct `reg_id`.
+    pub fn set_reg_id(&mut self, reg_id: u32) {
+        assert!(
+            self.instance_id.is_some(),
+            "SIP Outbound requires an instance id"
+        );
+
+        self.contact
+            .params
+            .push_or_edit("reg-id", reg_id.to_string());
+
+        self.reg_id = Some(reg_id);
+    }
+
+    /// Route the REGISTER request through the given proxy (e.g. an edge proxy of the registrar)
+    ///
+    /// The URI should contain the `lr` parameter.
+    pub fn set_outbound_proxy(&mut self, proxy: Box<dyn Uri>) {
+        self.outbound_proxy = Some(Route(Routing {
+            uri: NameAddr::uri(proxy),
+            params: Default::default(),
+        }));
+    }
+
+    /// Flow the registration has been established over
+    ///
+    /// Is only set if a registration id is set and the registration was sent over a connection oriented transport.
+    pub fn flow(&self) -> Option<TpKey> {
+        self.flow
+    }
+
+    /// Keep-alive interval in seconds requested by the registrar using the `Flow-Timer` header
+    pub fn flow_timer(&self) -> Option<u32> {
+        self.flow_timer
+    }
+
+    /// Returns if the registrar supports SIP Outbound
+    pub fn outbound_supported(&self) -> bool {
+        self.outbound
+    }
+
+    /// Forget the current flow, the next REGISTER will establish a new one
+    pub fn reset_flow(&mut self) {
+        self.flow = None;
+        self.flow_timer = None;
+    }
+
+    /// Service route returned by the registrar in the `Service-Route` header of the last successful response
+    pub fn service_route(&self) -> &[ServiceRoute] {
+        &self.service_route
+    }
+
+    /// Returns the preloaded route set for out-of-dialog requests sent using this registration
+    ///
+    /// Contains the outbound proxy (if set) followed by the service route. Pass it to the
+    /// `set_route_set` method of the senders (e.g. [`Initiator::set_route_set`](crate::invite::initiator::Initiator::set_route_set))
+    /// or add it using [`Registration::prepare_request`].
+    pub fn route_set(&self) -> Vec<Route> {
+        self.outbound_proxy
+            .iter()
+            .cloned()
+            .chain(
+                self.service_route
+                    .iter()
+                    .map(|service_route| Route(service_route.0.clone())),
+            )
+            .collect()
+    }
+
+    /// Prepare an out-of-dialog request to be sent using this registration
+    ///
+    /// Adds the route set returned by [`Registration::route_set`] as preloaded Route headers
+    /// and sends the request over the registration's flow, if there is one.
+    pub fn prepare_request(&self, request: &mut Request) {
+        request.headers.insert_type(&self.route_set());
+
+        if self.flow.is_some() {
+            request.flow = self.flow;
+        }
+    }
+
+    /// Public GRUU assigned by the registrar
+    pub fn pub_gruu(&self) -> Option<&SipUri> {
+        self.pub_gruu.as_ref()
+    }
+
+    /// Latest temporary GRUU assigned by the registrar
+    pub fn temp_gruu(&self) -> Option<&SipUri> {
+        self.temp_gruu.as_ref()
+    }
+
+    /// Returns the contact to use in dialogs, which is the public GRUU if one was assigned
+    pub fn local_contact(&self) -> Contact {
+        match &self.pub_gruu {
+            Some(pub_gruu) => Contact::new(NameAddr::uri(pub_gruu.clone())),
+            None => {
+                let mut contact = self.contact.clone();
+                contact.params.take(gruu::INSTANCE_PARAM);
+                contact
+            }
         }
     }
 
@@ -62,6 +226,20 @@ impl Registration {
         request.headers.insert_type(&expires);
         request.headers.insert_type(&self.contact);
 
+        if self.instance_id.is_some() {
+            request.headers.insert_type(&Supported("gruu".into()));
+        }
+
+        if self.reg_id.is_some() {
+            request.headers.insert_type(&Supported("path".into()));
+            request.headers.insert_type(&Supported("outbound".into()));
+            request.flow = self.flow;
+        }
+
+        if let Some(outbound_proxy) = &self.outbound_proxy {
+            request.headers.insert_type(outbound_proxy);
+        }
+
         request
     }
 
@@ -70,11 +248,53 @@ impl Registration {
 
         if let Ok(expires) = response.headers.get::<Expires>() {
             if self.expires != expires.0 {
-                self.register_interval = create_reg_interval(expires.0);
+                self.register_interval = create_refresh_interval(expires.0);
                 self.expires = expires.0;
             }
         }
 
+        if let Some(instance_id) = &self.instance_id {
+            let contacts = response.headers.get::<Vec<Contact>>().unwrap_or_default();
+
+            let contact = contacts
+                .iter()
+                .find(|contact| gruu::instance_id(contact) == Some(instance_id.as_str()));
+
+            if let Some(contact) = contact {
+                let parse_gruu = |name| {
+                    contact
+                        .params
+                        .get_val(name)
+                        .and_then(|gruu| gruu.parse::<SipUri>().ok())
+                };
+
+                self.pub_gruu = parse_gruu("pub-gruu");
+                self.temp_gruu = parse_gruu("temp-gruu");
+            }
+        }
+
+        // The service route is replaced with every successful response
+        self.service_route = response.headers.get().unwrap_or_default();
+
+        if self.reg_id.is_some() {
+            let require: Vec<Require> = response.headers.get().unwrap_or_default();
+
+            self.outbound = require.iter().any(|require| require.0 == "outbound");
+            self.flow_timer = response
+                .headers
+                .get::<FlowTimer>()
+                .ok()
+                .map(|timer| timer.0);
+
+            let transport = &response.tp_info.transport;
+
+            self.flow = if transport.reliable() {
+                Some(transport.key())
+            } else {
+                None
+            };
+        }
+
         if self.to.tag.is_none() {
             self.to.tag = response.base_headers.to.0.tag;
         }
@@ -85,12 +305,262 @@ impl Registration {
     }
 }
 
-fn create_reg_interval(secs: u32) -> Interval {
-    let secs = (secs - 10) as u64;
-    let duration = Duration::from_secs(secs);
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::message::MessageSender;
+    use crate::test_util::{bind_loopback, uri};
+    use bytes::Bytes;
+    use sip_core::{Endpoint, IncomingRequest, Layer, MayTake};
+    use sip_types::header::typed::ContentType;
+    use sip_types::print::{AppendCtx, Print};
+    use sip_types::{Code, Name};
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicU32, Ordering};
 
-    let next = Instant::now() + duration;
-    let mut register_interval = interval_at(next, duration);
-    register_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
-    register_interval
+    const INSTANCE_ID: &str = "urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6";
+
+    /// Accepts all REGISTER requests, the n-th response contains the service route
+    /// `<sip:origN.example.com;lr>, <sip:home.example.com;lr>`
+    ///
+    /// Contacts with an instance id are returned with GRUUs, after the binding of another instance.
+    #[derive(Default)]
+    struct Registrar {
+        registered: AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl Layer for Registrar {
+        fn name(&self) -> &'static str {
+            "test-registrar"
+        }
+
+        async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+            if request.line.method != Method::REGISTER {
+                return;
+            }
+
+            let request = request.take();
+            let n = self.registered.fetch_add(1, Ordering::Relaxed) + 1;
+
+            let mut response = endpoint
+                .create_response(&request, Code::OK, None)
+                .await
+                .unwrap();
+
+            let mut contact: Contact = request.headers.get().unwrap();
+
+            if let Some(instance_id) = gruu::instance_id(&contact) {
+                let aor: SipUri = "sip:alice@example.com".parse().unwrap();
+
+                response.msg.headers.insert(
+                    Name::CONTACT,
+                    "<sip:alice@192.0.2.9>;+sip.instance=\"<urn:uuid:other>\";pub-gruu=\"sip:alice@example.com;gr=urn:uuid:other\"",
+                );
+
+                let pub_gruu = gruu::create_pub_gruu(&aor, instance_id);
+                let temp_gruu = gruu::create_temp_gruu(&aor);
+                gruu::add_gruus(&mut contact, &pub_gruu, Some(&temp_gruu));
+            }
+
+            response.msg.headers.insert_type(&contact);
+            response.msg.headers.insert(
+                Name::SERVICE_ROUTE,
+                format!("<sip:orig{}.example.com;lr>", n),
+            );
+            response
+                .msg
+                .headers
+                .insert(Name::SERVICE_ROUTE, "<sip:home.example.com;lr>");
+
+            endpoint
+                .create_server_tsx(&request)
+                .respond(response)
+                .await
+                .unwrap();
+        }
+    }
+
+    struct Setup {
+        registrar_addr: SocketAddr,
+        client: Endpoint,
+        registration: Registration,
+        _registrar: Endpoint,
+    }
+
+    async fn setup() -> Setup {
+        let mut builder = Endpoint::builder();
+        builder.add_layer(Registrar::default());
+        let registrar_addr = bind_loopback(&mut builder).await;
+        let registrar = builder.build();
+
+        let mut builder = Endpoint::builder();
+        let client_addr = bind_loopback(&mut builder).await;
+        let client = builder.build();
+
+        let registration = Registration::new(
+            NameAddr::uri(uri("alice", client_addr)),
+            Box::new(uri("registrar", registrar_addr)),
+        );
+
+        Setup {
+            registrar_addr,
+            client,
+            registration,
+            _registrar: registrar,
+        }
+    }
+
+    async fn register(endpoint: &Endpoint, registration: &mut Registration) {
+        let request = registration.create_register(false);
+
+        let response = endpoint
+            .send_request(request)
+            .await
+            .unwrap()
+            .receive_final()
+            .await
+            .unwrap();
+
+        registration.receive_success_response(response);
+    }
+
+    fn print<P: Print>(routes: &[P]) -> Vec<String> {
+        routes
+            .iter()
+            .map(|route| route.default_print_ctx().to_string())
+            .collect()
+    }
+
+    fn routes(request: &Request) -> Vec<String> {
+        print(&request.headers.get::<Vec<Route>>().unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn service_route_is_replaced_on_refresh() {
+        let Setup {
+            client,
+            mut registration,
+            ..
+        } = setup().await;
+        assert!(registration.service_route().is_empty());
+
+        register(&client, &mut registration).await;
+        assert_eq!(
+            print(registration.service_route()),
+            ["<sip:orig1.example.com;lr>", "<sip:home.example.com;lr>"]
+        );
+
+        register(&client, &mut registration).await;
+        assert_eq!(
+            print(registration.service_route()),
+            ["<sip:orig2.example.com;lr>", "<sip:home.example.com;lr>"]
+        );
+    }
+
+    #[tokio::test]
+    async fn route_set_starts_with_outbound_proxy() {
+        let Setup {
+            registrar_addr,
+            client,
+            mut registration,
+            ..
+        } = setup().await;
+
+        // The registrar acts as its own edge proxy
+        let proxy: SipUri = format!("sip:{};lr", registrar_addr).parse().unwrap();
+        registration.set_outbound_proxy(Box::new(proxy));
+
+        register(&client, &mut registration).await;
+
+        let expected = [
+            format!("<sip:{};lr>", registrar_addr),
+            "<sip:orig1.example.com;lr>".into(),
+            "<sip:home.example.com;lr>".into(),
+        ];
+        assert_eq!(print(&registration.route_set()), expected);
+
+        let mut request = Request::new(Method::OPTIONS, uri("bob", ([127, 0, 0, 1], 5060).into()));
+        registration.prepare_request(&mut request);
+        assert_eq!(routes(&request), expected);
+        assert_eq!(request.flow, None);
+
+        let mut sender = MessageSender::new(
+            NameAddr::uri(uri("alice", ([127, 0, 0, 1], 5060).into())),
+            Box::new(uri("bob", ([127, 0, 0, 1], 5060).into())),
+        );
+        sender.set_route_set(registration.route_set());
+
+        let message = sender.create_message(
+            ContentType::from_static("text/plain"),
+            Bytes::from_static(b"hi"),
+        );
+        assert_eq!(routes(&message), expected);
+    }
+
+    #[tokio::test]
+    async fn prepare_request_without_route_set() {
+        let Setup { registration, .. } = setup().await;
+
+        let mut request = Request::new(Method::OPTIONS, uri("bob", ([127, 0, 0, 1], 5060).into()));
+        registration.prepare_request(&mut request);
+        assert!(!request.headers.contains::<Route>());
+    }
+
+    #[tokio::test]
+    async fn gruus_of_own_instance() {
+        let Setup {
+            client,
+            mut registration,
+            ..
+        } = setup().await;
+
+        registration.set_instance_id(INSTANCE_ID.into());
+
+        let request = registration.create_register(false);
+        let contact: Contact = request.headers.get().unwrap();
+        assert_eq!(gruu::instance_id(&contact), Some(INSTANCE_ID));
+        assert!(gruu::supports_gruu(&request.headers));
+
+        register(&client, &mut registration).await;
+
+        let pub_gruu = registration
+            .pub_gruu()
+            .unwrap()
+            .default_print_ctx()
+            .to_string();
+        assert_eq!(
+            pub_gruu,
+            format!("sip:alice@example.com;gr={}", INSTANCE_ID)
+        );
+
+        let temp_gruu = registration
+            .temp_gruu()
+            .unwrap()
+            .default_print_ctx()
+            .to_string();
+        assert!(temp_gruu.starts_with("sip:tgruu."));
+
+        // The public GRUU is used as contact in dialogs
+        let local_contact = registration.local_contact();
+        assert_eq!(
+            local_contact.uri.uri.default_print_ctx().to_string(),
+            pub_gruu
+        );
+        assert!(local_contact.params.get_val(gruu::INSTANCE_PARAM).is_none());
+    }
+
+    #[tokio::test]
+    async fn local_contact_without_gruu() {
+        let Setup {
+            mut registration, ..
+        } = setup().await;
+
+        registration.set_instance_id(INSTANCE_ID.into());
+
+        // Nothing has been assigned yet, the instance id is not part of the contact
+        let local_contact = registration.local_contact();
+        assert!(registration.pub_gruu().is_none());
+        assert!(local_contact.params.get_val(gruu::INSTANCE_PARAM).is_none());
+    }
 }
@@ -0,0 +1,490 @@
+//! SIP Outbound (RFC 5626)
+//!
+//! Maintains multiple registrations of the same instance over different edge proxies,
+//! each forming a connection oriented flow which is kept alive using CRLF keep-alives.
+//! Failed flows are recovered by re-registering after a backoff.
+
+use super::Registration;
+use bytesstr::BytesStr;
+use parking_lot as pl;
+use rand::Rng;
+use sip_core::transport::TpKey;
+use sip_core::{Endpoint, Error, Request, Result};
+use sip_types::uri::{NameAddr, Uri};
+use sip_types::CodeKind;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+/// Configuration of an [`OutboundRegistration`]
+#[derive(Debug, Clone)]
+pub struct OutboundConfig {
+    /// Identity (address of record) to register
+    pub id: NameAddr,
+
+    /// URI of the registrar
+    pub registrar: Box<dyn Uri>,
+
+    /// Instance id of this user agent without angle brackets (e.g. created using
+    /// [`create_instance_id`](super::gruu::create_instance_id)), must be stable across restarts
+    pub instance_id: BytesStr,
+
+    /// Keep-alive interval used when the registrar does not send a `Flow-Timer` header
+    pub keep_alive_interval: Duration,
+
+    /// Time to wait for the pong of a keep-alive before considering the flow failed
+    pub keep_alive_timeout: Duration,
+
+    /// Base time to wait before recovering a flow, if all flows have failed
+    pub base_time_all_failed: Duration,
+
+    /// Base time to wait before recovering a flow, if some flows are still active
+    pub base_time_some_ok: Duration,
+
+    /// Upper bound of the time to wait before recovering a flow
+    pub max_time: Duration,
+}
+
+impl OutboundConfig {
+    pub fn new(id: NameAddr, registrar: Box<dyn Uri>, instance_id: BytesStr) -> Self {
+        Self {
+            id,
+            registrar,
+            instance_id,
+            keep_alive_interval: Duration::from_secs(120),
+            keep_alive_timeout: Duration::from_secs(10),
+            base_time_all_failed: Duration::from_secs(30),
+            base_time_some_ok: Duration::from_secs(90),
+            max_time: Duration::from_secs(1800),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowState {
+    /// The flow is being established
+    Pending,
+    /// The flow is registered and kept alive
+    Active,
+    /// The flow has failed and will be recovered
+    Failed,
+}
+
+/// Emitted by the [`OutboundRegistration`] when the state of a flow changes
+#[derive(Debug, Clone)]
+pub struct FlowStateChanged {
+    /// Registration id of the flow
+    pub reg_id: u32,
+    pub state: FlowState,
+}
+
+#[derive(Debug)]
+struct FlowStatus {
+    state: FlowState,
+    flow: Option<TpKey>,
+}
+
+/// Registers one flow through each of the given edge proxies, keeps them alive and recovers
+/// them when they fail
+///
+/// The flow through the edge proxy at index `n` uses the registration id `n + 1`.
+/// All registrations stop being refreshed when the `OutboundRegistration` is dropped.
+pub struct OutboundRegistration {
+    status: Arc<pl::Mutex<Vec<FlowStatus>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for OutboundRegistration {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl OutboundRegistration {
+    /// Start registering over all `edge_proxies`, which should contain the `lr` parameter
+    ///
+    /// Returns the registration and a receiver for all changes to the state of the flows.
+    pub fn start(
+        endpoint: Endpoint,
+        config: OutboundConfig,
+        edge_proxies: Vec<Box<dyn Uri>>,
+    ) -> (Self, mpsc::UnboundedReceiver<FlowStateChanged>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+
+        let status = Arc::new(pl::Mutex::new(
+            edge_proxies
+                .iter()
+                .map(|_| FlowStatus {
+                    state: FlowState::Pending,
+                    flow: None,
+                })
+                .collect::<Vec<_>>(),
+        ));
+
+        let tasks = edge_proxies
+            .into_iter()
+            .enumerate()
+            .map(|(index, edge_proxy)| {
+                tokio::spawn(flow_task(
+                    endpoint.clone(),
+                    config.clone(),
+                    index,
+                    edge_proxy,
+                    status.clone(),
+                    events.clone(),
+                ))
+            })
+            .collect();
+
+        (Self { status, tasks }, receiver)
+    }
+
+    /// Returns the current state of all flows, ordered by their registration id
+    pub fn states(&self) -> Vec<FlowState> {
+        self.status
+            .lock()
+            .iter()
+            .map(|status| status.state)
+            .collect()
+    }
+
+    /// Returns the first active flow
+    pub fn flow(&self) -> Option<TpKey> {
+        self.status
+            .lock()
+            .iter()
+            .filter(|status| status.state == FlowState::Active)
+            .find_map(|status| status.flow)
+    }
+
+    /// Send the request over the first active flow
+    ///
+    /// Returns `false` if there is no active flow, leaving the request unmodified.
+    pub fn prepare_request(&self, request: &mut Request) -> bool {
+        match self.flow() {
+            Some(flow) => {
+                request.flow = Some(flow);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+async fn flow_task(
+    endpoint: Endpoint,
+    config: OutboundConfig,
+    index: usize,
+    edge_proxy: Box<dyn Uri>,
+    status: Arc<pl::Mutex<Vec<FlowStatus>>>,
+    events: mpsc::UnboundedSender<FlowStateChanged>,
+) {
+    let reg_id = index as u32 + 1;
+
+    let mut registration = Registration::new(config.id.clone(), config.registrar.clone());
+    registration.set_instance_id(config.instance_id.clone());
+    registration.set_reg_id(reg_id);
+    registration.set_outbound_proxy(edge_proxy);
+
+    let set_state = |state: FlowState, flow: Option<TpKey>| {
+        {
+            let mut status = status.lock();
+            let status = &mut status[index];
+
+            if status.state == state && status.flow == flow {
+                return;
+            }
+
+            status.state = state;
+            status.flow = flow;
+        }
+
+        if events.send(FlowStateChanged { reg_id, state }).is_err() {
+            log::debug!("Flow state receiver has been dropped");
+        }
+    };
+
+    let mut failures = 0;
+
+    loop {
+        match register(&endpoint, &mut registration).await {
+            Ok(()) => match registration.flow() {
+                Some(flow) => {
+                    if !registration.outbound_supported() {
+                        log::warn!("registrar does not support SIP Outbound, flow {}", reg_id);
+                    }
+
+                    failures = 0;
+                    set_state(FlowState::Active, Some(flow));
+
+                    maintain_flow(&endpoint, &config, &mut registration, flow).await;
+                }
+                None => {
+                    log::warn!(
+                        "flow {} is not connection oriented and cannot be kept alive",
+                        reg_id
+                    );
+
+                    set_state(FlowState::Active, None);
+
+                    registration.wait_for_expiry().await;
+                    continue;
+                }
+            },
+            Err(e) => log::warn!("failed to register flow {}, {:?}", reg_id, e),
+        }
+
+        set_state(FlowState::Failed, None);
+        registration.reset_flow();
+
+        failures += 1;
+
+        let all_failed = status
+            .lock()
+            .iter()
+            .all(|status| status.state != FlowState::Active);
+
+        sleep(backoff(&config, failures, all_failed)).await;
+    }
+}
+
+/// Keep the flow alive and refresh the registration until the flow fails
+async fn maintain_flow(
+    endpoint: &Endpoint,
+    config: &OutboundConfig,
+    registration: &mut Registration,
+    flow: TpKey,
+) {
+    loop {
+        let keep_alive_interval = registration
+            .flow_timer()
+            .map(|secs| Duration::from_secs(secs.into()))
+            .unwrap_or(config.keep_alive_interval);
+
+        // Send keep-alives randomly between 80% and 100% of the interval
+        let keep_alive_interval =
+            keep_alive_interval.mul_f64(rand::thread_rng().gen_range(0.8..=1.0));
+
+        let refresh = tokio::select! {
+            _ = registration.wait_for_expiry() => true,
+            _ = sleep(keep_alive_interval) => false,
+        };
+
+        if refresh {
+            if let Err(e) = register(endpoint, registration).await {
+                log::warn!("failed to refresh flow registration, {:?}", e);
+                return;
+            }
+
+            if registration.flow() != Some(flow) {
+                return;
+            }
+        } else {
+            if let Err(e) = endpoint.keep_alive(&flow, config.keep_alive_timeout).await {
+                log::warn!("keep-alive failed, {:?}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Send a REGISTER and handle its final response
+async fn register(endpoint: &Endpoint, registration: &mut Registration) -> Result<()> {
+    let request = registration.create_register(false);
+    let transaction = endpoint.send_request(request).await?;
+    let response = transaction.receive_final().await?;
+
+    match response.line.code.kind() {
+        CodeKind::Success => {
+            registration.receive_success_response(response);
+            Ok(())
+        }
+        _ => Err(Error::new(response.line.code)),
+    }
+}
+
+/// Time to wait before recovering a failed flow, as described in RFC 5626 section 4.5
+fn backoff(config: &OutboundConfig, failures: u32, all_failed: bool) -> Duration {
+    let base_time = if all_failed {
+        config.base_time_all_failed
+    } else {
+        config.base_time_some_ok
+    };
+
+    let wait_time = base_time
+        .checked_mul(1 << failures.min(16))
+        .map_or(config.max_time, |wait_time| wait_time.min(config.max_time));
+
+    // Wait randomly between 50% and 100% of the wait time
+    wait_time.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::uri;
+    use sip_core::transport::streaming::generalized::StreamingTransport;
+    use sip_core::transport::streaming::tcp::Tcp;
+    use sip_types::uri::sip::SipUri;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    fn config() -> OutboundConfig {
+        let uri: SipUri = "sip:alice@example.com".parse().unwrap();
+
+        OutboundConfig::new(
+            NameAddr::uri(uri.clone()),
+            Box::new(uri),
+            BytesStr::from_static("urn:uuid:00000000-0000-1000-8000-000a95a0e128"),
+        )
+    }
+
+    fn assert_backoff(config: &OutboundConfig, failures: u32, all_failed: bool, wait: u64) {
+        let wait = Duration::from_secs(wait);
+
+        for _ in 0..100 {
+            let backoff = backoff(config, failures, all_failed);
+
+            assert!(backoff >= wait / 2 && backoff <= wait, "{:?}", backoff);
+        }
+    }
+
+    #[test]
+    fn backoff_all_failed() {
+        let config = config();
+
+        assert_backoff(&config, 1, true, 60);
+        assert_backoff(&config, 2, true, 120);
+        assert_backoff(&config, 5, true, 960);
+    }
+
+    #[test]
+    fn backoff_some_ok() {
+        let config = config();
+
+        assert_backoff(&config, 1, false, 180);
+        assert_backoff(&config, 3, false, 720);
+    }
+
+    #[test]
+    fn backoff_max_time() {
+        let config = config();
+
+        assert_backoff(&config, 6, true, 1800);
+        assert_backoff(&config, 5, false, 1800);
+        assert_backoff(&config, u32::MAX, true, 1800);
+    }
+
+    /// Read a message without body, skipping leading keep-alives
+    async fn read_message(stream: &mut TcpStream) -> String {
+        let mut message = vec![];
+
+        while !message.ends_with(b"\r\n\r\n") {
+            let byte = stream.read_u8().await.unwrap();
+
+            if message.is_empty() && (byte == b'\r' || byte == b'\n') {
+                continue;
+            }
+
+            message.push(byte);
+        }
+
+        String::from_utf8(message).unwrap()
+    }
+
+    /// Accept a connection and answer the REGISTER received over it with a `200 OK` confirming SIP Outbound
+    async fn accept_register(listener: &TcpListener) -> TcpStream {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let request = read_message(&mut stream).await;
+
+        let mut response = String::from("SIP/2.0 200 OK\r\n");
+
+        for line in request.lines().skip(1) {
+            let name = line.split(':').next().unwrap().trim().to_ascii_lowercase();
+
+            match name.as_str() {
+                "via" | "v" | "from" | "f" | "call-id" | "i" | "cseq" | "contact" | "m" => {
+                    response += line;
+                }
+                "to" | "t" if !line.contains("tag=") => {
+                    response += line;
+                    response += ";tag=registrar";
+                }
+                "to" | "t" => {
+                    response += line;
+                }
+                _ => continue,
+            }
+
+            response += "\r\n";
+        }
+
+        response += "Require: outbound\r\nContent-Length: 0\r\n\r\n";
+
+        stream.write_all(response.as_bytes()).await.unwrap();
+        stream
+    }
+
+    async fn next_state(events: &mut mpsc::UnboundedReceiver<FlowStateChanged>) -> FlowState {
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.reg_id, 1);
+        event.state
+    }
+
+    #[tokio::test]
+    async fn failed_flow_is_recovered() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let registrar_addr = listener.local_addr().unwrap();
+
+        let mut builder = Endpoint::builder();
+        Tcp.spawn(&mut builder, "127.0.0.1:0").await.unwrap();
+        let endpoint = builder.build();
+
+        let registrar: SipUri = format!("sip:{};transport=tcp", registrar_addr)
+            .parse()
+            .unwrap();
+        let edge_proxy: SipUri = format!("sip:{};transport=tcp;lr", registrar_addr)
+            .parse()
+            .unwrap();
+
+        let mut config = config();
+        config.registrar = Box::new(registrar);
+        config.id = NameAddr::uri(uri("alice", ([127, 0, 0, 1], 5060).into()));
+        config.keep_alive_interval = Duration::from_millis(50);
+        config.keep_alive_timeout = Duration::from_millis(50);
+        config.base_time_all_failed = Duration::from_millis(10);
+
+        let (outbound, mut events) =
+            OutboundRegistration::start(endpoint, config, vec![Box::new(edge_proxy)]);
+
+        let mut stream = accept_register(&listener).await;
+        assert_eq!(next_state(&mut events).await, FlowState::Active);
+
+        let flow = outbound.flow().unwrap();
+        assert_eq!(outbound.states(), [FlowState::Active]);
+
+        // The first keep-alive is answered
+        let mut ping = [0; 4];
+        stream.read_exact(&mut ping).await.unwrap();
+        assert_eq!(&ping, b"\r\n\r\n");
+        stream.write_all(b"\r\n").await.unwrap();
+
+        // The second one is not, the connection is closed instead
+        stream.read_exact(&mut ping).await.unwrap();
+        assert_eq!(&ping, b"\r\n\r\n");
+        drop(stream);
+
+        assert_eq!(next_state(&mut events).await, FlowState::Failed);
+        assert_eq!(outbound.flow(), None);
+
+        // The flow is recovered by registering over a new connection
+        let _stream = accept_register(&listener).await;
+        assert_eq!(next_state(&mut events).await, FlowState::Active);
+
+        assert_ne!(outbound.flow().unwrap(), flow);
+    }
+}
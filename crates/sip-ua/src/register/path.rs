@@ -0,0 +1,150 @@
+//! Path (RFC 3327) and Service-Route (RFC 3608)
+//!
+//! Contains the helpers used by registrars to store the Path vector of a binding
+//! and to route requests to the registered contact back through the proxies on the path.
+
+use sip_core::Request;
+use sip_types::header::typed::{Contact, Path, Route, ServiceRoute, Supported};
+use sip_types::header::HeaderError;
+use sip_types::Headers;
+use std::ops::Deref;
+
+/// Returns if the message contains `Supported: path`
+pub fn supports_path(headers: &Headers) -> bool {
+    headers
+        .get::<Vec<Supported>>()
+        .unwrap_or_default()
+        .iter()
+        .any(|ext| ext.deref() == "path")
+}
+
+/// Contact bound to an address-of-record, together with the Path vector of the REGISTER which created it
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub contact: Contact,
+
+    /// Proxies the REGISTER traversed, requests to the contact must be routed through them
+    pub path: Vec<Path>,
+}
+
+impl Binding {
+    /// Create a binding from a contact of a REGISTER request with the given headers
+    pub fn from_register(contact: Contact, headers: &Headers) -> Result<Self, HeaderError> {
+        let path = match headers.try_get() {
+            Some(path) => path?,
+            None => vec![],
+        };
+
+        Ok(Self { contact, path })
+    }
+
+    /// Returns the preloaded route set for requests sent to the contact
+    pub fn route_set(&self) -> Vec<Route> {
+        self.path.iter().map(|path| Route(path.0.clone())).collect()
+    }
+
+    /// Retarget a request for the address-of-record to the contact of this binding
+    ///
+    /// Replaces the request URI with the contact's URI and adds the Path vector as preloaded Route headers.
+    pub fn prepare_request(&self, request: &mut Request) {
+        request.line.uri = self.contact.uri.uri.clone();
+        request.headers.insert_type(&self.route_set());
+    }
+
+    /// Add the binding to the headers of a successful response to a REGISTER request
+    ///
+    /// The Path vector is echoed back so the user agent can inspect it.
+    pub fn add_to_response(&self, headers: &mut Headers) {
+        headers.insert_type(&self.contact);
+        headers.insert_type(&self.path);
+    }
+}
+
+/// Add the service route which the user agent must use for its out-of-dialog requests
+/// to the headers of a successful response to a REGISTER request
+pub fn add_service_route(headers: &mut Headers, service_route: &[ServiceRoute]) {
+    for route in service_route {
+        headers.insert_type(route);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sip_types::print::{AppendCtx, Print};
+    use sip_types::uri::sip::SipUri;
+    use sip_types::uri::NameAddr;
+    use sip_types::{Method, Name};
+
+    fn print<P: Print>(routes: &[P]) -> Vec<String> {
+        routes
+            .iter()
+            .map(|route| route.default_print_ctx().to_string())
+            .collect()
+    }
+
+    fn binding(path: &[&'static str]) -> Binding {
+        let uri: SipUri = "sip:alice@192.0.2.4:5060".parse().unwrap();
+
+        let mut headers = Headers::new();
+        for path in path {
+            headers.insert(Name::PATH, *path);
+        }
+
+        Binding::from_register(Contact::new(NameAddr::uri(uri)), &headers).unwrap()
+    }
+
+    #[test]
+    fn from_register() {
+        let without_path = binding(&[]);
+        assert!(without_path.path.is_empty());
+
+        let binding = binding(&["<sip:p2.example.com;lr>", "<sip:p1.example.com;lr>"]);
+
+        assert_eq!(
+            print(&binding.path),
+            ["<sip:p2.example.com;lr>", "<sip:p1.example.com;lr>"]
+        );
+        assert_eq!(print(&binding.route_set()), print(&binding.path));
+    }
+
+    #[test]
+    fn prepare_request() {
+        let binding = binding(&["<sip:p2.example.com;lr>", "<sip:p1.example.com;lr>"]);
+
+        let aor: SipUri = "sip:alice@example.com".parse().unwrap();
+        let mut request = Request::new(Method::INVITE, aor);
+        binding.prepare_request(&mut request);
+
+        assert_eq!(
+            request.line.uri.default_print_ctx().to_string(),
+            "sip:alice@192.0.2.4:5060"
+        );
+        assert_eq!(
+            print(&request.headers.get::<Vec<Route>>().unwrap()),
+            ["<sip:p2.example.com;lr>", "<sip:p1.example.com;lr>"]
+        );
+    }
+
+    #[test]
+    fn add_to_response() {
+        let binding = binding(&["<sip:p1.example.com;lr>"]);
+
+        let mut headers = Headers::new();
+        binding.add_to_response(&mut headers);
+        add_service_route(&mut headers, &[ServiceRoute(binding.path[0].0.clone())]);
+
+        assert_eq!(
+            print(&headers.get::<Vec<Contact>>().unwrap()),
+            ["<sip:alice@192.0.2.4:5060>"]
+        );
+        assert_eq!(
+            print(&headers.get::<Vec<Path>>().unwrap()),
+            ["<sip:p1.example.com;lr>"]
+        );
+        assert_eq!(
+            print(&headers.get::<Vec<ServiceRoute>>().unwrap()),
+            ["<sip:p1.example.com;lr>"]
+        );
+    }
+}
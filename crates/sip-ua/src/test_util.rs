@@ -0,0 +1,82 @@
+//! Helpers to run endpoints talking to each other over the loopback interface
+
+use crate::dialog::DialogLayer;
+use crate::invite::acceptor::Acceptor;
+use crate::invite::initiator::{Initiator, Response};
+use crate::invite::session::Session;
+use crate::invite::InviteLayer;
+use sip_core::transport::udp::Udp;
+use sip_core::{Endpoint, EndpointBuilder, IncomingRequest, Layer, LayerKey, MayTake};
+use sip_types::header::typed::Contact;
+use sip_types::host::HostPort;
+use sip_types::uri::sip::{SipUri, UserPart};
+use sip_types::{Code, Method};
+use std::net::{SocketAddr, UdpSocket};
+use tokio::sync::mpsc;
+
+/// Add a UDP transport bound to a free port on the loopback interface, returns its address
+pub(crate) async fn bind_loopback(builder: &mut EndpointBuilder) -> SocketAddr {
+    let addr = UdpSocket::bind("127.0.0.1:0")
+        .and_then(|socket| socket.local_addr())
+        .unwrap();
+
+    Udp::spawn(builder, addr).await.unwrap();
+
+    addr
+}
+
+/// SIP URI of `user` reachable at the given address
+pub(crate) fn uri(user: &str, addr: SocketAddr) -> SipUri {
+    let mut uri = SipUri::new(HostPort::from(addr));
+    uri.user_part = UserPart::User(user.into());
+    uri
+}
+
+/// Accepts all INVITEs and passes the established sessions to the test
+pub(crate) struct AcceptLayer {
+    pub(crate) dialog_layer: LayerKey<DialogLayer>,
+    pub(crate) invite_layer: LayerKey<InviteLayer>,
+    pub(crate) contact: Contact,
+    pub(crate) sessions: mpsc::Sender<Session>,
+}
+
+#[async_trait::async_trait]
+impl Layer for AcceptLayer {
+    fn name(&self) -> &'static str {
+        "test-accept"
+    }
+
+    async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+        if request.line.method != Method::INVITE {
+            return;
+        }
+
+        let acceptor = Acceptor::new(
+            endpoint.clone(),
+            self.dialog_layer,
+            self.invite_layer,
+            request.take(),
+            self.contact.clone(),
+        )
+        .unwrap();
+
+        let response = acceptor.create_response(Code::OK, None).await.unwrap();
+        let (session, _ack) = acceptor.respond_success(response).await.unwrap();
+
+        self.sessions.send(session).await.unwrap();
+    }
+}
+
+/// Send the INVITE of the initiator and wait until the session is established
+pub(crate) async fn establish(mut initiator: Initiator) -> Session {
+    let invite = initiator.create_invite();
+    initiator.send_invite(invite).await.unwrap();
+
+    loop {
+        match initiator.receive().await.unwrap() {
+            Response::Session(session, _) => return session,
+            Response::Provisional(_) => continue,
+            _ => panic!("call has not been established"),
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use bytesstr::BytesStr;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
+use std::time::Duration;
+use tokio::time::{interval_at, Instant, Interval};
 
 pub fn random_string() -> BytesStr {
     thread_rng()
@@ -14,3 +16,14 @@ pub fn random_string() -> BytesStr {
 pub fn random_sequence_number() -> u32 {
     rand::thread_rng().gen_range(0..(u32::MAX >> 1))
 }
+
+/// Create an interval which ticks 10 seconds before something expiring after `secs` seconds
+pub fn create_refresh_interval(secs: u32) -> Interval {
+    let secs = secs.saturating_sub(10).max(1) as u64;
+    let duration = Duration::from_secs(secs);
+
+    let next = Instant::now() + duration;
+    let mut interval = interval_at(next, duration);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    interval
+}
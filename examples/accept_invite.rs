@@ -58,6 +58,12 @@ impl Layer for InviteAcceptLayer {
                 Event::ReInviteReceived(event) => {
                     event.process_default().await.unwrap();
                 }
+                Event::UpdateReceived(event) => {
+                    event.process_default().await.unwrap();
+                }
+                Event::InfoReceived(event) => {
+                    event.process_default().await.unwrap();
+                }
                 Event::Bye(event) => {
                     event.process_default().await.unwrap();
                 }
@@ -0,0 +1,83 @@
+use sip_core::transport::udp::Udp;
+use sip_core::{Endpoint, IncomingRequest, Layer, LayerKey, MayTake, Result};
+use sip_types::header::typed::Contact;
+use sip_types::uri::sip::SipUri;
+use sip_types::uri::NameAddr;
+use sip_types::Method;
+use sip_ua::b2bua::{B2bua, OutgoingLeg};
+use sip_ua::dialog::DialogLayer;
+use sip_ua::invite::InviteLayer;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// Custom layer which bridges all incoming calls to bob
+struct BridgeLayer {
+    dialog_layer: LayerKey<DialogLayer>,
+    invite_layer: LayerKey<InviteLayer>,
+}
+
+#[async_trait::async_trait]
+impl Layer for BridgeLayer {
+    fn name(&self) -> &'static str {
+        "bridge-layer"
+    }
+
+    async fn receive(&self, endpoint: &Endpoint, request: MayTake<'_, IncomingRequest>) {
+        let invite = if request.line.method == Method::INVITE {
+            request.take()
+        } else {
+            return;
+        };
+
+        let b2bua = B2bua::new(endpoint.clone(), self.dialog_layer, self.invite_layer);
+
+        let contact: SipUri = "sip:b2bua@127.0.0.1:5060".parse().unwrap();
+        let contact = Contact::new(NameAddr::uri(contact));
+
+        let id: SipUri = "sip:b2bua@example.com".parse().unwrap();
+        let target: SipUri = "sip:bob@127.0.0.1:5070".parse().unwrap();
+
+        let outgoing = OutgoingLeg {
+            id: NameAddr::uri(id),
+            contact: contact.clone(),
+            target: Box::new(target),
+        };
+
+        let call = match b2bua.bridge(invite, contact, outgoing).await {
+            Ok(call) => call,
+            Err(e) => {
+                println!("failed to bridge call: {:?}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = call.run().await {
+            println!("bridged call failed: {:?}", e);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let mut builder = Endpoint::builder();
+
+    let dialog_layer = builder.add_layer(DialogLayer::default());
+    let invite_layer = builder.add_layer(InviteLayer::default());
+
+    builder.add_layer(BridgeLayer {
+        dialog_layer,
+        invite_layer,
+    });
+
+    Udp::spawn(&mut builder, "127.0.0.1:5060").await?;
+
+    // Build endpoint to start the SIP Stack
+    let _endpoint = builder.build();
+
+    // Busy sleep loop
+    loop {
+        sleep(Duration::from_secs(1)).await;
+    }
+}
@@ -0,0 +1,86 @@
+use sip_core::transport::udp::Udp;
+use sip_core::{Endpoint, Error, Result};
+use sip_types::header::typed::Contact;
+use sip_types::uri::sip::SipUri;
+use sip_types::uri::NameAddr;
+use sip_ua::dialog::DialogLayer;
+use sip_ua::invite::initiator::{Initiator, Response};
+use sip_ua::invite::session::{Event, RefreshMethod};
+use sip_ua::invite::InviteLayer;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let mut builder = Endpoint::builder();
+
+    let dialog_layer = builder.add_layer(DialogLayer::default());
+    let invite_layer = builder.add_layer(InviteLayer::default());
+
+    Udp::spawn(&mut builder, "127.0.0.1:5070").await?;
+
+    let endpoint = builder.build();
+
+    let id: SipUri = "sip:alice@example.com".parse().unwrap();
+    let contact: SipUri = "sip:alice@127.0.0.1:5070".parse().unwrap();
+    let target: SipUri = "sip:bob@127.0.0.1:5060".parse().unwrap();
+
+    let mut initiator = Initiator::new(
+        endpoint,
+        dialog_layer,
+        invite_layer,
+        NameAddr::uri(id),
+        Contact::new(NameAddr::uri(contact)),
+        target.into(),
+    );
+
+    // Here goes SDP handling
+    let invite = initiator.create_invite();
+
+    initiator.send_invite(invite).await?;
+
+    let mut session = loop {
+        match initiator.receive().await? {
+            Response::Provisional(_) => {}
+            Response::EarlyMedia(_) => {
+                // Here goes SDP handling of the early dialog
+            }
+            Response::ReliableProvisional(response) => {
+                let prack = initiator.create_prack(&response)?;
+                initiator.send_prack(prack).await?;
+            }
+            Response::Session(session, _) => break session,
+            Response::Failure(response) => return Err(Error::new(response.line.code)),
+            Response::Finished => panic!("invite transaction finished without final response"),
+        }
+    };
+
+    loop {
+        match session.drive().await? {
+            Event::RefreshNeeded(event) => {
+                event.process(RefreshMethod::Update).await?;
+            }
+            Event::ReInviteReceived(event) => {
+                event.process_default().await?;
+            }
+            Event::UpdateReceived(event) => {
+                event.process_default().await?;
+            }
+            Event::InfoReceived(event) => {
+                if let Some(Ok(dtmf)) = event.dtmf() {
+                    println!("Received DTMF {}", dtmf);
+                }
+
+                event.process_default().await?;
+            }
+            Event::Bye(event) => {
+                event.process_default().await?;
+            }
+            Event::Terminated => {
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}